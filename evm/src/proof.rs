@@ -1,18 +1,23 @@
 use ethereum_types::{Address, H160, H256, U256};
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::polynomial::PolynomialCoeffs;
 use plonky2::fri::oracle::PolynomialBatch;
-use plonky2::fri::proof::{FriChallenges, FriChallengesTarget, FriProof, FriProofTarget};
+use plonky2::fri::proof::{
+    FriChallenges, FriChallengesTarget, FriInitialTreeProof, FriProof, FriProofTarget,
+    FriQueryRound, FriQueryStep,
+};
 use plonky2::fri::structure::{
     FriOpeningBatch, FriOpeningBatchTarget, FriOpenings, FriOpeningsTarget,
 };
 use plonky2::hash::hash_types::{MerkleCapTarget, RichField};
+use plonky2::hash::hashing::PlonkyPermutation;
 use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::target::{BoolTarget, Target};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{GenericConfig, Hasher};
-use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
@@ -867,6 +872,154 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> S
     pub fn num_ctl_zs(&self) -> usize {
         self.openings.ctl_zs_first.len()
     }
+
+    /// Serializes a STARK proof, with the lengths of its vectors, so that it can be deserialized
+    /// without the shape of the STARK.
+    pub(crate) fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        write_merkle_cap(buffer, &self.trace_cap)?;
+        write_merkle_cap(buffer, &self.auxiliary_polys_cap)?;
+        write_merkle_cap(buffer, &self.quotient_polys_cap)?;
+        self.openings.to_buffer(buffer)?;
+        write_fri_proof(buffer, &self.opening_proof)
+    }
+
+    /// Deserializes a STARK proof written by `to_buffer`.
+    pub(crate) fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let trace_cap = read_merkle_cap(buffer)?;
+        let auxiliary_polys_cap = read_merkle_cap(buffer)?;
+        let quotient_polys_cap = read_merkle_cap(buffer)?;
+        let openings = StarkOpeningSet::from_buffer(buffer)?;
+        let opening_proof = read_fri_proof(buffer)?;
+
+        Ok(Self {
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof,
+        })
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    StarkProofWithMetadata<F, C, D>
+{
+    /// Serializes a STARK proof along with its initial Fiat-Shamir state.
+    pub(crate) fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        buffer.write_field_vec(self.init_challenger_state.as_ref())?;
+        self.proof.to_buffer(buffer)
+    }
+
+    /// Deserializes a STARK proof written by `to_buffer`.
+    pub(crate) fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let init_challenger_state = <C::Hasher as Hasher<F>>::Permutation::new(
+            buffer.read_field_vec(<C::Hasher as Hasher<F>>::Permutation::WIDTH)?,
+        );
+        let proof = StarkProof::from_buffer(buffer)?;
+
+        Ok(Self {
+            init_challenger_state,
+            proof,
+        })
+    }
+}
+
+fn write_merkle_cap<F: RichField, H: Hasher<F>, W: Write>(
+    buffer: &mut W,
+    cap: &MerkleCap<F, H>,
+) -> IoResult<()> {
+    buffer.write_usize(cap.height())?;
+    buffer.write_merkle_cap(cap)
+}
+
+fn read_merkle_cap<F: RichField, H: Hasher<F>>(buffer: &mut Buffer) -> IoResult<MerkleCap<F, H>> {
+    let cap_height = buffer.read_usize()?;
+    if cap_height >= usize::BITS as usize {
+        return Err(IoError);
+    }
+    buffer.read_merkle_cap(cap_height)
+}
+
+fn read_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
+    buffer: &mut Buffer,
+) -> IoResult<Vec<F::Extension>> {
+    let len = buffer.read_usize()?;
+    buffer.read_field_ext_vec::<F, D>(len)
+}
+
+/// Serializes a FRI proof with the lengths of its vectors, which `Write::write_fri_proof` leaves
+/// out since they are given by the `CommonCircuitData` of plonky2 circuits.
+fn write_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize, W: Write>(
+    buffer: &mut W,
+    proof: &FriProof<F, H, D>,
+) -> IoResult<()> {
+    buffer.write_usize(proof.commit_phase_merkle_caps.len())?;
+    for cap in &proof.commit_phase_merkle_caps {
+        write_merkle_cap(buffer, cap)?;
+    }
+    buffer.write_usize(proof.query_round_proofs.len())?;
+    for round in &proof.query_round_proofs {
+        let evals_proofs = &round.initial_trees_proof.evals_proofs;
+        buffer.write_usize(evals_proofs.len())?;
+        for (evals, merkle_proof) in evals_proofs {
+            buffer.write_usize(evals.len())?;
+            buffer.write_field_vec(evals)?;
+            buffer.write_merkle_proof(merkle_proof)?;
+        }
+        buffer.write_usize(round.steps.len())?;
+        for step in &round.steps {
+            buffer.write_usize(step.evals.len())?;
+            buffer.write_field_ext_vec::<F, D>(&step.evals)?;
+            buffer.write_merkle_proof(&step.merkle_proof)?;
+        }
+    }
+    buffer.write_usize(proof.final_poly.len())?;
+    buffer.write_field_ext_vec::<F, D>(&proof.final_poly.coeffs)?;
+    buffer.write_field(proof.pow_witness)
+}
+
+/// Deserializes a FRI proof written by `write_fri_proof`.
+fn read_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Buffer,
+) -> IoResult<FriProof<F, H, D>> {
+    let num_caps = buffer.read_usize()?;
+    let commit_phase_merkle_caps = (0..num_caps)
+        .map(|_| read_merkle_cap(buffer))
+        .collect::<IoResult<Vec<_>>>()?;
+    let num_rounds = buffer.read_usize()?;
+    let mut query_round_proofs = Vec::with_capacity(num_rounds);
+    for _ in 0..num_rounds {
+        let num_evals_proofs = buffer.read_usize()?;
+        let mut evals_proofs = Vec::with_capacity(num_evals_proofs);
+        for _ in 0..num_evals_proofs {
+            let len = buffer.read_usize()?;
+            let evals = buffer.read_field_vec(len)?;
+            evals_proofs.push((evals, buffer.read_merkle_proof()?));
+        }
+        let num_steps = buffer.read_usize()?;
+        let mut steps = Vec::with_capacity(num_steps);
+        for _ in 0..num_steps {
+            let evals = read_field_ext_vec::<F, D>(buffer)?;
+            let merkle_proof = buffer.read_merkle_proof()?;
+            steps.push(FriQueryStep {
+                evals,
+                merkle_proof,
+            });
+        }
+        query_round_proofs.push(FriQueryRound {
+            initial_trees_proof: FriInitialTreeProof { evals_proofs },
+            steps,
+        });
+    }
+    let final_poly = PolynomialCoeffs::new(read_field_ext_vec::<F, D>(buffer)?);
+    let pow_witness = buffer.read_field()?;
+
+    Ok(FriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs,
+        final_poly,
+        pow_witness,
+    })
 }
 
 /// Circuit version of `StarkProof`.
@@ -1003,6 +1156,43 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
         }
     }
 
+    /// Serializes a STARK's opening set, with the lengths of its vectors.
+    pub(crate) fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        for values in [
+            &self.local_values,
+            &self.next_values,
+            &self.auxiliary_polys,
+            &self.auxiliary_polys_next,
+        ] {
+            buffer.write_usize(values.len())?;
+            buffer.write_field_ext_vec::<F, D>(values)?;
+        }
+        buffer.write_usize(self.ctl_zs_first.len())?;
+        buffer.write_field_vec(&self.ctl_zs_first)?;
+        buffer.write_usize(self.quotient_polys.len())?;
+        buffer.write_field_ext_vec::<F, D>(&self.quotient_polys)
+    }
+
+    /// Deserializes a STARK's opening set written by `to_buffer`.
+    pub(crate) fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let local_values = read_field_ext_vec::<F, D>(buffer)?;
+        let next_values = read_field_ext_vec::<F, D>(buffer)?;
+        let auxiliary_polys = read_field_ext_vec::<F, D>(buffer)?;
+        let auxiliary_polys_next = read_field_ext_vec::<F, D>(buffer)?;
+        let ctl_zs_len = buffer.read_usize()?;
+        let ctl_zs_first = buffer.read_field_vec(ctl_zs_len)?;
+        let quotient_polys = read_field_ext_vec::<F, D>(buffer)?;
+
+        Ok(Self {
+            local_values,
+            next_values,
+            auxiliary_polys,
+            auxiliary_polys_next,
+            ctl_zs_first,
+            quotient_polys,
+        })
+    }
+
     /// Constructs the openings required by FRI.
     /// All openings but `ctl_zs_first` are grouped together.
     pub(crate) fn to_fri_openings(&self) -> FriOpenings<F, D> {
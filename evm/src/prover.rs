@@ -11,10 +11,12 @@ use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2::field::types::Field;
 use plonky2::field::zero_poly_coset::ZeroPolyOnCoset;
 use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::fri::FriConfig;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::timed;
+use plonky2::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 use plonky2::util::timing::TimingTree;
 use plonky2::util::transpose;
 use plonky2_maybe_rayon::*;
@@ -26,7 +28,7 @@ use crate::constraint_consumer::ConstraintConsumer;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cross_table_lookup::{
     cross_table_lookup_data, get_grand_product_challenge_set, CtlCheckVars, CtlData,
-    GrandProductChallengeSet,
+    GrandProductChallenge, GrandProductChallengeSet,
 };
 use crate::evaluation_frame::StarkEvaluationFrame;
 use crate::generation::{generate_traces, GenerationInputs};
@@ -49,6 +51,25 @@ pub fn prove<F, C, const D: usize>(
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
 ) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    prove_with_checkpoints(all_stark, config, inputs, |_| Ok(()), timing, abort_signal)
+}
+
+/// Like `prove`, but calls `on_checkpoint` once the traces are committed and after the STARK proof
+/// of each table, e.g. to store the checkpoint with `AllProofCheckpoint::write_to`, so that the
+/// proof can be finished with `resume_proof` if the prover is interrupted. An error returned by
+/// `on_checkpoint` aborts the proof.
+pub fn prove_with_checkpoints<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &StarkConfig,
+    inputs: GenerationInputs,
+    on_checkpoint: impl FnMut(&AllProofCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+    abort_signal: Option<Arc<AtomicBool>>,
+) -> Result<AllProof<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -66,18 +87,177 @@ where
         config,
         traces,
         public_values,
+        on_checkpoint,
         timing,
         abort_signal,
     )?;
     Ok(proof)
 }
 
+/// Finishes the proof from `checkpoint`, calling `on_checkpoint` after the STARK proof of each
+/// remaining table. The proof is the same as if the prover had not been interrupted. The traces
+/// are recovered from their commitments, which are kept in memory whatever `config.storage` is.
+pub fn resume_proof<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &StarkConfig,
+    checkpoint: AllProofCheckpoint<F, C, D>,
+    on_checkpoint: impl FnMut(&AllProofCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+    abort_signal: Option<Arc<AtomicBool>>,
+) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    ensure!(
+        checkpoint.num_challenges == config.num_challenges
+            && checkpoint.fri_config == config.fri_config,
+        "The checkpoint was not taken with this config."
+    );
+    ensure!(
+        checkpoint.trace_commitments.len() == NUM_TABLES
+            && checkpoint.stark_proofs.len() <= NUM_TABLES
+            && checkpoint.ctl_challenges.challenges.len() == config.num_challenges,
+        "The checkpoint does not have the shape of a proof of all tables."
+    );
+
+    let trace_poly_values = timed!(
+        timing,
+        "recover traces from trace commitments",
+        core::array::from_fn(|i| {
+            checkpoint.trace_commitments[i]
+                .polynomials
+                .par_iter()
+                .map(|p| p.clone().fft())
+                .collect()
+        })
+    );
+    finish_proof(
+        all_stark,
+        config,
+        checkpoint,
+        &trace_poly_values,
+        on_checkpoint,
+        timing,
+        abort_signal,
+    )
+}
+
+/// The state of `prove` once the traces are committed, and after the STARK proof of each table,
+/// from which `resume_proof` can finish the proof.
+pub struct AllProofCheckpoint<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// The parts of the `StarkConfig` which the proof depends on, which `resume_proof` checks.
+    pub num_challenges: usize,
+    pub fri_config: FriConfig,
+    pub public_values: PublicValues,
+    /// The commitments to the traces of all tables, from which `resume_proof` recovers the traces.
+    pub trace_commitments: Vec<PolynomialBatch<F, C, D>>,
+    pub ctl_challenges: GrandProductChallengeSet<F>,
+    /// The transcript after the proofs of `stark_proofs`.
+    pub challenger: Challenger<F, C::Hasher>,
+    /// The STARK proofs of the first tables, in the order of `Table::all`.
+    pub stark_proofs: Vec<StarkProofWithMetadata<F, C, D>>,
+}
+
+impl<F, C, const D: usize> AllProofCheckpoint<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// The number of tables whose STARK proofs are in the checkpoint.
+    pub fn num_proven_tables(&self) -> usize {
+        self.stark_proofs.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write_to(&mut buffer)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Serializes the checkpoint into `writer`, without buffering the commitments in memory, e.g.
+    /// to a file through `IoWriter`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        writer.write_usize(self.num_challenges)?;
+        writer.write_fri_config(&self.fri_config)?;
+        let public_values = serde_json::to_vec(&self.public_values).map_err(|_| IoError)?;
+        writer.write_usize(public_values.len())?;
+        writer.write_all(&public_values)?;
+        writer.write_usize(self.trace_commitments.len())?;
+        for commitment in &self.trace_commitments {
+            writer.write_polynomial_batch(commitment)?;
+        }
+        writer.write_usize(self.ctl_challenges.challenges.len())?;
+        for challenge in &self.ctl_challenges.challenges {
+            writer.write_field(challenge.beta)?;
+            writer.write_field(challenge.gamma)?;
+        }
+        writer.write_challenger(&self.challenger)?;
+        writer.write_usize(self.stark_proofs.len())?;
+        for proof in &self.stark_proofs {
+            proof.to_buffer(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let mut buffer = Buffer::new(&bytes);
+        let checkpoint = Self::from_buffer(&mut buffer).map_err(anyhow::Error::msg)?;
+        Ok(checkpoint)
+    }
+
+    fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let num_challenges = buffer.read_usize()?;
+        let fri_config = buffer.read_fri_config()?;
+        let public_values_len = buffer.read_usize()?;
+        let mut public_values = vec![0; public_values_len];
+        buffer.read_exact(&mut public_values)?;
+        let public_values = serde_json::from_slice(&public_values).map_err(|_| IoError)?;
+        let num_tables = buffer.read_usize()?;
+        let trace_commitments = (0..num_tables)
+            .map(|_| buffer.read_polynomial_batch())
+            .collect::<IoResult<Vec<_>>>()?;
+        let num_ctl_challenges = buffer.read_usize()?;
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: (0..num_ctl_challenges)
+                .map(|_| {
+                    Ok(GrandProductChallenge {
+                        beta: buffer.read_field()?,
+                        gamma: buffer.read_field()?,
+                    })
+                })
+                .collect::<IoResult<Vec<_>>>()?,
+        };
+        let challenger = buffer.read_challenger()?;
+        let num_proofs = buffer.read_usize()?;
+        let stark_proofs = (0..num_proofs)
+            .map(|_| StarkProofWithMetadata::from_buffer(buffer))
+            .collect::<IoResult<Vec<_>>>()?;
+
+        Ok(Self {
+            num_challenges,
+            fri_config,
+            public_values,
+            trace_commitments,
+            ctl_challenges,
+            challenger,
+            stark_proofs,
+        })
+    }
+}
+
 /// Compute all STARK proofs.
 pub(crate) fn prove_with_traces<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
     config: &StarkConfig,
     trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
     public_values: PublicValues,
+    mut on_checkpoint: impl FnMut(&AllProofCheckpoint<F, C, D>) -> Result<()>,
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
 ) -> Result<AllProof<F, C, D>>
@@ -99,13 +279,14 @@ where
                 timed!(
                     timing,
                     &format!("compute trace commitment for {:?}", table),
-                    PolynomialBatch::<F, C, D>::from_values(
+                    PolynomialBatch::<F, C, D>::from_values_with_storage(
                         trace.clone(),
                         rate_bits,
                         false,
                         cap_height,
                         timing,
                         None,
+                        &config.storage,
                     )
                 )
             })
@@ -127,186 +308,224 @@ where
 
     // Get challenges for the cross-table lookups.
     let ctl_challenges = get_grand_product_challenge_set(&mut challenger, config.num_challenges);
+
+    let checkpoint = AllProofCheckpoint {
+        num_challenges: config.num_challenges,
+        fri_config: config.fri_config.clone(),
+        public_values,
+        trace_commitments,
+        ctl_challenges,
+        challenger,
+        stark_proofs: Vec::new(),
+    };
+    on_checkpoint(&checkpoint)?;
+    finish_proof(
+        all_stark,
+        config,
+        checkpoint,
+        &trace_poly_values,
+        on_checkpoint,
+        timing,
+        abort_signal,
+    )
+}
+
+/// Generates a proof for each STARK which isn't proven in `checkpoint`, in the order of
+/// `Table::all`, calling `on_checkpoint` after each of them.
+/// At this stage, we have computed the trace polynomials commitments for the various STARKs,
+/// and the cross-table lookup challenges.
+/// - `trace_poly_values` are the trace values for each STARK.
+/// Each STARK uses its trace, its commitment and its cross-table lookup data to generate a proof.
+fn finish_proof<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &StarkConfig,
+    mut checkpoint: AllProofCheckpoint<F, C, D>,
+    trace_poly_values: &[Vec<PolynomialValues<F>>; NUM_TABLES],
+    mut on_checkpoint: impl FnMut(&AllProofCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+    abort_signal: Option<Arc<AtomicBool>>,
+) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
     // For each STARK, compute its cross-table lookup Z polynomials and get the associated `CtlData`.
     let ctl_data_per_table = timed!(
         timing,
         "compute CTL data",
         cross_table_lookup_data::<F, D>(
-            &trace_poly_values,
+            trace_poly_values,
             &all_stark.cross_table_lookups,
-            &ctl_challenges,
+            &checkpoint.ctl_challenges,
         )
     );
 
-    let stark_proofs = timed!(
-        timing,
-        "compute all proofs given commitments",
-        prove_with_commitments(
-            all_stark,
-            config,
-            &trace_poly_values,
-            trace_commitments,
-            ctl_data_per_table,
-            &mut challenger,
-            &ctl_challenges,
-            timing,
-            abort_signal,
-        )?
-    );
+    timed!(timing, "compute all proofs given commitments", {
+        for table in Table::all()
+            .into_iter()
+            .skip(checkpoint.num_proven_tables())
+        {
+            let proof = prove_table(
+                all_stark,
+                table,
+                config,
+                &trace_poly_values[table as usize],
+                &checkpoint.trace_commitments[table as usize],
+                &ctl_data_per_table[table as usize],
+                &checkpoint.ctl_challenges,
+                &mut checkpoint.challenger,
+                timing,
+                abort_signal.clone(),
+            )?;
+            checkpoint.stark_proofs.push(proof);
+            on_checkpoint(&checkpoint)?;
+        }
+    });
 
     #[cfg(test)]
     {
         check_ctls(
-            &trace_poly_values,
+            trace_poly_values,
             &all_stark.cross_table_lookups,
-            &get_memory_extra_looking_values(&public_values),
+            &get_memory_extra_looking_values(&checkpoint.public_values),
         );
     }
 
+    let stark_proofs = checkpoint
+        .stark_proofs
+        .try_into()
+        .expect("All tables are proven.");
     Ok(AllProof {
         stark_proofs,
-        ctl_challenges,
-        public_values,
+        ctl_challenges: checkpoint.ctl_challenges,
+        public_values: checkpoint.public_values,
     })
 }
 
-/// Generates a proof for each STARK.
-/// At this stage, we have computed the trace polynomials commitments for the various STARKs,
-/// and we have the cross-table lookup data for each table, including the associated challenges.
-/// - `trace_poly_values` are the trace values for each STARK.
-/// - `trace_commitments` are the trace polynomials commitments for each STARK.
-/// - `ctl_data_per_table` group all the cross-table lookup data for each STARK.
-/// Each STARK uses its associated data to generate a proof.
-fn prove_with_commitments<F, C, const D: usize>(
+/// Generates the proof of the STARK of `table`.
+fn prove_table<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
+    table: Table,
     config: &StarkConfig,
-    trace_poly_values: &[Vec<PolynomialValues<F>>; NUM_TABLES],
-    trace_commitments: Vec<PolynomialBatch<F, C, D>>,
-    ctl_data_per_table: [CtlData<F>; NUM_TABLES],
-    challenger: &mut Challenger<F, C::Hasher>,
+    trace_poly_values: &[PolynomialValues<F>],
+    trace_commitment: &PolynomialBatch<F, C, D>,
+    ctl_data: &CtlData<F>,
     ctl_challenges: &GrandProductChallengeSet<F>,
+    challenger: &mut Challenger<F, C::Hasher>,
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
-) -> Result<[StarkProofWithMetadata<F, C, D>; NUM_TABLES]>
+) -> Result<StarkProofWithMetadata<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let arithmetic_proof = timed!(
-        timing,
-        "prove Arithmetic STARK",
-        prove_single_table(
-            &all_stark.arithmetic_stark,
-            config,
-            &trace_poly_values[Table::Arithmetic as usize],
-            &trace_commitments[Table::Arithmetic as usize],
-            &ctl_data_per_table[Table::Arithmetic as usize],
-            ctl_challenges,
-            challenger,
+    match table {
+        Table::Arithmetic => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let byte_packing_proof = timed!(
-        timing,
-        "prove byte packing STARK",
-        prove_single_table(
-            &all_stark.byte_packing_stark,
-            config,
-            &trace_poly_values[Table::BytePacking as usize],
-            &trace_commitments[Table::BytePacking as usize],
-            &ctl_data_per_table[Table::BytePacking as usize],
-            ctl_challenges,
-            challenger,
+            "prove Arithmetic STARK",
+            prove_single_table(
+                &all_stark.arithmetic_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::BytePacking => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let cpu_proof = timed!(
-        timing,
-        "prove CPU STARK",
-        prove_single_table(
-            &all_stark.cpu_stark,
-            config,
-            &trace_poly_values[Table::Cpu as usize],
-            &trace_commitments[Table::Cpu as usize],
-            &ctl_data_per_table[Table::Cpu as usize],
-            ctl_challenges,
-            challenger,
+            "prove byte packing STARK",
+            prove_single_table(
+                &all_stark.byte_packing_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::Cpu => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let keccak_proof = timed!(
-        timing,
-        "prove Keccak STARK",
-        prove_single_table(
-            &all_stark.keccak_stark,
-            config,
-            &trace_poly_values[Table::Keccak as usize],
-            &trace_commitments[Table::Keccak as usize],
-            &ctl_data_per_table[Table::Keccak as usize],
-            ctl_challenges,
-            challenger,
+            "prove CPU STARK",
+            prove_single_table(
+                &all_stark.cpu_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::Keccak => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let keccak_sponge_proof = timed!(
-        timing,
-        "prove Keccak sponge STARK",
-        prove_single_table(
-            &all_stark.keccak_sponge_stark,
-            config,
-            &trace_poly_values[Table::KeccakSponge as usize],
-            &trace_commitments[Table::KeccakSponge as usize],
-            &ctl_data_per_table[Table::KeccakSponge as usize],
-            ctl_challenges,
-            challenger,
+            "prove Keccak STARK",
+            prove_single_table(
+                &all_stark.keccak_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::KeccakSponge => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let logic_proof = timed!(
-        timing,
-        "prove logic STARK",
-        prove_single_table(
-            &all_stark.logic_stark,
-            config,
-            &trace_poly_values[Table::Logic as usize],
-            &trace_commitments[Table::Logic as usize],
-            &ctl_data_per_table[Table::Logic as usize],
-            ctl_challenges,
-            challenger,
+            "prove Keccak sponge STARK",
+            prove_single_table(
+                &all_stark.keccak_sponge_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::Logic => timed!(
             timing,
-            abort_signal.clone(),
-        )?
-    );
-    let memory_proof = timed!(
-        timing,
-        "prove memory STARK",
-        prove_single_table(
-            &all_stark.memory_stark,
-            config,
-            &trace_poly_values[Table::Memory as usize],
-            &trace_commitments[Table::Memory as usize],
-            &ctl_data_per_table[Table::Memory as usize],
-            ctl_challenges,
-            challenger,
+            "prove logic STARK",
+            prove_single_table(
+                &all_stark.logic_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+        Table::Memory => timed!(
             timing,
-            abort_signal,
-        )?
-    );
-
-    Ok([
-        arithmetic_proof,
-        byte_packing_proof,
-        cpu_proof,
-        keccak_proof,
-        keccak_sponge_proof,
-        logic_proof,
-        memory_proof,
-    ])
+            "prove memory STARK",
+            prove_single_table(
+                &all_stark.memory_stark,
+                config,
+                trace_poly_values,
+                trace_commitment,
+                ctl_data,
+                ctl_challenges,
+                challenger,
+                timing,
+                abort_signal,
+            )
+        ),
+    }
 }
 
 /// Computes a proof for a single STARK table, including:
@@ -387,13 +606,14 @@ where
     let auxiliary_polys_commitment = timed!(
         timing,
         "compute auxiliary polynomials commitment",
-        PolynomialBatch::from_values(
+        PolynomialBatch::from_values_with_storage(
             auxiliary_polys,
             rate_bits,
             false,
             config.fri_config.cap_height,
             timing,
             None,
+            &config.storage,
         )
     );
 
@@ -455,13 +675,14 @@ where
     let quotient_commitment = timed!(
         timing,
         "compute quotient commitment",
-        PolynomialBatch::from_coeffs(
+        PolynomialBatch::from_coeffs_with_storage(
             all_quotient_chunks,
             rate_bits,
             false,
             config.fri_config.cap_height,
             timing,
             None,
+            &config.storage,
         )
     );
     // Observe the quotient polynomials Merkle cap.
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
+use ethereum_types::{BigEndianHash, H256};
+use keccak_hash::keccak;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::KeccakGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+use plonky2_evm::all_stark::AllStark;
+use plonky2_evm::config::StarkConfig;
+use plonky2_evm::generation::{GenerationInputs, TrieInputs};
+use plonky2_evm::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use plonky2_evm::prover::{prove_with_checkpoints, resume_proof, AllProofCheckpoint};
+use plonky2_evm::verifier::verify_proof;
+use plonky2_evm::Node;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = KeccakGoldilocksConfig;
+
+/// Interrupts the proof of an empty list of transactions after the STARK proofs of the first
+/// tables, and finishes it from the serialized checkpoint.
+#[test]
+fn test_resume_proof() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+
+    let block_metadata = BlockMetadata {
+        block_number: 1.into(),
+        ..Default::default()
+    };
+
+    let state_trie = HashedPartialTrie::from(Node::Empty);
+    let transactions_trie = HashedPartialTrie::from(Node::Empty);
+    let receipts_trie = HashedPartialTrie::from(Node::Empty);
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    let trie_roots_after = TrieRoots {
+        state_root: state_trie.hash(),
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+    let mut initial_block_hashes = vec![H256::default(); 256];
+    initial_block_hashes[255] = H256::from_uint(&0x200.into());
+    let inputs = GenerationInputs {
+        signed_txn: None,
+        withdrawals: vec![],
+        tries: TrieInputs {
+            state_trie,
+            transactions_trie,
+            receipts_trie,
+            storage_tries: vec![],
+        },
+        trie_roots_after,
+        contract_code,
+        checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: initial_block_hashes,
+            cur_hash: H256::default(),
+        },
+    };
+
+    // Stop the prover once the first three tables are proven, as if it had been preempted.
+    let mut checkpoint_bytes = Vec::new();
+    let mut timing = TimingTree::new("prove", log::Level::Debug);
+    let interrupted = prove_with_checkpoints::<F, C, D>(
+        &all_stark,
+        &config,
+        inputs,
+        |checkpoint| {
+            checkpoint_bytes = checkpoint.to_bytes();
+            anyhow::ensure!(checkpoint.num_proven_tables() < 3, "Preempted.");
+            Ok(())
+        },
+        &mut timing,
+        None,
+    );
+    assert!(interrupted.is_err());
+
+    let checkpoint = AllProofCheckpoint::<F, C, D>::from_bytes(checkpoint_bytes)?;
+    assert_eq!(checkpoint.num_proven_tables(), 3);
+    let proof = resume_proof(
+        &all_stark,
+        &config,
+        checkpoint,
+        |_| Ok(()),
+        &mut timing,
+        None,
+    )?;
+    timing.filter(Duration::from_millis(100)).print();
+
+    verify_proof(&all_stark, proof, &config)
+}
+
+fn init_logger() {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+}
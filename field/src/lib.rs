@@ -3,7 +3,6 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::len_without_is_empty)]
 #![allow(clippy::needless_range_loop)]
-#![feature(stdsimd)]
 #![feature(specialization)]
 #![cfg_attr(not(test), no_std)]
 
@@ -12,6 +11,8 @@ extern crate alloc;
 pub(crate) mod arch;
 
 pub mod batch_util;
+pub mod bn254_base;
+pub mod bn254_scalar;
 pub mod cosets;
 pub mod extension;
 pub mod fft;
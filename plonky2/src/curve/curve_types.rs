@@ -0,0 +1,106 @@
+use core::fmt::Debug;
+use core::ops::{Add, Neg};
+
+use crate::field::ops::Square;
+use crate::field::types::{Field, PrimeField};
+
+/// A short Weierstrass curve `y^2 = x^3 + A x + B` over `BaseField`, with a subgroup of prime
+/// order generated by `GENERATOR_AFFINE`.
+pub trait Curve: 'static + Sync + Sized + Copy + Debug + Eq {
+    type BaseField: PrimeField;
+    type ScalarField: PrimeField;
+
+    const A: Self::BaseField;
+    const B: Self::BaseField;
+
+    const GENERATOR_AFFINE: AffinePoint<Self>;
+}
+
+/// A point on a short Weierstrass curve, represented in affine coordinates.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AffinePoint<C: Curve> {
+    pub x: C::BaseField,
+    pub y: C::BaseField,
+    pub zero: bool,
+}
+
+impl<C: Curve> AffinePoint<C> {
+    pub const ZERO: Self = Self {
+        x: C::BaseField::ZERO,
+        y: C::BaseField::ZERO,
+        zero: true,
+    };
+
+    pub const fn nonzero(x: C::BaseField, y: C::BaseField) -> Self {
+        Self { x, y, zero: false }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let Self { x, y, zero } = *self;
+        zero || y.square() == x.cube() + C::A * x + C::B
+    }
+
+    pub fn double(&self) -> Self {
+        let Self { x, y, zero } = *self;
+        if zero || y.is_zero() {
+            return Self::ZERO;
+        }
+
+        let lambda = (x.square().triple() + C::A) / y.double();
+        let x3 = lambda.square() - x.double();
+        let y3 = lambda * (x - x3) - y;
+        Self::nonzero(x3, y3)
+    }
+
+    /// Computes `k * self` by double-and-add.
+    pub fn scalar_mul(&self, k: C::ScalarField) -> Self {
+        let k = k.to_canonical_biguint();
+        let mut result = Self::ZERO;
+        for i in (0..k.bits()).rev() {
+            result = result.double();
+            if k.bit(i) {
+                result = result + *self;
+            }
+        }
+        result
+    }
+}
+
+impl<C: Curve> Add for AffinePoint<C> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        if self.zero {
+            return rhs;
+        }
+        if rhs.zero {
+            return self;
+        }
+        if self.x == rhs.x {
+            return if self.y == rhs.y {
+                self.double()
+            } else {
+                Self::ZERO
+            };
+        }
+
+        let lambda = (rhs.y - self.y) / (rhs.x - self.x);
+        let x3 = lambda.square() - self.x - rhs.x;
+        let y3 = lambda * (self.x - x3) - self.y;
+        Self::nonzero(x3, y3)
+    }
+}
+
+impl<C: Curve> Neg for AffinePoint<C> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let Self { x, y, zero } = self;
+        Self { x, y: -y, zero }
+    }
+}
+
+/// Converts a base field element to a scalar field element, reducing it if needed.
+pub fn base_to_scalar<C: Curve>(x: C::BaseField) -> C::ScalarField {
+    C::ScalarField::from_noncanonical_biguint(x.to_canonical_biguint() % C::ScalarField::order())
+}
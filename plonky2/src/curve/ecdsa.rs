@@ -0,0 +1,74 @@
+use crate::curve::curve_types::{base_to_scalar, AffinePoint, Curve};
+use crate::field::types::{Field, Sample};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ECDSASecretKey<C: Curve>(pub C::ScalarField);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ECDSAPublicKey<C: Curve>(pub AffinePoint<C>);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ECDSASignature<C: Curve> {
+    pub r: C::ScalarField,
+    pub s: C::ScalarField,
+}
+
+impl<C: Curve> ECDSASecretKey<C> {
+    pub fn to_public(&self) -> ECDSAPublicKey<C> {
+        ECDSAPublicKey(C::GENERATOR_AFFINE.scalar_mul(self.0))
+    }
+}
+
+/// Signs the message hash `msg` with a random nonce.
+pub fn sign_message<C: Curve>(msg: C::ScalarField, sk: ECDSASecretKey<C>) -> ECDSASignature<C> {
+    loop {
+        let k = C::ScalarField::rand();
+        let rr = C::GENERATOR_AFFINE.scalar_mul(k);
+        let r = base_to_scalar::<C>(rr.x);
+        if k.is_zero() || r.is_zero() {
+            continue;
+        }
+        let s = k.inverse() * (msg + r * sk.0);
+        if !s.is_zero() {
+            return ECDSASignature { r, s };
+        }
+    }
+}
+
+pub fn verify_message<C: Curve>(
+    msg: C::ScalarField,
+    sig: ECDSASignature<C>,
+    pk: ECDSAPublicKey<C>,
+) -> bool {
+    let ECDSASignature { r, s } = sig;
+    if r.is_zero() || s.is_zero() || pk.0.zero || !pk.0.is_valid() {
+        return false;
+    }
+
+    let s_inv = s.inverse();
+    let u1 = msg * s_inv;
+    let u2 = r * s_inv;
+    let point = C::GENERATOR_AFFINE.scalar_mul(u1) + pk.0.scalar_mul(u2);
+    !point.zero && base_to_scalar::<C>(point.x) == r
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::ecdsa::{sign_message, verify_message, ECDSASecretKey};
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::Sample;
+
+    #[test]
+    fn test_ecdsa_native() {
+        type C = Secp256K1;
+
+        let msg = Secp256K1Scalar::rand();
+        let sk = ECDSASecretKey::<C>(Secp256K1Scalar::rand());
+        let pk = sk.to_public();
+
+        let sig = sign_message(msg, sk);
+        assert!(verify_message(msg, sig, pk));
+        assert!(!verify_message(msg + Secp256K1Scalar::rand(), sig, pk));
+    }
+}
@@ -0,0 +1,124 @@
+//! The GLV endomorphism of secp256k1, `(x, y) -> (beta x, y)`, which acts as multiplication by
+//! `lambda` on the prime-order subgroup. It lets us split a 256-bit scalar `k` into two scalars of
+//! about 128 bits each with `k = k1 + lambda k2`, halving the number of doublings needed.
+
+use num::{BigInt, BigUint, Integer, Signed};
+
+use crate::curve::curve_types::AffinePoint;
+use crate::curve::secp256k1::Secp256K1;
+use crate::field::secp256k1_base::Secp256K1Base;
+use crate::field::secp256k1_scalar::Secp256K1Scalar;
+use crate::field::types::{Field, PrimeField};
+
+/// A primitive cube root of unity in the base field.
+pub const GLV_BETA: Secp256K1Base = Secp256K1Base([
+    0x3EC693D68E6AFA40,
+    0x630FB68AED0A766A,
+    0x919BB86153CBCB16,
+    0x851695D49A83F8EF,
+]);
+
+/// The cube root of unity in the scalar field matching `GLV_BETA`.
+pub const GLV_LAMBDA: Secp256K1Scalar = Secp256K1Scalar([
+    0xE0CFC810B51283CE,
+    0xA880B9FC8EC739C2,
+    0x5AD9E3FD77ED9BA4,
+    0xAC9C52B33FA3CF1F,
+]);
+
+/// The number of bits of the scalars returned by `decompose_secp256k1_scalar`.
+pub const GLV_SCALAR_BITS: usize = 128;
+
+// A reduced basis `(A1, -B1_NEG), (A2, B2)` of the lattice of pairs `(a, b)` with
+// `a + b lambda = 0 (mod n)`, found with the extended Euclidean algorithm.
+const A1: u128 = 0xE4437ED6010E88286F547FA90ABFE4C3;
+const B1_NEG: u128 = 0x3086D221A7D46BCDE86C90E49284EB15;
+const A2: u128 = 0x3086D221A7D46BCDE86C90E49284EB15;
+const B2_HEX: &str = "114CA50F7A8E2F3F657C1108D9D44CFD8";
+
+/// Splits `k` into `(|k1|, |k2|, k1 < 0, k2 < 0)` such that `k = k1 + lambda k2`, where both
+/// `|k1|` and `|k2|` have at most `GLV_SCALAR_BITS` bits.
+pub fn decompose_secp256k1_scalar(
+    k: Secp256K1Scalar,
+) -> (Secp256K1Scalar, Secp256K1Scalar, bool, bool) {
+    let n = BigInt::from(Secp256K1Scalar::order());
+    let k = BigInt::from(k.to_canonical_biguint());
+    let a1 = BigInt::from(A1);
+    let b1 = -BigInt::from(B1_NEG);
+    let a2 = BigInt::from(A2);
+    let b2 = BigInt::parse_bytes(B2_HEX.as_bytes(), 16).unwrap();
+
+    // The basis has determinant n, so (k, 0) = c1 (a1, b1) + c2 (a2, b2) with the rational
+    // coefficients below. Rounding them gives a lattice point close to (k, 0).
+    let round_div = |x: BigInt| (x * 2u32 + &n).div_floor(&(&n * 2u32));
+    let c1 = round_div(&b2 * &k);
+    let c2 = round_div(-&b1 * &k);
+
+    let k1 = &k - &c1 * &a1 - &c2 * &a2;
+    let k2 = -&c1 * &b1 - &c2 * &b2;
+    debug_assert!(k1.bits() <= GLV_SCALAR_BITS as u64 && k2.bits() <= GLV_SCALAR_BITS as u64);
+
+    let to_scalar = |x: &BigInt| Secp256K1Scalar::from_noncanonical_biguint(abs_biguint(x));
+    (
+        to_scalar(&k1),
+        to_scalar(&k2),
+        k1.is_negative(),
+        k2.is_negative(),
+    )
+}
+
+fn abs_biguint(x: &BigInt) -> BigUint {
+    x.abs().to_biguint().unwrap()
+}
+
+/// Computes `k * p` using the GLV decomposition of `k`.
+pub fn glv_mul(p: AffinePoint<Secp256K1>, k: Secp256K1Scalar) -> AffinePoint<Secp256K1> {
+    let (k1, k2, k1_neg, k2_neg) = decompose_secp256k1_scalar(k);
+    let p_beta = AffinePoint::nonzero(p.x * GLV_BETA, p.y);
+    let p = if k1_neg { -p } else { p };
+    let p_beta = if k2_neg { -p_beta } else { p_beta };
+    p.scalar_mul(k1) + p_beta.scalar_mul(k2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::curve_types::{AffinePoint, Curve};
+    use crate::curve::glv::{
+        decompose_secp256k1_scalar, glv_mul, GLV_BETA, GLV_LAMBDA, GLV_SCALAR_BITS,
+    };
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::{Field, PrimeField, Sample};
+
+    #[test]
+    fn test_endomorphism() {
+        let g = Secp256K1::GENERATOR_AFFINE;
+        assert_eq!(GLV_BETA.cube(), Field::ONE);
+        assert_eq!(GLV_LAMBDA.cube(), Field::ONE);
+        assert_eq!(
+            g.scalar_mul(GLV_LAMBDA),
+            AffinePoint::nonzero(g.x * GLV_BETA, g.y)
+        );
+    }
+
+    #[test]
+    fn test_glv_decompose() {
+        for _ in 0..100 {
+            let k = Secp256K1Scalar::rand();
+            let (k1, k2, k1_neg, k2_neg) = decompose_secp256k1_scalar(k);
+            assert!(k1.to_canonical_biguint().bits() <= GLV_SCALAR_BITS as u64);
+            assert!(k2.to_canonical_biguint().bits() <= GLV_SCALAR_BITS as u64);
+
+            let k1 = if k1_neg { -k1 } else { k1 };
+            let k2 = if k2_neg { -k2 } else { k2 };
+            assert_eq!(k1 + GLV_LAMBDA * k2, k);
+        }
+    }
+
+    #[test]
+    fn test_glv_mul() {
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let k = Secp256K1Scalar::rand();
+        assert_eq!(glv_mul(g, k), g.scalar_mul(k));
+    }
+}
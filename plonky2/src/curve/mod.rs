@@ -0,0 +1,7 @@
+//! Native elliptic curve arithmetic, used to generate witnesses for the curve gadgets and as a
+//! reference implementation in tests.
+
+pub mod curve_types;
+pub mod ecdsa;
+pub mod glv;
+pub mod secp256k1;
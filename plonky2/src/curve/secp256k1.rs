@@ -0,0 +1,59 @@
+use crate::curve::curve_types::{AffinePoint, Curve};
+use crate::field::secp256k1_base::Secp256K1Base;
+use crate::field::secp256k1_scalar::Secp256K1Scalar;
+use crate::field::types::Field;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Secp256K1;
+
+impl Curve for Secp256K1 {
+    type BaseField = Secp256K1Base;
+    type ScalarField = Secp256K1Scalar;
+
+    const A: Secp256K1Base = Secp256K1Base::ZERO;
+    const B: Secp256K1Base = Secp256K1Base([7, 0, 0, 0]);
+
+    const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint::nonzero(
+        Secp256K1Base([
+            0x59F2815B16F81798,
+            0x029BFCDB2DCE28D9,
+            0x55A06295CE870B07,
+            0x79BE667EF9DCBBAC,
+        ]),
+        Secp256K1Base([
+            0x9C47D08FFB10D4B8,
+            0xFD17B448A6855419,
+            0x5DA4FBFC0E1108A8,
+            0x483ADA7726A3C465,
+        ]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::curve_types::{AffinePoint, Curve};
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::{Field, Sample};
+
+    #[test]
+    fn test_generator() {
+        let g = Secp256K1::GENERATOR_AFFINE;
+        assert!(g.is_valid());
+
+        let neg_one = Secp256K1Scalar::NEG_ONE;
+        assert_eq!(g.scalar_mul(neg_one), -g);
+        assert_eq!(g.scalar_mul(neg_one) + g, AffinePoint::ZERO);
+    }
+
+    #[test]
+    fn test_scalar_mul_linear() {
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let a = Secp256K1Scalar::rand();
+        let b = Secp256K1Scalar::rand();
+        let lhs = g.scalar_mul(a) + g.scalar_mul(b);
+        assert!(lhs.is_valid());
+        assert_eq!(lhs, g.scalar_mul(a + b));
+        assert_eq!(g.scalar_mul(a).scalar_mul(b), g.scalar_mul(a * b));
+    }
+}
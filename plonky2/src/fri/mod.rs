@@ -10,8 +10,10 @@ pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
 pub mod reduction_strategies;
+#[cfg(feature = "std")]
+pub mod security;
 pub mod structure;
-mod validate_shape;
+pub(crate) mod validate_shape;
 pub mod verifier;
 pub mod witness_util;
 
@@ -100,4 +102,37 @@ impl FriParams {
     pub fn final_poly_len(&self) -> usize {
         1 << self.final_poly_bits()
     }
+
+    /// Estimates the size in bytes of a serialized FRI proof, with initial oracles whose Merkle
+    /// leaves have `leaf_sizes` field elements, hashes of `hash_size` bytes, and an extension field
+    /// of degree `extension_degree`. The lengths prefixed to serialized vectors are ignored.
+    pub fn estimate_proof_size(
+        &self,
+        leaf_sizes: &[usize],
+        hash_size: usize,
+        extension_degree: usize,
+    ) -> usize {
+        // Field elements are serialized as `u64`s.
+        let field_size = 8;
+        let ext_size = extension_degree * field_size;
+        let cap_height = self.config.cap_height;
+        let cap_size = self.config.num_cap_elements() * hash_size;
+
+        let lde_bits = self.lde_bits();
+        let initial_trees_size = leaf_sizes.iter().sum::<usize>() * field_size
+            + leaf_sizes.len() * (lde_bits - cap_height) * hash_size;
+        let mut steps_size = 0;
+        let mut tree_bits = lde_bits;
+        for &arity_bits in &self.reduction_arity_bits {
+            tree_bits -= arity_bits;
+            steps_size += (1 << arity_bits) * ext_size + (tree_bits - cap_height) * hash_size;
+        }
+
+        // The caps of the reduction steps, the query rounds, the final polynomial and the
+        // proof-of-work witness.
+        self.reduction_arity_bits.len() * cap_size
+            + self.config.num_query_rounds * (initial_trees_size + steps_size)
+            + self.final_poly_len() * ext_size
+            + field_size
+    }
 }
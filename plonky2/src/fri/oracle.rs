@@ -14,11 +14,12 @@ use crate::fri::prover::fri_proof;
 use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
-use crate::hash::merkle_tree::MerkleTree;
+use crate::hash::merkle_tree::{MerkleLeaves, MerkleTree};
 use crate::iop::challenger::Challenger;
 use crate::plonk::config::GenericConfig;
 use crate::timed;
 use crate::util::reducing::ReducingFactor;
+use crate::util::storage::Storage;
 use crate::util::timing::TimingTree;
 use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place, transpose};
 
@@ -61,6 +62,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_values_with_storage(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            &Storage::Memory,
+        )
+    }
+
+    /// Like `from_values`, but stores the LDEs and the Merkle digests as described by `storage`.
+    pub fn from_values_with_storage(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        storage: &Storage,
     ) -> Self {
         let coeffs = timed!(
             timing,
@@ -68,13 +90,14 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
         );
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_storage(
             coeffs,
             rate_bits,
             blinding,
             cap_height,
             timing,
             fft_root_table,
+            storage,
         )
     }
 
@@ -87,19 +110,49 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
     ) -> Self {
-        let degree = polynomials[0].len();
-        let lde_values = timed!(
+        Self::from_coeffs_with_storage(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
             timing,
-            "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
-        );
+            fft_root_table,
+            &Storage::Memory,
+        )
+    }
 
-        let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
-        reverse_index_bits_in_place(&mut leaves);
+    /// Like `from_coeffs`, but stores the LDEs and the Merkle digests as described by `storage`.
+    pub fn from_coeffs_with_storage(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        storage: &Storage,
+    ) -> Self {
+        let degree = polynomials[0].len();
+        let leaves = if *storage == Storage::Memory {
+            let lde_values = timed!(
+                timing,
+                "FFT + blinding",
+                Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            );
+
+            let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
+            reverse_index_bits_in_place(&mut leaves);
+            MerkleLeaves::Vecs(leaves)
+        } else {
+            timed!(
+                timing,
+                "FFT + blinding into storage",
+                Self::lde_matrix(&polynomials, rate_bits, blinding, fft_root_table, storage)
+            )
+        };
         let merkle_tree = timed!(
             timing,
             "build Merkle tree",
-            MerkleTree::new(leaves, cap_height)
+            MerkleTree::new_with_storage(leaves, cap_height, storage)
         );
 
         Self {
@@ -138,6 +191,71 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .collect()
     }
 
+    /// Writes the LDEs of `polynomials`, salted if blinding, to a matrix stored as described by
+    /// `storage`, whose rows are the Merkle leaves in bit-reversed order. In that order, each block
+    /// of `degree` rows holds the evaluations on a coset of the subgroup of order `degree`, so the
+    /// matrix is written in a single pass, one block of rows at a time, and only the evaluations
+    /// of the current block are held in memory.
+    fn lde_matrix(
+        polynomials: &[PolynomialCoeffs<F>],
+        rate_bits: usize,
+        blinding: bool,
+        fft_root_table: Option<&FftRootTable<F>>,
+        storage: &Storage,
+    ) -> MerkleLeaves<F> {
+        let degree = polynomials[0].len();
+        let degree_bits = log2_strict(degree);
+        let lde_bits = degree_bits + rate_bits;
+        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        let leaf_len = polynomials.len() + salt_size;
+        for p in polynomials {
+            assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
+        }
+
+        // The FFTs of the blocks are over the subgroup of order `degree`, whose roots are the first
+        // rows of the table of the LDE subgroup.
+        let root_table = match fft_root_table {
+            Some(table) => table[..degree_bits].to_vec(),
+            None => crate::field::fft::fft_root_table(degree),
+        };
+        let lde_generator = F::primitive_root_of_unity(lde_bits);
+
+        let values = unsafe {
+            // SAFETY: Each row is filled with the LDEs of all polynomials, then the salt.
+            storage.alloc_with(leaf_len << lde_bits, |matrix| {
+                for (k, block) in matrix.chunks_exact_mut(leaf_len << degree_bits).enumerate() {
+                    // Row `t` of block `k` is the leaf at index `rev(t) * 2^rate_bits + rev(k)`.
+                    let shift =
+                        F::coset_shift() * lde_generator.exp_u64(reverse_bits(k, rate_bits) as u64);
+                    let evals = polynomials
+                        .par_iter()
+                        .map(|p| {
+                            p.coset_fft_with_options(shift, None, Some(&root_table))
+                                .values
+                        })
+                        .collect::<Vec<_>>();
+                    block
+                        .par_chunks_exact_mut(leaf_len)
+                        .enumerate()
+                        .for_each(|(t, row)| {
+                            let j = reverse_bits(t, degree_bits);
+                            for (value, evals) in row.iter_mut().zip(&evals) {
+                                value.write(evals[j]);
+                            }
+                            for (value, salt) in row[polynomials.len()..]
+                                .iter_mut()
+                                .zip(F::rand_vec(salt_size))
+                            {
+                                value.write(salt);
+                            }
+                        });
+                }
+            })
+        };
+
+        MerkleLeaves::Matrix { values, leaf_len }
+    }
+
     /// Fetches LDE values at the `index * step`th point.
     pub fn get_lde_values(&self, index: usize, step: usize) -> &[F] {
         let index = index * step;
@@ -232,3 +350,66 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         fri_proof
     }
 }
+
+#[cfg(all(test, feature = "mmap", unix))]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_mmap_storage() {
+        let degree_log = 6;
+        let rate_bits = 2;
+        let cap_height = 2;
+        let values = (0..40)
+            .map(|_| PolynomialValues::new(F::rand_vec(1 << degree_log)))
+            .collect::<Vec<_>>();
+
+        let commit = |storage: &Storage, blinding: bool| {
+            PolynomialBatch::<F, C, D>::from_values_with_storage(
+                values.clone(),
+                rate_bits,
+                blinding,
+                cap_height,
+                &mut TimingTree::default(),
+                None,
+                storage,
+            )
+        };
+        let storage = Storage::Mmap(std::env::temp_dir());
+        let in_memory = commit(&Storage::Memory, false);
+        let mapped = commit(&storage, false);
+        assert!(matches!(
+            mapped.merkle_tree.leaves,
+            MerkleLeaves::Matrix { .. }
+        ));
+        assert_eq!(mapped.merkle_tree.cap, in_memory.merkle_tree.cap);
+        let root_table = crate::field::fft::fft_root_table(1 << (degree_log + rate_bits));
+        let with_root_table = PolynomialBatch::<F, C, D>::from_values_with_storage(
+            values.clone(),
+            rate_bits,
+            false,
+            cap_height,
+            &mut TimingTree::default(),
+            Some(&root_table),
+            &storage,
+        );
+        assert_eq!(with_root_table.merkle_tree.cap, in_memory.merkle_tree.cap);
+        for i in 0..1 << (degree_log + rate_bits) {
+            assert_eq!(mapped.get_lde_values(i, 1), in_memory.get_lde_values(i, 1));
+            assert_eq!(mapped.merkle_tree.prove(i), in_memory.merkle_tree.prove(i));
+        }
+
+        let blinded = commit(&storage, true);
+        assert_eq!(
+            blinded.merkle_tree.leaves[0].len(),
+            values.len() + SALT_SIZE
+        );
+        assert_eq!(blinded.get_lde_values(5, 1), in_memory.get_lde_values(5, 1));
+    }
+}
@@ -0,0 +1,271 @@
+//! Estimates of the soundness and costs of FRI proofs, to choose a `FriConfig`.
+
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::fri::{FriConfig, FriParams};
+use crate::hash::hash_types::RichField;
+use crate::plonk::config::Hasher;
+
+/// The largest `rate_bits` considered by `FriProofShape::search`.
+const MAX_RATE_BITS: usize = 8;
+/// The largest `proof_of_work_bits` considered by `FriProofShape::search`.
+const MAX_PROOF_OF_WORK_BITS: u32 = 24;
+/// The largest `num_query_rounds` considered by `FriProofShape::search`.
+const MAX_QUERY_ROUNDS: usize = 256;
+
+/// The soundness model in which security is measured.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SecurityModel {
+    /// Assumes the conjecture of the ethSTARK paper, that each query round brings `rate_bits` bits
+    /// of security, up to the size of the field relative to the LDE domain.
+    Conjectured,
+    /// Uses the bounds proven in the Johnson bound regime, in "Proximity Gaps for Reed-Solomon
+    /// Codes" by Ben-Sasson et al., which require many more query rounds.
+    Proven,
+}
+
+/// The cost to minimize in `FriProofShape::search`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FriCost {
+    ProofSize,
+    ProverTime,
+}
+
+/// What a FRI proof is about, on top of its `FriConfig`: the degree of the committed
+/// polynomials, the fields and hash in use, and the number of polynomials in each initial oracle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FriProofShape {
+    pub degree_bits: usize,
+    /// The number of bits of the extension field challenges are drawn from.
+    pub field_bits: f64,
+    pub extension_degree: usize,
+    /// The size of a serialized hash, in bytes.
+    pub hash_size: usize,
+    /// The number of field elements in the Merkle leaves of each initial oracle, salt included.
+    pub leaf_sizes: Vec<usize>,
+    /// Whether the initial oracles are salted, as in zero-knowledge proofs.
+    pub hiding: bool,
+}
+
+/// The estimated soundness and costs of FRI proofs with a given `FriConfig`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FriEstimate {
+    pub conjectured_bits: f64,
+    pub proven_bits: f64,
+    /// The size of a serialized FRI proof, in bytes.
+    pub proof_size: usize,
+    /// A machine-independent estimate of proving time: the number of field elements hashed, in
+    /// the initial oracles and while grinding for proof-of-work.
+    pub prover_cost: usize,
+}
+
+impl FriProofShape {
+    pub fn new<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+        degree_bits: usize,
+        leaf_sizes: Vec<usize>,
+        hiding: bool,
+    ) -> Self {
+        Self {
+            degree_bits,
+            field_bits: D as f64 * (F::ORDER as f64).log2(),
+            extension_degree: D,
+            hash_size: H::HASH_SIZE,
+            leaf_sizes,
+            hiding,
+        }
+    }
+
+    pub fn fri_params(&self, config: &FriConfig) -> FriParams {
+        config.fri_params(self.degree_bits, self.hiding)
+    }
+
+    pub fn security_bits(&self, config: &FriConfig, model: SecurityModel) -> f64 {
+        let params = self.fri_params(config);
+        match model {
+            SecurityModel::Conjectured => conjectured_security_bits(&params, self.field_bits),
+            SecurityModel::Proven => {
+                let num_polys = self.leaf_sizes.iter().sum();
+                proven_security_bits(&params, self.field_bits, num_polys)
+            }
+        }
+    }
+
+    pub fn estimate(&self, config: &FriConfig) -> FriEstimate {
+        let params = self.fri_params(config);
+        let num_polys = self.leaf_sizes.iter().sum();
+        FriEstimate {
+            conjectured_bits: conjectured_security_bits(&params, self.field_bits),
+            proven_bits: proven_security_bits(&params, self.field_bits, num_polys),
+            proof_size: params.estimate_proof_size(
+                &self.leaf_sizes,
+                self.hash_size,
+                self.extension_degree,
+            ),
+            prover_cost: estimate_fri_prover_cost(&params, num_polys),
+        }
+    }
+
+    /// Searches for the configuration reaching `target_bits` of security in `model` at the lowest
+    /// `cost`. The cap height and reduction strategy are those of `base`, while the rate, the
+    /// number of query rounds and the proof-of-work bits are searched. Returns `None` if no
+    /// configuration reaches the target, e.g. because the field is too small.
+    pub fn search(
+        &self,
+        base: &FriConfig,
+        target_bits: f64,
+        model: SecurityModel,
+        cost: FriCost,
+    ) -> Option<(FriConfig, FriEstimate)> {
+        let mut best: Option<(FriConfig, FriEstimate)> = None;
+        for rate_bits in 1..=MAX_RATE_BITS {
+            for proof_of_work_bits in 0..=MAX_PROOF_OF_WORK_BITS {
+                // Security grows with the number of query rounds, so the fewest rounds reaching
+                // the target are the cheapest.
+                let config = (1..=MAX_QUERY_ROUNDS)
+                    .map(|num_query_rounds| FriConfig {
+                        rate_bits,
+                        proof_of_work_bits,
+                        num_query_rounds,
+                        ..base.clone()
+                    })
+                    .find(|config| self.security_bits(config, model) >= target_bits);
+                let Some(config) = config else {
+                    continue;
+                };
+                let estimate = self.estimate(&config);
+                let key = |e: &FriEstimate| match cost {
+                    FriCost::ProofSize => (e.proof_size, e.prover_cost),
+                    FriCost::ProverTime => (e.prover_cost, e.proof_size),
+                };
+                let is_better = match &best {
+                    Some((_, b)) => key(&estimate) < key(b),
+                    None => true,
+                };
+                if is_better {
+                    best = Some((config, estimate));
+                }
+            }
+        }
+        best
+    }
+}
+
+/// The conjectured security of FRI in bits: each query round brings `rate_bits` bits, and
+/// proof-of-work adds its own bits, up to the probability that a random challenge from a field of
+/// `field_bits` bits falls in the LDE domain.
+pub fn conjectured_security_bits(params: &FriParams, field_bits: f64) -> f64 {
+    let config = &params.config;
+    let query_bits =
+        (config.rate_bits * config.num_query_rounds) as f64 + config.proof_of_work_bits as f64;
+    query_bits.min(field_bits - params.lde_bits() as f64)
+}
+
+/// The proven security of FRI in bits, in the Johnson bound regime, for `num_polys` polynomials
+/// combined with powers of a random challenge. The bound of Theorem 8.3 of "Proximity Gaps for
+/// Reed-Solomon Codes" is computed for each proximity parameter `m`, and the best is kept.
+pub fn proven_security_bits(params: &FriParams, field_bits: f64, num_polys: usize) -> f64 {
+    let config = &params.config;
+    let rho = config.rate();
+    let field_size = field_bits.exp2();
+    let lde_size = params.lde_size() as f64;
+    let folding = params
+        .reduction_arity_bits
+        .iter()
+        .map(|&arity_bits| (1 << arity_bits) as f64)
+        .sum::<f64>();
+    let batching = num_polys.max(2) as f64 - 1.0;
+
+    (3..=64)
+        .map(|m| {
+            let m = m as f64;
+            let commit_error =
+                batching * (m + 0.5).powi(7) / (3.0 * rho.powf(1.5)) * lde_size * lde_size
+                    / field_size
+                    + (2.0 * m + 1.0) * (lde_size + 1.0) * folding / (rho.sqrt() * field_size);
+            let alpha = rho.sqrt() * (1.0 + 0.5 / m);
+            let query_error = alpha.powi(config.num_query_rounds as i32)
+                / (config.proof_of_work_bits as f64).exp2();
+            -(commit_error + query_error).log2()
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Estimates the number of field elements hashed by the prover: those of the LDEs of `num_polys`
+/// polynomials, and a block of 8 per proof-of-work attempt.
+pub fn estimate_fri_prover_cost(params: &FriParams, num_polys: usize) -> usize {
+    num_polys * params.lde_size() + (8 << params.config.proof_of_work_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    fn shape() -> FriProofShape {
+        FriProofShape::new::<F, H, D>(16, vec![100, 135, 20, 16], false)
+    }
+
+    #[test]
+    fn test_standard_config_security() {
+        let config = CircuitConfig::standard_recursion_config().fri_config;
+        let shape = shape();
+        let estimate = shape.estimate(&config);
+        assert_eq!(estimate.conjectured_bits, 100.0);
+        assert!(estimate.proven_bits < estimate.conjectured_bits);
+        assert!(estimate.proven_bits > 0.0);
+
+        let more_queries = FriConfig {
+            num_query_rounds: 2 * config.num_query_rounds,
+            ..config.clone()
+        };
+        // Conjectured security is then bounded by the size of the field.
+        assert_eq!(
+            shape.security_bits(&more_queries, SecurityModel::Conjectured),
+            shape.field_bits - shape.fri_params(&config).lde_bits() as f64
+        );
+        assert!(shape.estimate(&more_queries).proven_bits > estimate.proven_bits);
+        assert!(shape.estimate(&more_queries).proof_size > estimate.proof_size);
+    }
+
+    #[test]
+    fn test_search() {
+        let base = CircuitConfig::standard_recursion_config().fri_config;
+        let shape = shape();
+        let standard = shape.estimate(&base);
+
+        let (config, estimate) = shape
+            .search(&base, 100.0, SecurityModel::Conjectured, FriCost::ProofSize)
+            .unwrap();
+        assert!(estimate.conjectured_bits >= 100.0);
+        assert!(estimate.proof_size <= standard.proof_size);
+        assert_eq!(config.cap_height, base.cap_height);
+
+        let (fast_config, fast_estimate) = shape
+            .search(
+                &base,
+                100.0,
+                SecurityModel::Conjectured,
+                FriCost::ProverTime,
+            )
+            .unwrap();
+        assert!(fast_estimate.prover_cost <= estimate.prover_cost);
+        assert!(fast_config.rate_bits <= config.rate_bits);
+
+        let (_, proven) = shape
+            .search(&base, 60.0, SecurityModel::Proven, FriCost::ProofSize)
+            .unwrap();
+        assert!(proven.proven_bits >= 60.0);
+
+        // Goldilocks' quadratic extension can't give 128 bits of conjectured security.
+        assert!(shape
+            .search(&base, 128.0, SecurityModel::Conjectured, FriCost::ProofSize)
+            .is_none());
+    }
+}
@@ -6,6 +6,7 @@ use core::borrow::Borrow;
 use crate::field::extension::Extendable;
 use crate::field::types::Field64;
 use crate::gates::arithmetic_base::ArithmeticGate;
+use crate::gates::bitwise::{BitOperationGate, BitwiseGate, BitwiseOp};
 use crate::gates::exponentiation::ExponentiationGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
@@ -80,6 +81,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
         let result = self.add_base_arithmetic_operation(operation);
         self.base_arithmetic_results.insert(operation, result);
+        self.record_arithmetic(
+            const_0,
+            const_1,
+            [&[multiplicand_0], &[multiplicand_1], &[addend]],
+            &[result],
+        );
         result
     }
 
@@ -346,6 +353,60 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
     }
 
+    /// computes the arithmetic extension of logical "xor": `b1 + b2 - 2 * b1 * b2`
+    pub fn xor(&mut self, b1: BoolTarget, b2: BoolTarget) -> BoolTarget {
+        let res_minus_b2 = self.arithmetic(-F::TWO, F::ONE, b1.target, b2.target, b1.target);
+        BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
+    }
+
+    /// Computes the XOR of each pair of bits in `a` and `b`, with `BitOperationGate`s which fit
+    /// more XORs in a row than `xor`. Callers holding packed values should rather use `xor_u32`.
+    pub fn xor_many(&mut self, a: &[BoolTarget], b: &[BoolTarget]) -> Vec<BoolTarget> {
+        self.bit_operation_many(BitwiseOp::Xor, a, b)
+    }
+
+    /// Computes the AND of each pair of bits in `a` and `b`, with `BitOperationGate`s which fit
+    /// more ANDs in a row than `and`. Callers holding packed values should rather use `and_u32`.
+    pub fn and_many(&mut self, a: &[BoolTarget], b: &[BoolTarget]) -> Vec<BoolTarget> {
+        self.bit_operation_many(BitwiseOp::And, a, b)
+    }
+
+    fn bit_operation_many(
+        &mut self,
+        op: BitwiseOp,
+        a: &[BoolTarget],
+        b: &[BoolTarget],
+    ) -> Vec<BoolTarget> {
+        assert_eq!(a.len(), b.len(), "Mismatched bit vector lengths");
+        let gate = BitOperationGate::new_from_config(op, &self.config);
+        a.iter()
+            .zip(b)
+            .map(|(&a_bit, &b_bit)| {
+                let (row, i) = self.find_slot(gate.clone(), &[], &[]);
+                self.connect(
+                    Target::wire(row, BitOperationGate::wire_ith_input_a(i)),
+                    a_bit.target,
+                );
+                self.connect(
+                    Target::wire(row, BitOperationGate::wire_ith_input_b(i)),
+                    b_bit.target,
+                );
+                BoolTarget::new_unsafe(Target::wire(row, BitOperationGate::wire_ith_output(i)))
+            })
+            .collect()
+    }
+
+    /// Returns `a op b`, for two limbs of at most `BitwiseGate::LIMB_BITS` bits. The circuit is
+    /// unsatisfiable if either input doesn't fit in a limb.
+    pub(crate) fn add_bitwise_operation(&mut self, op: BitwiseOp, a: Target, b: Target) -> Target {
+        let gate = BitwiseGate::new_from_config(op, &self.config);
+        let (row, i) = self.find_slot(gate, &[], &[]);
+
+        self.connect(Target::wire(row, BitwiseGate::wire_ith_input_a(i)), a);
+        self.connect(Target::wire(row, BitwiseGate::wire_ith_input_b(i)), b);
+        Target::wire(row, BitwiseGate::wire_ith_output(i))
+    }
+
     pub fn _if(&mut self, b: BoolTarget, x: Target, y: Target) -> Target {
         let not_b = self.not(b);
         let maybe_x = self.mul(b.target, x);
@@ -58,6 +58,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         };
         // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
         self.arithmetic_results.insert(operation, result);
+        self.record_arithmetic(
+            const_0,
+            const_1,
+            [&multiplicand_0.0, &multiplicand_1.0, &addend.0],
+            &result.0,
+        );
         result
     }
 
@@ -0,0 +1,511 @@
+//! Gadgets for 32-bit unsigned integers.
+//!
+//! Multiplications and additions go through `U32ArithmeticGate`, which computes
+//! `x * y + z` and splits the result into range-checked 32-bit halves, while subtractions and
+//! comparisons go through `U32SubtractionGate` and bitwise operations through `BitwiseGate`. The
+//! multi-limb helpers at the bottom of this file operate on little-endian slices of `U32Target`s,
+//! and are shared by the wider integer types.
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use num::{BigUint, Zero};
+
+use crate::field::extension::Extendable;
+use crate::field::types::PrimeField64;
+use crate::gates::arithmetic_u32::U32ArithmeticGate;
+use crate::gates::bitwise::BitwiseOp;
+use crate::gates::subtraction_u32::U32SubtractionGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A target holding a value in `[0, 2^32)`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct U32Target(pub Target);
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a new `U32Target`, range-checked to 32 bits.
+    pub fn add_virtual_u32_target(&mut self) -> U32Target {
+        let x = U32Target(self.add_virtual_target());
+        self.range_check_u32(&[x]);
+        x
+    }
+
+    /// Adds `n` new `U32Target`s, range-checked to 32 bits.
+    pub fn add_virtual_u32_targets(&mut self, n: usize) -> Vec<U32Target> {
+        (0..n).map(|_| self.add_virtual_u32_target()).collect()
+    }
+
+    pub fn constant_u32(&mut self, c: u32) -> U32Target {
+        U32Target(self.constant(F::from_canonical_u32(c)))
+    }
+
+    pub fn zero_u32(&mut self) -> U32Target {
+        U32Target(self.zero())
+    }
+
+    pub fn one_u32(&mut self) -> U32Target {
+        U32Target(self.one())
+    }
+
+    pub fn connect_u32(&mut self, x: U32Target, y: U32Target) {
+        self.connect(x.0, y.0)
+    }
+
+    pub fn assert_zero_u32(&mut self, x: U32Target) {
+        self.assert_zero(x.0)
+    }
+
+    /// Checks that each of `vals` is less than `2^32`.
+    pub fn range_check_u32(&mut self, vals: &[U32Target]) {
+        for &x in vals {
+            self.range_check_lookup(x.0, 32);
+        }
+    }
+
+    /// Computes `x * y + z`, returning the low and high 32-bit halves of the result.
+    pub fn mul_add_u32(
+        &mut self,
+        x: U32Target,
+        y: U32Target,
+        z: U32Target,
+    ) -> (U32Target, U32Target) {
+        let gate = U32ArithmeticGate::<F, D>::new_from_config(&self.config);
+        let (row, copy) = self.find_slot(gate, &[], &[]);
+
+        self.connect(Target::wire(row, gate.wire_ith_multiplicand_0(copy)), x.0);
+        self.connect(Target::wire(row, gate.wire_ith_multiplicand_1(copy)), y.0);
+        self.connect(Target::wire(row, gate.wire_ith_addend(copy)), z.0);
+
+        let output_low = U32Target(Target::wire(row, gate.wire_ith_output_low_half(copy)));
+        let output_high = U32Target(Target::wire(row, gate.wire_ith_output_high_half(copy)));
+        (output_low, output_high)
+    }
+
+    /// Computes `x + y`, returning the 32-bit sum and the carry bit.
+    pub fn add_u32(&mut self, x: U32Target, y: U32Target) -> (U32Target, U32Target) {
+        let one = self.one_u32();
+        self.mul_add_u32(x, one, y)
+    }
+
+    /// Computes the sum of `to_add`, returning the low 32 bits and the carry.
+    pub fn add_many_u32(&mut self, to_add: &[U32Target]) -> (U32Target, U32Target) {
+        match to_add.len() {
+            0 => (self.zero_u32(), self.zero_u32()),
+            1 => (to_add[0], self.zero_u32()),
+            2 => self.add_u32(to_add[0], to_add[1]),
+            _ => {
+                let zero = self.zero_u32();
+                self.add_u32s_with_carry(to_add, zero)
+            }
+        }
+    }
+
+    /// Computes the sum of `to_add` and `carry`, returning the low 32 bits and the new carry. There
+    /// must be fewer than `2^32` terms, so that the sum fits in 64 bits.
+    pub fn add_u32s_with_carry(
+        &mut self,
+        to_add: &[U32Target],
+        carry: U32Target,
+    ) -> (U32Target, U32Target) {
+        assert!((to_add.len() as u64) < 1 << 32, "Too many terms to add");
+        let sum = self.add_many(to_add.iter().chain([&carry]).map(|x| x.0));
+        // Splitting through the arithmetic gate range-checks both halves, and rejects any
+        // decomposition which wraps around the field order.
+        let one = self.one_u32();
+        let zero = self.zero_u32();
+        self.mul_add_u32(U32Target(sum), one, zero)
+    }
+
+    /// Computes `x * y`, returning the low and high 32-bit halves of the product.
+    pub fn mul_u32(&mut self, x: U32Target, y: U32Target) -> (U32Target, U32Target) {
+        let zero = self.zero_u32();
+        self.mul_add_u32(x, y, zero)
+    }
+
+    /// Computes `x - y - borrow` modulo `2^32`, returning the difference and whether it
+    /// underflowed. `borrow` must be a bit.
+    pub fn sub_u32(
+        &mut self,
+        x: U32Target,
+        y: U32Target,
+        borrow: U32Target,
+    ) -> (U32Target, U32Target) {
+        let gate = U32SubtractionGate::<F, D>::new_from_config(&self.config);
+        let (row, copy) = self.find_slot(gate, &[], &[]);
+
+        self.connect(Target::wire(row, gate.wire_ith_input_x(copy)), x.0);
+        self.connect(Target::wire(row, gate.wire_ith_input_y(copy)), y.0);
+        self.connect(
+            Target::wire(row, gate.wire_ith_input_borrow(copy)),
+            borrow.0,
+        );
+
+        let output_result = U32Target(Target::wire(row, gate.wire_ith_output_result(copy)));
+        let output_borrow = U32Target(Target::wire(row, gate.wire_ith_output_borrow(copy)));
+        (output_result, output_borrow)
+    }
+
+    /// Returns whether `x < y`.
+    pub fn is_less_than_u32(&mut self, x: U32Target, y: U32Target) -> BoolTarget {
+        let zero = self.zero_u32();
+        let (_, borrow) = self.sub_u32(x, y, zero);
+        // `U32SubtractionGate` constrains the output borrow to be a bit.
+        BoolTarget::new_unsafe(borrow.0)
+    }
+
+    /// Returns whether `x <= y`.
+    pub fn is_less_than_or_equal_u32(&mut self, x: U32Target, y: U32Target) -> BoolTarget {
+        let y_lt_x = self.is_less_than_u32(y, x);
+        self.not(y_lt_x)
+    }
+
+    /// Computes the quotient and remainder of `x / y`. The circuit is unsatisfiable if `y = 0`.
+    pub fn div_rem_u32(&mut self, x: U32Target, y: U32Target) -> (U32Target, U32Target) {
+        let (quotient, remainder) = self.div_rem_u32_limbs(&[x], &[y]);
+        (quotient[0], remainder[0])
+    }
+
+    /// Splits `x` into 32 little-endian bits.
+    pub fn split_u32_to_bits(&mut self, x: U32Target) -> Vec<BoolTarget> {
+        self.split_le(x.0, 32)
+    }
+
+    /// Computes the bitwise AND of `x` and `y`, with a `BitwiseGate`. This also range-checks both
+    /// inputs.
+    pub fn and_u32(&mut self, x: U32Target, y: U32Target) -> U32Target {
+        U32Target(self.add_bitwise_operation(BitwiseOp::And, x.0, y.0))
+    }
+
+    /// Computes the bitwise XOR of `x` and `y`, with a `BitwiseGate`. This also range-checks both
+    /// inputs.
+    pub fn xor_u32(&mut self, x: U32Target, y: U32Target) -> U32Target {
+        U32Target(self.add_bitwise_operation(BitwiseOp::Xor, x.0, y.0))
+    }
+
+    /// Computes the bitwise NOT of `x`.
+    pub fn not_u32(&mut self, x: U32Target) -> U32Target {
+        let u32_max = self.constant(F::from_canonical_u32(u32::MAX));
+        U32Target(self.sub(u32_max, x.0))
+    }
+
+    /// Adds two little-endian multi-limb integers, returning `max(a.len(), b.len())` limbs and the
+    /// final carry.
+    pub(crate) fn add_u32_limbs(
+        &mut self,
+        a: &[U32Target],
+        b: &[U32Target],
+    ) -> (Vec<U32Target>, U32Target) {
+        let num_limbs = a.len().max(b.len());
+        let mut carry = self.zero_u32();
+        let mut sum = Vec::with_capacity(num_limbs);
+        for i in 0..num_limbs {
+            let terms = [a.get(i), b.get(i)]
+                .into_iter()
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            let (limb, new_carry) = self.add_u32s_with_carry(&terms, carry);
+            sum.push(limb);
+            carry = new_carry;
+        }
+        (sum, carry)
+    }
+
+    /// Subtracts two little-endian multi-limb integers modulo `2^(32 * max(a.len(), b.len()))`,
+    /// returning the difference and whether it underflowed.
+    pub(crate) fn sub_u32_limbs(
+        &mut self,
+        a: &[U32Target],
+        b: &[U32Target],
+    ) -> (Vec<U32Target>, U32Target) {
+        let num_limbs = a.len().max(b.len());
+        let zero = self.zero_u32();
+        let mut borrow = zero;
+        let mut diff = Vec::with_capacity(num_limbs);
+        for i in 0..num_limbs {
+            let x = a.get(i).copied().unwrap_or(zero);
+            let y = b.get(i).copied().unwrap_or(zero);
+            let (limb, new_borrow) = self.sub_u32(x, y, borrow);
+            diff.push(limb);
+            borrow = new_borrow;
+        }
+        (diff, borrow)
+    }
+
+    /// Multiplies two little-endian multi-limb integers, returning all `a.len() + b.len()` limbs of
+    /// the product.
+    pub(crate) fn mul_u32_limbs(&mut self, a: &[U32Target], b: &[U32Target]) -> Vec<U32Target> {
+        let total_limbs = a.len() + b.len();
+        let mut to_add = vec![vec![]; total_limbs];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                let (low, high) = self.mul_u32(x, y);
+                to_add[i + j].push(low);
+                to_add[i + j + 1].push(high);
+            }
+        }
+
+        let mut carry = self.zero_u32();
+        let mut product = Vec::with_capacity(total_limbs);
+        for summands in to_add {
+            let (limb, new_carry) = self.add_u32s_with_carry(&summands, carry);
+            product.push(limb);
+            carry = new_carry;
+        }
+        // The product fits in `total_limbs` limbs, so the final carry is zero.
+        self.assert_zero_u32(carry);
+        product
+    }
+
+    /// Returns whether the little-endian multi-limb integer `a` is less than `b`.
+    pub(crate) fn is_less_than_u32_limbs(
+        &mut self,
+        a: &[U32Target],
+        b: &[U32Target],
+    ) -> BoolTarget {
+        let (_, borrow) = self.sub_u32_limbs(a, b);
+        BoolTarget::new_unsafe(borrow.0)
+    }
+
+    /// Computes the quotient and remainder of two little-endian multi-limb integers, with as many
+    /// limbs as `a` and `b` respectively. The circuit is unsatisfiable if `b = 0`.
+    pub(crate) fn div_rem_u32_limbs(
+        &mut self,
+        a: &[U32Target],
+        b: &[U32Target],
+    ) -> (Vec<U32Target>, Vec<U32Target>) {
+        let quotient = self.add_virtual_u32_targets(a.len());
+        let remainder = self.add_virtual_u32_targets(b.len());
+        self.add_simple_generator(U32DivRemGenerator {
+            dividend: a.to_vec(),
+            divisor: b.to_vec(),
+            quotient: quotient.clone(),
+            remainder: remainder.clone(),
+        });
+
+        // Check that `a = quotient * b + remainder`, with no overflow past `a.len()` limbs.
+        let product = self.mul_u32_limbs(&quotient, b);
+        let (sum, carry) = self.add_u32_limbs(&product, &remainder);
+        self.assert_zero_u32(carry);
+        for (i, &limb) in sum.iter().enumerate() {
+            match a.get(i) {
+                Some(&a_limb) => self.connect_u32(limb, a_limb),
+                None => self.assert_zero_u32(limb),
+            }
+        }
+
+        let remainder_lt_divisor = self.is_less_than_u32_limbs(&remainder, b);
+        self.assert_one(remainder_lt_divisor.target);
+
+        (quotient, remainder)
+    }
+}
+
+/// Splits `x` into `num_limbs` little-endian 32-bit limbs, truncating any higher bits.
+pub(crate) fn biguint_to_u32_limbs(x: &BigUint, num_limbs: usize) -> Vec<u32> {
+    let mut digits = x.to_u32_digits();
+    digits.resize(num_limbs, 0);
+    digits
+}
+
+fn get_u32_limbs<F: PrimeField64>(witness: &PartitionWitness<F>, limbs: &[U32Target]) -> BigUint {
+    BigUint::new(
+        limbs
+            .iter()
+            .map(|x| witness.get_target(x.0).to_canonical_u64() as u32)
+            .collect(),
+    )
+}
+
+fn write_u32_target_vec(dst: &mut Vec<u8>, v: &[U32Target]) -> IoResult<()> {
+    dst.write_target_vec(&v.iter().map(|x| x.0).collect::<Vec<_>>())
+}
+
+fn read_u32_target_vec(src: &mut Buffer) -> IoResult<Vec<U32Target>> {
+    Ok(src.read_target_vec()?.into_iter().map(U32Target).collect())
+}
+
+/// Computes the quotient and remainder of two multi-limb integers.
+#[derive(Debug, Default)]
+pub struct U32DivRemGenerator {
+    dividend: Vec<U32Target>,
+    divisor: Vec<U32Target>,
+    quotient: Vec<U32Target>,
+    remainder: Vec<U32Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for U32DivRemGenerator {
+    fn id(&self) -> String {
+        "U32DivRemGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.dividend
+            .iter()
+            .chain(&self.divisor)
+            .map(|x| x.0)
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let dividend = get_u32_limbs(witness, &self.dividend);
+        let divisor = get_u32_limbs(witness, &self.divisor);
+        // Division by zero has no valid witness; we still fill the targets so that the failure is
+        // reported as an unsatisfied constraint.
+        let (quotient, remainder) = if divisor.is_zero() {
+            (BigUint::zero(), dividend)
+        } else {
+            (&dividend / &divisor, &dividend % &divisor)
+        };
+
+        for (targets, value) in [(&self.quotient, quotient), (&self.remainder, remainder)] {
+            let limbs = biguint_to_u32_limbs(&value, targets.len());
+            for (&t, limb) in targets.iter().zip(limbs) {
+                out_buffer.set_target(t.0, F::from_canonical_u32(limb));
+            }
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        write_u32_target_vec(dst, &self.dividend)?;
+        write_u32_target_vec(dst, &self.divisor)?;
+        write_u32_target_vec(dst, &self.quotient)?;
+        write_u32_target_vec(dst, &self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let dividend = read_u32_target_vec(src)?;
+        let divisor = read_u32_target_vec(src)?;
+        let quotient = read_u32_target_vec(src)?;
+        let remainder = read_u32_target_vec(src)?;
+        Ok(Self {
+            dividend,
+            divisor,
+            quotient,
+            remainder,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_u32_arithmetic() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let a = rng.gen::<u32>();
+        let b = rng.gen::<u32>();
+        let c = rng.gen::<u32>();
+
+        let x = builder.add_virtual_u32_target();
+        let y = builder.add_virtual_u32_target();
+        let z = builder.add_virtual_u32_target();
+
+        let expect = |builder: &mut CircuitBuilder<F, D>, t: U32Target, v: u32| {
+            let v = builder.constant_u32(v);
+            builder.connect_u32(t, v);
+        };
+
+        let product = a as u64 * b as u64 + c as u64;
+        let (low, high) = builder.mul_add_u32(x, y, z);
+        expect(&mut builder, low, product as u32);
+        expect(&mut builder, high, (product >> 32) as u32);
+
+        let sum = a as u64 + b as u64;
+        let (low, carry) = builder.add_u32(x, y);
+        expect(&mut builder, low, sum as u32);
+        expect(&mut builder, carry, (sum >> 32) as u32);
+
+        let sum = a as u64 + b as u64 + c as u64;
+        let (low, carry) = builder.add_many_u32(&[x, y, z]);
+        expect(&mut builder, low, sum as u32);
+        expect(&mut builder, carry, (sum >> 32) as u32);
+
+        let one = builder.one_u32();
+        let (diff, borrow) = builder.sub_u32(x, y, one);
+        let (expected_diff, borrow_0) = a.overflowing_sub(b);
+        let (expected_diff, borrow_1) = expected_diff.overflowing_sub(1);
+        expect(&mut builder, diff, expected_diff);
+        expect(&mut builder, borrow, (borrow_0 || borrow_1) as u32);
+
+        let lt = builder.is_less_than_u32(x, y);
+        let le = builder.is_less_than_or_equal_u32(x, x);
+        expect(&mut builder, U32Target(lt.target), (a < b) as u32);
+        expect(&mut builder, U32Target(le.target), 1);
+
+        let (quotient, remainder) = builder.div_rem_u32(x, y);
+        expect(&mut builder, quotient, a / b);
+        expect(&mut builder, remainder, a % b);
+
+        let and = builder.and_u32(x, y);
+        let xor = builder.xor_u32(x, y);
+        let not = builder.not_u32(x);
+        expect(&mut builder, and, a & b);
+        expect(&mut builder, xor, a ^ b);
+        expect(&mut builder, not, !a);
+
+        let mut pw = PartialWitness::new();
+        pw.set_u32_target(x, a);
+        pw.set_u32_target(y, b);
+        pw.set_u32_target(z, c);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_u32_range_check() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_u32_target();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x.0, F::from_canonical_u64(1 << 32));
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_div_rem_u32_by_zero() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_u32_target();
+        let zero = builder.zero_u32();
+        builder.div_rem_u32(x, zero);
+
+        let mut pw = PartialWitness::new();
+        pw.set_u32_target(x, 7);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+//! Gadgets for 64-bit unsigned integers, represented as two 32-bit limbs.
+
+use crate::field::extension::Extendable;
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A 64-bit integer, as little-endian 32-bit limbs.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct U64Target(pub [U32Target; 2]);
+
+impl U64Target {
+    pub fn low(&self) -> U32Target {
+        self.0[0]
+    }
+
+    pub fn high(&self) -> U32Target {
+        self.0[1]
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a new `U64Target`, with both limbs range-checked to 32 bits.
+    pub fn add_virtual_u64_target(&mut self) -> U64Target {
+        U64Target([self.add_virtual_u32_target(), self.add_virtual_u32_target()])
+    }
+
+    pub fn constant_u64(&mut self, c: u64) -> U64Target {
+        U64Target([
+            self.constant_u32(c as u32),
+            self.constant_u32((c >> 32) as u32),
+        ])
+    }
+
+    pub fn zero_u64(&mut self) -> U64Target {
+        let zero = self.zero_u32();
+        U64Target([zero; 2])
+    }
+
+    pub fn connect_u64(&mut self, x: U64Target, y: U64Target) {
+        for (a, b) in x.0.into_iter().zip(y.0) {
+            self.connect_u32(a, b);
+        }
+    }
+
+    /// Computes `x + y`, returning the 64-bit sum and the carry bit.
+    pub fn add_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U32Target) {
+        let (sum, carry) = self.add_u32_limbs(&x.0, &y.0);
+        (U64Target([sum[0], sum[1]]), carry)
+    }
+
+    /// Computes `x - y` modulo `2^64`, returning the difference and whether it underflowed.
+    pub fn sub_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U32Target) {
+        let (diff, borrow) = self.sub_u32_limbs(&x.0, &y.0);
+        (U64Target([diff[0], diff[1]]), borrow)
+    }
+
+    /// Computes `x * y`, returning the low and high 64-bit halves of the product.
+    pub fn mul_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U64Target) {
+        let product = self.mul_u32_limbs(&x.0, &y.0);
+        (
+            U64Target([product[0], product[1]]),
+            U64Target([product[2], product[3]]),
+        )
+    }
+
+    /// Returns whether `x < y`.
+    pub fn is_less_than_u64(&mut self, x: U64Target, y: U64Target) -> BoolTarget {
+        self.is_less_than_u32_limbs(&x.0, &y.0)
+    }
+
+    /// Computes the quotient and remainder of `x / y`. The circuit is unsatisfiable if `y = 0`.
+    pub fn div_rem_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U64Target) {
+        let (quotient, remainder) = self.div_rem_u32_limbs(&x.0, &y.0);
+        (
+            U64Target([quotient[0], quotient[1]]),
+            U64Target([remainder[0], remainder[1]]),
+        )
+    }
+
+    /// Computes the bitwise AND of `x` and `y`.
+    pub fn and_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        U64Target([0, 1].map(|i| self.and_u32(x.0[i], y.0[i])))
+    }
+
+    /// Computes the bitwise XOR of `x` and `y`.
+    pub fn xor_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        U64Target([0, 1].map(|i| self.xor_u32(x.0[i], y.0[i])))
+    }
+
+    /// Computes the bitwise NOT of `x`.
+    pub fn not_u64(&mut self, x: U64Target) -> U64Target {
+        U64Target(x.0.map(|limb| self.not_u32(limb)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::gadgets::arithmetic_u32::U32Target;
+    use crate::gadgets::arithmetic_u64::U64Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_u64_arithmetic() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let a = rng.gen::<u64>();
+        // Keep the divisor small enough for the quotient to have two non-trivial limbs.
+        let b = rng.gen::<u64>() >> 16;
+
+        let x = builder.add_virtual_u64_target();
+        let y = builder.add_virtual_u64_target();
+
+        let expect = |builder: &mut CircuitBuilder<F, D>, t: U64Target, v: u64| {
+            let v = builder.constant_u64(v);
+            builder.connect_u64(t, v);
+        };
+        let expect_u32 = |builder: &mut CircuitBuilder<F, D>, t: U32Target, v: u32| {
+            let v = builder.constant_u32(v);
+            builder.connect_u32(t, v);
+        };
+
+        let (sum, carry) = builder.add_u64(x, y);
+        let (expected_sum, expected_carry) = a.overflowing_add(b);
+        expect(&mut builder, sum, expected_sum);
+        expect_u32(&mut builder, carry, expected_carry as u32);
+
+        let (diff, borrow) = builder.sub_u64(x, y);
+        let (expected_diff, expected_borrow) = a.overflowing_sub(b);
+        expect(&mut builder, diff, expected_diff);
+        expect_u32(&mut builder, borrow, expected_borrow as u32);
+
+        let (low, high) = builder.mul_u64(x, y);
+        let product = a as u128 * b as u128;
+        expect(&mut builder, low, product as u64);
+        expect(&mut builder, high, (product >> 64) as u64);
+
+        let lt = builder.is_less_than_u64(y, x);
+        expect_u32(&mut builder, U32Target(lt.target), (b < a) as u32);
+
+        let (quotient, remainder) = builder.div_rem_u64(x, y);
+        expect(&mut builder, quotient, a / b);
+        expect(&mut builder, remainder, a % b);
+
+        let and = builder.and_u64(x, y);
+        let xor = builder.xor_u64(x, y);
+        let not = builder.not_u64(x);
+        expect(&mut builder, and, a & b);
+        expect(&mut builder, xor, a ^ b);
+        expect(&mut builder, not, !a);
+
+        let mut pw = PartialWitness::new();
+        pw.set_u64_target(x, a);
+        pw.set_u64_target(y, b);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,217 @@
+//! Gadgets for arbitrary-precision unsigned integers, represented as little-endian 32-bit limbs.
+
+use alloc::vec::Vec;
+
+use num::{BigUint, Zero};
+
+use crate::field::extension::Extendable;
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// An unsigned integer of `32 * num_limbs()` bits.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct BigUintTarget {
+    pub limbs: Vec<U32Target>,
+}
+
+impl BigUintTarget {
+    pub fn num_limbs(&self) -> usize {
+        self.limbs.len()
+    }
+
+    pub fn get_limb(&self, i: usize) -> U32Target {
+        self.limbs[i]
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a new `BigUintTarget` with `num_limbs` range-checked limbs.
+    pub fn add_virtual_biguint_target(&mut self, num_limbs: usize) -> BigUintTarget {
+        BigUintTarget {
+            limbs: self.add_virtual_u32_targets(num_limbs),
+        }
+    }
+
+    pub fn constant_biguint(&mut self, value: &BigUint) -> BigUintTarget {
+        let limbs = value
+            .to_u32_digits()
+            .into_iter()
+            .map(|limb| self.constant_u32(limb))
+            .collect();
+        BigUintTarget { limbs }
+    }
+
+    pub fn zero_biguint(&mut self) -> BigUintTarget {
+        self.constant_biguint(&BigUint::zero())
+    }
+
+    /// Connects `lhs` and `rhs`, which may have different numbers of limbs. Any extra limbs must be
+    /// zero.
+    pub fn connect_biguint(&mut self, lhs: &BigUintTarget, rhs: &BigUintTarget) {
+        let (lhs, rhs) = self.pad_biguints(lhs, rhs);
+        for (&l, &r) in lhs.limbs.iter().zip(&rhs.limbs) {
+            self.connect_u32(l, r);
+        }
+    }
+
+    /// Pads the shorter of `a` and `b` with zero limbs, so that both have the same number of limbs.
+    pub fn pad_biguints(
+        &mut self,
+        a: &BigUintTarget,
+        b: &BigUintTarget,
+    ) -> (BigUintTarget, BigUintTarget) {
+        let num_limbs = a.num_limbs().max(b.num_limbs());
+        let zero = self.zero_u32();
+        let pad = |x: &BigUintTarget| {
+            let mut limbs = x.limbs.clone();
+            limbs.resize(num_limbs, zero);
+            BigUintTarget { limbs }
+        };
+        (pad(a), pad(b))
+    }
+
+    /// Returns whether `a < b`.
+    pub fn is_less_than_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget {
+        self.is_less_than_u32_limbs(&a.limbs, &b.limbs)
+    }
+
+    /// Returns whether `a <= b`.
+    pub fn cmp_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget {
+        let b_lt_a = self.is_less_than_biguint(b, a);
+        self.not(b_lt_a)
+    }
+
+    /// Computes `a + b`, with one more limb than the longer input.
+    pub fn add_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        let (mut limbs, carry) = self.add_u32_limbs(&a.limbs, &b.limbs);
+        limbs.push(carry);
+        BigUintTarget { limbs }
+    }
+
+    /// Computes `a - b`. The circuit is unsatisfiable if `a < b`.
+    pub fn sub_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        let (limbs, borrow) = self.sub_u32_limbs(&a.limbs, &b.limbs);
+        self.assert_zero_u32(borrow);
+        BigUintTarget { limbs }
+    }
+
+    /// Computes `a * b`, with as many limbs as both inputs combined.
+    pub fn mul_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        BigUintTarget {
+            limbs: self.mul_u32_limbs(&a.limbs, &b.limbs),
+        }
+    }
+
+    /// Computes `a` if `b` is true, and zero otherwise.
+    pub fn mul_biguint_by_bool(&mut self, a: &BigUintTarget, b: BoolTarget) -> BigUintTarget {
+        let limbs = a
+            .limbs
+            .iter()
+            .map(|&limb| U32Target(self.mul(limb.0, b.target)))
+            .collect();
+        BigUintTarget { limbs }
+    }
+
+    /// Computes the quotient and remainder of `a / b`, with as many limbs as `a` and `b`
+    /// respectively. The circuit is unsatisfiable if `b = 0`.
+    pub fn div_rem_biguint(
+        &mut self,
+        a: &BigUintTarget,
+        b: &BigUintTarget,
+    ) -> (BigUintTarget, BigUintTarget) {
+        let (quotient, remainder) = self.div_rem_u32_limbs(&a.limbs, &b.limbs);
+        (
+            BigUintTarget { limbs: quotient },
+            BigUintTarget { limbs: remainder },
+        )
+    }
+
+    pub fn div_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        self.div_rem_biguint(a, b).0
+    }
+
+    pub fn rem_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        self.div_rem_biguint(a, b).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::{BigUint, FromPrimitive, Integer};
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn random_biguint(rng: &mut OsRng, num_limbs: usize) -> BigUint {
+        BigUint::new((0..num_limbs).map(|_| rng.gen()).collect())
+    }
+
+    #[test]
+    fn test_biguint_arithmetic() -> Result<()> {
+        let mut rng = OsRng;
+        let x_value = random_biguint(&mut rng, 4);
+        let y_value = random_biguint(&mut rng, 2);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(4);
+        let y = builder.add_virtual_biguint_target(2);
+
+        let outputs = [
+            (builder.add_biguint(&x, &y), &x_value + &y_value),
+            (builder.sub_biguint(&x, &y), &x_value - &y_value),
+            (builder.mul_biguint(&x, &y), &x_value * &y_value),
+            (builder.div_biguint(&x, &y), x_value.div_floor(&y_value)),
+            (builder.rem_biguint(&x, &y), x_value.mod_floor(&y_value)),
+        ];
+        for (target, expected) in &outputs {
+            let expected = builder.constant_biguint(expected);
+            builder.connect_biguint(target, &expected);
+        }
+
+        let y_le_x = builder.cmp_biguint(&y, &x);
+        let x_lt_y = builder.is_less_than_biguint(&x, &y);
+        builder.assert_one(y_le_x.target);
+        builder.assert_zero(x_lt_y.target);
+
+        let mut pw = PartialWitness::new();
+        pw.set_biguint_target(&x, &x_value);
+        pw.set_biguint_target(&y, &y_value);
+        assert_eq!(pw.get_biguint_target(&x), x_value);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sub_biguint_underflow() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(2);
+        let y = builder.add_virtual_biguint_target(2);
+        builder.sub_biguint(&x, &y);
+
+        let mut pw = PartialWitness::new();
+        pw.set_biguint_target(&x, &BigUint::from_u64(1).unwrap());
+        pw.set_biguint_target(&y, &BigUint::from_u64(2).unwrap());
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
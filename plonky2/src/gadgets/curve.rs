@@ -0,0 +1,359 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use keccak_hash::keccak;
+use num::BigUint;
+
+use crate::curve::curve_types::{AffinePoint, Curve};
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField};
+use crate::gadgets::nonnative::NonNativeTarget;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of scalar bits consumed per step of a windowed multiplication.
+pub const WINDOW_BITS: usize = 4;
+
+/// A target representing a nonzero point on the curve `C`, in affine coordinates.
+#[derive(Clone, Debug)]
+pub struct AffinePointTarget<C: Curve> {
+    pub x: NonNativeTarget<C::BaseField>,
+    pub y: NonNativeTarget<C::BaseField>,
+}
+
+/// A point whose discrete log relative to the generator is unknown, found by hashing to the curve.
+/// Windowed multiplications start from this point so that, with overwhelming probability, the
+/// incomplete addition formulas never see two points with equal x-coordinates.
+fn starting_point<C: Curve>() -> AffinePoint<C> {
+    let seed = BigUint::from_bytes_le(keccak(b"plonky2 curve starting point").as_bytes());
+    let mut x = C::BaseField::from_noncanonical_biguint(seed % C::BaseField::order());
+    loop {
+        if let Some(y) = (x.cube() + C::A * x + C::B).sqrt() {
+            return AffinePoint::nonzero(x, y);
+        }
+        x += C::BaseField::ONE;
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn constant_affine_point<C: Curve>(
+        &mut self,
+        point: AffinePoint<C>,
+    ) -> AffinePointTarget<C> {
+        debug_assert!(!point.zero);
+        AffinePointTarget {
+            x: self.constant_nonnative(point.x),
+            y: self.constant_nonnative(point.y),
+        }
+    }
+
+    pub fn add_virtual_affine_point_target<C: Curve>(&mut self) -> AffinePointTarget<C> {
+        AffinePointTarget {
+            x: self.add_virtual_nonnative_target(),
+            y: self.add_virtual_nonnative_target(),
+        }
+    }
+
+    pub fn connect_affine_point<C: Curve>(
+        &mut self,
+        lhs: &AffinePointTarget<C>,
+        rhs: &AffinePointTarget<C>,
+    ) {
+        self.connect_nonnative(&lhs.x, &rhs.x);
+        self.connect_nonnative(&lhs.y, &rhs.y);
+    }
+
+    /// Checks that `p` satisfies the curve equation `y^2 = x^3 + A x + B`.
+    pub fn curve_assert_valid<C: Curve>(&mut self, p: &AffinePointTarget<C>) {
+        let y_squared = self.mul_nonnative(&p.y, &p.y);
+        let x_squared = self.mul_nonnative(&p.x, &p.x);
+        let x_cubed = self.mul_nonnative(&x_squared, &p.x);
+        let mut rhs = x_cubed;
+        if !C::A.is_zero() {
+            let a = self.constant_nonnative(C::A);
+            let a_x = self.mul_nonnative(&a, &p.x);
+            rhs = self.add_nonnative(&rhs, &a_x);
+        }
+        let b = self.constant_nonnative(C::B);
+        let rhs = self.add_nonnative(&rhs, &b);
+        self.connect_nonnative(&y_squared, &rhs);
+    }
+
+    pub fn curve_neg<C: Curve>(&mut self, p: &AffinePointTarget<C>) -> AffinePointTarget<C> {
+        AffinePointTarget {
+            x: p.x.clone(),
+            y: self.neg_nonnative(&p.y),
+        }
+    }
+
+    /// Returns `-p` if `b` is true, otherwise `p`.
+    pub fn curve_conditional_neg<C: Curve>(
+        &mut self,
+        p: &AffinePointTarget<C>,
+        b: BoolTarget,
+    ) -> AffinePointTarget<C> {
+        let neg_y = self.neg_nonnative(&p.y);
+        AffinePointTarget {
+            x: p.x.clone(),
+            y: self.select_nonnative(b, &neg_y, &p.y),
+        }
+    }
+
+    pub fn curve_double<C: Curve>(&mut self, p: &AffinePointTarget<C>) -> AffinePointTarget<C> {
+        let AffinePointTarget { x, y } = p;
+
+        // lambda = (3 x^2 + A) / (2 y). The inverse makes the circuit unsatisfiable if y = 0.
+        let x_squared = self.mul_nonnative(x, x);
+        let two_x_squared = self.add_nonnative(&x_squared, &x_squared);
+        let mut numerator = self.add_nonnative(&two_x_squared, &x_squared);
+        if !C::A.is_zero() {
+            let a = self.constant_nonnative(C::A);
+            numerator = self.add_nonnative(&numerator, &a);
+        }
+        let two_y = self.add_nonnative(y, y);
+        let two_y_inv = self.inv_nonnative(&two_y);
+        let lambda = self.mul_nonnative(&numerator, &two_y_inv);
+
+        let lambda_squared = self.mul_nonnative(&lambda, &lambda);
+        let two_x = self.add_nonnative(x, x);
+        let x3 = self.sub_nonnative(&lambda_squared, &two_x);
+        let x_diff = self.sub_nonnative(x, &x3);
+        let lambda_x_diff = self.mul_nonnative(&lambda, &x_diff);
+        let y3 = self.sub_nonnative(&lambda_x_diff, y);
+        AffinePointTarget { x: x3, y: y3 }
+    }
+
+    pub fn curve_repeated_double<C: Curve>(
+        &mut self,
+        p: &AffinePointTarget<C>,
+        n: usize,
+    ) -> AffinePointTarget<C> {
+        let mut result = p.clone();
+        for _ in 0..n {
+            result = self.curve_double(&result);
+        }
+        result
+    }
+
+    /// Adds two points using the incomplete addition formula. The circuit is unsatisfiable if the
+    /// points have equal x-coordinates, so this can't be used for doubling.
+    pub fn curve_add<C: Curve>(
+        &mut self,
+        p1: &AffinePointTarget<C>,
+        p2: &AffinePointTarget<C>,
+    ) -> AffinePointTarget<C> {
+        let AffinePointTarget { x: x1, y: y1 } = p1;
+        let AffinePointTarget { x: x2, y: y2 } = p2;
+
+        let x_diff = self.sub_nonnative(x2, x1);
+        let y_diff = self.sub_nonnative(y2, y1);
+        let x_diff_inv = self.inv_nonnative(&x_diff);
+        let lambda = self.mul_nonnative(&y_diff, &x_diff_inv);
+
+        let lambda_squared = self.mul_nonnative(&lambda, &lambda);
+        let x_sum = self.add_nonnative(x1, x2);
+        let x3 = self.sub_nonnative(&lambda_squared, &x_sum);
+        let x_diff = self.sub_nonnative(x1, &x3);
+        let lambda_x_diff = self.mul_nonnative(&lambda, &x_diff);
+        let y3 = self.sub_nonnative(&lambda_x_diff, y1);
+        AffinePointTarget { x: x3, y: y3 }
+    }
+
+    /// Returns `p1 + p2` if `b` is true, otherwise `p1`.
+    pub fn curve_conditional_add<C: Curve>(
+        &mut self,
+        p1: &AffinePointTarget<C>,
+        p2: &AffinePointTarget<C>,
+        b: BoolTarget,
+    ) -> AffinePointTarget<C> {
+        let sum = self.curve_add(p1, p2);
+        AffinePointTarget {
+            x: self.select_nonnative(b, &sum.x, &p1.x),
+            y: self.select_nonnative(b, &sum.y, &p1.y),
+        }
+    }
+
+    /// Returns `points[index]`.
+    pub fn random_access_curve_points<C: Curve>(
+        &mut self,
+        index: Target,
+        points: &[AffinePointTarget<C>],
+    ) -> AffinePointTarget<C> {
+        let num_limbs = points[0].x.limbs.len();
+        let mut access = |coords: Vec<&NonNativeTarget<C::BaseField>>| {
+            let limbs = (0..num_limbs)
+                .map(|i| {
+                    let v = coords.iter().map(|c| c.limbs[i]).collect();
+                    self.random_access(index, v)
+                })
+                .collect();
+            NonNativeTarget::from_limbs(limbs)
+        };
+        let x = access(points.iter().map(|p| &p.x).collect());
+        let y = access(points.iter().map(|p| &p.y).collect());
+        AffinePointTarget { x, y }
+    }
+
+    /// Splits range-checked little-endian 16-bit limbs into little-endian `WINDOW_BITS`-bit
+    /// windows.
+    pub(crate) fn split_limbs_to_windows(&mut self, limbs: &[Target]) -> Vec<Target> {
+        limbs
+            .iter()
+            .flat_map(|&limb| {
+                let bits = self.split_le(limb, 16);
+                bits.chunks(WINDOW_BITS)
+                    .map(|chunk| self.le_sum(chunk.iter()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns `[p, p, 2p, 3p, ..., (2^WINDOW_BITS - 1) p]`. The first entry is only a placeholder,
+    /// since zero windows are skipped.
+    fn precompute_window<C: Curve>(
+        &mut self,
+        p: &AffinePointTarget<C>,
+    ) -> Vec<AffinePointTarget<C>> {
+        let mut table = vec![p.clone(), p.clone(), self.curve_double(p)];
+        for i in 3..1 << WINDOW_BITS {
+            let next = self.curve_add(&table[i - 1], p);
+            table.push(next);
+        }
+        table
+    }
+
+    /// Computes `sum_i k_i p_i`, where each scalar `k_i` is given by its little-endian
+    /// `WINDOW_BITS`-bit windows. All scalars must have the same number of windows, so that the
+    /// doublings can be shared.
+    pub(crate) fn curve_msm_windowed<C: Curve>(
+        &mut self,
+        terms: &[(AffinePointTarget<C>, Vec<Target>)],
+    ) -> AffinePointTarget<C> {
+        let num_windows = terms[0].1.len();
+        assert!(terms.iter().all(|(_, w)| w.len() == num_windows));
+
+        let tables = terms
+            .iter()
+            .map(|(p, _)| self.precompute_window(p))
+            .collect::<Vec<_>>();
+
+        let start = starting_point::<C>();
+        let mut result = self.constant_affine_point(start);
+        let zero = self.zero();
+        for i in (0..num_windows).rev() {
+            if i != num_windows - 1 {
+                result = self.curve_repeated_double(&result, WINDOW_BITS);
+            }
+            for (table, (_, windows)) in tables.iter().zip(terms) {
+                let to_add = self.random_access_curve_points(windows[i], table);
+                let is_zero = self.is_equal(windows[i], zero);
+                let should_add = self.not(is_zero);
+                result = self.curve_conditional_add(&result, &to_add, should_add);
+            }
+        }
+
+        // The starting point has been doubled `WINDOW_BITS (num_windows - 1)` times; remove it.
+        let mut offset = start;
+        for _ in 0..WINDOW_BITS * (num_windows - 1) {
+            offset = offset.double();
+        }
+        let neg_offset = self.constant_affine_point(-offset);
+        self.curve_add(&result, &neg_offset)
+    }
+
+    /// Computes `n p` with a windowed double-and-add.
+    pub fn curve_scalar_mul_windowed<C: Curve>(
+        &mut self,
+        p: &AffinePointTarget<C>,
+        n: &NonNativeTarget<C::ScalarField>,
+    ) -> AffinePointTarget<C> {
+        let windows = self.split_limbs_to_windows(&n.limbs);
+        self.curve_msm_windowed(&[(p.clone(), windows)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::curve::curve_types::{AffinePoint, Curve};
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_curve_add_double() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let a = g.scalar_mul(Secp256K1Scalar::rand());
+        let b = g.scalar_mul(Secp256K1Scalar::rand());
+
+        let a_target = builder.constant_affine_point(a);
+        let b_target = builder.constant_affine_point(b);
+        builder.curve_assert_valid(&a_target);
+        let sum = builder.curve_add(&a_target, &b_target);
+        let double = builder.curve_double(&a_target);
+        let neg = builder.curve_neg(&b_target);
+
+        let expected_sum = builder.constant_affine_point(a + b);
+        let expected_double = builder.constant_affine_point(a.double());
+        let expected_neg = builder.constant_affine_point(-b);
+        builder.connect_affine_point(&sum, &expected_sum);
+        builder.connect_affine_point(&double, &expected_double);
+        builder.connect_affine_point(&neg, &expected_neg);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_curve_invalid_point() {
+        let config = CircuitConfig::standard_ecc_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let not_a_point = AffinePoint::<Secp256K1>::nonzero(g.x, g.y + Field::ONE);
+        let p = builder.constant_affine_point(not_a_point);
+        builder.curve_assert_valid(&p);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_curve_scalar_mul_windowed() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let k = Secp256K1Scalar::rand();
+
+        let g_target = builder.constant_affine_point(g);
+        let k_target = builder.constant_nonnative(k);
+        let product = builder.curve_scalar_mul_windowed(&g_target, &k_target);
+        let expected = builder.constant_affine_point(g.scalar_mul(k));
+        builder.connect_affine_point(&product, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::curve::curve_types::Curve;
+use crate::curve::secp256k1::Secp256K1;
+use crate::field::extension::Extendable;
+use crate::field::secp256k1_scalar::Secp256K1Scalar;
+use crate::gadgets::curve::AffinePointTarget;
+use crate::gadgets::nonnative::NonNativeTarget;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+#[derive(Clone, Debug)]
+pub struct ECDSAPublicKeyTarget<C: Curve>(pub AffinePointTarget<C>);
+
+#[derive(Clone, Debug)]
+pub struct ECDSASignatureTarget<C: Curve> {
+    pub r: NonNativeTarget<C::ScalarField>,
+    pub s: NonNativeTarget<C::ScalarField>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_ecdsa_public_key_target<C: Curve>(&mut self) -> ECDSAPublicKeyTarget<C> {
+        ECDSAPublicKeyTarget(self.add_virtual_affine_point_target())
+    }
+
+    pub fn add_virtual_ecdsa_signature_target<C: Curve>(&mut self) -> ECDSASignatureTarget<C> {
+        ECDSASignatureTarget {
+            r: self.add_virtual_nonnative_target(),
+            s: self.add_virtual_nonnative_target(),
+        }
+    }
+
+    /// Checks that `sig` is a valid ECDSA signature of the message hash `msg_hash` under `pk`.
+    ///
+    /// Both scalar multiplications `u1 G` and `u2 pk` are split with the GLV endomorphism and
+    /// evaluated together as a single multi-scalar multiplication, so they share their doublings.
+    pub fn verify_secp256k1_signature(
+        &mut self,
+        msg_hash: &NonNativeTarget<Secp256K1Scalar>,
+        pk: &ECDSAPublicKeyTarget<Secp256K1>,
+        sig: &ECDSASignatureTarget<Secp256K1>,
+    ) {
+        let ECDSASignatureTarget { r, s } = sig;
+
+        self.curve_assert_valid(&pk.0);
+
+        // The inverse also ensures that `s` is nonzero.
+        let s_inv = self.inv_nonnative(s);
+        let u1 = self.mul_nonnative(msg_hash, &s_inv);
+        let u2 = self.mul_nonnative(r, &s_inv);
+
+        let g = self.constant_affine_point(Secp256K1::GENERATOR_AFFINE);
+        let g_terms = self.glv_msm_terms(&g, &u1);
+        let pk_terms = self.glv_msm_terms(&pk.0, &u2);
+        let point = self.curve_msm_windowed(&[g_terms, pk_terms].concat());
+
+        let x = self.reduce_nonnative_limbs::<Secp256K1Scalar>(&point.x.limbs);
+        self.connect_nonnative(&x, r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::curve::ecdsa::{sign_message, ECDSASecretKey};
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit verifying a random signature, and a witness for it. If `tamper` is set, the
+    /// witness uses a different message than the signed one.
+    fn ecdsa_circuit(tamper: bool) -> (CircuitBuilder<F, D>, PartialWitness<F>) {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let msg_target = builder.add_virtual_nonnative_target();
+        let pk_target = builder.add_virtual_ecdsa_public_key_target();
+        let sig_target = builder.add_virtual_ecdsa_signature_target();
+        builder.verify_secp256k1_signature(&msg_target, &pk_target, &sig_target);
+
+        let msg = Secp256K1Scalar::rand();
+        let sk = ECDSASecretKey::<Secp256K1>(Secp256K1Scalar::rand());
+        let pk = sk.to_public();
+        let sig = sign_message(msg, sk);
+
+        let mut pw = PartialWitness::new();
+        let signed_msg = if tamper {
+            msg + Secp256K1Scalar::ONE
+        } else {
+            msg
+        };
+        pw.set_nonnative_target(&msg_target, signed_msg);
+        pw.set_nonnative_target(&pk_target.0.x, pk.0.x);
+        pw.set_nonnative_target(&pk_target.0.y, pk.0.y);
+        pw.set_nonnative_target(&sig_target.r, sig.r);
+        pw.set_nonnative_target(&sig_target.s, sig.s);
+
+        (builder, pw)
+    }
+
+    fn test_ecdsa_circuit(tamper: bool) -> Result<()> {
+        let (builder, pw) = ecdsa_circuit(tamper);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    // Proving takes a while, so the tests below only generate the witness and check it against the
+    // constraints. The full proofs are covered by the ignored tests, which CI runs separately.
+
+    #[test]
+    fn test_ecdsa_witness_valid() -> Result<()> {
+        let (builder, pw) = ecdsa_circuit(false);
+        builder.mock_build::<C>().check_witness(pw)
+    }
+
+    #[test]
+    fn test_ecdsa_witness_tampered_message() {
+        let (builder, pw) = ecdsa_circuit(true);
+        assert!(builder.mock_build::<C>().check_witness(pw).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ecdsa_circuit_valid() -> Result<()> {
+        test_ecdsa_circuit(false)
+    }
+
+    #[test]
+    #[ignore]
+    #[should_panic]
+    fn test_ecdsa_circuit_tampered_message() {
+        test_ecdsa_circuit(true).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::curve::glv::{decompose_secp256k1_scalar, GLV_BETA, GLV_LAMBDA, GLV_SCALAR_BITS};
+use crate::curve::secp256k1::Secp256K1;
+use crate::field::extension::Extendable;
+use crate::field::secp256k1_scalar::Secp256K1Scalar;
+use crate::field::types::{Field, PrimeField};
+use crate::gadgets::curve::AffinePointTarget;
+use crate::gadgets::nonnative::{
+    biguint_to_limbs, limbs_to_biguint, num_nonnative_limbs, NonNativeTarget, NONNATIVE_LIMB_BITS,
+};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Splits `k` into `(|k1|, |k2|, k1 < 0, k2 < 0)` such that `k = k1 + lambda k2`, where
+    /// `|k1|` and `|k2|` are constrained to `GLV_SCALAR_BITS` bits.
+    pub fn decompose_secp256k1_scalar(
+        &mut self,
+        k: &NonNativeTarget<Secp256K1Scalar>,
+    ) -> (
+        NonNativeTarget<Secp256K1Scalar>,
+        NonNativeTarget<Secp256K1Scalar>,
+        BoolTarget,
+        BoolTarget,
+    ) {
+        let num_limbs = GLV_SCALAR_BITS / NONNATIVE_LIMB_BITS;
+        let k1_limbs = self.add_virtual_nonnative_limbs(num_limbs);
+        let k2_limbs = self.add_virtual_nonnative_limbs(num_limbs);
+        let k1_neg = self.add_virtual_bool_target_safe();
+        let k2_neg = self.add_virtual_bool_target_safe();
+
+        self.add_simple_generator(GLVDecompositionGenerator {
+            k: k.limbs.clone(),
+            k1: k1_limbs.clone(),
+            k2: k2_limbs.clone(),
+            k1_neg,
+            k2_neg,
+        });
+
+        // Pad the halves with zero limbs, so they're canonical scalars by construction.
+        let zero = self.zero();
+        let padding = vec![zero; num_nonnative_limbs::<Secp256K1Scalar>() - num_limbs];
+        let k1 = NonNativeTarget::from_limbs([k1_limbs, padding.clone()].concat());
+        let k2 = NonNativeTarget::from_limbs([k2_limbs, padding].concat());
+
+        let k1_neg_value = self.neg_nonnative(&k1);
+        let k1_signed = self.select_nonnative(k1_neg, &k1_neg_value, &k1);
+        let k2_neg_value = self.neg_nonnative(&k2);
+        let k2_signed = self.select_nonnative(k2_neg, &k2_neg_value, &k2);
+
+        let lambda = self.constant_nonnative(GLV_LAMBDA);
+        let lambda_k2 = self.mul_nonnative(&lambda, &k2_signed);
+        let sum = self.add_nonnative(&k1_signed, &lambda_k2);
+        self.connect_nonnative(&sum, k);
+
+        (k1, k2, k1_neg, k2_neg)
+    }
+
+    /// Returns the terms `(k1, ±p)` and `(k2, ±phi(p))` of a multi-scalar multiplication equal to
+    /// `k p`, where `phi` is the GLV endomorphism.
+    pub(crate) fn glv_msm_terms(
+        &mut self,
+        p: &AffinePointTarget<Secp256K1>,
+        k: &NonNativeTarget<Secp256K1Scalar>,
+    ) -> [(AffinePointTarget<Secp256K1>, Vec<Target>); 2] {
+        let (k1, k2, k1_neg, k2_neg) = self.decompose_secp256k1_scalar(k);
+
+        let beta = self.constant_nonnative(GLV_BETA);
+        let p_beta = AffinePointTarget {
+            x: self.mul_nonnative(&p.x, &beta),
+            y: p.y.clone(),
+        };
+        let p = self.curve_conditional_neg(p, k1_neg);
+        let p_beta = self.curve_conditional_neg(&p_beta, k2_neg);
+
+        let num_limbs = GLV_SCALAR_BITS / NONNATIVE_LIMB_BITS;
+        let k1_windows = self.split_limbs_to_windows(&k1.limbs[..num_limbs]);
+        let k2_windows = self.split_limbs_to_windows(&k2.limbs[..num_limbs]);
+        [(p, k1_windows), (p_beta, k2_windows)]
+    }
+
+    /// Computes `k p` using the GLV endomorphism, which halves the number of doublings compared to
+    /// `curve_scalar_mul_windowed`.
+    pub fn glv_mul(
+        &mut self,
+        p: &AffinePointTarget<Secp256K1>,
+        k: &NonNativeTarget<Secp256K1Scalar>,
+    ) -> AffinePointTarget<Secp256K1> {
+        let terms = self.glv_msm_terms(p, k);
+        self.curve_msm_windowed(&terms)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GLVDecompositionGenerator {
+    k: Vec<Target>,
+    k1: Vec<Target>,
+    k2: Vec<Target>,
+    k1_neg: BoolTarget,
+    k2_neg: BoolTarget,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for GLVDecompositionGenerator
+{
+    fn id(&self) -> String {
+        "GLVDecompositionGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.k.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let k_limbs = witness
+            .get_targets(&self.k)
+            .into_iter()
+            .map(|limb| limb.to_canonical_u64())
+            .collect::<Vec<_>>();
+        let k = Secp256K1Scalar::from_noncanonical_biguint(limbs_to_biguint(&k_limbs));
+        let (k1, k2, k1_neg, k2_neg) = decompose_secp256k1_scalar(k);
+
+        for (targets, value) in [(&self.k1, k1), (&self.k2, k2)] {
+            let limbs = biguint_to_limbs(&value.to_canonical_biguint(), targets.len());
+            for (&t, limb) in targets.iter().zip(limbs) {
+                out_buffer.set_target(t, F::from_canonical_u64(limb));
+            }
+        }
+        out_buffer.set_bool_target(self.k1_neg, k1_neg);
+        out_buffer.set_bool_target(self.k2_neg, k2_neg);
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.k)?;
+        dst.write_target_vec(&self.k1)?;
+        dst.write_target_vec(&self.k2)?;
+        dst.write_target_bool(self.k1_neg)?;
+        dst.write_target_bool(self.k2_neg)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let k = src.read_target_vec()?;
+        let k1 = src.read_target_vec()?;
+        let k2 = src.read_target_vec()?;
+        let k1_neg = src.read_target_bool()?;
+        let k2_neg = src.read_target_bool()?;
+        Ok(Self {
+            k,
+            k1,
+            k2,
+            k1_neg,
+            k2_neg,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::curve::curve_types::Curve;
+    use crate::curve::secp256k1::Secp256K1;
+    use crate::field::secp256k1_scalar::Secp256K1Scalar;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_glv_decompose() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let k = Secp256K1Scalar::rand();
+        let k_target = builder.add_virtual_nonnative_target();
+        pw.set_nonnative_target(&k_target, k);
+        builder.decompose_secp256k1_scalar(&k_target);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_glv_mul() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let g = Secp256K1::GENERATOR_AFFINE;
+        let k = Secp256K1Scalar::rand();
+
+        let g_target = builder.constant_affine_point(g);
+        let k_target = builder.constant_nonnative(k);
+        let product = builder.glv_mul(&g_target, &k_target);
+        let expected = builder.constant_affine_point(g.scalar_mul(k));
+        builder.connect_affine_point(&product, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,563 @@
+//! An in-circuit Keccak-256, as used by Ethereum (i.e. with the original `0x01` padding rather
+//! than the SHA-3 `0x06` one).
+//!
+//! Each lane of the state is kept as 16 little-endian nibbles, and every step of Keccak-f is
+//! evaluated nibble by nibble with lookups: the input nibbles are packed into a lookup index, and
+//! the table maps it to the output nibble. Rotations by a multiple of 4 bits just reorder nibbles;
+//! other rotations look up the two parts of each rotated nibble separately, which lets the theta
+//! XOR be folded into the same lookups. Since every table only outputs nibbles, the packed indices
+//! are unambiguous; the input bytes are range-checked when they are split into nibbles.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of bytes absorbed per permutation by Keccak-256.
+pub const KECCAK256_RATE_BYTES: usize = 136;
+
+const KECCAK_ROUNDS: usize = 24;
+const LANE_NIBBLES: usize = 16;
+
+/// The round constants added in the iota step.
+const ROUND_CONSTANTS: [u64; KECCAK_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation offsets of the rho step, indexed by `[x][y]`.
+const ROTATION_OFFSETS: [[usize; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// A lane of the Keccak state, as little-endian nibbles.
+type Lane = [Target; LANE_NIBBLES];
+
+/// The Keccak state, indexed by `[x][y]`.
+type KeccakState = [[Lane; 5]; 5];
+
+/// The Keccak padding of a `len`-byte message, which is fixed once `len` is.
+fn keccak256_padding(len: usize) -> Vec<u8> {
+    let padding_len = KECCAK256_RATE_BYTES - len % KECCAK256_RATE_BYTES;
+    let mut padding = vec![0; padding_len];
+    padding[0] |= 0x01;
+    padding[padding_len - 1] |= 0x80;
+    padding
+}
+
+/// Maps a byte to its high nibble. Looking bytes up in this table also range-checks them.
+fn high_nibble(byte: u16) -> u16 {
+    byte >> 4
+}
+
+/// Maps a nibble to its top bit.
+fn top_bit(nibble: u16) -> u16 {
+    nibble >> 3
+}
+
+/// Maps `16 a + b` to `a ^ b`.
+fn xor2_nibbles(index: u16) -> u16 {
+    (index >> 4) ^ (index & 0xf)
+}
+
+/// Maps `256 a + 16 b + c` to `a ^ b ^ c`.
+fn xor3_nibbles(index: u16) -> u16 {
+    (index >> 8) ^ ((index >> 4) & 0xf) ^ (index & 0xf)
+}
+
+/// Maps `32 a + 2 b + t` to `a ^ ((b << 1) | t)`, i.e. the theta step's `D` nibble, with `t` the
+/// bit rotated in from the previous nibble of `b`'s lane.
+fn theta_d_nibble(index: u16) -> u16 {
+    let (a, b, t) = (index >> 5, (index >> 1) & 0xf, index & 1);
+    a ^ (((b << 1) & 0xf) | t)
+}
+
+/// Maps `16 a + d` to the low part of `a ^ d` rotated left by `S` bits, i.e. the part staying in
+/// the same nibble.
+fn xor_rotate_low<const S: u16>(index: u16) -> u16 {
+    (xor2_nibbles(index) << S) & 0xf
+}
+
+/// Maps `16 a + d` to the high part of `a ^ d` rotated left by `S` bits, i.e. the part carried
+/// into the next nibble.
+fn xor_rotate_high<const S: u16>(index: u16) -> u16 {
+    xor2_nibbles(index) >> (4 - S)
+}
+
+/// Maps `256 a + 16 b + c` to `a ^ (!b & c)`.
+fn chi_nibbles(index: u16) -> u16 {
+    let (a, b, c) = (index >> 8, (index >> 4) & 0xf, index & 0xf);
+    a ^ (!b & c & 0xf)
+}
+
+/// The indices of the lookup tables used by Keccak-f.
+struct KeccakTables {
+    high_nibble: usize,
+    top_bit: usize,
+    xor2: usize,
+    xor3: usize,
+    theta_d: usize,
+    /// The low and high parts of rotations by 1, 2 and 3 bits, indexed by the rotation minus 1.
+    rotate: [(usize, usize); 3],
+    chi: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the Keccak-256 digest of `bytes`. Each input target is range-checked to be a byte,
+    /// and the digest is returned as 32 bytes.
+    pub fn keccak256(&mut self, bytes: &[Target]) -> [Target; 32] {
+        // The message length is fixed when building the circuit, so the padding is constant.
+        let mut padded = bytes.to_vec();
+        padded.extend(
+            keccak256_padding(bytes.len())
+                .into_iter()
+                .map(|byte| self.constant(F::from_canonical_u8(byte))),
+        );
+
+        let tables = self.keccak_tables();
+        let zero = self.zero();
+        let mut state: KeccakState = [[[zero; LANE_NIBBLES]; 5]; 5];
+        for (block_index, block) in padded.chunks(KECCAK256_RATE_BYTES).enumerate() {
+            self.keccak_absorb(&tables, &mut state, block, block_index == 0);
+        }
+
+        self.keccak_digest(&state)
+    }
+
+    /// Computes the Keccak-256 digest of the first `len` bytes of `bytes`, where `len` is only known
+    /// when proving. The circuit is unsatisfiable if `len > bytes.len()`. Bytes past `len` are
+    /// ignored, and are not range-checked.
+    pub fn keccak256_variable(&mut self, bytes: &[Target], len: Target) -> [Target; 32] {
+        let max_len = bytes.len();
+        let num_blocks = max_len / KECCAK256_RATE_BYTES + 1;
+        let padded_len = num_blocks * KECCAK256_RATE_BYTES;
+
+        // `is_end[i]` is set iff `len = i`; exactly one of them must be set.
+        let _false = self._false();
+        let is_end = (0..padded_len)
+            .map(|i| {
+                if i <= max_len {
+                    let i = self.constant(F::from_canonical_usize(i));
+                    self.is_equal(len, i)
+                } else {
+                    _false
+                }
+            })
+            .collect::<Vec<_>>();
+        let num_ends = self.add_many(is_end.iter().map(|b| b.target));
+        self.assert_one(num_ends);
+
+        // The padding goes in the block containing the end of the message.
+        let is_last_block = is_end
+            .chunks(KECCAK256_RATE_BYTES)
+            .map(|block| self.add_many(block.iter().map(|b| b.target)))
+            .collect::<Vec<_>>();
+
+        let first_pad_byte = F::from_canonical_u8(0x01);
+        let last_pad_byte = F::from_canonical_u8(0x80);
+        let mut in_message = self.one();
+        let mut padded = Vec::with_capacity(padded_len);
+        for i in 0..padded_len {
+            // After this, `in_message` is set iff `i < len`.
+            in_message = self.sub(in_message, is_end[i].target);
+            let mut byte = match bytes.get(i) {
+                Some(&byte) => self.mul(in_message, byte),
+                None => self.zero(),
+            };
+            byte = self.mul_const_add(first_pad_byte, is_end[i].target, byte);
+            if i % KECCAK256_RATE_BYTES == KECCAK256_RATE_BYTES - 1 {
+                let block_index = i / KECCAK256_RATE_BYTES;
+                byte = self.mul_const_add(last_pad_byte, is_last_block[block_index], byte);
+            }
+            padded.push(byte);
+        }
+
+        let tables = self.keccak_tables();
+        let zero = self.zero();
+        let mut state: KeccakState = [[[zero; LANE_NIBBLES]; 5]; 5];
+        let mut digest = [zero; 32];
+        for (block_index, block) in padded.chunks(KECCAK256_RATE_BYTES).enumerate() {
+            self.keccak_absorb(&tables, &mut state, block, block_index == 0);
+
+            let block_digest = self.keccak_digest(&state);
+            for (d, b) in digest.iter_mut().zip(block_digest) {
+                *d = self.mul_add(is_last_block[block_index], b, *d);
+            }
+        }
+
+        digest
+    }
+
+    fn keccak_tables(&mut self) -> KeccakTables {
+        let nibble_pairs = (0..1 << 8).collect_vec();
+        let nibble_triples = (0..1 << 12).collect_vec();
+        KeccakTables {
+            high_nibble: self.add_lookup_table_from_fn(high_nibble, &nibble_pairs),
+            top_bit: self.add_lookup_table_from_fn(top_bit, &(0..1 << 4).collect_vec()),
+            xor2: self.add_lookup_table_from_fn(xor2_nibbles, &nibble_pairs),
+            xor3: self.add_lookup_table_from_fn(xor3_nibbles, &nibble_triples),
+            theta_d: self.add_lookup_table_from_fn(theta_d_nibble, &(0..1 << 9).collect_vec()),
+            rotate: [
+                (
+                    self.add_lookup_table_from_fn(xor_rotate_low::<1>, &nibble_pairs),
+                    self.add_lookup_table_from_fn(xor_rotate_high::<1>, &nibble_pairs),
+                ),
+                (
+                    self.add_lookup_table_from_fn(xor_rotate_low::<2>, &nibble_pairs),
+                    self.add_lookup_table_from_fn(xor_rotate_high::<2>, &nibble_pairs),
+                ),
+                (
+                    self.add_lookup_table_from_fn(xor_rotate_low::<3>, &nibble_pairs),
+                    self.add_lookup_table_from_fn(xor_rotate_high::<3>, &nibble_pairs),
+                ),
+            ],
+            chi: self.add_lookup_table_from_fn(chi_nibbles, &nibble_triples),
+        }
+    }
+
+    /// Returns the lookup index `16 a + b` of two nibbles.
+    fn nibble_pair(&mut self, a: Target, b: Target) -> Target {
+        self.mul_const_add(F::from_canonical_u64(1 << 4), a, b)
+    }
+
+    /// Returns the lookup index `256 a + 16 b + c` of three nibbles.
+    fn nibble_triple(&mut self, a: Target, b: Target, c: Target) -> Target {
+        let ab = self.nibble_pair(a, b);
+        self.nibble_pair(ab, c)
+    }
+
+    /// XORs the bytes of `block` into the rate portion of `state`, and applies the permutation.
+    fn keccak_absorb(
+        &mut self,
+        tables: &KeccakTables,
+        state: &mut KeccakState,
+        block: &[Target],
+        first: bool,
+    ) {
+        for (i, lane_bytes) in block.chunks(LANE_NIBBLES / 2).enumerate() {
+            let (x, y) = (i % 5, i / 5);
+            for (k, &byte) in lane_bytes.iter().enumerate() {
+                let high = self.add_lookup_from_index(byte, tables.high_nibble);
+                let low = self.mul_const_add(-F::from_canonical_u64(1 << 4), high, byte);
+                for (j, nibble) in [(2 * k, low), (2 * k + 1, high)] {
+                    // The initial state is zero, so the first block can be copied in directly.
+                    state[x][y][j] = if first {
+                        nibble
+                    } else {
+                        let index = self.nibble_pair(state[x][y][j], nibble);
+                        self.add_lookup_from_index(index, tables.xor2)
+                    };
+                }
+            }
+        }
+        self.keccak_f(tables, state);
+    }
+
+    /// Squeezes a 32-byte digest out of `state`.
+    fn keccak_digest(&mut self, state: &KeccakState) -> [Target; 32] {
+        let output_nibbles = (0..4).flat_map(|x| state[x][0]).collect_vec();
+        output_nibbles
+            .chunks(2)
+            .map(|nibbles| self.nibble_pair(nibbles[1], nibbles[0]))
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Applies the Keccak-f[1600] permutation to `state`.
+    fn keccak_f(&mut self, tables: &KeccakTables, state: &mut KeccakState) {
+        for round_constant in ROUND_CONSTANTS {
+            // theta
+            let c: [Lane; 5] = core::array::from_fn(|x| {
+                core::array::from_fn(|j| {
+                    let lane = |y: usize| state[x][y][j];
+                    let index = self.nibble_triple(lane(0), lane(1), lane(2));
+                    let partial = self.add_lookup_from_index(index, tables.xor3);
+                    let index = self.nibble_triple(partial, lane(3), lane(4));
+                    self.add_lookup_from_index(index, tables.xor3)
+                })
+            });
+            let d: [Lane; 5] = core::array::from_fn(|x| {
+                let (c_prev, c_next) = (c[(x + 4) % 5], c[(x + 1) % 5]);
+                core::array::from_fn(|j| {
+                    // `D[x] = C[x - 1] ^ rotate_left(C[x + 1], 1)`.
+                    let carry = c_next[(j + LANE_NIBBLES - 1) % LANE_NIBBLES];
+                    let carry = self.add_lookup_from_index(carry, tables.top_bit);
+                    let index = self.nibble_pair(c_prev[j], c_next[j]);
+                    let index = self.mul_const_add(F::TWO, index, carry);
+                    self.add_lookup_from_index(index, tables.theta_d)
+                })
+            });
+
+            // The rest of theta, fused with rho and pi.
+            let mut b = *state;
+            for x in 0..5 {
+                for y in 0..5 {
+                    let indices: Lane =
+                        core::array::from_fn(|j| self.nibble_pair(state[x][y][j], d[x][j]));
+                    let (q, s) = (ROTATION_OFFSETS[x][y] / 4, ROTATION_OFFSETS[x][y] % 4);
+                    let rotated = if s == 0 {
+                        indices.map(|index| self.add_lookup_from_index(index, tables.xor2))
+                    } else {
+                        let (low_table, high_table) = tables.rotate[s - 1];
+                        let low = indices.map(|index| self.add_lookup_from_index(index, low_table));
+                        let high =
+                            indices.map(|index| self.add_lookup_from_index(index, high_table));
+                        core::array::from_fn(|j| {
+                            self.add(low[j], high[(j + LANE_NIBBLES - 1) % LANE_NIBBLES])
+                        })
+                    };
+                    b[y][(2 * x + 3 * y) % 5] =
+                        core::array::from_fn(|j| rotated[(j + LANE_NIBBLES - q) % LANE_NIBBLES]);
+                }
+            }
+
+            // chi
+            for x in 0..5 {
+                for y in 0..5 {
+                    for j in 0..LANE_NIBBLES {
+                        let index = self.nibble_triple(
+                            b[x][y][j],
+                            b[(x + 1) % 5][y][j],
+                            b[(x + 2) % 5][y][j],
+                        );
+                        state[x][y][j] = self.add_lookup_from_index(index, tables.chi);
+                    }
+                }
+            }
+
+            // iota
+            for j in 0..LANE_NIBBLES {
+                let constant_nibble = (round_constant >> (4 * j)) & 0xf;
+                if constant_nibble != 0 {
+                    let index =
+                        self.add_const(state[0][0][j], F::from_canonical_u64(constant_nibble << 4));
+                    state[0][0][j] = self.add_lookup_from_index(index, tables.xor2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use keccak_hash::keccak;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::{
+        KeccakState, KECCAK256_RATE_BYTES, LANE_NIBBLES, ROTATION_OFFSETS, ROUND_CONSTANTS,
+    };
+    use crate::field::extension::Extendable;
+    use crate::field::types::Field;
+    use crate::hash::hash_types::RichField;
+    use crate::iop::target::BoolTarget;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    type BitLane = [BoolTarget; 64];
+
+    fn rotate_left(lane: &BitLane, n: usize) -> BitLane {
+        core::array::from_fn(|z| lane[(z + 64 - n) % 64])
+    }
+
+    /// Keccak-f with one base arithmetic operation per bit operation, to compare costs against.
+    fn keccak_f_bits<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        state: &mut [[BitLane; 5]; 5],
+    ) {
+        for round_constant in ROUND_CONSTANTS {
+            let c: [BitLane; 5] = core::array::from_fn(|x| {
+                core::array::from_fn(|z| {
+                    let mut parity = state[x][0][z];
+                    for y in 1..5 {
+                        parity = builder.xor(parity, state[x][y][z]);
+                    }
+                    parity
+                })
+            });
+            for x in 0..5 {
+                let rotated = rotate_left(&c[(x + 1) % 5], 1);
+                for z in 0..64 {
+                    let d = builder.xor(c[(x + 4) % 5][z], rotated[z]);
+                    for y in 0..5 {
+                        state[x][y][z] = builder.xor(state[x][y][z], d);
+                    }
+                }
+            }
+
+            let mut b = *state;
+            for x in 0..5 {
+                for y in 0..5 {
+                    b[y][(2 * x + 3 * y) % 5] = rotate_left(&state[x][y], ROTATION_OFFSETS[x][y]);
+                }
+            }
+
+            for x in 0..5 {
+                for y in 0..5 {
+                    for z in 0..64 {
+                        let (b1, b2) = (b[(x + 1) % 5][y][z], b[(x + 2) % 5][y][z]);
+                        let and_not =
+                            builder.arithmetic(-F::ONE, F::ONE, b1.target, b2.target, b2.target);
+                        state[x][y][z] = builder.xor(b[x][y][z], BoolTarget::new_unsafe(and_not));
+                    }
+                }
+            }
+
+            for z in 0..64 {
+                if (round_constant >> z) & 1 == 1 {
+                    state[0][0][z] = builder.not(state[0][0][z]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_keccak_f_gate_count() {
+        let config = CircuitConfig::standard_recursion_config();
+
+        // Absorb one block in both cases, so that both count the cost of range-checking bytes.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let block = builder.add_virtual_targets(KECCAK256_RATE_BYTES);
+        let block_bits = block
+            .iter()
+            .flat_map(|&byte| builder.split_le(byte, 8))
+            .collect::<Vec<_>>();
+        let _false = builder._false();
+        let mut state = [[[_false; 64]; 5]; 5];
+        for (i, lane) in block_bits.chunks(64).enumerate() {
+            state[i % 5][i / 5].copy_from_slice(lane);
+        }
+        keccak_f_bits(&mut builder, &mut state);
+        let bit_gates = builder.num_gates();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let block = builder.add_virtual_targets(KECCAK256_RATE_BYTES);
+        let tables = builder.keccak_tables();
+        let zero = builder.zero();
+        let mut state: KeccakState = [[[zero; LANE_NIBBLES]; 5]; 5];
+        builder.keccak_absorb(&tables, &mut state, &block, true);
+        // Count the lookup and lookup table rows too.
+        builder.add_all_lookups();
+        let lookup_gates = builder.num_gates();
+
+        assert!(
+            3 * lookup_gates < bit_gates,
+            "{lookup_gates} gates with lookups, {bit_gates} gates with bit operations"
+        );
+    }
+
+    fn test_keccak256(len: usize) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let input = builder.add_virtual_targets(len);
+        let output = builder.keccak256(&input);
+        builder.register_public_inputs(&output);
+
+        let data = builder.build::<C>();
+
+        let mut rng = OsRng;
+        let message = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        let mut pw = PartialWitness::new();
+        for (&t, &byte) in input.iter().zip(&message) {
+            pw.set_target(t, F::from_canonical_u8(byte));
+        }
+
+        let proof = data.prove(pw)?;
+        let expected = keccak(&message)
+            .0
+            .iter()
+            .map(|&byte| F::from_canonical_u8(byte))
+            .collect::<Vec<_>>();
+        assert_eq!(proof.public_inputs, expected);
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_keccak256_empty() -> Result<()> {
+        test_keccak256(0)
+    }
+
+    #[test]
+    fn test_keccak256_multi_block() -> Result<()> {
+        // A full block of message, so the padding spills into a second block.
+        test_keccak256(KECCAK256_RATE_BYTES)
+    }
+
+    #[test]
+    fn test_keccak256_variable() -> Result<()> {
+        const MAX_LEN: usize = KECCAK256_RATE_BYTES + 4;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let input = builder.add_virtual_targets(MAX_LEN);
+        let len = builder.add_virtual_target();
+        let output = builder.keccak256_variable(&input, len);
+        builder.register_public_inputs(&output);
+
+        let data = builder.build::<C>();
+
+        let mut rng = OsRng;
+        // Cover an empty message, a message ending just before a block boundary, one ending on
+        // it, and one using the whole input.
+        for message_len in [0, KECCAK256_RATE_BYTES - 1, KECCAK256_RATE_BYTES, MAX_LEN] {
+            let input_values = (0..MAX_LEN).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+            let mut pw = PartialWitness::new();
+            for (&t, &byte) in input.iter().zip(&input_values) {
+                pw.set_target(t, F::from_canonical_u8(byte));
+            }
+            pw.set_target(len, F::from_canonical_usize(message_len));
+
+            let proof = data.prove(pw)?;
+            let expected = keccak(&input_values[..message_len])
+                .0
+                .iter()
+                .map(|&byte| F::from_canonical_u8(byte))
+                .collect::<Vec<_>>();
+            assert_eq!(proof.public_inputs, expected);
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,531 @@
+//! Verification of Merkle-Patricia trie proofs, as used by Ethereum's state and storage tries.
+//!
+//! A proof is the list of RLP-encoded nodes on the path from the root to a key. Each node is
+//! hashed with Keccak-256 and checked against the reference held by its parent, and the key is
+//! consumed nibble by nibble through branch, extension and leaf nodes. The path ends either at a
+//! leaf holding the key, proving inclusion, or at an empty branch slot or a diverging path,
+//! proving exclusion.
+//!
+//! Keys are expected to be 32 bytes, as in Ethereum's secure tries where they are hashes of
+//! addresses or storage slots. Child nodes must be referenced by their hash: tries with nodes
+//! shorter than 32 bytes, which Ethereum embeds in their parent, are not supported.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::gadgets::rlp::RlpItemTarget;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The maximum length of an RLP-encoded node in Ethereum's tries, reached by a branch node with 16
+/// hashed children and a 32-byte value.
+pub const MAX_MPT_NODE_BYTES: usize = 532;
+
+const KEY_NIBBLES: usize = 64;
+
+/// The maximum length of a hex-prefix encoded path: a flag byte followed by 32 bytes.
+const MAX_PATH_BYTES: usize = 33;
+
+/// An RLP-encoded trie node, zero-padded to a fixed maximum length.
+#[derive(Clone, Debug)]
+pub struct MptNodeTarget {
+    pub bytes: Vec<Target>,
+    pub len: Target,
+}
+
+/// A proof for a key in a trie, as the nodes on the path from the root. Proofs may be shorter
+/// than the number of nodes, in which case the remaining nodes are ignored.
+#[derive(Clone, Debug)]
+pub struct MptProofTarget {
+    pub nodes: Vec<MptNodeTarget>,
+}
+
+impl MptProofTarget {
+    pub fn max_depth(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn max_node_bytes(&self) -> usize {
+        self.nodes[0].bytes.len()
+    }
+}
+
+/// The outcome of verifying an `MptProofTarget`.
+#[derive(Clone, Debug)]
+pub struct MptProofResultTarget {
+    /// Whether the key is in the trie. If not, the proof is an exclusion proof.
+    pub found: BoolTarget,
+    /// The value stored at the key, zero-padded; all zeros if the key is not in the trie.
+    pub value: Vec<Target>,
+    /// The length of the value, or zero if the key is not in the trie.
+    pub value_len: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a new `MptProofTarget` of up to `max_depth` nodes of up to `max_node_bytes` bytes.
+    pub fn add_virtual_mpt_proof_target(
+        &mut self,
+        max_depth: usize,
+        max_node_bytes: usize,
+    ) -> MptProofTarget {
+        assert!(max_depth > 0, "A proof has at least one node");
+        let nodes = (0..max_depth)
+            .map(|_| MptNodeTarget {
+                bytes: self.add_virtual_targets(max_node_bytes),
+                len: self.add_virtual_target(),
+            })
+            .collect();
+        MptProofTarget { nodes }
+    }
+
+    /// Verifies `proof` for the 32-byte `key` against the Keccak-256 `root` of a trie, and returns
+    /// whether the key is in the trie, along with its value. Values longer than `max_value_bytes`
+    /// make the circuit unsatisfiable, as does a proof which does not end within its maximum
+    /// depth.
+    pub fn verify_mpt_proof(
+        &mut self,
+        root: &[Target; 32],
+        key: &[Target; 32],
+        proof: &MptProofTarget,
+        max_value_bytes: usize,
+    ) -> MptProofResultTarget {
+        let zero = self.zero();
+        let one = self.one();
+        let key_nibbles = key
+            .iter()
+            .flat_map(|&byte| self.split_byte_to_nibbles(byte))
+            .collect::<Vec<_>>();
+
+        let mut active = self._true();
+        let mut expected_hash = root.to_vec();
+        let mut key_index = zero;
+        let mut found = self._false();
+        let mut value_offset = zero;
+        let mut value_len = zero;
+        let mut value_node_bytes = vec![zero; proof.max_node_bytes()];
+
+        for node in &proof.nodes {
+            let hash = self.keccak256_variable(&node.bytes, node.len);
+            for (&h, &e) in hash.iter().zip(&expected_hash) {
+                let diff = self.sub(h, e);
+                self.assert_zero_if(active, diff);
+            }
+
+            // Pad the node, so that fixed-length reads past its end stay in range.
+            let bytes = node
+                .bytes
+                .iter()
+                .copied()
+                .chain([zero; 2 * MAX_PATH_BYTES])
+                .collect::<Vec<_>>();
+
+            let list = self.rlp_decode_item(&bytes, zero);
+            let list_end = self.rlp_item_end(&list);
+            let not_list = self.not(list.is_list);
+            self.assert_zero_if(active, not_list.target);
+            let len_diff = self.sub(list_end, node.len);
+            self.assert_zero_if(active, len_diff);
+
+            // Branch nodes have 17 items, and extension and leaf nodes have 2. The extra 15 items
+            // take at least 15 bytes, so at most one of these holds.
+            let items = self.rlp_decode_list_items(&bytes, &list, 17);
+            let short_end = self.rlp_item_end(&items[1]);
+            let branch_end = self.rlp_item_end(&items[16]);
+            let is_short = self.is_equal(short_end, list_end);
+            let is_branch = self.is_equal(branch_end, list_end);
+            let num_kinds = self.add(is_short.target, is_branch.target);
+            let invalid_kind = self.sub(num_kinds, one);
+            self.assert_zero_if(active, invalid_kind);
+
+            // In a branch node, the next nibble of the key selects a child.
+            let nibble = self.random_access(key_index, key_nibbles.clone());
+            let child_offsets = items[..16]
+                .iter()
+                .map(|item| item.payload_offset)
+                .collect::<Vec<_>>();
+            let child_lens = items[..16]
+                .iter()
+                .map(|item| item.payload_len)
+                .collect::<Vec<_>>();
+            let branch_child_offset = self.random_access_padded(nibble, &child_offsets);
+            let branch_child_len = self.random_access_padded(nibble, &child_lens);
+            let branch_child_empty = self.is_equal(branch_child_len, zero);
+
+            // In an extension or leaf node, the path must match the next nibbles of the key.
+            let active_short = self.and(active, is_short);
+            let (is_leaf, path_len, path_matches) =
+                self.mpt_match_path(&bytes, &items[0], &key_nibbles, key_index, active_short);
+            let key_end = self.add(key_index, path_len);
+            let num_key_nibbles = self.constant(F::from_canonical_usize(KEY_NIBBLES));
+            let reaches_key_end = self.is_equal(key_end, num_key_nibbles);
+
+            let branch_child_not_empty = self.not(branch_child_empty);
+            let branch_continues = self.and(is_branch, branch_child_not_empty);
+            let is_extension = self.not(is_leaf);
+            let extension_matches = self.and(is_extension, path_matches);
+            let extension_continues = self.and(is_short, extension_matches);
+            let continues = self.or(branch_continues, extension_continues);
+            let continues = self.and(active, continues);
+
+            let leaf_matches = self.and(is_leaf, path_matches);
+            let leaf_matches = self.and(leaf_matches, reaches_key_end);
+            let found_here = self.and(active_short, leaf_matches);
+
+            // The reference to the next node, which must be a 32-byte hash.
+            let child_offset = self.select(is_branch, branch_child_offset, items[1].payload_offset);
+            let child_len = self.select(is_branch, branch_child_len, items[1].payload_len);
+            let thirty_two = self.constant(F::from_canonical_usize(32));
+            let child_len_diff = self.sub(child_len, thirty_two);
+            self.assert_zero_if(continues, child_len_diff);
+            expected_hash = self.rlp_read_bytes(&bytes, child_offset, 32);
+
+            let consumed = self.select(is_branch, one, path_len);
+            let next_key_index = self.add(key_index, consumed);
+            key_index = self.select(continues, next_key_index, key_index);
+
+            // Remember where the value is, if this is the leaf holding the key.
+            found = self.or(found, found_here);
+            value_offset = self.mul_add(found_here.target, items[1].payload_offset, value_offset);
+            value_len = self.mul_add(found_here.target, items[1].payload_len, value_len);
+            for (v, &b) in value_node_bytes.iter_mut().zip(&node.bytes) {
+                *v = self.mul_add(found_here.target, b, *v);
+            }
+
+            active = continues;
+        }
+
+        // The proof must end within its maximum depth.
+        self.assert_zero(active.target);
+
+        // Extract the value, checking that it fits in `max_value_bytes`.
+        value_node_bytes.extend(vec![zero; max_value_bytes]);
+        let mut in_value = one;
+        let mut num_ends = zero;
+        let mut value = Vec::with_capacity(max_value_bytes);
+        for i in 0..=max_value_bytes {
+            let i_target = self.constant(F::from_canonical_usize(i));
+            let is_end = self.is_equal(value_len, i_target);
+            num_ends = self.add(num_ends, is_end.target);
+            in_value = self.sub(in_value, is_end.target);
+            if i < max_value_bytes {
+                let index = self.add(value_offset, i_target);
+                let byte = self.random_access_padded(index, &value_node_bytes);
+                value.push(self.mul(in_value, byte));
+            }
+        }
+        self.assert_one(num_ends);
+
+        MptProofResultTarget {
+            found,
+            value,
+            value_len,
+        }
+    }
+
+    /// Asserts that `x` is zero if `condition` holds.
+    fn assert_zero_if(&mut self, condition: BoolTarget, x: Target) {
+        let product = self.mul(condition.target, x);
+        self.assert_zero(product);
+    }
+
+    /// Splits a byte into its high and low nibbles, in that order.
+    fn split_byte_to_nibbles(&mut self, byte: Target) -> [Target; 2] {
+        let bits = self.split_le(byte, 8);
+        [self.le_sum(bits[4..].iter()), self.le_sum(bits[..4].iter())]
+    }
+
+    /// Returns `nibbles[shift..]`, padded with zeros to the length of `nibbles`. The shift must be
+    /// less than `KEY_NIBBLES`.
+    fn shift_nibbles(&mut self, nibbles: &[Target], shift: Target) -> Vec<Target> {
+        let zero = self.zero();
+        let shift_bits = self.split_le(shift, KEY_NIBBLES.trailing_zeros() as usize);
+        let mut shifted = nibbles.to_vec();
+        for (i, &bit) in shift_bits.iter().enumerate() {
+            shifted = (0..nibbles.len())
+                .map(|j| {
+                    let moved = shifted.get(j + (1 << i)).copied().unwrap_or(zero);
+                    self.select(bit, moved, shifted[j])
+                })
+                .collect();
+        }
+        shifted
+    }
+
+    /// Decodes the hex-prefix encoded `path` of an extension or leaf node, and compares it with the
+    /// key nibbles starting at `key_index`. Returns whether the node is a leaf, the length of the
+    /// path in nibbles, and whether it matches the key. The path encoding is only checked to be
+    /// valid if `check` holds.
+    fn mpt_match_path(
+        &mut self,
+        bytes: &[Target],
+        path: &RlpItemTarget,
+        key_nibbles: &[Target],
+        key_index: Target,
+        check: BoolTarget,
+    ) -> (BoolTarget, Target, BoolTarget) {
+        let zero = self.zero();
+        let one = self.one();
+        let path_bytes = self.rlp_read_bytes(bytes, path.payload_offset, MAX_PATH_BYTES);
+
+        // The high nibble of the first byte holds the flags: 1 for odd paths, 2 for leaves.
+        let flag_bits = self.split_le(path_bytes[0], 8);
+        let is_odd = flag_bits[4];
+        let is_leaf = flag_bits[5];
+        let invalid_flags = self.add(flag_bits[6].target, flag_bits[7].target);
+        self.assert_zero_if(check, invalid_flags);
+
+        // Odd paths start with the low nibble of the first byte; even paths with the second byte.
+        let hex_prefix_nibbles = path_bytes
+            .iter()
+            .flat_map(|&byte| self.split_byte_to_nibbles(byte))
+            .collect::<Vec<_>>();
+        let path_nibbles = (0..KEY_NIBBLES)
+            .map(|j| self.select(is_odd, hex_prefix_nibbles[j + 1], hex_prefix_nibbles[j + 2]))
+            .collect::<Vec<_>>();
+        let path_len = self.mul_const_add(F::TWO, path.payload_len, is_odd.target);
+        let path_len = self.add_const(path_len, -F::TWO);
+
+        let key_nibbles = self.shift_nibbles(key_nibbles, key_index);
+        let mut in_path = one;
+        let mut num_ends = zero;
+        let mut num_mismatches = zero;
+        for j in 0..=KEY_NIBBLES {
+            let j_target = self.constant(F::from_canonical_usize(j));
+            let is_end = self.is_equal(path_len, j_target);
+            num_ends = self.add(num_ends, is_end.target);
+            in_path = self.sub(in_path, is_end.target);
+            if j < KEY_NIBBLES {
+                let nibble_matches = self.is_equal(path_nibbles[j], key_nibbles[j]);
+                // in_path * (1 - nibble_matches)
+                let mismatch =
+                    self.arithmetic(-F::ONE, F::ONE, in_path, nibble_matches.target, in_path);
+                num_mismatches = self.add(num_mismatches, mismatch);
+            }
+        }
+        let invalid_len = self.sub(num_ends, one);
+        self.assert_zero_if(check, invalid_len);
+        let path_matches = self.is_equal(num_mismatches, zero);
+
+        (is_leaf, path_len, path_matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+    use keccak_hash::keccak;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::MptProofTarget;
+    use crate::field::types::{Field, PrimeField64};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const MAX_DEPTH: usize = 3;
+    const MAX_NODE_BYTES: usize = 100;
+    const MAX_VALUE_BYTES: usize = 16;
+
+    fn encode_header(len: usize, short_base: u8) -> Vec<u8> {
+        assert!(len < 1 << 8);
+        if len <= 55 {
+            vec![short_base + len as u8]
+        } else {
+            vec![short_base + 56, len as u8]
+        }
+    }
+
+    fn encode_string(s: &[u8]) -> Vec<u8> {
+        if s.len() == 1 && s[0] < 0x80 {
+            return s.to_vec();
+        }
+        [encode_header(s.len(), 0x80), s.to_vec()].concat()
+    }
+
+    fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload = items.concat();
+        [encode_header(payload.len(), 0xc0), payload].concat()
+    }
+
+    fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+        let is_odd = nibbles.len() % 2 == 1;
+        let mut padded = vec![2 * is_leaf as u8 + is_odd as u8];
+        if !is_odd {
+            padded.push(0);
+        }
+        padded.extend(nibbles);
+        padded.chunks(2).map(|n| (n[0] << 4) | n[1]).collect()
+    }
+
+    fn nibbles(key: &[u8; 32]) -> Vec<u8> {
+        key.iter().flat_map(|b| [b >> 4, b & 0xf]).collect()
+    }
+
+    /// A trie holding two keys starting with nibble 1, and then nibbles 2 and 7: an extension node
+    /// at the root, a branch node, and two leaves.
+    struct TestTrie {
+        keys: [[u8; 32]; 2],
+        values: [Vec<u8>; 2],
+        extension: Vec<u8>,
+        branch: Vec<u8>,
+        leaves: [Vec<u8>; 2],
+    }
+
+    impl TestTrie {
+        fn new(rng: &mut OsRng) -> Self {
+            let mut keys = [rng.gen::<[u8; 32]>(), rng.gen::<[u8; 32]>()];
+            keys[0][0] = 0x12;
+            keys[1][0] = 0x17;
+            let values = [vec![0xab; 5], vec![0x01; 12]];
+
+            let leaves = [0, 1].map(|i| {
+                let path = hex_prefix(&nibbles(&keys[i])[2..], true);
+                encode_list(&[encode_string(&path), encode_string(&values[i])])
+            });
+            let mut children = vec![encode_string(&[]); 17];
+            children[2] = encode_string(&keccak(&leaves[0]).0);
+            children[7] = encode_string(&keccak(&leaves[1]).0);
+            let branch = encode_list(&children);
+            let extension = encode_list(&[
+                encode_string(&hex_prefix(&[1], false)),
+                encode_string(&keccak(&branch).0),
+            ]);
+
+            Self {
+                keys,
+                values,
+                extension,
+                branch,
+                leaves,
+            }
+        }
+
+        fn root(&self) -> [u8; 32] {
+            keccak(&self.extension).0
+        }
+    }
+
+    struct MptCircuit {
+        data: CircuitData<F, C, D>,
+        root: [Target; 32],
+        key: [Target; 32],
+        proof: MptProofTarget,
+    }
+
+    fn mpt_circuit() -> MptCircuit {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let root = builder.add_virtual_target_arr();
+        let key = builder.add_virtual_target_arr();
+        let proof = builder.add_virtual_mpt_proof_target(MAX_DEPTH, MAX_NODE_BYTES);
+        let result = builder.verify_mpt_proof(&root, &key, &proof, MAX_VALUE_BYTES);
+        builder.register_public_input(result.found.target);
+        builder.register_public_input(result.value_len);
+        builder.register_public_inputs(&result.value);
+
+        MptCircuit {
+            data: builder.build::<C>(),
+            root,
+            key,
+            proof,
+        }
+    }
+
+    /// Proves the given proof, and returns the value found, if any.
+    fn prove(
+        circuit: &MptCircuit,
+        root: [u8; 32],
+        key: [u8; 32],
+        nodes: &[Vec<u8>],
+    ) -> Result<Option<Vec<u8>>> {
+        let mut pw = PartialWitness::new();
+        for (&t, &b) in circuit.root.iter().zip(&root) {
+            pw.set_target(t, F::from_canonical_u8(b));
+        }
+        for (&t, &b) in circuit.key.iter().zip(&key) {
+            pw.set_target(t, F::from_canonical_u8(b));
+        }
+        pw.set_mpt_proof_target(&circuit.proof, nodes);
+
+        let proof = circuit.data.prove(pw)?;
+        let public_inputs = proof
+            .public_inputs
+            .iter()
+            .map(|x| x.to_canonical_u64() as u8)
+            .collect::<Vec<_>>();
+        circuit.data.verify(proof)?;
+
+        let value_len = public_inputs[1] as usize;
+        Ok((public_inputs[0] == 1).then(|| public_inputs[2..2 + value_len].to_vec()))
+    }
+
+    #[test]
+    fn test_mpt_proofs() -> Result<()> {
+        let mut rng = OsRng;
+        let trie = TestTrie::new(&mut rng);
+        let root = trie.root();
+        let circuit = mpt_circuit();
+
+        // Inclusion proofs for both keys.
+        for i in 0..2 {
+            let nodes = [
+                trie.extension.clone(),
+                trie.branch.clone(),
+                trie.leaves[i].clone(),
+            ];
+            let value = prove(&circuit, root, trie.keys[i], &nodes)?;
+            assert_eq!(value, Some(trie.values[i].clone()));
+        }
+
+        // The extension node's path doesn't match.
+        let mut key = trie.keys[0];
+        key[0] = 0x52;
+        let value = prove(&circuit, root, key, core::slice::from_ref(&trie.extension))?;
+        assert_eq!(value, None);
+
+        // The branch node has no child for the second nibble.
+        key[0] = 0x13;
+        let nodes = [trie.extension.clone(), trie.branch.clone()];
+        let value = prove(&circuit, root, key, &nodes)?;
+        assert_eq!(value, None);
+
+        // The leaf's path diverges from the key.
+        let mut key = trie.keys[1];
+        key[31] ^= 1;
+        let nodes = [
+            trie.extension.clone(),
+            trie.branch.clone(),
+            trie.leaves[1].clone(),
+        ];
+        let value = prove(&circuit, root, key, &nodes)?;
+        assert_eq!(value, None);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mpt_proof_tampered_leaf() {
+        let mut rng = OsRng;
+        let trie = TestTrie::new(&mut rng);
+        let circuit = mpt_circuit();
+
+        let mut leaf = trie.leaves[0].clone();
+        *leaf.last_mut().unwrap() ^= 1;
+        let nodes = [trie.extension.clone(), trie.branch.clone(), leaf];
+        prove(&circuit, trie.root(), trie.keys[0], &nodes).unwrap();
+    }
+}
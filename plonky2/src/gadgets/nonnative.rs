@@ -0,0 +1,640 @@
+//! Arithmetic over a prime field `FF` other than the circuit's native field, e.g. the base field of
+//! secp256k1 or of BN254.
+//!
+//! An element of `FF` is represented by little-endian limbs of `NONNATIVE_LIMB_BITS` bits each.
+//! Every limb is range-checked with a lookup into a byte table, and every value produced by one of
+//! the gadgets below is checked to be canonical, i.e. less than the order of `FF`.
+//!
+//! Each operation witnesses a quotient `q` and a remainder `r` such that `expr = q * N + r` holds
+//! over the integers, where `N` is the order of `FF`. The identity is checked column by column: the
+//! limb products are accumulated into columns of weight `2^(16 k)`, and the signed carries between
+//! consecutive columns are witnessed and range-checked, so that no column can wrap around the
+//! native modulus.
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use num::{BigInt, BigUint, Integer, One, Zero};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField, PrimeField64};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+use crate::util::{ceil_div_usize, log2_ceil};
+
+/// The number of bits in each limb of a `NonNativeTarget`.
+pub const NONNATIVE_LIMB_BITS: usize = 16;
+
+/// The number of limbs needed to represent an element of `FF`.
+pub fn num_nonnative_limbs<FF: Field>() -> usize {
+    ceil_div_usize(FF::BITS, NONNATIVE_LIMB_BITS)
+}
+
+/// A target representing an element of the non-native field `FF`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NonNativeTarget<FF: Field> {
+    pub(crate) limbs: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<FF: Field> NonNativeTarget<FF> {
+    pub(crate) fn from_limbs(limbs: Vec<Target>) -> Self {
+        debug_assert_eq!(limbs.len(), num_nonnative_limbs::<FF>());
+        Self {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The little-endian `NONNATIVE_LIMB_BITS`-bit limbs of this value.
+    pub fn limbs(&self) -> &[Target] {
+        &self.limbs
+    }
+}
+
+/// Splits `x` into `num_limbs` little-endian `NONNATIVE_LIMB_BITS`-bit limbs, truncating any
+/// higher bits.
+pub(crate) fn biguint_to_limbs(x: &BigUint, num_limbs: usize) -> Vec<u64> {
+    let mask = BigUint::from((1u64 << NONNATIVE_LIMB_BITS) - 1);
+    (0..num_limbs)
+        .map(|i| {
+            let limb = (x >> (i * NONNATIVE_LIMB_BITS)) & &mask;
+            limb.to_u64_digits().first().copied().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Recombines little-endian `NONNATIVE_LIMB_BITS`-bit limbs into an integer.
+pub(crate) fn limbs_to_biguint(limbs: &[u64]) -> BigUint {
+    limbs.iter().rev().fold(BigUint::zero(), |acc, &limb| {
+        (acc << NONNATIVE_LIMB_BITS) + BigUint::from(limb)
+    })
+}
+
+/// Interprets a native field element as a signed integer in `(-p/2, p/2)`.
+fn to_signed<F: PrimeField64>(x: F) -> i128 {
+    let x = x.to_canonical_u64();
+    if x > F::ORDER / 2 {
+        x as i128 - F::ORDER as i128
+    } else {
+        x as i128
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds range-checked limbs of a new `NonNativeTarget`. The value is not checked to be
+    /// canonical.
+    pub(crate) fn add_virtual_nonnative_limbs(&mut self, num_limbs: usize) -> Vec<Target> {
+        let limbs = self.add_virtual_targets(num_limbs);
+        for &limb in &limbs {
+            self.range_check_lookup(limb, NONNATIVE_LIMB_BITS);
+        }
+        limbs
+    }
+
+    /// Adds a new `NonNativeTarget`, constrained to be a canonical element of `FF`.
+    pub fn add_virtual_nonnative_target<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        let x = self.add_virtual_nonnative_target_unchecked();
+        self.assert_less_than_modulus(&x);
+        x
+    }
+
+    fn add_virtual_nonnative_target_unchecked<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        let limbs = self.add_virtual_nonnative_limbs(num_nonnative_limbs::<FF>());
+        NonNativeTarget::from_limbs(limbs)
+    }
+
+    pub fn constant_nonnative<FF: PrimeField>(&mut self, x: FF) -> NonNativeTarget<FF> {
+        let limbs = biguint_to_limbs(&x.to_canonical_biguint(), num_nonnative_limbs::<FF>())
+            .into_iter()
+            .map(|limb| self.constant(F::from_canonical_u64(limb)))
+            .collect();
+        NonNativeTarget::from_limbs(limbs)
+    }
+
+    pub fn zero_nonnative<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        self.constant_nonnative(FF::ZERO)
+    }
+
+    pub fn connect_nonnative<FF: Field>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) {
+        for (&l, &r) in x.limbs.iter().zip(&y.limbs) {
+            self.connect(l, r);
+        }
+    }
+
+    /// Returns `x` if `b` is true, otherwise `y`.
+    pub fn select_nonnative<FF: Field>(
+        &mut self,
+        b: BoolTarget,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let limbs = x
+            .limbs
+            .iter()
+            .zip(&y.limbs)
+            .map(|(&l, &r)| self.select(b, l, r))
+            .collect();
+        NonNativeTarget::from_limbs(limbs)
+    }
+
+    pub fn add_nonnative<FF: PrimeField>(
+        &mut self,
+        a: &NonNativeTarget<FF>,
+        b: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let cols = a
+            .limbs
+            .iter()
+            .zip(&b.limbs)
+            .map(|(&x, &y)| self.add(x, y))
+            .collect::<Vec<_>>();
+        // a + b < 2N, so the quotient is at most 1.
+        self.reduce_nonnative_columns(&cols, NONNATIVE_LIMB_BITS + 1, 1)
+    }
+
+    pub fn sub_nonnative<FF: PrimeField>(
+        &mut self,
+        a: &NonNativeTarget<FF>,
+        b: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        // Compute a + N - b, which lies in [0, 2N).
+        let modulus_limbs = biguint_to_limbs(&FF::order(), num_nonnative_limbs::<FF>());
+        let cols = a
+            .limbs
+            .iter()
+            .zip(&b.limbs)
+            .zip(modulus_limbs)
+            .map(|((&x, &y), n)| {
+                let diff = self.sub(x, y);
+                self.add_const(diff, F::from_canonical_u64(n))
+            })
+            .collect::<Vec<_>>();
+        self.reduce_nonnative_columns(&cols, NONNATIVE_LIMB_BITS + 2, 1)
+    }
+
+    pub fn neg_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let zero = self.zero_nonnative();
+        self.sub_nonnative(&zero, x)
+    }
+
+    pub fn mul_nonnative<FF: PrimeField>(
+        &mut self,
+        a: &NonNativeTarget<FF>,
+        b: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let num_limbs = num_nonnative_limbs::<FF>();
+        let mut cols = vec![self.zero(); 2 * num_limbs - 1];
+        for (i, &x) in a.limbs.iter().enumerate() {
+            for (j, &y) in b.limbs.iter().enumerate() {
+                cols[i + j] = self.mul_add(x, y, cols[i + j]);
+            }
+        }
+        let col_bits = 2 * NONNATIVE_LIMB_BITS + log2_ceil(num_limbs);
+        self.reduce_nonnative_columns(&cols, col_bits, num_limbs)
+    }
+
+    /// Returns `x^-1`. The circuit is unsatisfiable if `x` is zero.
+    pub fn inv_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let inv = self.add_virtual_nonnative_target::<FF>();
+        self.add_simple_generator(NonNativeInverseGenerator {
+            x: x.limbs.clone(),
+            inv: inv.limbs.clone(),
+            modulus: FF::order(),
+        });
+
+        let product = self.mul_nonnative(x, &inv);
+        let one = self.constant_nonnative(FF::ONE);
+        self.connect_nonnative(&product, &one);
+        inv
+    }
+
+    /// Reduces an integer given by little-endian `NONNATIVE_LIMB_BITS`-bit limbs modulo the order
+    /// of `FF`. The limbs are assumed to be range-checked already.
+    pub fn reduce_nonnative_limbs<FF: PrimeField>(
+        &mut self,
+        limbs: &[Target],
+    ) -> NonNativeTarget<FF> {
+        let num_quotient_limbs = limbs.len().saturating_sub(num_nonnative_limbs::<FF>()) + 1;
+        self.reduce_nonnative_columns(limbs, NONNATIVE_LIMB_BITS, num_quotient_limbs)
+    }
+
+    /// Returns the little-endian bits of `x`.
+    pub fn split_nonnative_to_bits<FF: Field>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+    ) -> Vec<BoolTarget> {
+        x.limbs
+            .iter()
+            .flat_map(|&limb| self.split_le(limb, NONNATIVE_LIMB_BITS))
+            .collect()
+    }
+
+    /// Returns the little-endian 2-bit limbs of `x`.
+    pub fn split_nonnative_to_2_bit_limbs<FF: Field>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+    ) -> Vec<Target> {
+        x.limbs
+            .iter()
+            .flat_map(|&limb| self.split_le_base::<4>(limb, NONNATIVE_LIMB_BITS / 2))
+            .collect()
+    }
+
+    /// Checks that `x` is less than the order of `FF`, by showing that `(N - 1) - x` has a
+    /// non-negative limb decomposition.
+    pub fn assert_less_than_modulus<FF: PrimeField>(&mut self, x: &NonNativeTarget<FF>) {
+        let num_limbs = num_nonnative_limbs::<FF>();
+        let max = biguint_to_limbs(&(FF::order() - BigUint::one()), num_limbs);
+        let cols = x
+            .limbs
+            .iter()
+            .zip(max)
+            .map(|(&limb, m)| {
+                let m = self.constant(F::from_canonical_u64(m));
+                self.sub(m, limb)
+            })
+            .collect::<Vec<_>>();
+
+        let diff = self.add_virtual_nonnative_limbs(num_limbs);
+        self.add_simple_generator(NonNativeReduceGenerator {
+            cols: cols.clone(),
+            modulus: FF::order(),
+            quotient: vec![],
+            remainder: diff.clone(),
+        });
+
+        let cols = cols
+            .into_iter()
+            .zip(diff)
+            .map(|(c, d)| self.sub(c, d))
+            .collect::<Vec<_>>();
+        self.assert_zero_limb_sum(&cols, NONNATIVE_LIMB_BITS + 2);
+    }
+
+    /// Given columns `cols` whose signed values are less than `2^col_bits` in absolute value,
+    /// witnesses `q` and `r` such that `sum_k cols[k] 2^(16 k) = q N + r` and returns `r`.
+    fn reduce_nonnative_columns<FF: PrimeField>(
+        &mut self,
+        cols: &[Target],
+        col_bits: usize,
+        num_quotient_limbs: usize,
+    ) -> NonNativeTarget<FF> {
+        let num_limbs = num_nonnative_limbs::<FF>();
+        let modulus = FF::order();
+        let modulus_limbs = biguint_to_limbs(&modulus, num_limbs);
+
+        let quotient = self.add_virtual_nonnative_limbs(num_quotient_limbs);
+        let remainder = self.add_virtual_nonnative_target_unchecked::<FF>();
+        self.add_simple_generator(NonNativeReduceGenerator {
+            cols: cols.to_vec(),
+            modulus,
+            quotient: quotient.clone(),
+            remainder: remainder.limbs.clone(),
+        });
+
+        let num_cols = cols.len().max(num_quotient_limbs + num_limbs - 1);
+        let mut diff = (0..num_cols)
+            .map(|k| cols.get(k).copied().unwrap_or_else(|| self.zero()))
+            .collect::<Vec<_>>();
+        for (i, &q) in quotient.iter().enumerate() {
+            for (j, &n) in modulus_limbs.iter().enumerate() {
+                diff[i + j] = self.mul_const_add(-F::from_canonical_u64(n), q, diff[i + j]);
+            }
+        }
+        for (k, &r) in remainder.limbs.iter().enumerate() {
+            diff[k] = self.sub(diff[k], r);
+        }
+
+        let product_bits = 2 * NONNATIVE_LIMB_BITS + log2_ceil(num_quotient_limbs.min(num_limbs));
+        self.assert_zero_limb_sum(&diff, col_bits.max(product_bits) + 1);
+        self.assert_less_than_modulus(&remainder);
+        remainder
+    }
+
+    /// Checks that `sum_k cols[k] 2^(16 k) = 0` over the integers, given that each column's
+    /// signed value is less than `2^col_bits` in absolute value.
+    fn assert_zero_limb_sum(&mut self, cols: &[Target], col_bits: usize) {
+        let (&last, init) = cols.split_last().expect("No columns");
+        // Each carry is less than `2^(col_bits - 15)` in absolute value; we shift it to be
+        // non-negative before range-checking it.
+        assert!(col_bits + 2 < 63, "Columns may overflow the native field");
+        let carry_bits = col_bits.saturating_sub(NONNATIVE_LIMB_BITS - 1) + 1;
+        let carry_offset = F::from_canonical_u64(1 << (carry_bits - 1));
+        let base = F::from_canonical_u64(1 << NONNATIVE_LIMB_BITS);
+
+        let carries = self.add_virtual_targets(init.len());
+        self.add_simple_generator(CarryGenerator {
+            cols: init.to_vec(),
+            carries: carries.clone(),
+        });
+
+        let mut prev_carry = self.zero();
+        for (&col, &carry) in init.iter().zip(&carries) {
+            let sum = self.add(col, prev_carry);
+            let shifted = self.mul_const(base, carry);
+            self.connect(sum, shifted);
+
+            let offset_carry = self.add_const(carry, carry_offset);
+            self.range_check_lookup(offset_carry, carry_bits);
+            prev_carry = carry;
+        }
+        let sum = self.add(last, prev_carry);
+        self.assert_zero(sum);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NonNativeReduceGenerator {
+    cols: Vec<Target>,
+    modulus: BigUint,
+    quotient: Vec<Target>,
+    remainder: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for NonNativeReduceGenerator
+{
+    fn id(&self) -> String {
+        "NonNativeReduceGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.cols.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let value = self.cols.iter().rev().fold(BigInt::zero(), |acc, &col| {
+            (acc << NONNATIVE_LIMB_BITS) + to_signed(witness.get_target(col))
+        });
+        let (quotient, remainder) = value.div_mod_floor(&self.modulus.clone().into());
+        let quotient = quotient.to_biguint().expect("Negative quotient");
+        let remainder = remainder.to_biguint().unwrap();
+
+        for (&t, limb) in self
+            .quotient
+            .iter()
+            .zip(biguint_to_limbs(&quotient, self.quotient.len()))
+        {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+        for (&t, limb) in self
+            .remainder
+            .iter()
+            .zip(biguint_to_limbs(&remainder, self.remainder.len()))
+        {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.cols)?;
+        write_biguint(dst, &self.modulus)?;
+        dst.write_target_vec(&self.quotient)?;
+        dst.write_target_vec(&self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let cols = src.read_target_vec()?;
+        let modulus = read_biguint(src)?;
+        let quotient = src.read_target_vec()?;
+        let remainder = src.read_target_vec()?;
+        Ok(Self {
+            cols,
+            modulus,
+            quotient,
+            remainder,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NonNativeInverseGenerator {
+    x: Vec<Target>,
+    inv: Vec<Target>,
+    modulus: BigUint,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for NonNativeInverseGenerator
+{
+    fn id(&self) -> String {
+        "NonNativeInverseGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.x.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x_limbs = witness
+            .get_targets(&self.x)
+            .into_iter()
+            .map(|limb| limb.to_canonical_u64())
+            .collect::<Vec<_>>();
+        let x = limbs_to_biguint(&x_limbs);
+        // The modulus is prime, so x^(N - 2) is the inverse of x.
+        let inv = x.modpow(&(&self.modulus - 2u32), &self.modulus);
+
+        for (&t, limb) in self.inv.iter().zip(biguint_to_limbs(&inv, self.inv.len())) {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.x)?;
+        dst.write_target_vec(&self.inv)?;
+        write_biguint(dst, &self.modulus)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let x = src.read_target_vec()?;
+        let inv = src.read_target_vec()?;
+        let modulus = read_biguint(src)?;
+        Ok(Self { x, inv, modulus })
+    }
+}
+
+/// Witnesses the signed carries between consecutive columns of a limb sum.
+#[derive(Debug, Default)]
+pub struct CarryGenerator {
+    cols: Vec<Target>,
+    carries: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for CarryGenerator {
+    fn id(&self) -> String {
+        "CarryGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.cols.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let mut carry = 0i128;
+        for (&col, &carry_target) in self.cols.iter().zip(&self.carries) {
+            carry = (to_signed(witness.get_target(col)) + carry) >> NONNATIVE_LIMB_BITS;
+            out_buffer.set_target(carry_target, F::from_noncanonical_i64(carry as i64));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.cols)?;
+        dst.write_target_vec(&self.carries)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let cols = src.read_target_vec()?;
+        let carries = src.read_target_vec()?;
+        Ok(Self { cols, carries })
+    }
+}
+
+fn write_biguint(dst: &mut Vec<u8>, x: &BigUint) -> IoResult<()> {
+    let digits = x.to_u32_digits();
+    dst.write_usize(digits.len())?;
+    for digit in digits {
+        dst.write_u32(digit)?;
+    }
+    Ok(())
+}
+
+fn read_biguint(src: &mut Buffer) -> IoResult<BigUint> {
+    let len = src.read_usize()?;
+    let digits = (0..len)
+        .map(|_| src.read_u32())
+        .collect::<IoResult<Vec<_>>>()?;
+    Ok(BigUint::new(digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::NonNativeTarget;
+    use crate::field::bn254_base::Bn254Base;
+    use crate::field::secp256k1_base::Secp256K1Base;
+    use crate::field::types::{Field, PrimeField, Sample};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    type BinaryOp<FF> = fn(
+        &mut CircuitBuilder<F, D>,
+        &NonNativeTarget<FF>,
+        &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF>;
+
+    fn test_binary_op<FF: PrimeField>(
+        op: BinaryOp<FF>,
+        x_ff: FF,
+        y_ff: FF,
+        expected_ff: FF,
+    ) -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.constant_nonnative(x_ff);
+        let y = builder.constant_nonnative(y_ff);
+        let result = op(&mut builder, &x, &y);
+
+        let expected = builder.constant_nonnative(expected_ff);
+        builder.connect_nonnative(&result, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_nonnative_add() -> Result<()> {
+        let x = Secp256K1Base::rand();
+        let y = Secp256K1Base::rand();
+        test_binary_op(CircuitBuilder::add_nonnative, x, y, x + y)
+    }
+
+    #[test]
+    fn test_nonnative_sub() -> Result<()> {
+        let x = Secp256K1Base::rand();
+        let y = Secp256K1Base::rand();
+        test_binary_op(CircuitBuilder::sub_nonnative, x, y, x - y)
+    }
+
+    #[test]
+    fn test_nonnative_mul() -> Result<()> {
+        let x = Secp256K1Base::rand();
+        let y = Secp256K1Base::rand();
+        test_binary_op(CircuitBuilder::mul_nonnative, x, y, x * y)
+    }
+
+    #[test]
+    fn test_nonnative_mul_bn254() -> Result<()> {
+        let x = Bn254Base::rand();
+        let y = Bn254Base::rand();
+        test_binary_op(CircuitBuilder::mul_nonnative, x, y, x * y)
+    }
+
+    #[test]
+    fn test_nonnative_neg() -> Result<()> {
+        let x = Secp256K1Base::rand();
+        test_binary_op(
+            |builder, x, _| builder.neg_nonnative(x),
+            x,
+            Secp256K1Base::ZERO,
+            -x,
+        )
+    }
+
+    #[test]
+    fn test_nonnative_inv() -> Result<()> {
+        let x = Secp256K1Base::rand();
+        test_binary_op(
+            |builder, x, _| builder.inv_nonnative(x),
+            x,
+            Secp256K1Base::ZERO,
+            x.inverse(),
+        )
+    }
+
+    #[test]
+    fn test_nonnative_reduce() -> Result<()> {
+        // Reduce the 512-bit concatenation of x and y, i.e. x + 2^256 y.
+        let x = Secp256K1Base::rand();
+        let y = Secp256K1Base::rand();
+        let two_256 = Secp256K1Base::TWO.exp_u64(256);
+        test_binary_op(
+            |builder, x, y| {
+                let limbs = [x.limbs.clone(), y.limbs.clone()].concat();
+                builder.reduce_nonnative_limbs(&limbs)
+            },
+            x,
+            y,
+            x + two_256 * y,
+        )
+    }
+}
@@ -7,7 +7,7 @@ use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::VerifierCircuitTarget;
-use crate::util::log2_strict;
+use crate::util::{log2_ceil, log2_strict};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Checks that a `Target` matches a vector at a particular index.
@@ -38,6 +38,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         claimed_element
     }
 
+    /// Like `random_access`, but `v` may have any length. It is padded with zeros to a power of two,
+    /// and longer vectors are split into chunks small enough for a single `RandomAccessGate`. The
+    /// circuit is unsatisfiable if `access_index` is out of the padded range.
+    pub fn random_access_padded(&mut self, access_index: Target, v: &[Target]) -> Target {
+        let bits = log2_ceil(v.len().max(1));
+        let mut padded = v.to_vec();
+        padded.resize(1 << bits, self.zero());
+
+        // A gate needs `2 + 2^chunk_bits` routed wires for each copy.
+        let chunk_bits = (self.config.num_routed_wires - 2).ilog2() as usize;
+        if bits <= chunk_bits {
+            return self.random_access(access_index, padded);
+        }
+
+        let index_bits = self.split_le(access_index, bits);
+        let low = self.le_sum(index_bits[..chunk_bits].iter());
+        let high = self.le_sum(index_bits[chunk_bits..].iter());
+        let chunk_values = padded
+            .chunks(1 << chunk_bits)
+            .map(|chunk| self.random_access(low, chunk.to_vec()))
+            .collect::<Vec<_>>();
+        self.random_access_padded(high, &chunk_values)
+    }
+
     /// Like `random_access`, but with `ExtensionTarget`s rather than simple `Target`s.
     pub fn random_access_extension(
         &mut self,
@@ -142,4 +166,33 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_random_access_padded() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // Long enough to need several chunks, and not a power of two.
+        let len = 300;
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let vec = F::rand_vec(len);
+        let v: Vec<_> = vec.iter().map(|x| builder.constant(*x)).collect();
+
+        for i in [0, 1, 63, 64, 200, len - 1] {
+            let it = builder.constant(F::from_canonical_usize(i));
+            let elem = builder.constant(vec[i]);
+            let res = builder.random_access_padded(it, &v);
+            builder.connect(elem, res);
+        }
+        let it = builder.constant(F::from_canonical_usize(len));
+        let res = builder.random_access_padded(it, &v);
+        builder.assert_zero(res);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
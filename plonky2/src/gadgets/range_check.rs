@@ -2,6 +2,8 @@ use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 
+use itertools::Itertools;
+
 use crate::field::extension::Extendable;
 use crate::hash::hash_types::RichField;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
@@ -9,6 +11,7 @@ use crate::iop::target::{BoolTarget, Target};
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::ceil_div_usize;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -17,6 +20,55 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.split_le(x, n_log);
     }
 
+    /// Checks that `x < 2^n_log` by splitting it into bytes and looking each of them up in a table
+    /// of all byte values. Each lookup only takes two routed wires of a `LookupGate`, so this is
+    /// much cheaper than `range_check` when many values need to be checked.
+    pub fn range_check_lookup(&mut self, x: Target, n_log: usize) {
+        assert!(n_log < 64, "{} bits may overflow the field", n_log);
+        if n_log == 0 {
+            self.assert_zero(x);
+            return;
+        }
+
+        let num_bytes = ceil_div_usize(n_log, 8);
+        let bytes = self.add_virtual_targets(num_bytes);
+        self.add_simple_generator(ByteSplitGenerator {
+            integer: x,
+            bytes: bytes.clone(),
+        });
+
+        let byte_table = self.byte_table();
+        for &byte in &bytes {
+            self.add_lookup_from_index(byte, byte_table);
+        }
+
+        // If the top byte should only have `top_bits < 8` bits, also check that shifting it up to
+        // a full byte doesn't overflow.
+        let top_bits = n_log - 8 * (num_bytes - 1);
+        if top_bits < 8 {
+            let shift = F::from_canonical_u64(1 << (8 - top_bits));
+            let shifted_top = self.mul_const(shift, bytes[num_bytes - 1]);
+            self.add_lookup_from_index(shifted_top, byte_table);
+        }
+
+        let byte_base = F::from_canonical_u64(1 << 8);
+        let mut sum = bytes[num_bytes - 1];
+        for &byte in bytes.iter().rev().skip(1) {
+            sum = self.mul_const_add(byte_base, sum, byte);
+        }
+        self.connect(x, sum);
+    }
+
+    /// Returns the index of the lookup table of all byte values, adding it the first time.
+    fn byte_table(&mut self) -> usize {
+        if let Some(index) = self.byte_table_index {
+            return index;
+        }
+        let index = self.add_lookup_table_from_fn(|b| b, &(0..=u8::MAX as u16).collect_vec());
+        self.byte_table_index = Some(index);
+        index
+    }
+
     /// Returns the first `num_low_bits` little-endian bits of `x`.
     pub fn low_bits(&mut self, x: Target, num_low_bits: usize, num_bits: usize) -> Vec<BoolTarget> {
         let mut res = self.split_le(x, num_bits);
@@ -100,3 +152,38 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Low
         })
     }
 }
+
+#[derive(Debug, Default)]
+pub struct ByteSplitGenerator {
+    integer: Target,
+    bytes: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for ByteSplitGenerator {
+    fn id(&self) -> String {
+        "ByteSplitGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.integer]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let mut integer_value = witness.get_target(self.integer).to_canonical_u64();
+        for &byte in &self.bytes {
+            out_buffer.set_target(byte, F::from_canonical_u64(integer_value & 0xff));
+            integer_value >>= 8;
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.integer)?;
+        dst.write_target_vec(&self.bytes)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let integer = src.read_target()?;
+        let bytes = src.read_target_vec()?;
+        Ok(Self { integer, bytes })
+    }
+}
@@ -0,0 +1,256 @@
+//! Gadgets for decoding RLP, the serialization format used by Ethereum.
+//!
+//! An RLP item is either a byte string or a list of items, and starts with a header encoding its
+//! kind and payload length. The decoders here work on byte targets at offsets which are only known
+//! when proving, reading them with `random_access_padded`. The type of each header is classified
+//! with lookup tables indexed by its first byte.
+
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The decoded header of an RLP item within a byte array.
+#[derive(Copy, Clone, Debug)]
+pub struct RlpItemTarget {
+    /// The offset of the item's payload in the byte array.
+    pub payload_offset: Target,
+    /// The length of the item's payload.
+    pub payload_len: Target,
+    /// Whether the item is a list, rather than a byte string.
+    pub is_list: BoolTarget,
+}
+
+/// Indices of the lookup tables used to decode RLP headers.
+#[derive(Copy, Clone, Debug)]
+struct RlpTables {
+    short_len: usize,
+    header_len: usize,
+    len_of_len: usize,
+    is_list: usize,
+}
+
+/// The payload length of an item with a short header, or zero for a long header.
+fn rlp_short_len(prefix: u16) -> u16 {
+    match prefix {
+        0x00..=0x7f => 1,
+        0x80..=0xb7 => prefix - 0x80,
+        0xc0..=0xf7 => prefix - 0xc0,
+        _ => 0,
+    }
+}
+
+/// The length of the header, not counting the big-endian length of a long header.
+fn rlp_header_len(prefix: u16) -> u16 {
+    (prefix >= 0x80) as u16
+}
+
+/// The length of the big-endian payload length of a long header, or zero for a short one.
+fn rlp_len_of_len(prefix: u16) -> u16 {
+    match prefix {
+        0xb8..=0xbf => prefix - 0xb7,
+        0xf8..=0xff => prefix - 0xf7,
+        _ => 0,
+    }
+}
+
+fn rlp_is_list(prefix: u16) -> u16 {
+    (prefix >= 0xc0) as u16
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Decodes the header of the RLP item starting at `bytes[offset]`. Long payload lengths are
+    /// limited to two bytes, which covers any item shorter than 64 KiB. Headers are not checked to
+    /// be canonical.
+    pub fn rlp_decode_item(&mut self, bytes: &[Target], offset: Target) -> RlpItemTarget {
+        let tables = self.rlp_tables();
+        self.rlp_decode_item_with_tables(tables, bytes, offset)
+    }
+
+    /// Decodes the headers of the first `num_items` items in the payload of `list`. If the list
+    /// has fewer items, the remaining headers are decoded from whatever bytes follow it; callers
+    /// can compare `rlp_item_end` of the last item with that of `list` to check that the items
+    /// exactly fill it.
+    pub fn rlp_decode_list_items(
+        &mut self,
+        bytes: &[Target],
+        list: &RlpItemTarget,
+        num_items: usize,
+    ) -> Vec<RlpItemTarget> {
+        let tables = self.rlp_tables();
+        let mut offset = list.payload_offset;
+        (0..num_items)
+            .map(|_| {
+                let item = self.rlp_decode_item_with_tables(tables, bytes, offset);
+                offset = self.rlp_item_end(&item);
+                item
+            })
+            .collect()
+    }
+
+    /// Returns the offset just past the end of `item`.
+    pub fn rlp_item_end(&mut self, item: &RlpItemTarget) -> Target {
+        self.add(item.payload_offset, item.payload_len)
+    }
+
+    /// Reads `len` bytes of `bytes` starting at `offset`.
+    pub fn rlp_read_bytes(&mut self, bytes: &[Target], offset: Target, len: usize) -> Vec<Target> {
+        (0..len)
+            .map(|i| {
+                let index = self.add_const(offset, F::from_canonical_usize(i));
+                self.random_access_padded(index, bytes)
+            })
+            .collect()
+    }
+
+    fn rlp_tables(&mut self) -> RlpTables {
+        let inputs = (0..=u8::MAX as u16).collect_vec();
+        RlpTables {
+            short_len: self.add_lookup_table_from_fn(rlp_short_len, &inputs),
+            header_len: self.add_lookup_table_from_fn(rlp_header_len, &inputs),
+            len_of_len: self.add_lookup_table_from_fn(rlp_len_of_len, &inputs),
+            is_list: self.add_lookup_table_from_fn(rlp_is_list, &inputs),
+        }
+    }
+
+    fn rlp_decode_item_with_tables(
+        &mut self,
+        tables: RlpTables,
+        bytes: &[Target],
+        offset: Target,
+    ) -> RlpItemTarget {
+        // Pad the bytes, so that reading a short header at the very end stays in range.
+        let zero = self.zero();
+        let padded_bytes = bytes.iter().copied().chain([zero; 2]).collect::<Vec<_>>();
+        let [prefix, len_0, len_1] = self
+            .rlp_read_bytes(&padded_bytes, offset, 3)
+            .try_into()
+            .unwrap();
+
+        // The lookups also range-check `prefix` to be a byte.
+        let short_len = self.add_lookup_from_index(prefix, tables.short_len);
+        let header_len = self.add_lookup_from_index(prefix, tables.header_len);
+        let len_of_len = self.add_lookup_from_index(prefix, tables.len_of_len);
+        let is_list = BoolTarget::new_unsafe(self.add_lookup_from_index(prefix, tables.is_list));
+
+        // Only one- and two-byte long lengths are supported.
+        let one = self.one();
+        let two = self.two();
+        let len_of_len_minus_one = self.sub(len_of_len, one);
+        let len_of_len_minus_two = self.sub(len_of_len, two);
+        let unsupported = self.mul_many([len_of_len, len_of_len_minus_one, len_of_len_minus_two]);
+        self.assert_zero(unsupported);
+        let is_two_byte_len = self.is_equal(len_of_len, two);
+
+        // `len_of_len` is 0, 1 or 2, so this is set iff it is 1.
+        let is_one_byte_len =
+            self.arithmetic(-F::TWO, F::ONE, is_two_byte_len.target, one, len_of_len);
+        let two_byte_len = self.mul_const_add(F::from_canonical_u16(1 << 8), len_0, len_1);
+        let long_len = self.mul(is_one_byte_len, len_0);
+        let long_len = self.mul_add(is_two_byte_len.target, two_byte_len, long_len);
+
+        let payload_len = self.add(short_len, long_len);
+        let header_len = self.add(header_len, len_of_len);
+        let payload_offset = self.add(offset, header_len);
+        RlpItemTarget {
+            payload_offset,
+            payload_len,
+            is_list,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn encode_header(len: usize, short_base: u8) -> Vec<u8> {
+        if len <= 55 {
+            vec![short_base + len as u8]
+        } else if len < 1 << 8 {
+            vec![short_base + 56, len as u8]
+        } else {
+            vec![short_base + 57, (len >> 8) as u8, len as u8]
+        }
+    }
+
+    fn encode_string(s: &[u8]) -> Vec<u8> {
+        if s.len() == 1 && s[0] < 0x80 {
+            return s.to_vec();
+        }
+        [encode_header(s.len(), 0x80), s.to_vec()].concat()
+    }
+
+    fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload = items.concat();
+        [encode_header(payload.len(), 0xc0), payload].concat()
+    }
+
+    #[test]
+    fn test_rlp_decode_list() -> Result<()> {
+        let strings = [
+            vec![0x42],
+            vec![0x85],
+            vec![],
+            vec![7; 55],
+            vec![9; 60],
+            vec![11; 300],
+        ];
+        let items = strings.iter().map(|s| encode_string(s)).collect::<Vec<_>>();
+        let encoded = encode_list(&items);
+        assert!(
+            encoded.len() > 1 << 8,
+            "the list should have a two-byte length"
+        );
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let bytes = encoded
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u8(b)))
+            .collect::<Vec<_>>();
+        let zero = builder.zero();
+        let list = builder.rlp_decode_item(&bytes, zero);
+        builder.assert_one(list.is_list.target);
+        let list_end = builder.rlp_item_end(&list);
+        let len = builder.constant(F::from_canonical_usize(encoded.len()));
+        builder.connect(list_end, len);
+
+        let decoded = builder.rlp_decode_list_items(&bytes, &list, strings.len());
+        let mut expected_offset = 3;
+        for (item, (s, encoded_item)) in decoded.iter().zip(strings.iter().zip(&items)) {
+            expected_offset += encoded_item.len() - s.len();
+            let offset = builder.constant(F::from_canonical_usize(expected_offset));
+            let payload_len = builder.constant(F::from_canonical_usize(s.len()));
+            builder.connect(item.payload_offset, offset);
+            builder.connect(item.payload_len, payload_len);
+            builder.assert_zero(item.is_list.target);
+            expected_offset += s.len();
+        }
+        let last_end = builder.rlp_item_end(decoded.last().unwrap());
+        builder.connect(last_end, list_end);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
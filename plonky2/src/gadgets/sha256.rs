@@ -0,0 +1,338 @@
+//! An in-circuit SHA-256.
+//!
+//! Words are kept both as 32 little-endian boolean targets, so that rotations and shifts are free,
+//! and as packed `U32Target`s. The XORs inside the Σ/σ functions act on rotated words, so they are
+//! evaluated nibble by nibble with lookups: the three input nibbles are packed into a 12-bit index,
+//! and a lookup table maps it to the output nibble. `Ch` and `Maj` act on unrotated words, so they
+//! are computed on the packed values with `BitwiseGate`s, which takes about half as many rows as
+//! nibble lookups would, since the lookup indices don't need to be packed. Modular additions are
+//! done on the packed values, and the sums are split back into bits.
+
+use itertools::Itertools;
+
+use crate::field::extension::Extendable;
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::hash::hash_types::RichField;
+use crate::hash::sha256::{sha256_padding, SHA256_BLOCK_BYTES, SHA256_IV, SHA256_K};
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A 32-bit word.
+#[derive(Copy, Clone, Debug)]
+struct Word {
+    /// The little-endian bits of the word.
+    bits: [BoolTarget; 32],
+    /// The value of the word, which matches `bits`.
+    value: U32Target,
+}
+
+fn xor3_nibbles(index: u16) -> u16 {
+    let (x, y, z) = ((index >> 8) & 0xf, (index >> 4) & 0xf, index & 0xf);
+    x ^ y ^ z
+}
+
+fn rotate_right(bits: &[BoolTarget; 32], n: usize) -> [BoolTarget; 32] {
+    core::array::from_fn(|i| bits[(i + n) % 32])
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the SHA-256 digest of `bytes`. Each input target is range-checked to be a byte,
+    /// and the digest is returned as 32 bytes.
+    pub fn sha256(&mut self, bytes: &[Target]) -> [Target; 32] {
+        // The message length is fixed when building the circuit, so the padding is constant.
+        let mut padded = bytes.to_vec();
+        padded.extend(
+            sha256_padding(bytes.len())
+                .into_iter()
+                .map(|byte| self.constant(F::from_canonical_u8(byte))),
+        );
+
+        let xor3_table = self.add_lookup_table_from_fn(xor3_nibbles, &(0..1 << 12).collect_vec());
+        let mut state = SHA256_IV.map(|word| self.constant_word(word));
+        for block in padded.chunks(SHA256_BLOCK_BYTES) {
+            let words = block
+                .chunks(4)
+                .map(|word_bytes| self.word_from_be_bytes(word_bytes))
+                .collect_vec();
+            state = self.sha256_compress(xor3_table, &state, &words.try_into().unwrap());
+        }
+
+        state
+            .iter()
+            .flat_map(|word| word.bits.chunks(8).rev().collect_vec())
+            .map(|byte_bits| self.le_sum(byte_bits.iter()))
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn constant_word(&mut self, word: u32) -> Word {
+        Word {
+            bits: core::array::from_fn(|i| self.constant_bool((word >> i) & 1 == 1)),
+            value: self.constant_u32(word),
+        }
+    }
+
+    /// Reads a word from four big-endian bytes, range-checking them.
+    fn word_from_be_bytes(&mut self, bytes: &[Target]) -> Word {
+        // Our bits are little-endian.
+        let bits = bytes
+            .iter()
+            .rev()
+            .flat_map(|&byte| self.split_le(byte, 8))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let base = F::from_canonical_u64(1 << 8);
+        let value = bytes
+            .iter()
+            .copied()
+            .reduce(|acc, byte| self.mul_const_add(base, acc, byte))
+            .unwrap();
+        Word {
+            bits,
+            value: U32Target(value),
+        }
+    }
+
+    /// Returns the value of the XOR of `x`, `y` and `z`, computed with the lookup table `table`.
+    fn sha256_xor3(
+        &mut self,
+        table: usize,
+        x: &[BoolTarget; 32],
+        y: &[BoolTarget; 32],
+        z: &[BoolTarget; 32],
+    ) -> Target {
+        let nibbles = (0..8)
+            .map(|i| {
+                let bits = [
+                    &z[4 * i..4 * i + 4],
+                    &y[4 * i..4 * i + 4],
+                    &x[4 * i..4 * i + 4],
+                ];
+                let index = self.le_sum(bits.concat().iter());
+                self.add_lookup_from_index(index, table)
+            })
+            .collect_vec();
+
+        let base = F::from_canonical_u64(1 << 4);
+        nibbles
+            .into_iter()
+            .rev()
+            .reduce(|acc, nibble| self.mul_const_add(base, acc, nibble))
+            .unwrap()
+    }
+
+    /// Returns the value of `Ch(e, f, g) = (e & f) ^ (!e & g)`. The two terms have no bits in
+    /// common, so their XOR is their sum.
+    fn sha256_ch(&mut self, e: &Word, f: &Word, g: &Word) -> Target {
+        let e_and_f = self.and_u32(e.value, f.value);
+        let not_e = self.not_u32(e.value);
+        let not_e_and_g = self.and_u32(not_e, g.value);
+        self.add(e_and_f.0, not_e_and_g.0)
+    }
+
+    /// Returns the value of `Maj(a, b, c) = (a & b) ^ (a & c) ^ (b & c)`, computed as
+    /// `(a & b) + (c & (a ^ b))` since the two terms have no bits in common.
+    fn sha256_maj(&mut self, a: &Word, b: &Word, c: &Word) -> Target {
+        let a_and_b = self.and_u32(a.value, b.value);
+        let a_xor_b = self.xor_u32(a.value, b.value);
+        let c_and_a_xor_b = self.and_u32(c.value, a_xor_b);
+        self.add(a_and_b.0, c_and_a_xor_b.0)
+    }
+
+    /// Returns the sum of `terms` modulo 2^32. The sum must be less than 2^35.
+    fn add_words(&mut self, terms: &[Target]) -> Word {
+        let sum = self.add_many(terms);
+        let bits = self.split_le(sum, 35);
+        let carry = self.le_sum(bits[32..].iter());
+        let value = self.mul_const_add(-F::from_canonical_u64(1 << 32), carry, sum);
+        Word {
+            bits: bits[..32].try_into().unwrap(),
+            value: U32Target(value),
+        }
+    }
+
+    fn sha256_compress(
+        &mut self,
+        xor3_table: usize,
+        state: &[Word; 8],
+        block: &[Word; 16],
+    ) -> [Word; 8] {
+        let _false = self._false();
+        let shift_right = |bits: &[BoolTarget; 32], n: usize| -> [BoolTarget; 32] {
+            core::array::from_fn(|i| *bits.get(i + n).unwrap_or(&_false))
+        };
+
+        let mut w = block.to_vec();
+        for i in 16..64 {
+            let s0 = self.sha256_xor3(
+                xor3_table,
+                &rotate_right(&w[i - 15].bits, 7),
+                &rotate_right(&w[i - 15].bits, 18),
+                &shift_right(&w[i - 15].bits, 3),
+            );
+            let s1 = self.sha256_xor3(
+                xor3_table,
+                &rotate_right(&w[i - 2].bits, 17),
+                &rotate_right(&w[i - 2].bits, 19),
+                &shift_right(&w[i - 2].bits, 10),
+            );
+            let next = self.add_words(&[w[i - 16].value.0, s0, w[i - 7].value.0, s1]);
+            w.push(next);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+        for i in 0..64 {
+            let big_s1 = self.sha256_xor3(
+                xor3_table,
+                &rotate_right(&e.bits, 6),
+                &rotate_right(&e.bits, 11),
+                &rotate_right(&e.bits, 25),
+            );
+            let ch = self.sha256_ch(&e, &f, &g);
+            let big_s0 = self.sha256_xor3(
+                xor3_table,
+                &rotate_right(&a.bits, 2),
+                &rotate_right(&a.bits, 13),
+                &rotate_right(&a.bits, 22),
+            );
+            let maj = self.sha256_maj(&a, &b, &c);
+
+            let k = self.constant(F::from_canonical_u32(SHA256_K[i]));
+            let t1 = self.add_many([h.value.0, big_s1, ch, k, w[i].value.0]);
+            let t2 = self.add(big_s0, maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = self.add_words(&[d.value.0, t1]);
+            d = c;
+            c = b;
+            b = a;
+            a = self.add_words(&[t1, t2]);
+        }
+
+        let mut output = *state;
+        for (s, x) in output.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = self.add_words(&[s.value.0, x.value.0]);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use itertools::Itertools;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::Word;
+    use crate::field::types::Field;
+    use crate::gates::lookup::LookupGate;
+    use crate::hash::sha256::sha256;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn test_sha256(len: usize) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let input = builder.add_virtual_targets(len);
+        let output = builder.sha256(&input);
+        builder.register_public_inputs(&output);
+
+        let data = builder.build::<C>();
+
+        let mut rng = OsRng;
+        let message = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        let mut pw = PartialWitness::new();
+        for (&t, &byte) in input.iter().zip(&message) {
+            pw.set_target(t, F::from_canonical_u8(byte));
+        }
+
+        let proof = data.prove(pw)?;
+        let expected = sha256(&message)
+            .iter()
+            .map(|&byte| F::from_canonical_u8(byte))
+            .collect::<Vec<_>>();
+        assert_eq!(proof.public_inputs, expected);
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    fn ch_nibbles(index: u16) -> u16 {
+        let (x, y, z) = ((index >> 8) & 0xf, (index >> 4) & 0xf, index & 0xf);
+        (x & y) ^ (!x & z)
+    }
+
+    fn maj_nibbles(index: u16) -> u16 {
+        let (x, y, z) = ((index >> 8) & 0xf, (index >> 4) & 0xf, index & 0xf);
+        (x & y) ^ (x & z) ^ (y & z)
+    }
+
+    fn virtual_word(builder: &mut CircuitBuilder<F, D>) -> Word {
+        Word {
+            bits: core::array::from_fn(|_| builder.add_virtual_bool_target_unsafe()),
+            value: builder.add_virtual_u32_target(),
+        }
+    }
+
+    #[test]
+    fn test_ch_maj_gate_count() {
+        const NUM_ROUNDS: usize = 64;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let words = (0..3 * NUM_ROUNDS)
+            .map(|_| virtual_word(&mut builder))
+            .collect_vec();
+        let start = builder.num_gates();
+        for [x, y, z] in words.chunks_exact(3).map(|w| [w[0], w[1], w[2]]) {
+            builder.sha256_ch(&x, &y, &z);
+            builder.sha256_maj(&x, &y, &z);
+        }
+        let bitwise_gates = builder.num_gates() - start;
+
+        // The same functions, computed nibble by nibble with lookups as in `sha256_xor3`. The rows
+        // of the lookup tables aren't counted.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let words = (0..3 * NUM_ROUNDS)
+            .map(|_| virtual_word(&mut builder))
+            .collect_vec();
+        let ch_table = builder.add_lookup_table_from_fn(ch_nibbles, &(0..1 << 12).collect_vec());
+        let maj_table = builder.add_lookup_table_from_fn(maj_nibbles, &(0..1 << 12).collect_vec());
+        let start = builder.num_gates();
+        for [x, y, z] in words.chunks_exact(3).map(|w| [w[0], w[1], w[2]]) {
+            builder.sha256_xor3(ch_table, &x.bits, &y.bits, &z.bits);
+            builder.sha256_xor3(maj_table, &x.bits, &y.bits, &z.bits);
+        }
+        let num_lookups = 2 * 8 * NUM_ROUNDS;
+        let lookup_gates =
+            builder.num_gates() - start + num_lookups.div_ceil(LookupGate::num_slots(&config));
+
+        assert!(
+            bitwise_gates < lookup_gates,
+            "{bitwise_gates} >= {lookup_gates}"
+        );
+    }
+
+    #[test]
+    fn test_sha256_single_block() -> Result<()> {
+        test_sha256(3)
+    }
+
+    #[test]
+    fn test_sha256_bitcoin_header() -> Result<()> {
+        // An 80-byte header spans two blocks.
+        test_sha256(80)
+    }
+}
@@ -0,0 +1,558 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use itertools::unfold;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate to perform a basic mul-add on 32-bit values (we assume they are range-checked beforehand).
+///
+/// Each operation computes `x * y + z = output_high * 2^32 + output_low`, where both outputs are
+/// range-checked to 32 bits with base-4 limbs. The gate also forbids `output_high = 2^32 - 1`
+/// unless `output_low = 0`, since otherwise the output would be a non-canonical encoding of a value
+/// wrapping around the field order.
+#[derive(Copy, Clone, Debug)]
+pub struct U32ArithmeticGate<F: RichField + Extendable<D>, const D: usize> {
+    pub num_ops: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> U32ArithmeticGate<F, D> {
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = Self::routed_wires_per_op() + Self::num_limbs() + 1;
+        (config.num_wires / wires_per_op).min(config.num_routed_wires / Self::routed_wires_per_op())
+    }
+
+    pub const fn wire_ith_multiplicand_0(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * i
+    }
+    pub const fn wire_ith_multiplicand_1(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * i + 1
+    }
+    pub const fn wire_ith_addend(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * i + 2
+    }
+    pub const fn wire_ith_output_low_half(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * i + 3
+    }
+    pub const fn wire_ith_output_high_half(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * i + 4
+    }
+    pub const fn wire_ith_inverse(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        Self::routed_wires_per_op() * self.num_ops + i
+    }
+
+    pub const fn limb_bits() -> usize {
+        2
+    }
+    pub const fn num_limbs() -> usize {
+        64 / Self::limb_bits()
+    }
+    pub const fn routed_wires_per_op() -> usize {
+        5
+    }
+    pub const fn wire_ith_output_jth_limb(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        debug_assert!(j < Self::num_limbs());
+        (Self::routed_wires_per_op() + 1) * self.num_ops + Self::num_limbs() * i + j
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for U32ArithmeticGate<F, D> {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_ops = src.read_usize()?;
+        Ok(Self {
+            num_ops,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        for i in 0..self.num_ops {
+            let multiplicand_0 = vars.local_wires[self.wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[self.wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[self.wire_ith_addend(i)];
+
+            let computed_output = multiplicand_0 * multiplicand_1 + addend;
+
+            let output_low = vars.local_wires[self.wire_ith_output_low_half(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high_half(i)];
+            let inverse = vars.local_wires[self.wire_ith_inverse(i)];
+
+            // Check canonicity of combined_output = output_high * 2^32 + output_low.
+            let combined_output = {
+                let base = F::Extension::from_canonical_u64(1 << 32u64);
+                let one = F::Extension::ONE;
+                let u32_max = F::Extension::from_canonical_u32(u32::MAX);
+
+                // This is zero if and only if the high limb is `u32::MAX`.
+                let diff = u32_max - output_high;
+                // If this is zero, the diff is invertible, so the high limb is not `u32::MAX`.
+                let hi_not_max = inverse * diff - one;
+                // If this is zero, either the high limb is not `u32::MAX`, or the low limb is zero.
+                let hi_not_max_or_lo_zero = hi_not_max * output_low;
+
+                constraints.push(hi_not_max_or_lo_zero);
+
+                output_high * base + output_low
+            };
+
+            constraints.push(combined_output - computed_output);
+
+            let mut combined_low_limbs = F::Extension::ZERO;
+            let mut combined_high_limbs = F::Extension::ZERO;
+            let midpoint = Self::num_limbs() / 2;
+            let base = F::Extension::from_canonical_u64(1u64 << Self::limb_bits());
+            for j in (0..Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+                let product = (0..max_limb)
+                    .map(|x| this_limb - F::Extension::from_canonical_usize(x))
+                    .product();
+                constraints.push(product);
+
+                if j < midpoint {
+                    combined_low_limbs = base * combined_low_limbs + this_limb;
+                } else {
+                    combined_high_limbs = base * combined_high_limbs + this_limb;
+                }
+            }
+            constraints.push(combined_low_limbs - output_low);
+            constraints.push(combined_high_limbs - output_high);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        for i in 0..self.num_ops {
+            let multiplicand_0 = vars.local_wires[self.wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[self.wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[self.wire_ith_addend(i)];
+
+            let computed_output = multiplicand_0 * multiplicand_1 + addend;
+
+            let output_low = vars.local_wires[self.wire_ith_output_low_half(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high_half(i)];
+            let inverse = vars.local_wires[self.wire_ith_inverse(i)];
+
+            let combined_output = {
+                let base = F::from_canonical_u64(1 << 32u64);
+                let u32_max = F::from_canonical_u32(u32::MAX);
+
+                let diff = u32_max - output_high;
+                let hi_not_max = inverse * diff - F::ONE;
+                yield_constr.one(hi_not_max * output_low);
+
+                output_high * base + output_low
+            };
+
+            yield_constr.one(combined_output - computed_output);
+
+            let mut combined_low_limbs = F::ZERO;
+            let mut combined_high_limbs = F::ZERO;
+            let midpoint = Self::num_limbs() / 2;
+            let base = F::from_canonical_u64(1u64 << Self::limb_bits());
+            for j in (0..Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+                let product = (0..max_limb)
+                    .map(|x| this_limb - F::from_canonical_usize(x))
+                    .product();
+                yield_constr.one(product);
+
+                if j < midpoint {
+                    combined_low_limbs = base * combined_low_limbs + this_limb;
+                } else {
+                    combined_high_limbs = base * combined_high_limbs + this_limb;
+                }
+            }
+            yield_constr.one(combined_low_limbs - output_low);
+            yield_constr.one(combined_high_limbs - output_high);
+        }
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        for i in 0..self.num_ops {
+            let multiplicand_0 = vars.local_wires[self.wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[self.wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[self.wire_ith_addend(i)];
+
+            let computed_output = builder.mul_add_extension(multiplicand_0, multiplicand_1, addend);
+
+            let output_low = vars.local_wires[self.wire_ith_output_low_half(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high_half(i)];
+            let inverse = vars.local_wires[self.wire_ith_inverse(i)];
+
+            let combined_output = {
+                let base: F::Extension = F::from_canonical_u64(1 << 32u64).into();
+                let base_target = builder.constant_extension(base);
+                let one = builder.one_extension();
+                let u32_max =
+                    builder.constant_extension(F::Extension::from_canonical_u32(u32::MAX));
+
+                let diff = builder.sub_extension(u32_max, output_high);
+                let hi_not_max = builder.mul_sub_extension(inverse, diff, one);
+                let hi_not_max_or_lo_zero = builder.mul_extension(hi_not_max, output_low);
+                constraints.push(hi_not_max_or_lo_zero);
+
+                builder.mul_add_extension(output_high, base_target, output_low)
+            };
+
+            constraints.push(builder.sub_extension(combined_output, computed_output));
+
+            let mut combined_low_limbs = builder.zero_extension();
+            let mut combined_high_limbs = builder.zero_extension();
+            let midpoint = Self::num_limbs() / 2;
+            let base = builder
+                .constant_extension(F::Extension::from_canonical_u64(1u64 << Self::limb_bits()));
+            for j in (0..Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+
+                let mut product = builder.one_extension();
+                for x in 0..max_limb {
+                    let x_target =
+                        builder.constant_extension(F::Extension::from_canonical_usize(x));
+                    let diff = builder.sub_extension(this_limb, x_target);
+                    product = builder.mul_extension(product, diff);
+                }
+                constraints.push(product);
+
+                if j < midpoint {
+                    combined_low_limbs =
+                        builder.mul_add_extension(base, combined_low_limbs, this_limb);
+                } else {
+                    combined_high_limbs =
+                        builder.mul_add_extension(base, combined_high_limbs, this_limb);
+                }
+            }
+
+            constraints.push(builder.sub_extension(combined_low_limbs, output_low));
+            constraints.push(builder.sub_extension(combined_high_limbs, output_high));
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                WitnessGeneratorRef::new(
+                    U32ArithmeticGenerator {
+                        gate: *self,
+                        row,
+                        i,
+                        _phantom: PhantomData,
+                    }
+                    .adapter(),
+                )
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * (Self::routed_wires_per_op() + Self::num_limbs() + 1)
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        1 << Self::limb_bits()
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (4 + Self::num_limbs())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct U32ArithmeticGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate: U32ArithmeticGate<F, D>,
+    row: usize,
+    i: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Default for U32ArithmeticGenerator<F, D> {
+    fn default() -> Self {
+        Self {
+            gate: U32ArithmeticGate {
+                num_ops: 0,
+                _phantom: PhantomData,
+            },
+            row: 0,
+            i: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for U32ArithmeticGenerator<F, D>
+{
+    fn id(&self) -> String {
+        "U32ArithmeticGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let local_target = |column| Target::wire(self.row, column);
+
+        vec![
+            local_target(self.gate.wire_ith_multiplicand_0(self.i)),
+            local_target(self.gate.wire_ith_multiplicand_1(self.i)),
+            local_target(self.gate.wire_ith_addend(self.i)),
+        ]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let get_local_wire = |column| witness.get_wire(local_wire(column));
+
+        let multiplicand_0 = get_local_wire(self.gate.wire_ith_multiplicand_0(self.i));
+        let multiplicand_1 = get_local_wire(self.gate.wire_ith_multiplicand_1(self.i));
+        let addend = get_local_wire(self.gate.wire_ith_addend(self.i));
+
+        let output = multiplicand_0 * multiplicand_1 + addend;
+        let mut output_u64 = output.to_canonical_u64();
+
+        let output_high_u64 = output_u64 >> 32;
+        let output_low_u64 = output_u64 & ((1 << 32) - 1);
+
+        let output_high = F::from_canonical_u64(output_high_u64);
+        let output_low = F::from_canonical_u64(output_low_u64);
+
+        let output_high_wire = local_wire(self.gate.wire_ith_output_high_half(self.i));
+        let output_low_wire = local_wire(self.gate.wire_ith_output_low_half(self.i));
+
+        out_buffer.set_wire(output_high_wire, output_high);
+        out_buffer.set_wire(output_low_wire, output_low);
+
+        let diff = F::from_canonical_u32(u32::MAX) - output_high;
+        let inverse = diff.try_inverse().unwrap_or(F::ZERO);
+        let inverse_wire = local_wire(self.gate.wire_ith_inverse(self.i));
+        out_buffer.set_wire(inverse_wire, inverse);
+
+        let num_limbs = U32ArithmeticGate::<F, D>::num_limbs();
+        let limb_base = 1 << U32ArithmeticGate::<F, D>::limb_bits();
+        let output_limbs_u64 = unfold((), move |_| {
+            let ret = output_u64 % limb_base;
+            output_u64 /= limb_base;
+            Some(ret)
+        })
+        .take(num_limbs);
+        let output_limbs_f = output_limbs_u64.map(F::from_canonical_u64);
+
+        for (j, output_limb) in output_limbs_f.enumerate() {
+            let wire = local_wire(self.gate.wire_ith_output_jth_limb(self.i, j));
+            out_buffer.set_wire(wire, output_limb);
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.gate.num_ops)?;
+        dst.write_usize(self.row)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_ops = src.read_usize()?;
+        let row = src.read_usize()?;
+        let i = src.read_usize()?;
+        Ok(Self {
+            gate: U32ArithmeticGate {
+                num_ops,
+                _phantom: PhantomData,
+            },
+            row,
+            i,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(U32ArithmeticGate::<GoldilocksField, 4> {
+            num_ops: 3,
+            _phantom: PhantomData,
+        })
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(U32ArithmeticGate::<F, D> {
+            num_ops: 3,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn get_wires<F: RichField + Extendable<D>, const D: usize>(
+        gate: &U32ArithmeticGate<F, D>,
+        multiplicands_0: &[u64],
+        multiplicands_1: &[u64],
+        addends: &[u64],
+        output_override: Option<(u64, u64)>,
+    ) -> Vec<F::Extension> {
+        let limb_bits = U32ArithmeticGate::<F, D>::limb_bits();
+        let num_limbs = U32ArithmeticGate::<F, D>::num_limbs();
+        let limb_base = 1 << limb_bits;
+
+        let mut v0 = Vec::new();
+        let mut v1 = Vec::new();
+        let mut inverses = Vec::new();
+        let mut limbs = Vec::new();
+        for c in 0..gate.num_ops {
+            let m0 = multiplicands_0[c];
+            let m1 = multiplicands_1[c];
+            let a = addends[c];
+
+            let (output_high, output_low) = match output_override {
+                Some(o) => o,
+                None => {
+                    let output = m0 * m1 + a;
+                    (output >> 32, output & ((1 << 32) - 1))
+                }
+            };
+            let mut output = (output_high << 32) + output_low;
+
+            let mut output_limbs = Vec::with_capacity(num_limbs);
+            for _i in 0..num_limbs {
+                output_limbs.push(output % limb_base);
+                output /= limb_base;
+            }
+
+            v0.extend([m0, m1, a, output_low, output_high].map(F::from_canonical_u64));
+            let diff = F::from_canonical_u32(u32::MAX) - F::from_canonical_u64(output_high);
+            inverses.push(diff.try_inverse().unwrap_or(F::ZERO));
+            limbs.extend(output_limbs.into_iter().map(F::from_canonical_u64));
+        }
+        v1.extend(inverses);
+        v1.extend(limbs);
+
+        v0.iter().chain(v1.iter()).map(|&x| x.into()).collect()
+    }
+
+    fn test_gate_constraint(output_override: Option<(u64, u64)>) -> bool {
+        type F = GoldilocksField;
+        type FF = <GoldilocksField as Extendable<4>>::Extension;
+        const D: usize = 4;
+        const NUM_U32_ARITHMETIC_OPS: usize = 3;
+
+        let mut rng = OsRng;
+        let multiplicands_0 = (0..NUM_U32_ARITHMETIC_OPS)
+            .map(|_| rng.gen::<u32>() as u64)
+            .collect::<Vec<_>>();
+        let multiplicands_1 = (0..NUM_U32_ARITHMETIC_OPS)
+            .map(|_| rng.gen::<u32>() as u64)
+            .collect::<Vec<_>>();
+        let addends = (0..NUM_U32_ARITHMETIC_OPS)
+            .map(|_| rng.gen::<u32>() as u64)
+            .collect::<Vec<_>>();
+
+        let gate = U32ArithmeticGate::<F, D> {
+            num_ops: NUM_U32_ARITHMETIC_OPS,
+            _phantom: PhantomData,
+        };
+
+        let vars = EvaluationVars {
+            local_constants: &[],
+            local_wires: &get_wires(
+                &gate,
+                &multiplicands_0,
+                &multiplicands_1,
+                &addends,
+                output_override,
+            ),
+            public_inputs_hash: &HashOut::rand(),
+        };
+
+        gate.eval_unfiltered(vars).iter().all(|x| *x == FF::ZERO)
+    }
+
+    #[test]
+    fn test_gate_constraint_valid() {
+        assert!(
+            test_gate_constraint(None),
+            "Gate constraints are not satisfied."
+        );
+    }
+
+    #[test]
+    fn test_gate_constraint_non_canonical() {
+        // A non-canonical output with a maximal high limb would otherwise wrap around the field.
+        assert!(
+            !test_gate_constraint(Some((u32::MAX as u64, u32::MAX as u64))),
+            "Non-canonical output should not satisfy the gate constraints."
+        );
+    }
+}
@@ -0,0 +1,777 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
+
+/// A binary operation on bits.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum BitwiseOp {
+    #[default]
+    And,
+    Xor,
+}
+
+impl BitwiseOp {
+    /// Evaluates the arithmetization of the operation, which agrees with it on bits.
+    fn eval<T>(self, a: T, b: T) -> T
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+    {
+        match self {
+            Self::And => a * b,
+            // a + b - 2ab
+            Self::Xor => a + b - (a * b + a * b),
+        }
+    }
+
+    fn eval_circuit<F: RichField + Extendable<D>, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        a: ExtensionTarget<D>,
+        b: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        match self {
+            Self::And => builder.mul_extension(a, b),
+            Self::Xor => {
+                let sum = builder.add_extension(a, b);
+                builder.arithmetic_extension(-F::TWO, F::ONE, a, b, sum)
+            }
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::And => 0,
+            Self::Xor => 1,
+        }
+    }
+
+    fn from_u8(x: u8) -> IoResult<Self> {
+        match x {
+            0 => Ok(Self::And),
+            1 => Ok(Self::Xor),
+            _ => Err(IoError),
+        }
+    }
+}
+
+/// Returns `sum_i bits[i] 2^i`, using only additions so that it works in any ring.
+fn le_sum<T: Copy + Add<Output = T>>(bits: impl DoubleEndedIterator<Item = T>) -> T {
+    bits.rev().reduce(|acc, bit| acc + acc + bit).unwrap()
+}
+
+/// A gate which applies a `BitwiseOp` to many pairs of packed limbs of `LIMB_BITS` bits, i.e.
+/// `output = a op b`. Only the limbs are routed; their bits live in advice wires, and are
+/// constrained to be bits which recompose to the limbs. This range-checks both inputs, and lets a
+/// row hold many more bit operations than the equivalent `ArithmeticGate`s.
+#[derive(Debug, Clone, Default)]
+pub struct BitwiseGate {
+    pub op: BitwiseOp,
+    /// Number of operations performed by the gate.
+    pub num_ops: usize,
+}
+
+impl BitwiseGate {
+    /// The number of bits in each limb.
+    pub const LIMB_BITS: usize = 32;
+
+    const ROUTED_WIRES_PER_OP: usize = 3;
+    const WIRES_PER_OP: usize = Self::ROUTED_WIRES_PER_OP + 2 * Self::LIMB_BITS;
+
+    pub const fn new_from_config(op: BitwiseOp, config: &CircuitConfig) -> Self {
+        Self {
+            op,
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) const fn num_ops(config: &CircuitConfig) -> usize {
+        let num_ops_routed = config.num_routed_wires / Self::ROUTED_WIRES_PER_OP;
+        let num_ops_total = config.num_wires / Self::WIRES_PER_OP;
+        if num_ops_routed < num_ops_total {
+            num_ops_routed
+        } else {
+            num_ops_total
+        }
+    }
+
+    pub const fn wire_ith_input_a(i: usize) -> usize {
+        3 * i
+    }
+    pub const fn wire_ith_input_b(i: usize) -> usize {
+        3 * i + 1
+    }
+    pub const fn wire_ith_output(i: usize) -> usize {
+        3 * i + 2
+    }
+
+    /// The wire holding the `j`-th bit of the first input of the `i`-th operation.
+    pub fn wire_ith_input_a_bit(&self, i: usize, j: usize) -> usize {
+        debug_assert!(j < Self::LIMB_BITS);
+        Self::ROUTED_WIRES_PER_OP * self.num_ops + 2 * Self::LIMB_BITS * i + j
+    }
+
+    /// The wire holding the `j`-th bit of the second input of the `i`-th operation.
+    pub fn wire_ith_input_b_bit(&self, i: usize, j: usize) -> usize {
+        self.wire_ith_input_a_bit(i, j) + Self::LIMB_BITS
+    }
+
+    /// Yields the constraints of the `i`-th operation, reading wires with `wire`.
+    fn eval_op<T>(&self, i: usize, wire: impl Fn(usize) -> T, mut yield_constr: impl FnMut(T))
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+    {
+        let a = wire(Self::wire_ith_input_a(i));
+        let b = wire(Self::wire_ith_input_b(i));
+        let output = wire(Self::wire_ith_output(i));
+        let a_bits = (0..Self::LIMB_BITS)
+            .map(|j| wire(self.wire_ith_input_a_bit(i, j)))
+            .collect::<Vec<_>>();
+        let b_bits = (0..Self::LIMB_BITS)
+            .map(|j| wire(self.wire_ith_input_b_bit(i, j)))
+            .collect::<Vec<_>>();
+
+        for &bit in a_bits.iter().chain(&b_bits) {
+            yield_constr(bit * bit - bit);
+        }
+        yield_constr(a - le_sum(a_bits.iter().copied()));
+        yield_constr(b - le_sum(b_bits.iter().copied()));
+        let computed_output = le_sum(
+            a_bits
+                .iter()
+                .zip(&b_bits)
+                .map(|(&a_bit, &b_bit)| self.op.eval(a_bit, b_bit)),
+        );
+        yield_constr(output - computed_output);
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for BitwiseGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_u8(self.op.to_u8())?;
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let op = BitwiseOp::from_u8(src.read_u8()?)?;
+        let num_ops = src.read_usize()?;
+        Ok(Self { op, num_ops })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(<Self as Gate<F, D>>::num_constraints(self));
+        for i in 0..self.num_ops {
+            self.eval_op(i, |w| vars.local_wires[w], |c| constraints.push(c));
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        for i in 0..self.num_ops {
+            self.eval_op(i, |w| vars.local_wires[w], |c| yield_constr.one(c));
+        }
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let le_sum_circuit = |builder: &mut CircuitBuilder<F, D>, bits: &[ExtensionTarget<D>]| {
+            bits.iter()
+                .rev()
+                .copied()
+                .reduce(|acc, bit| builder.mul_const_add_extension(F::TWO, acc, bit))
+                .unwrap()
+        };
+
+        let mut constraints = Vec::with_capacity(<Self as Gate<F, D>>::num_constraints(self));
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_input_a(i)];
+            let b = vars.local_wires[Self::wire_ith_input_b(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            let a_bits = (0..Self::LIMB_BITS)
+                .map(|j| vars.local_wires[self.wire_ith_input_a_bit(i, j)])
+                .collect::<Vec<_>>();
+            let b_bits = (0..Self::LIMB_BITS)
+                .map(|j| vars.local_wires[self.wire_ith_input_b_bit(i, j)])
+                .collect::<Vec<_>>();
+
+            for &bit in a_bits.iter().chain(&b_bits) {
+                // bit^2 - bit
+                constraints.push(builder.arithmetic_extension(F::ONE, F::NEG_ONE, bit, bit, bit));
+            }
+            let computed_a = le_sum_circuit(builder, &a_bits);
+            constraints.push(builder.sub_extension(a, computed_a));
+            let computed_b = le_sum_circuit(builder, &b_bits);
+            constraints.push(builder.sub_extension(b, computed_b));
+            let output_bits = a_bits
+                .iter()
+                .zip(&b_bits)
+                .map(|(&a_bit, &b_bit)| self.op.eval_circuit(builder, a_bit, b_bit))
+                .collect::<Vec<_>>();
+            let computed_output = le_sum_circuit(builder, &output_bits);
+            constraints.push(builder.sub_extension(output, computed_output));
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                WitnessGeneratorRef::new(
+                    BitwiseGenerator {
+                        row,
+                        gate: self.clone(),
+                        i,
+                    }
+                    .adapter(),
+                )
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * Self::WIRES_PER_OP
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        2
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (2 * Self::LIMB_BITS + 3)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for BitwiseGate {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            self.eval_op(i, |w| vars.local_wires[w], |c| yield_constr.one(c));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BitwiseGenerator {
+    row: usize,
+    gate: BitwiseGate,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for BitwiseGenerator {
+    fn id(&self) -> String {
+        "BitwiseGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            BitwiseGate::wire_ith_input_a(self.i),
+            BitwiseGate::wire_ith_input_b(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.row, i))
+        .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_wire = |wire: usize| -> u64 {
+            witness
+                .get_target(Target::wire(self.row, wire))
+                .to_canonical_u64()
+        };
+        let a = get_wire(BitwiseGate::wire_ith_input_a(self.i));
+        let b = get_wire(BitwiseGate::wire_ith_input_b(self.i));
+
+        // Inputs which don't fit in a limb are truncated, so that the recomposition constraints
+        // fail rather than the generator.
+        let mut output = 0;
+        for j in 0..BitwiseGate::LIMB_BITS {
+            let (a_bit, b_bit) = ((a >> j) & 1, (b >> j) & 1);
+            out_buffer.set_wire(
+                Wire {
+                    row: self.row,
+                    column: self.gate.wire_ith_input_a_bit(self.i, j),
+                },
+                F::from_canonical_u64(a_bit),
+            );
+            out_buffer.set_wire(
+                Wire {
+                    row: self.row,
+                    column: self.gate.wire_ith_input_b_bit(self.i, j),
+                },
+                F::from_canonical_u64(b_bit),
+            );
+            output |= self.gate.op.eval(a_bit, b_bit) << j;
+        }
+
+        let output_target = Target::wire(self.row, BitwiseGate::wire_ith_output(self.i));
+        out_buffer.set_target(output_target, F::from_canonical_u64(output))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        dst.write_u8(self.gate.op.to_u8())?;
+        dst.write_usize(self.gate.num_ops)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let op = BitwiseOp::from_u8(src.read_u8()?)?;
+        let num_ops = src.read_usize()?;
+        let i = src.read_usize()?;
+        Ok(Self {
+            row,
+            gate: BitwiseGate { op, num_ops },
+            i,
+        })
+    }
+}
+
+/// A gate which applies a `BitwiseOp` to many pairs of bits, i.e. `output = a op b`. Unlike
+/// `BitwiseGate`, the bits are routed directly, so that no packing or unpacking is needed for
+/// values held as bits, and a row holds a bit operation per three routed wires. The inputs are
+/// assumed to be bits, as with `BoolTarget`s.
+#[derive(Debug, Clone, Default)]
+pub struct BitOperationGate {
+    pub op: BitwiseOp,
+    /// Number of operations performed by the gate.
+    pub num_ops: usize,
+}
+
+impl BitOperationGate {
+    pub const fn new_from_config(op: BitwiseOp, config: &CircuitConfig) -> Self {
+        Self {
+            op,
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) const fn num_ops(config: &CircuitConfig) -> usize {
+        config.num_routed_wires / 3
+    }
+
+    pub const fn wire_ith_input_a(i: usize) -> usize {
+        3 * i
+    }
+    pub const fn wire_ith_input_b(i: usize) -> usize {
+        3 * i + 1
+    }
+    pub const fn wire_ith_output(i: usize) -> usize {
+        3 * i + 2
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for BitOperationGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_u8(self.op.to_u8())?;
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let op = BitwiseOp::from_u8(src.read_u8()?)?;
+        let num_ops = src.read_usize()?;
+        Ok(Self { op, num_ops })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        (0..self.num_ops)
+            .map(|i| {
+                let a = vars.local_wires[Self::wire_ith_input_a(i)];
+                let b = vars.local_wires[Self::wire_ith_input_b(i)];
+                let output = vars.local_wires[Self::wire_ith_output(i)];
+                output - self.op.eval(a, b)
+            })
+            .collect()
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_input_a(i)];
+            let b = vars.local_wires[Self::wire_ith_input_b(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            yield_constr.one(output - self.op.eval(a, b));
+        }
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let a = vars.local_wires[Self::wire_ith_input_a(i)];
+                let b = vars.local_wires[Self::wire_ith_input_b(i)];
+                let output = vars.local_wires[Self::wire_ith_output(i)];
+                let computed_output = self.op.eval_circuit(builder, a, b);
+                builder.sub_extension(output, computed_output)
+            })
+            .collect()
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                WitnessGeneratorRef::new(
+                    BitOperationGenerator {
+                        row,
+                        op: self.op,
+                        i,
+                    }
+                    .adapter(),
+                )
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        3 * self.num_ops
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        2
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for BitOperationGate {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_input_a(i)];
+            let b = vars.local_wires[Self::wire_ith_input_b(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            yield_constr.one(output - self.op.eval(a, b));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BitOperationGenerator {
+    row: usize,
+    op: BitwiseOp,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for BitOperationGenerator {
+    fn id(&self) -> String {
+        "BitOperationGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            BitOperationGate::wire_ith_input_a(self.i),
+            BitOperationGate::wire_ith_input_b(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.row, i))
+        .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_wire = |wire: usize| witness.get_target(Target::wire(self.row, wire));
+        let a = get_wire(BitOperationGate::wire_ith_input_a(self.i));
+        let b = get_wire(BitOperationGate::wire_ith_input_b(self.i));
+
+        let output_target = Target::wire(self.row, BitOperationGate::wire_ith_output(self.i));
+        out_buffer.set_target(output_target, self.op.eval(a, b))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        dst.write_u8(self.op.to_u8())?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let op = BitwiseOp::from_u8(src.read_u8()?)?;
+        let i = src.read_usize()?;
+        Ok(Self { row, op, i })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::gadgets::arithmetic_u32::U32Target;
+    use crate::gates::bitwise::{BitOperationGate, BitwiseGate, BitwiseOp};
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::util::StridedConstraintConsumer;
+    use crate::hash::hash_types::HashOut;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVarsBaseBatch;
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn low_degree() {
+        let config = CircuitConfig::standard_recursion_config();
+        for op in [BitwiseOp::And, BitwiseOp::Xor] {
+            let gate = BitwiseGate::new_from_config(op, &config);
+            test_low_degree::<GoldilocksField, _, 4>(gate);
+        }
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        for op in [BitwiseOp::And, BitwiseOp::Xor] {
+            let gate = BitwiseGate::new_from_config(op, &config);
+            test_eval_fns::<F, C, _, D>(gate)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bit_operation_low_degree() {
+        let config = CircuitConfig::standard_recursion_config();
+        for op in [BitwiseOp::And, BitwiseOp::Xor] {
+            let gate = BitOperationGate::new_from_config(op, &config);
+            test_low_degree::<GoldilocksField, _, 4>(gate);
+        }
+    }
+
+    #[test]
+    fn bit_operation_eval_fns() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        for op in [BitwiseOp::And, BitwiseOp::Xor] {
+            let gate = BitOperationGate::new_from_config(op, &config);
+            test_eval_fns::<F, C, _, D>(gate)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eval_base_one() {
+        let config = CircuitConfig::standard_recursion_config();
+        let gate = BitwiseGate::new_from_config(BitwiseOp::Xor, &config);
+        let num_constraints = <BitwiseGate as Gate<F, D>>::num_constraints(&gate);
+
+        let wires = F::rand_vec(<BitwiseGate as Gate<F, D>>::num_wires(&gate));
+        let public_inputs_hash = HashOut::rand();
+        let vars_batch = EvaluationVarsBaseBatch::new(1, &[], &wires, &public_inputs_hash);
+
+        let mut evals = vec![F::ZERO; num_constraints];
+        <BitwiseGate as Gate<F, D>>::eval_unfiltered_base_one(
+            &gate,
+            vars_batch.view(0),
+            StridedConstraintConsumer::new(&mut evals, 1, 0),
+        );
+        assert_eq!(
+            evals,
+            <BitwiseGate as Gate<F, D>>::eval_unfiltered_base_batch(&gate, vars_batch)
+        );
+    }
+
+    #[test]
+    fn test_bitwise_u32() -> Result<()> {
+        const NUM_OPS: usize = 5;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let a_values = (0..NUM_OPS).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+        let b_values = (0..NUM_OPS).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+
+        let a = builder.add_virtual_u32_targets(NUM_OPS);
+        let b = builder.add_virtual_u32_targets(NUM_OPS);
+        for i in 0..NUM_OPS {
+            let xor = builder.xor_u32(a[i], b[i]);
+            let and = builder.and_u32(a[i], b[i]);
+            let expected_xor = builder.constant_u32(a_values[i] ^ b_values[i]);
+            let expected_and = builder.constant_u32(a_values[i] & b_values[i]);
+            builder.connect_u32(xor, expected_xor);
+            builder.connect_u32(and, expected_and);
+        }
+
+        let mut pw = PartialWitness::new();
+        for i in 0..NUM_OPS {
+            pw.set_u32_target(a[i], a_values[i]);
+            pw.set_u32_target(b[i], b_values[i]);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_bitwise_u32_out_of_range() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Bypass the range check of `add_virtual_u32_target`, which the gate must do on its own.
+        let a = U32Target(builder.add_virtual_target());
+        let b = builder.add_virtual_u32_target();
+        builder.xor_u32(a, b);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(a.0, F::from_canonical_u64(1 << 32));
+        pw.set_u32_target(b, 1);
+
+        let data = builder.mock_build::<C>();
+        assert!(data.check_witness(pw).is_err());
+    }
+
+    #[test]
+    fn test_xor_and_many() -> Result<()> {
+        const NUM_BITS: usize = 100;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let a_values = (0..NUM_BITS).map(|_| rng.gen::<bool>()).collect::<Vec<_>>();
+        let b_values = (0..NUM_BITS).map(|_| rng.gen::<bool>()).collect::<Vec<_>>();
+
+        let a = (0..NUM_BITS)
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        let b = (0..NUM_BITS)
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+
+        let xor = builder.xor_many(&a, &b);
+        let and = builder.and_many(&a, &b);
+        for i in 0..NUM_BITS {
+            let expected_xor = builder.constant_bool(a_values[i] ^ b_values[i]);
+            let expected_and = builder.constant_bool(a_values[i] & b_values[i]);
+            builder.connect(xor[i].target, expected_xor.target);
+            builder.connect(and[i].target, expected_and.target);
+        }
+
+        let mut pw = PartialWitness::new();
+        for i in 0..NUM_BITS {
+            pw.set_bool_target(a[i], a_values[i]);
+            pw.set_bool_target(b[i], b_values[i]);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    /// Returns the number of gates used to apply `op` to 3200 pairs of bits, with `xor_many` or
+    /// `and_many` if `many` is set, or else bit by bit.
+    fn bit_operations_num_gates(op: BitwiseOp, many: bool) -> usize {
+        const NUM_BITS: usize = 3200;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let a = (0..NUM_BITS)
+            .map(|_| builder.add_virtual_bool_target_unsafe())
+            .collect::<Vec<_>>();
+        let b = (0..NUM_BITS)
+            .map(|_| builder.add_virtual_bool_target_unsafe())
+            .collect::<Vec<_>>();
+        match (op, many) {
+            (BitwiseOp::Xor, true) => {
+                builder.xor_many(&a, &b);
+            }
+            (BitwiseOp::And, true) => {
+                builder.and_many(&a, &b);
+            }
+            (BitwiseOp::Xor, false) => {
+                for (&a, &b) in a.iter().zip(&b) {
+                    builder.xor(a, b);
+                }
+            }
+            (BitwiseOp::And, false) => {
+                for (&a, &b) in a.iter().zip(&b) {
+                    builder.and(a, b);
+                }
+            }
+        }
+        builder.num_gates()
+    }
+
+    #[test]
+    fn test_xor_and_many_gate_count() {
+        for op in [BitwiseOp::And, BitwiseOp::Xor] {
+            let many = bit_operations_num_gates(op, true);
+            let per_bit = bit_operations_num_gates(op, false);
+            assert!(many < per_bit, "{op:?}: {many} >= {per_bit}");
+        }
+    }
+}
@@ -2,7 +2,9 @@
 
 pub mod arithmetic_base;
 pub mod arithmetic_extension;
+pub mod arithmetic_u32;
 pub mod base_sum;
+pub mod bitwise;
 pub mod constant;
 pub mod coset_interpolation;
 pub mod exponentiation;
@@ -13,12 +15,14 @@ pub mod multiplication_extension;
 pub mod noop;
 pub mod packed_util;
 pub mod poseidon;
+pub mod poseidon2;
 pub mod poseidon_mds;
 pub mod public_input;
 pub mod random_access;
 pub mod reducing;
 pub mod reducing_extension;
 pub(crate) mod selectors;
+pub mod subtraction_u32;
 pub mod util;
 
 // Can't use #[cfg(test)] here because it needs to be visible to other crates.
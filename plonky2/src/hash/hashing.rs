@@ -20,6 +20,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Pad the message using the `pad10*1` rule, then hash it, as `Hasher::hash_pad` does.
+    pub fn hash_pad<H: AlgebraicHasher<F>>(&mut self, mut inputs: Vec<Target>) -> HashOutTarget {
+        let zero = self.zero();
+        let one = self.one();
+        let rate = H::AlgebraicPermutation::RATE;
+        let num_zeros = (rate - (inputs.len() + 2) % rate) % rate;
+        inputs.push(one);
+        inputs.resize(inputs.len() + num_zeros, zero);
+        inputs.push(one);
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+
     pub fn hash_n_to_hash_no_pad<H: AlgebraicHasher<F>>(
         &mut self,
         inputs: Vec<Target>,
@@ -1,7 +1,8 @@
+use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -31,6 +32,30 @@ impl<F: RichField, H: Hasher<F>> MerkleProof<F, H> {
     }
 }
 
+/// A proof that updating a single leaf of a Merkle tree changes its root from `old_root` to
+/// `new_root`. The siblings on the leaf's path are the same before and after the update, so a
+/// single path authenticates both the old and the new leaf.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct MerkleUpdateProof<F: RichField, H: Hasher<F>> {
+    pub leaf_index: usize,
+    pub old_leaf: Vec<F>,
+    pub new_leaf: Vec<F>,
+    pub old_root: H::Hash,
+    pub new_root: H::Hash,
+    pub proof: MerkleProof<F, H>,
+}
+
+/// A proof that several leaves are present in a Merkle tree. Siblings which can be computed from
+/// the leaves themselves are omitted.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct MerkleMultiProof<F: RichField, H: Hasher<F>> {
+    /// The Merkle digests of the subtrees which contain none of the leaves, layer by layer from
+    /// the bottommost, and by increasing index within each layer.
+    pub siblings: Vec<H::Hash>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MerkleProofTarget {
     /// The Merkle digest of each sibling subtree, staying from the bottommost layer.
@@ -76,6 +101,99 @@ pub fn verify_merkle_proof_to_cap<F: RichField, H: Hasher<F>>(
     Ok(())
 }
 
+/// Verifies that the given leaf data is present at the given index in the `SparseMerkleTree` with
+/// the given root. Unlike `verify_merkle_proof`, the leaf is hashed with `hash_pad`, as in a
+/// `SparseMerkleTree`, so an empty leaf can be told apart from a leaf of zeros.
+pub fn verify_sparse_merkle_proof<F: RichField, H: Hasher<F>>(
+    leaf_data: Vec<F>,
+    leaf_index: usize,
+    merkle_root: H::Hash,
+    proof: &MerkleProof<F, H>,
+) -> Result<()> {
+    let mut index = leaf_index;
+    let mut current_digest = H::hash_pad(&leaf_data);
+    for &sibling_digest in proof.siblings.iter() {
+        let bit = index & 1;
+        index >>= 1;
+        current_digest = if bit == 1 {
+            H::two_to_one(sibling_digest, current_digest)
+        } else {
+            H::two_to_one(current_digest, sibling_digest)
+        }
+    }
+    ensure!(
+        index == 0 && current_digest == merkle_root,
+        "Invalid Merkle proof."
+    );
+
+    Ok(())
+}
+
+/// Verifies that `proof` updates the leaf at its index from `old_leaf` to `new_leaf`, changing
+/// the root of the `SparseMerkleTree` from `old_root` to `new_root`.
+pub fn verify_merkle_update<F: RichField, H: Hasher<F>>(
+    proof: &MerkleUpdateProof<F, H>,
+) -> Result<()> {
+    verify_sparse_merkle_proof(
+        proof.old_leaf.clone(),
+        proof.leaf_index,
+        proof.old_root,
+        &proof.proof,
+    )?;
+    verify_sparse_merkle_proof(
+        proof.new_leaf.clone(),
+        proof.leaf_index,
+        proof.new_root,
+        &proof.proof,
+    )
+}
+
+/// Verifies that the given leaves are present at the given indices in the `SparseMerkleTree` of the
+/// given height with the given root. The leaves are hashed as by `verify_sparse_merkle_proof`.
+pub fn verify_merkle_multi_proof<F: RichField, H: Hasher<F>>(
+    leaves: &[(usize, Vec<F>)],
+    height: usize,
+    merkle_root: H::Hash,
+    proof: &MerkleMultiProof<F, H>,
+) -> Result<()> {
+    ensure!(!leaves.is_empty(), "No leaves to verify.");
+    let mut layer = BTreeMap::new();
+    for (index, leaf_data) in leaves {
+        ensure!(*index >> height == 0, "Leaf index out of range.");
+        let digest = H::hash_pad(leaf_data);
+        ensure!(
+            layer.insert(*index, digest).is_none(),
+            "Duplicate leaf index."
+        );
+    }
+
+    let mut siblings = proof.siblings.iter();
+    for _ in 0..height {
+        let mut next_layer = BTreeMap::new();
+        for (&index, &digest) in &layer {
+            if index & 1 == 1 && layer.contains_key(&(index ^ 1)) {
+                // This pair was already hashed when visiting the left child.
+                continue;
+            }
+            let sibling = match layer.get(&(index ^ 1)) {
+                Some(&sibling) => sibling,
+                None => *siblings.next().ok_or_else(|| anyhow!("Missing sibling."))?,
+            };
+            let parent = if index & 1 == 1 {
+                H::two_to_one(sibling, digest)
+            } else {
+                H::two_to_one(digest, sibling)
+            };
+            next_layer.insert(index >> 1, parent);
+        }
+        layer = next_layer;
+    }
+    ensure!(siblings.next().is_none(), "Too many siblings.");
+    ensure!(layer[&0] == merkle_root, "Invalid Merkle multi-proof.");
+
+    Ok(())
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
     /// given root. The index is given by its little-endian bits.
@@ -119,10 +237,54 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
+        let leaf_digest = self.hash_or_noop::<H>(leaf_data);
+        let state = self.merkle_root_from_leaf_digest::<H>(leaf_digest, leaf_index_bits, proof);
+
+        for i in 0..NUM_HASH_OUT_ELTS {
+            let result = self.random_access(
+                cap_index,
+                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+            );
+            self.connect(result, state.elements[i]);
+        }
+    }
+
+    /// Verifies that updating the leaf at the given index from `old_leaf_data` to
+    /// `new_leaf_data` changes the root of the `SparseMerkleTree` from `old_root` to `new_root`.
+    /// The index is given by its little-endian bits, and `proof` is the path of the leaf, which is
+    /// the same in both trees. The leaves are hashed as by `verify_sparse_merkle_proof`.
+    pub fn verify_merkle_update<H: AlgebraicHasher<F>>(
+        &mut self,
+        old_leaf_data: Vec<Target>,
+        new_leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        old_root: HashOutTarget,
+        new_root: HashOutTarget,
+        proof: &MerkleProofTarget,
+    ) {
+        assert_eq!(leaf_index_bits.len(), proof.siblings.len());
+        let old_leaf_digest = self.hash_pad::<H>(old_leaf_data);
+        let new_leaf_digest = self.hash_pad::<H>(new_leaf_data);
+        let computed_old_root =
+            self.merkle_root_from_leaf_digest::<H>(old_leaf_digest, leaf_index_bits, proof);
+        let computed_new_root =
+            self.merkle_root_from_leaf_digest::<H>(new_leaf_digest, leaf_index_bits, proof);
+        self.connect_hashes(computed_old_root, old_root);
+        self.connect_hashes(computed_new_root, new_root);
+    }
+
+    /// Computes the digest at the top of the Merkle path `proof`, starting from the digest of the
+    /// leaf at the given index.
+    fn merkle_root_from_leaf_digest<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_digest: HashOutTarget,
+        leaf_index_bits: &[BoolTarget],
+        proof: &MerkleProofTarget,
+    ) -> HashOutTarget {
         debug_assert!(H::AlgebraicPermutation::RATE >= NUM_HASH_OUT_ELTS);
 
         let zero = self.zero();
-        let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
+        let mut state = leaf_digest;
         debug_assert_eq!(state.elements.len(), NUM_HASH_OUT_ELTS);
 
         for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
@@ -142,13 +304,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             };
         }
 
-        for i in 0..NUM_HASH_OUT_ELTS {
-            let result = self.random_access(
-                cap_index,
-                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
-            );
-            self.connect(result, state.elements[i]);
-        }
+        state
     }
 
     pub fn connect_hashes(&mut self, x: HashOutTarget, y: HashOutTarget) {
@@ -176,8 +332,9 @@ mod tests {
     use rand::Rng;
 
     use super::*;
-    use crate::field::types::Field;
+    use crate::field::types::{Field, Sample};
     use crate::hash::merkle_tree::MerkleTree;
+    use crate::hash::sparse_merkle_tree::SparseMerkleTree;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
@@ -232,4 +389,52 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_recursive_merkle_update() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let height = 20;
+        let mut tree = SparseMerkleTree::<F, H>::new(height);
+        for _ in 0..10 {
+            tree.update(OsRng.gen_range(0..1 << height), F::rand_vec(4));
+        }
+        let i = OsRng.gen_range(0..1 << height);
+        let update = tree.update(i, F::rand_vec(4));
+
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(height),
+        };
+        for (&t, &sibling) in proof_t.siblings.iter().zip(&update.proof.siblings) {
+            pw.set_hash_target(t, sibling);
+        }
+        let old_root_t = builder.add_virtual_hash();
+        let new_root_t = builder.add_virtual_hash();
+        pw.set_hash_target(old_root_t, update.old_root);
+        pw.set_hash_target(new_root_t, update.new_root);
+
+        let old_leaf_t = builder.add_virtual_targets(update.old_leaf.len());
+        let new_leaf_t = builder.add_virtual_targets(update.new_leaf.len());
+        pw.set_target_arr(&old_leaf_t, &update.old_leaf);
+        pw.set_target_arr(&new_leaf_t, &update.new_leaf);
+
+        let i_t = builder.add_virtual_target();
+        pw.set_target(i_t, F::from_canonical_usize(i));
+        let i_bits = builder.split_le(i_t, height);
+
+        builder.verify_merkle_update::<H>(
+            old_leaf_t, new_leaf_t, &i_bits, old_root_t, new_root_t, &proof_t,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
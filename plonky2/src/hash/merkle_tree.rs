@@ -1,5 +1,6 @@
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
+use core::ops::Index;
 use core::slice;
 
 use plonky2_maybe_rayon::*;
@@ -9,6 +10,7 @@ use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::{GenericHashOut, Hasher};
 use crate::util::log2_strict;
+use crate::util::storage::{Buffer, Storage};
 
 /// The Merkle cap of height `h` of a Merkle tree is the `h`-th layer (from the root) of the tree.
 /// It can be used in place of the root to verify Merkle paths, which are `h` elements shorter.
@@ -41,10 +43,92 @@ impl<F: RichField, H: Hasher<F>> MerkleCap<F, H> {
     }
 }
 
+/// The data in the leaves of a Merkle tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MerkleLeaves<F> {
+    Vecs(Vec<Vec<F>>),
+    /// Leaves of `leaf_len > 0` elements each, stored one after the other in `values`.
+    Matrix {
+        values: Buffer<F>,
+        leaf_len: usize,
+    },
+}
+
+impl<F> MerkleLeaves<F> {
+    pub fn len(&self) -> usize {
+        match self {
+            MerkleLeaves::Vecs(leaves) => leaves.len(),
+            MerkleLeaves::Matrix { values, leaf_len } => values.len() / leaf_len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn view(&self) -> LeavesView<'_, F> {
+        match self {
+            MerkleLeaves::Vecs(leaves) => LeavesView::Vecs(leaves),
+            MerkleLeaves::Matrix { values, leaf_len } => LeavesView::Matrix(values, *leaf_len),
+        }
+    }
+}
+
+impl<F> Index<usize> for MerkleLeaves<F> {
+    type Output = [F];
+
+    fn index(&self, i: usize) -> &[F] {
+        match self {
+            MerkleLeaves::Vecs(leaves) => &leaves[i],
+            MerkleLeaves::Matrix { values, leaf_len } => &values[i * leaf_len..(i + 1) * leaf_len],
+        }
+    }
+}
+
+impl<F> From<Vec<Vec<F>>> for MerkleLeaves<F> {
+    fn from(leaves: Vec<Vec<F>>) -> Self {
+        MerkleLeaves::Vecs(leaves)
+    }
+}
+
+/// A range of the leaves of a Merkle tree, which can be split between threads.
+#[derive(Clone, Copy)]
+enum LeavesView<'a, F> {
+    Vecs(&'a [Vec<F>]),
+    Matrix(&'a [F], usize),
+}
+
+impl<'a, F> LeavesView<'a, F> {
+    fn len(self) -> usize {
+        match self {
+            LeavesView::Vecs(leaves) => leaves.len(),
+            LeavesView::Matrix(values, leaf_len) => values.len() / leaf_len,
+        }
+    }
+
+    fn get(self, i: usize) -> &'a [F] {
+        match self {
+            LeavesView::Vecs(leaves) => &leaves[i],
+            LeavesView::Matrix(values, leaf_len) => &values[i * leaf_len..(i + 1) * leaf_len],
+        }
+    }
+
+    /// Returns the `len` leaves starting at leaf `start`.
+    fn slice(self, start: usize, len: usize) -> Self {
+        match self {
+            LeavesView::Vecs(leaves) => LeavesView::Vecs(&leaves[start..start + len]),
+            LeavesView::Matrix(values, leaf_len) => LeavesView::Matrix(
+                &values[start * leaf_len..(start + len) * leaf_len],
+                leaf_len,
+            ),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MerkleTree<F: RichField, H: Hasher<F>> {
     /// The data in the leaves of the Merkle tree.
-    pub leaves: Vec<Vec<F>>,
+    pub leaves: MerkleLeaves<F>,
 
     /// The digests in the tree. Consists of `cap.len()` sub-trees, each corresponding to one
     /// element in `cap`. Each subtree is contiguous and located at
@@ -54,7 +138,7 @@ pub struct MerkleTree<F: RichField, H: Hasher<F>> {
     /// left_child_digest and right_child_digest are H::Hash and left_child_subtree and
     /// right_child_subtree recurse. Observe that the digest of a node is stored by its _parent_.
     /// Consequently, the digests of the roots are not stored here (they can be found in `cap`).
-    pub digests: Vec<H::Hash>,
+    pub digests: Buffer<H::Hash>,
 
     /// The Merkle cap.
     pub cap: MerkleCap<F, H>,
@@ -63,8 +147,8 @@ pub struct MerkleTree<F: RichField, H: Hasher<F>> {
 impl<F: RichField, H: Hasher<F>> Default for MerkleTree<F, H> {
     fn default() -> Self {
         Self {
-            leaves: Vec::new(),
-            digests: Vec::new(),
+            leaves: MerkleLeaves::Vecs(Vec::new()),
+            digests: Buffer::default(),
             cap: MerkleCap::default(),
         }
     }
@@ -84,11 +168,11 @@ fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
 
 fn fill_subtree<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: LeavesView<F>,
 ) -> H::Hash {
     assert_eq!(leaves.len(), digests_buf.len() / 2 + 1);
     if digests_buf.is_empty() {
-        H::hash_or_noop(&leaves[0])
+        H::hash_or_noop(leaves.get(0))
     } else {
         // Layout is: left recursive output || left child digest
         //             || right child digest || right recursive output.
@@ -98,7 +182,8 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
         let (left_digest_mem, left_digests_buf) = left_digests_buf.split_last_mut().unwrap();
         let (right_digest_mem, right_digests_buf) = right_digests_buf.split_first_mut().unwrap();
         // Split `leaves` between both children.
-        let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
+        let half = leaves.len() / 2;
+        let (left_leaves, right_leaves) = (leaves.slice(0, half), leaves.slice(half, half));
 
         let (left_digest, right_digest) = plonky2_maybe_rayon::join(
             || fill_subtree::<F, H>(left_digests_buf, left_leaves),
@@ -114,7 +199,7 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
 fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: LeavesView<F>,
     cap_height: usize,
 ) {
     // Special case of a tree that's all cap. The usual case will panic because we'll try to split
@@ -122,33 +207,36 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     // `blah` chunks as opposed to chunks _of_ `blah`.)
     if digests_buf.is_empty() {
         debug_assert_eq!(cap_buf.len(), leaves.len());
-        cap_buf
-            .par_iter_mut()
-            .zip(leaves)
-            .for_each(|(cap_buf, leaf)| {
-                cap_buf.write(H::hash_or_noop(leaf));
-            });
+        cap_buf.par_iter_mut().enumerate().for_each(|(i, cap_buf)| {
+            cap_buf.write(H::hash_or_noop(leaves.get(i)));
+        });
         return;
     }
 
     let subtree_digests_len = digests_buf.len() >> cap_height;
     let subtree_leaves_len = leaves.len() >> cap_height;
     let digests_chunks = digests_buf.par_chunks_exact_mut(subtree_digests_len);
-    let leaves_chunks = leaves.par_chunks_exact(subtree_leaves_len);
     assert_eq!(digests_chunks.len(), cap_buf.len());
-    assert_eq!(digests_chunks.len(), leaves_chunks.len());
-    digests_chunks.zip(cap_buf).zip(leaves_chunks).for_each(
-        |((subtree_digests, subtree_cap), subtree_leaves)| {
+    assert_eq!(digests_chunks.len() * subtree_leaves_len, leaves.len());
+    digests_chunks
+        .zip(cap_buf)
+        .enumerate()
+        .for_each(|(i, (subtree_digests, subtree_cap))| {
             // We have `1 << cap_height` sub-trees, one for each entry in `cap`. They are totally
             // independent, so we schedule one task for each. `digests_buf` and `leaves` are split
             // into `1 << cap_height` slices, one for each sub-tree.
+            let subtree_leaves = leaves.slice(i * subtree_leaves_len, subtree_leaves_len);
             subtree_cap.write(fill_subtree::<F, H>(subtree_digests, subtree_leaves));
-        },
-    );
+        });
 }
 
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
+        Self::new_with_storage(MerkleLeaves::Vecs(leaves), cap_height, &Storage::Memory)
+    }
+
+    /// Like `new`, but stores the digests as described by `storage`.
+    pub fn new_with_storage(leaves: MerkleLeaves<F>, cap_height: usize, storage: &Storage) -> Self {
         let log2_leaves_len = log2_strict(leaves.len());
         assert!(
             cap_height <= log2_leaves_len,
@@ -158,19 +246,19 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
         );
 
         let num_digests = 2 * (leaves.len() - (1 << cap_height));
-        let mut digests = Vec::with_capacity(num_digests);
-
         let len_cap = 1 << cap_height;
         let mut cap = Vec::with_capacity(len_cap);
 
-        let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
         let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+        let digests = unsafe {
+            // SAFETY: `fill_digests_buf` initializes all of `digests_buf`.
+            storage.alloc_with(num_digests, |digests_buf| {
+                fill_digests_buf::<F, H>(digests_buf, cap_buf, leaves.view(), cap_height);
+            })
+        };
 
         unsafe {
-            // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
-            // `num_digests` and `len_cap`, resp.
-            digests.set_len(num_digests);
+            // SAFETY: `fill_digests_buf` initialized the spare capacity of `cap` up to `len_cap`.
             cap.set_len(len_cap);
         }
 
@@ -295,4 +383,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_matrix_leaves() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 8;
+        let n = 1 << log_n;
+        let leaves = random_data::<F>(n, 7);
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 2);
+
+        let values = leaves.concat().into();
+        let matrix = MerkleLeaves::Matrix {
+            values,
+            leaf_len: 7,
+        };
+        let matrix_tree = MerkleTree::<F, H>::new_with_storage(matrix, 2, &Storage::Memory);
+        assert_eq!(matrix_tree.leaves.len(), n);
+        assert_eq!(matrix_tree.cap, tree.cap);
+        assert_eq!(matrix_tree.digests, tree.digests);
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            assert_eq!(matrix_tree.get(i), &leaf[..]);
+            verify_merkle_proof_to_cap(leaf, i, &matrix_tree.cap, &matrix_tree.prove(i))?;
+        }
+
+        Ok(())
+    }
 }
@@ -281,9 +281,12 @@ pub trait Poseidon: PrimeField64 {
     where
         Self: RichField + Extendable<D>,
     {
-        // If we have enough routed wires, we will use PoseidonMdsGate.
+        // If we have enough routed wires, we will use PoseidonMdsGate, unless the builder is
+        // tracing arithmetic operations.
         let mds_gate = PoseidonMdsGate::<Self, D>::new();
-        if builder.config.num_routed_wires >= mds_gate.num_wires() {
+        if builder.config.num_routed_wires >= mds_gate.num_wires()
+            && !builder.is_tracing_arithmetic()
+        {
             let index = builder.add_gate(mds_gate, vec![]);
             for i in 0..SPONGE_WIDTH {
                 let input_wire = PoseidonMdsGate::<Self, D>::wires_input(i);
@@ -0,0 +1,238 @@
+//! Implementation of Poseidon2 over the Goldilocks field with width 12.
+//!
+//! The round constants and the internal diagonal (`RC12` and `MAT_DIAG12_M_1`) are those of the
+//! Poseidon2 reference implementation, <https://github.com/HorizenLabs/poseidon2>, in
+//! `plain_implementations/src/poseidon2/poseidon2_instance_goldilocks.rs`. The round constants
+//! are the output of the Grain LFSR from Appendix F of the Poseidon paper, initialized for
+//! a 64-bit prime field, the `x^7` S-box, width 12, 8 full rounds and 22 partial rounds: first
+//! the constants of the initial full rounds, then those of the partial rounds, then those of the
+//! final full rounds. `test_round_constants` regenerates them, and `test_vectors` checks the
+//! reference implementation's test vector.
+
+use crate::field::goldilocks_field::GoldilocksField;
+use crate::hash::poseidon2::{Poseidon2, N_FULL_ROUNDS_TOTAL, N_PARTIAL_ROUNDS, SPONGE_WIDTH};
+
+#[rustfmt::skip]
+impl Poseidon2 for GoldilocksField {
+    const EXTERNAL_ROUND_CONSTANTS: [[u64; SPONGE_WIDTH]; N_FULL_ROUNDS_TOTAL] = [
+        [0x13dcf33aba214f46, 0x30b3b654a1da6d83, 0x1fc634ada6159b56, 0x937459964dc03466,
+         0xedd2ef2ca7949924, 0xede9affde0e22f68, 0x8515b9d6bac9282d, 0x6b5c07b4e9e900d8,
+         0x1ec66368838c8a08, 0x9042367d80d1fbab, 0x400283564a3c3799, 0x4a00be0466bca75e],
+        [0x7913beee58e3817f, 0xf545e88532237d90, 0x22f8cb8736042005, 0x6f04990e247a2623,
+         0xfe22e87ba37c38cd, 0xd20e32c85ffe2815, 0x117227674048fe73, 0x4e9fb7ea98a6b145,
+         0xe0866c232b8af08b, 0x00bbc77916884964, 0x7031c0fb990d7116, 0x240a9e87cf35108f],
+        [0x2e6363a5a12244b3, 0x5e1c3787d1b5011c, 0x4132660e2a196e8b, 0x3a013b648d3d4327,
+         0xf79839f49888ea43, 0xfe85658ebafe1439, 0xb6889825a14240bd, 0x578453605541382b,
+         0x4508cda8f6b63ce9, 0x9c3ef35848684c91, 0x0812bde23c87178c, 0xfe49638f7f722c14],
+        [0x8e3f688ce885cbf5, 0xb8e110acf746a87d, 0xb4b2e8973a6dabef, 0x9e714c5da3d462ec,
+         0x6438f9033d3d0c15, 0x24312f7cf1a27199, 0x23f843bb47acbf71, 0x9183f11a34be9f01,
+         0x839062fbb9d45dbf, 0x24b56e7e6c2e43fa, 0xe1683da61c962a72, 0xa95c63971a19bfa7],
+        [0xc68be7c94882a24d, 0xaf996d5d5cdaedd9, 0x9717f025e7daf6a5, 0x6436679e6e7216f4,
+         0x8a223d99047af267, 0xbb512e35a133ba9a, 0xfbbf44097671aa03, 0xf04058ebf6811e61,
+         0x5cca84703fac7ffb, 0x9b55c7945de6469f, 0x8e05bf09808e934f, 0x2ea900de876307d7],
+        [0x7748fff2b38dfb89, 0x6b99a676dd3b5d81, 0xac4bb7c627cf7c13, 0xadb6ebe5e9e2f5ba,
+         0x2d33378cafa24ae3, 0x1e5b73807543f8c2, 0x09208814bfebb10f, 0x782e64b6bb5b93dd,
+         0xadd5a48eac90b50f, 0xadd4c54c736ea4b1, 0xd58dbb86ed817fd8, 0x6d5ed1a533f34ddd],
+        [0x28686aa3e36b7cb9, 0x591abd3476689f36, 0x047d766678f13875, 0xa2a11112625f5b49,
+         0x21fd10a3f8304958, 0xf9b40711443b0280, 0xd2697eb8b2bde88e, 0x3493790b51731b3f,
+         0x11caf9dd73764023, 0x7acfb8f72878164e, 0x744ec4db23cefc26, 0x1e00e58f422c6340],
+        [0x21dd28d906a62dda, 0xf32a46ab5f465b5f, 0xbfce13201f3f7e6b, 0xf30d2e7adb5304e2,
+         0xecdf4ee4abad48e9, 0xf94e82182d395019, 0x4ee52e3744d887c5, 0xa1341c7cac0083b2,
+         0x2302fb26c30c834a, 0xaea3c587273bf7d3, 0xf798e24961823ec7, 0x962deba3e9a2cd94],
+    ];
+
+    const INTERNAL_ROUND_CONSTANTS: [u64; N_PARTIAL_ROUNDS] = [
+        0x4adf842aa75d4316, 0xf8fbb871aa4ab4eb, 0x68e85b6eb2dd6aeb, 0x07a0b06b2d270380,
+        0xd94e0228bd282de4, 0x8bdd91d3250c5278, 0x209c68b88bba778f, 0xb5e18cdab77f3877,
+        0xb296a3e808da93fa, 0x8370ecbda11a327e, 0x3f9075283775dad8, 0xb78095bb23c6aa84,
+        0x3f36b9fe72ad4e5f, 0x69bc96780b10b553, 0x3f1d341f2eb7b881, 0x4e939e9815838818,
+        0xda366b3ae2a31604, 0xbc89db1e7287d509, 0x6102f411f9ef5659, 0x58725c5e7ac1f0ab,
+        0x0df5856c798883e7, 0xf7bb62a8da4c961b,
+    ];
+
+    const INTERNAL_MATRIX_DIAG_M_1: [u64; SPONGE_WIDTH] = [
+        0xc3b6c08e23ba9300, 0xd84b5de94a324fb6, 0x0d0c371c5b35b84f, 0x7964f570e7188037,
+        0x5daf18bbd996604b, 0x6743bc47b9595257, 0x5528b9362c59bb70, 0xac45e25b7127b68b,
+        0xa2077d7dfbb606b5, 0xf3faac6faee378ae, 0x0c6388b51545e883, 0xd27dbb6944917b60,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::goldilocks_field::GoldilocksField as F;
+    use crate::field::types::{Field, Field64, PrimeField64, Sample};
+    use crate::hash::poseidon2::test_helpers::check_test_vectors;
+    use crate::hash::poseidon2::{
+        Poseidon2, HALF_N_FULL_ROUNDS, N_FULL_ROUNDS_TOTAL, N_PARTIAL_ROUNDS, SPONGE_WIDTH,
+    };
+
+    /// The Poseidon2 permutation, written with dense matrices and no optimizations.
+    fn reference_permutation(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        const M4: [[u64; 4]; 4] = [[5, 7, 1, 3], [4, 6, 1, 1], [1, 3, 5, 7], [1, 1, 4, 6]];
+        let external: [[F; SPONGE_WIDTH]; SPONGE_WIDTH] = core::array::from_fn(|i| {
+            core::array::from_fn(|j| {
+                let factor = if i / 4 == j / 4 { 2 } else { 1 };
+                F::from_canonical_u64(factor * M4[i % 4][j % 4])
+            })
+        });
+        let internal: [[F; SPONGE_WIDTH]; SPONGE_WIDTH] = core::array::from_fn(|i| {
+            core::array::from_fn(|j| {
+                let diag = if i == j {
+                    F::from_canonical_u64(F::INTERNAL_MATRIX_DIAG_M_1[i])
+                } else {
+                    F::ZERO
+                };
+                F::ONE + diag
+            })
+        });
+        let mul = |matrix: &[[F; SPONGE_WIDTH]; SPONGE_WIDTH], state: [F; SPONGE_WIDTH]| {
+            core::array::from_fn(|i| (0..SPONGE_WIDTH).map(|j| matrix[i][j] * state[j]).sum())
+        };
+        let full_round = |state: [F; SPONGE_WIDTH], round: usize| {
+            let state = core::array::from_fn(|i| {
+                (state[i] + F::from_canonical_u64(F::EXTERNAL_ROUND_CONSTANTS[round][i])).exp_u64(7)
+            });
+            mul(&external, state)
+        };
+
+        let mut state = mul(&external, input);
+        for round in 0..HALF_N_FULL_ROUNDS {
+            state = full_round(state, round);
+        }
+        for &constant in &F::INTERNAL_ROUND_CONSTANTS {
+            state[0] = (state[0] + F::from_canonical_u64(constant)).exp_u64(7);
+            state = mul(&internal, state);
+        }
+        for round in HALF_N_FULL_ROUNDS..N_FULL_ROUNDS_TOTAL {
+            state = full_round(state, round);
+        }
+        state
+    }
+
+    /// The Grain LFSR used by the reference implementation to generate round constants.
+    struct GrainLfsr {
+        bits: [bool; 80],
+    }
+
+    impl GrainLfsr {
+        fn new(sbox: u64, n: u64, t: u64, r_f: u64, r_p: u64) -> Self {
+            // The field type is 1 for prime fields.
+            let fields = [(1, 2), (sbox, 4), (n, 12), (t, 12), (r_f, 10), (r_p, 10)];
+            let mut bits = Vec::with_capacity(80);
+            for (value, width) in fields {
+                bits.extend((0..width).rev().map(|i| (value >> i) & 1 == 1));
+            }
+            bits.resize(80, true);
+
+            let mut lfsr = Self {
+                bits: bits.try_into().unwrap(),
+            };
+            for _ in 0..160 {
+                lfsr.step();
+            }
+            lfsr
+        }
+
+        fn step(&mut self) -> bool {
+            let b = &self.bits;
+            let new_bit = b[62] ^ b[51] ^ b[38] ^ b[23] ^ b[13] ^ b[0];
+            self.bits.rotate_left(1);
+            self.bits[79] = new_bit;
+            new_bit
+        }
+
+        /// Bits are drawn in pairs, and the second bit is output only if the first one is set.
+        fn next_bit(&mut self) -> bool {
+            loop {
+                let keep = self.step();
+                let bit = self.step();
+                if keep {
+                    return bit;
+                }
+            }
+        }
+
+        /// Big-endian 64-bit samples, rejected unless they are canonical.
+        fn next_field_element(&mut self) -> u64 {
+            loop {
+                let value = (0..64).fold(0, |acc, _| (acc << 1) | self.next_bit() as u64);
+                if value < F::ORDER {
+                    return value;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_constants() {
+        let mut grain = GrainLfsr::new(
+            0,
+            64,
+            SPONGE_WIDTH as u64,
+            N_FULL_ROUNDS_TOTAL as u64,
+            N_PARTIAL_ROUNDS as u64,
+        );
+        let mut sample = || grain.next_field_element();
+
+        let initial: [[u64; SPONGE_WIDTH]; HALF_N_FULL_ROUNDS] =
+            core::array::from_fn(|_| core::array::from_fn(|_| sample()));
+        let partial: [u64; N_PARTIAL_ROUNDS] = core::array::from_fn(|_| sample());
+        let terminal: [[u64; SPONGE_WIDTH]; HALF_N_FULL_ROUNDS] =
+            core::array::from_fn(|_| core::array::from_fn(|_| sample()));
+
+        assert_eq!(F::EXTERNAL_ROUND_CONSTANTS[..HALF_N_FULL_ROUNDS], initial);
+        assert_eq!(F::INTERNAL_ROUND_CONSTANTS, partial);
+        assert_eq!(F::EXTERNAL_ROUND_CONSTANTS[HALF_N_FULL_ROUNDS..], terminal);
+    }
+
+    #[test]
+    fn test_reference_permutation() {
+        for _ in 0..10 {
+            let input = F::rand_array::<SPONGE_WIDTH>();
+            assert_eq!(F::poseidon2(input), reference_permutation(input));
+        }
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Test inputs are:
+        // 1. all zeros
+        // 2. range 0..WIDTH
+        // 3. all -1's
+        // 4. random elements of GoldilocksField.
+        // The expected output for the second input is the reference implementation's test vector;
+        // the others are those of `reference_permutation`.
+
+        let neg_one: u64 = F::NEG_ONE.to_canonical_u64();
+
+        #[rustfmt::skip]
+        let test_vectors12: Vec<([u64; 12], [u64; 12])> = vec![
+            ([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, ],
+             [0xef311849263abcb4, 0x8bf04d36f9a01799, 0x9e570c4df0f2699f, 0x6927c3a96db0b2ad,
+              0x760d22fbb5fc5de0, 0xafd1fedcdef654f4, 0xbb8c81621d5d5aed, 0x298915feb162422c,
+              0x2082259c8351dacb, 0x90e205e0814883e3, 0x2fd0c9106556082d, 0xa08b335154cbefc5, ]),
+            ([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, ],
+             [0x01eaef96bdf1c0c1, 0x1f0d2cc525b2540c, 0x6282c1dfe1e0358d, 0xe780d721f698e1e6,
+              0x280c0b6f753d833b, 0x1b942dd5023156ab, 0x43f0df3fcccb8398, 0xe8e8190585489025,
+              0x56bdbf72f77ada22, 0x7911c32bf9dcd705, 0xec467926508fbe67, 0x6a50450ddf85a6ed, ]),
+            ([neg_one, neg_one, neg_one, neg_one,
+              neg_one, neg_one, neg_one, neg_one,
+              neg_one, neg_one, neg_one, neg_one, ],
+             [0x3f56a9a7aa786049, 0xf320150bc2d01e34, 0x06e3150b85cd1fc6, 0xaf7493cbe0918063,
+              0xe13c55e947c18211, 0x499b83527cb38e47, 0x51e3f3dc2c5b0a2d, 0x7eb3696091d3fb64,
+              0x35ff59edc014bc95, 0xfda3001e8f6852d5, 0x5f67d6471c4391ab, 0x6484973933877089, ]),
+            ([0x8ccbbbea4fe5d2b7, 0xc2af59ee9ec49970, 0x90f7e1a9e658446a, 0xdcc0630a3ab8b1b8,
+              0x7ff8256bca20588c, 0x5d99a7ca0c44ecfb, 0x48452b17a70fbee3, 0xeb09d654690b6c88,
+              0x4a55d3a39c676a88, 0xc0407a38d2285139, 0xa234bac9356386d1, 0xe1633f2bad98a52f, ],
+             [0xca4cec87a21a2766, 0x1e662eb3a8ee88b4, 0x6ef0f6d0d1421ee9, 0x2f173f8d0587219c,
+              0x08da7463e7d15d6b, 0xa3d44ecc12061f3e, 0x305a9b4f6ff87ac5, 0x532912d2f9654e77,
+              0xf2e6d18425cfd79e, 0xa12fd4eb7decb1ba, 0x81fe3683dae66d20, 0xb7f565df36131dfd, ]),
+        ];
+
+        for (input, expected) in &test_vectors12 {
+            let output = reference_permutation(input.map(F::from_canonical_u64));
+            assert_eq!(output.map(|x| x.to_canonical_u64()), *expected);
+        }
+        check_test_vectors::<F>(test_vectors12);
+    }
+}
@@ -0,0 +1,293 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs::{MerkleMultiProof, MerkleProof, MerkleUpdateProof};
+use crate::plonk::config::Hasher;
+
+/// A Merkle tree with `2^height` leaves, most of which are empty. Only the digests of subtrees
+/// containing a non-empty leaf are stored; the digest of an empty subtree only depends on its
+/// height, and is computed once.
+///
+/// Leaves are hashed with `H::hash_pad`, rather than `H::hash_or_noop` as in `MerkleTree`, which
+/// would give a leaf such as `[0]` the digest of an empty leaf. So only an empty leaf, i.e. one
+/// that was never set, has the digest `H::hash_pad(&[])`. Otherwise, proofs use the same layout as
+/// proofs of a `MerkleTree` with a cap of height 0 over the leaf digests, and can be checked with
+/// `verify_sparse_merkle_proof`, `verify_merkle_update`, `verify_merkle_multi_proof`, or the
+/// `verify_merkle_update` circuit gadget.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<F: RichField, H: Hasher<F>> {
+    height: usize,
+
+    /// The data in the non-empty leaves.
+    leaves: HashMap<usize, Vec<F>>,
+
+    /// The digests of non-empty subtrees, indexed by layer, starting from the leaves, and by
+    /// index within the layer.
+    digests: HashMap<(usize, usize), H::Hash>,
+
+    /// The digest of an empty subtree of each height, from 0 up to `height`.
+    empty_digests: Vec<H::Hash>,
+}
+
+impl<F: RichField, H: Hasher<F>> SparseMerkleTree<F, H> {
+    /// Creates a tree of the given height with all leaves empty.
+    pub fn new(height: usize) -> Self {
+        assert!(
+            height < usize::BITS as usize,
+            "height={} should be less than {}",
+            height,
+            usize::BITS
+        );
+
+        let mut empty_digests = Vec::with_capacity(height + 1);
+        empty_digests.push(H::hash_pad(&[]));
+        for i in 0..height {
+            empty_digests.push(H::two_to_one(empty_digests[i], empty_digests[i]));
+        }
+
+        Self {
+            height,
+            leaves: HashMap::new(),
+            digests: HashMap::new(),
+            empty_digests,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn root(&self) -> H::Hash {
+        self.digest(self.height, 0)
+    }
+
+    /// Returns the data in the leaf at index `i`, which is empty if the leaf was never set.
+    pub fn get(&self, i: usize) -> &[F] {
+        self.check_index(i);
+        self.leaves.get(&i).map_or(&[], |leaf| leaf.as_slice())
+    }
+
+    /// Returns the number of non-empty leaves.
+    pub fn num_non_empty_leaves(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Create a Merkle proof from a leaf index.
+    pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
+        self.check_index(leaf_index);
+        let siblings = (0..self.height)
+            .map(|layer| self.digest(layer, (leaf_index >> layer) ^ 1))
+            .collect();
+        MerkleProof { siblings }
+    }
+
+    /// Create a single proof for several leaves, omitting siblings which can be computed from the
+    /// leaves.
+    pub fn prove_multi(&self, leaf_indices: &[usize]) -> MerkleMultiProof<F, H> {
+        let mut known = BTreeSet::new();
+        for &i in leaf_indices {
+            self.check_index(i);
+            known.insert(i);
+        }
+
+        let mut siblings = Vec::new();
+        for layer in 0..self.height {
+            for &i in &known {
+                if !known.contains(&(i ^ 1)) {
+                    siblings.push(self.digest(layer, i ^ 1));
+                }
+            }
+            known = known.iter().map(|&i| i >> 1).collect();
+        }
+
+        MerkleMultiProof { siblings }
+    }
+
+    /// Sets the data in the leaf at index `leaf_index`, updating the digests on its path, and
+    /// returns a proof of the update. Setting a leaf to empty data removes it.
+    pub fn update(&mut self, leaf_index: usize, leaf: Vec<F>) -> MerkleUpdateProof<F, H> {
+        self.check_index(leaf_index);
+        let old_root = self.root();
+        let proof = self.prove(leaf_index);
+
+        let mut digest = H::hash_pad(&leaf);
+        let old_leaf = if leaf.is_empty() {
+            self.leaves.remove(&leaf_index)
+        } else {
+            self.leaves.insert(leaf_index, leaf.clone())
+        };
+
+        let mut index = leaf_index;
+        for layer in 0..=self.height {
+            // Keep the digest map sparse, by not storing digests of empty subtrees.
+            if digest == self.empty_digests[layer] {
+                self.digests.remove(&(layer, index));
+            } else {
+                self.digests.insert((layer, index), digest);
+            }
+
+            if layer < self.height {
+                let sibling = proof.siblings[layer];
+                digest = if index & 1 == 1 {
+                    H::two_to_one(sibling, digest)
+                } else {
+                    H::two_to_one(digest, sibling)
+                };
+                index >>= 1;
+            }
+        }
+
+        MerkleUpdateProof {
+            leaf_index,
+            old_leaf: old_leaf.unwrap_or_default(),
+            new_leaf: leaf,
+            old_root,
+            new_root: self.root(),
+            proof,
+        }
+    }
+
+    fn digest(&self, layer: usize, index: usize) -> H::Hash {
+        self.digests
+            .get(&(layer, index))
+            .copied()
+            .unwrap_or(self.empty_digests[layer])
+    }
+
+    fn check_index(&self, i: usize) {
+        assert_eq!(
+            i >> self.height,
+            0,
+            "Leaf index {} out of range for a tree of height {}",
+            i,
+            self.height
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::merkle_proofs::{
+        verify_merkle_multi_proof, verify_merkle_update, verify_sparse_merkle_proof,
+    };
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_sparse_merkle_tree_matches_merkle_tree() -> Result<()> {
+        let log_n = 6;
+        let n = 1 << log_n;
+        let mut leaves = vec![Vec::new(); n];
+        let digest_leaf = |leaf: &[F]| H::hash_pad(leaf).elements.to_vec();
+        let mut tree = SparseMerkleTree::<F, H>::new(log_n);
+
+        let mut rng = OsRng;
+        for _ in 0..20 {
+            let i = rng.gen_range(0..n);
+            let leaf = F::rand_vec(7);
+            let update = tree.update(i, leaf.clone());
+            assert_eq!(update.old_leaf, leaves[i]);
+            verify_merkle_update(&update)?;
+            leaves[i] = leaf;
+        }
+
+        // A `MerkleTree` doesn't hash leaves of 4 elements, so its leaves can be the digests.
+        let dense_tree =
+            MerkleTree::<F, H>::new(leaves.iter().map(|l| digest_leaf(l)).collect(), 0);
+        assert_eq!(tree.root(), dense_tree.cap.0[0]);
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            assert_eq!(tree.prove(i), dense_tree.prove(i));
+            verify_sparse_merkle_proof(leaf, i, tree.root(), &tree.prove(i))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_removal() {
+        let mut tree = SparseMerkleTree::<F, H>::new(40);
+        let empty_root = tree.root();
+
+        tree.update(1 << 39, F::rand_vec(4));
+        tree.update(12345, F::rand_vec(4));
+        assert_ne!(tree.root(), empty_root);
+        assert_eq!(tree.num_non_empty_leaves(), 2);
+
+        tree.update(1 << 39, Vec::new());
+        tree.update(12345, Vec::new());
+        assert_eq!(tree.root(), empty_root);
+        assert_eq!(tree.num_non_empty_leaves(), 0);
+        assert!(tree.digests.is_empty());
+    }
+
+    #[test]
+    fn test_zero_leaf_is_not_empty() -> Result<()> {
+        let mut tree = SparseMerkleTree::<F, H>::new(10);
+        let empty_root = tree.root();
+
+        let update = tree.update(5, vec![F::ZERO]);
+        verify_merkle_update(&update)?;
+        assert_ne!(tree.root(), empty_root);
+        assert_eq!(tree.num_non_empty_leaves(), 1);
+
+        // The leaf can't pass as empty, nor as any other run of zeros.
+        let proof = tree.prove(5);
+        verify_sparse_merkle_proof(vec![F::ZERO], 5, tree.root(), &proof)?;
+        for len in [0, 2, 4] {
+            assert!(
+                verify_sparse_merkle_proof(vec![F::ZERO; len], 5, tree.root(), &proof).is_err()
+            );
+        }
+        assert!(verify_merkle_multi_proof(
+            &[(5, Vec::new())],
+            10,
+            tree.root(),
+            &tree.prove_multi(&[5])
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_proof() -> Result<()> {
+        let height = 10;
+        let mut tree = SparseMerkleTree::<F, H>::new(height);
+        let indices = [3, 2, 100, 513, 1023];
+        for &i in &indices {
+            tree.update(i, F::rand_vec(5));
+        }
+        tree.update(700, F::rand_vec(5));
+
+        let proof = tree.prove_multi(&indices);
+        assert!(proof.siblings.len() < indices.len() * height);
+        let leaves = indices
+            .iter()
+            .map(|&i| (i, tree.get(i).to_vec()))
+            .collect::<Vec<_>>();
+        verify_merkle_multi_proof(&leaves, height, tree.root(), &proof)?;
+
+        let mut wrong_leaves = leaves.clone();
+        wrong_leaves[2].1[0] += F::ONE;
+        assert!(verify_merkle_multi_proof(&wrong_leaves, height, tree.root(), &proof).is_err());
+
+        // An empty leaf can be proven too, which shows that it was never set.
+        let empty_leaves = [(0, Vec::new()), (1, Vec::new())];
+        let proof = tree.prove_multi(&[0, 1]);
+        verify_merkle_multi_proof(&empty_leaves, height, tree.root(), &proof)
+    }
+}
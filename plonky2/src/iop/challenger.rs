@@ -16,7 +16,7 @@ use crate::plonk::config::{AlgebraicHasher, GenericHashOut, Hasher};
 pub struct Challenger<F: RichField, H: Hasher<F>> {
     pub(crate) sponge_state: H::Permutation,
     pub(crate) input_buffer: Vec<F>,
-    output_buffer: Vec<F>,
+    pub(crate) output_buffer: Vec<F>,
 }
 
 /// Observes prover messages, and generates verifier challenges based on the transcript.
@@ -361,7 +361,8 @@ mod tests {
         }
         let circuit = builder.build::<C>();
         let inputs = PartialWitness::new();
-        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+        let witness =
+            generate_partial_witness(inputs, &circuit.prover_only, &circuit.common).unwrap();
         let recursive_output_values_per_round: Vec<Vec<F>> = recursive_outputs_per_round
             .iter()
             .map(|outputs| witness.get_targets(outputs))
@@ -2,9 +2,11 @@ use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
-use core::fmt::Debug;
+use core::fmt::{Debug, Display, Formatter};
 use core::marker::PhantomData;
 
+use plonky2_maybe_rayon::*;
+
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
 use crate::hash::hash_types::RichField;
@@ -16,8 +18,87 @@ use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::GenericConfig;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
+/// A generator which could not finish running during witness generation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StalledGenerator {
+    /// The ID of the generator.
+    pub id: String,
+    /// The targets watched by the generator which were never set.
+    pub missing_targets: Vec<Target>,
+    /// The contexts which were open when the generator was added to the circuit, as pushed with
+    /// `CircuitBuilder::push_context` or `with_context!`.
+    pub context: String,
+}
+
+/// A target which was set to a value other than the one its partition already held, e.g. because
+/// two connected targets were generated differently.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConflictingTarget {
+    pub target: Target,
+    /// The ID of the generator which set the target, or `None` if it was given as an input.
+    pub generator_id: Option<String>,
+    /// The contexts which were open when the generator was added to the circuit, or an empty
+    /// string for an input.
+    pub context: String,
+    /// The value the partition of the target already held, as a canonical integer.
+    pub old_value: u64,
+    /// The conflicting value, as a canonical integer.
+    pub new_value: u64,
+}
+
+/// An error returned by `generate_partial_witness` when the witness could not be completed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WitnessGenerationError {
+    /// Some generators could not run, typically because a target they depend on was neither given
+    /// as an input nor generated.
+    StalledGenerators(Vec<StalledGenerator>),
+    /// A target was set twice with different values, so the copy constraints cannot hold.
+    Conflict(ConflictingTarget),
+}
+
+impl Display for WitnessGenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        const MAX_REPORTED_GENERATORS: usize = 20;
+
+        match self {
+            Self::StalledGenerators(stalled_generators) => {
+                write!(f, "{} generators weren't run", stalled_generators.len())?;
+                for generator in stalled_generators.iter().take(MAX_REPORTED_GENERATORS) {
+                    write!(
+                        f,
+                        "\n{} ({}) is waiting on {:?}",
+                        generator.id, generator.context, generator.missing_targets
+                    )?;
+                }
+                if stalled_generators.len() > MAX_REPORTED_GENERATORS {
+                    write!(
+                        f,
+                        "\n... and {} more",
+                        stalled_generators.len() - MAX_REPORTED_GENERATORS
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Conflict(conflict) => {
+                write!(
+                    f,
+                    "Partition containing {:?} was set twice with different values: {} != {}",
+                    conflict.target, conflict.old_value, conflict.new_value
+                )?;
+                match &conflict.generator_id {
+                    Some(id) => write!(f, ", the latter by {} ({})", id, conflict.context),
+                    None => write!(f, ", the latter as an input"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WitnessGenerationError {}
+
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
-/// given set of generators.
+/// given set of generators. Fails if some generators could not run.
 pub fn generate_partial_witness<
     'a,
     F: RichField + Extendable<D>,
@@ -27,10 +108,48 @@ pub fn generate_partial_witness<
     inputs: PartialWitness<F>,
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, D>,
-) -> PartitionWitness<'a, F> {
+) -> Result<PartitionWitness<'a, F>, WitnessGenerationError> {
+    generate_partial_witness_observed(inputs, prover_data, common_data, |_, _| {})
+}
+
+/// Like `generate_partial_witness`, but calls `on_generated` with the index of the generator and
+/// the representative of the partition, whenever a generator sets a partition.
+pub(crate) fn generate_partial_witness_observed<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+    on_generated: impl FnMut(usize, usize),
+) -> Result<PartitionWitness<'a, F>, WitnessGenerationError> {
+    generate_partial_witness_scheduled(
+        inputs,
+        prover_data,
+        common_data,
+        cfg!(feature = "parallel"),
+        on_generated,
+    )
+}
+
+/// Generates the witness, running generators either in levels, concurrently within each level, or
+/// one at a time. Both schedules give the same witness.
+fn generate_partial_witness_scheduled<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+    in_levels: bool,
+    on_generated: impl FnMut(usize, usize),
+) -> Result<PartitionWitness<'a, F>, WitnessGenerationError> {
     let config = &common_data.config;
     let generators = &prover_data.generators;
-    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
     let mut witness = PartitionWitness::new(
         config.num_wires,
@@ -39,17 +158,94 @@ pub fn generate_partial_witness<
     );
 
     for (t, v) in inputs.target_values.into_iter() {
-        witness.set_target(t, v);
+        set_target_checked(&mut witness, prover_data, None, t, v)?;
     }
 
+    // We track a list of "expired" generators which have already returned true.
+    let mut generator_is_expired = vec![false; generators.len()];
+
+    if in_levels {
+        run_generators_in_levels(
+            &mut witness,
+            prover_data,
+            &mut generator_is_expired,
+            on_generated,
+        )?;
+    } else {
+        run_generators_sequentially(
+            &mut witness,
+            prover_data,
+            &mut generator_is_expired,
+            on_generated,
+        )?;
+    }
+
+    if generator_is_expired.contains(&false) {
+        let stalled_generators = (0..generators.len())
+            .filter(|&i| !generator_is_expired[i])
+            .map(|i| {
+                let generator = &generators[i].0;
+                StalledGenerator {
+                    id: generator.id(),
+                    missing_targets: generator
+                        .watch_list()
+                        .into_iter()
+                        .filter(|&t| witness.try_get_target(t).is_none())
+                        .collect(),
+                    context: prover_data.contexts[prover_data.generator_contexts[i]].clone(),
+                }
+            })
+            .collect();
+        return Err(WitnessGenerationError::StalledGenerators(
+            stalled_generators,
+        ));
+    }
+
+    Ok(witness)
+}
+
+/// Sets `target` in `witness`, or returns an error if its partition already holds another value.
+/// `generator_idx` is the index of the generator which produced the value, if any.
+fn set_target_checked<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    witness: &mut PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    generator_idx: Option<usize>,
+    target: Target,
+    value: F,
+) -> Result<Option<usize>, WitnessGenerationError> {
+    witness
+        .try_set_target_returning_rep(target, value)
+        .map_err(|old_value| {
+            WitnessGenerationError::Conflict(ConflictingTarget {
+                target,
+                generator_id: generator_idx.map(|i| prover_data.generators[i].0.id()),
+                context: generator_idx
+                    .map(|i| prover_data.contexts[prover_data.generator_contexts[i]].clone())
+                    .unwrap_or_default(),
+                old_value: old_value.to_canonical_u64(),
+                new_value: value.to_canonical_u64(),
+            })
+        })
+}
+
+/// Runs generators one at a time from a queue, until no generator can make progress.
+fn run_generators_sequentially<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &mut PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    generator_is_expired: &mut [bool],
+    mut on_generated: impl FnMut(usize, usize),
+) -> Result<(), WitnessGenerationError> {
+    let generators = &prover_data.generators;
+    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
+
     // Build a list of "pending" generators which are queued to be run. Initially, all generators
     // are queued.
     let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
 
-    // We also track a list of "expired" generators which have already returned false.
-    let mut generator_is_expired = vec![false; generators.len()];
-    let mut remaining_generators = generators.len();
-
     let mut buffer = GeneratedValues::empty();
 
     // Keep running generators until we fail to make progress.
@@ -61,18 +257,22 @@ pub fn generate_partial_witness<
                 continue;
             }
 
-            let finished = generators[generator_idx].0.run(&witness, &mut buffer);
+            let finished = generators[generator_idx].0.run(witness, &mut buffer);
             if finished {
                 generator_is_expired[generator_idx] = true;
-                remaining_generators -= 1;
             }
 
             // Merge any generated values into our witness, and get a list of newly-populated
             // targets' representatives.
-            let new_target_reps = buffer
-                .target_values
-                .drain(..)
-                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
+            let mut new_target_reps = Vec::new();
+            for (t, v) in buffer.target_values.drain(..) {
+                if let Some(rep) =
+                    set_target_checked(witness, prover_data, Some(generator_idx), t, v)?
+                {
+                    on_generated(generator_idx, rep);
+                    new_target_reps.push(rep);
+                }
+            }
 
             // Enqueue unfinished generators that were watching one of the newly populated targets.
             for watch in new_target_reps {
@@ -90,13 +290,66 @@ pub fn generate_partial_witness<
         pending_generator_indices = next_pending_generator_indices;
     }
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
-    );
+    Ok(())
+}
 
-    witness
+/// Runs generators in levels, until no generator can make progress. The pending generators of a
+/// level run concurrently on the witness left by the previous level. Their outputs are then merged
+/// in the order of the generators, and the generators watching the newly set targets make up the
+/// next level.
+fn run_generators_in_levels<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &mut PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    generator_is_expired: &mut [bool],
+    mut on_generated: impl FnMut(usize, usize),
+) -> Result<(), WitnessGenerationError> {
+    let generators = &prover_data.generators;
+    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
+
+    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+
+    while !pending_generator_indices.is_empty() {
+        let level_witness = &*witness;
+        let outputs = pending_generator_indices
+            .par_iter()
+            .map(|&generator_idx| {
+                let mut buffer = GeneratedValues::empty();
+                let finished = generators[generator_idx].0.run(level_witness, &mut buffer);
+                (generator_idx, finished, buffer)
+            })
+            .collect::<Vec<_>>();
+
+        let mut next_pending_generator_indices = Vec::new();
+        for (generator_idx, finished, buffer) in outputs {
+            if finished {
+                generator_is_expired[generator_idx] = true;
+            }
+
+            for (t, v) in buffer.target_values {
+                if let Some(watch) =
+                    set_target_checked(witness, prover_data, Some(generator_idx), t, v)?
+                {
+                    on_generated(generator_idx, watch);
+                    if let Some(watchers) = generator_indices_by_watches.get(&watch) {
+                        next_pending_generator_indices.extend_from_slice(watchers);
+                    }
+                }
+            }
+        }
+
+        // A generator may be watching several of the new targets, or have finished after being
+        // enqueued.
+        next_pending_generator_indices.sort_unstable();
+        next_pending_generator_indices.dedup();
+        next_pending_generator_indices.retain(|&i| !generator_is_expired[i]);
+        pending_generator_indices = next_pending_generator_indices;
+    }
+
+    Ok(())
 }
 
 /// A generator participates in the generation of the witness.
@@ -415,3 +668,150 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Con
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_partial_witness, generate_partial_witness_scheduled, WitnessGenerationError,
+    };
+    use crate::field::types::Field;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_stalled_generators() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let x_squared = with_context!(builder, "square", builder.mul(x, x));
+        let y_cubed = with_context!(builder, "cube", builder.exp_u64(y, 3));
+        builder.register_public_input(x_squared);
+        builder.register_public_input(y_cubed);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(y, F::TWO);
+        let error = generate_partial_witness(pw, &data.prover_only, &data.common).unwrap_err();
+        let WitnessGenerationError::StalledGenerators(stalled_generators) = &error else {
+            panic!("Expected stalled generators, got {error}");
+        };
+
+        let square = stalled_generators
+            .iter()
+            .find(|g| g.context == "root > square")
+            .expect("The generator of x^2 should be stalled");
+        assert_eq!(square.id, "ArithmeticBaseGenerator");
+        assert!(!square.missing_targets.is_empty());
+        assert!(stalled_generators
+            .iter()
+            .all(|g| !g.context.contains("cube")));
+        assert!(error.to_string().contains("root > square"));
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(y, F::TWO);
+        assert!(data.prove(pw).is_err());
+    }
+
+    #[test]
+    fn test_conflicting_targets() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let x_squared = with_context!(builder, "square", builder.mul(x, x));
+        builder.connect(x_squared, y);
+        let data = builder.build::<C>();
+
+        for in_levels in [false, true] {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::TWO);
+            pw.set_target(y, F::from_canonical_u64(5));
+            let error = generate_partial_witness_scheduled(
+                pw,
+                &data.prover_only,
+                &data.common,
+                in_levels,
+                |_, _| {},
+            )
+            .unwrap_err();
+            let WitnessGenerationError::Conflict(conflict) = &error else {
+                panic!("Expected a conflict, got {error}");
+            };
+            assert_eq!(
+                conflict.generator_id.as_deref(),
+                Some("ArithmeticBaseGenerator")
+            );
+            assert_eq!(conflict.context, "root > square");
+            assert_eq!((conflict.old_value, conflict.new_value), (5, 4));
+        }
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        pw.set_target(y, F::from_canonical_u64(5));
+        assert!(data.prove(pw).is_err());
+    }
+
+    #[test]
+    fn test_schedules_agree() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = builder.add_virtual_targets(8);
+        let mut state = inputs.clone();
+        for _ in 0..10 {
+            let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(state.clone());
+            let product = builder.mul_many(&state);
+            let bits = builder.split_le(product, 64);
+            state = hash
+                .elements
+                .into_iter()
+                .chain(bits[..4].iter().map(|b| b.target))
+                .map(|t| builder.add(t, product))
+                .collect();
+        }
+        // Rows added when building hold random values, so only compare these rows.
+        let num_rows = builder.num_gates();
+        let data = builder.build::<C>();
+
+        let generate = |in_levels| {
+            let mut pw = PartialWitness::new();
+            for (i, &t) in inputs.iter().enumerate() {
+                pw.set_target(t, F::from_canonical_usize(i + 1));
+            }
+            generate_partial_witness_scheduled(
+                pw,
+                &data.prover_only,
+                &data.common,
+                in_levels,
+                |_, _| {},
+            )
+            .unwrap()
+        };
+        let sequential = generate(false);
+        let levelled = generate(true);
+        for row in 0..num_rows {
+            for column in 0..data.common.config.num_wires {
+                let wire = Target::wire(row, column);
+                assert_eq!(
+                    sequential.try_get_target(wire),
+                    levelled.try_get_target(wire)
+                );
+            }
+        }
+        for &t in &state {
+            assert_eq!(sequential.get_target(t), levelled.get_target(t));
+        }
+    }
+}
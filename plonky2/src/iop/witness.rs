@@ -3,11 +3,17 @@ use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 use itertools::{zip_eq, Itertools};
+use num::BigUint;
 
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::types::Field;
+use crate::field::types::{Field, PrimeField, PrimeField64};
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::witness_util::set_fri_proof_target;
+use crate::gadgets::arithmetic_u32::{biguint_to_u32_limbs, U32Target};
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::gadgets::biguint::BigUintTarget;
+use crate::gadgets::mpt::MptProofTarget;
+use crate::gadgets::nonnative::{biguint_to_limbs, limbs_to_biguint, NonNativeTarget};
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
@@ -67,6 +73,68 @@ pub trait WitnessWrite<F: Field> {
         self.set_target(target.target, F::from_bool(value))
     }
 
+    fn set_nonnative_target<FF: PrimeField>(&mut self, target: &NonNativeTarget<FF>, value: FF) {
+        let limbs = biguint_to_limbs(&value.to_canonical_biguint(), target.limbs.len());
+        for (&t, limb) in target.limbs.iter().zip(limbs) {
+            self.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+
+    fn set_u32_target(&mut self, target: U32Target, value: u32) {
+        self.set_target(target.0, F::from_canonical_u32(value));
+    }
+
+    fn set_u64_target(&mut self, target: U64Target, value: u64) {
+        self.set_u32_target(target.low(), value as u32);
+        self.set_u32_target(target.high(), (value >> 32) as u32);
+    }
+
+    /// Sets the limbs of `target` to those of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not fit in the limbs of `target`.
+    fn set_biguint_target(&mut self, target: &BigUintTarget, value: &BigUint) {
+        assert!(
+            value.bits() <= 32 * target.num_limbs() as u64,
+            "{} does not fit in {} limbs",
+            value,
+            target.num_limbs()
+        );
+        let limbs = biguint_to_u32_limbs(value, target.num_limbs());
+        for (&t, limb) in target.limbs.iter().zip(limbs) {
+            self.set_u32_target(t, limb);
+        }
+    }
+
+    /// Sets the nodes of `target` to the RLP-encoded `nodes`, leaving any remaining nodes empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are too many nodes, or if a node is too long.
+    fn set_mpt_proof_target(&mut self, target: &MptProofTarget, nodes: &[Vec<u8>]) {
+        assert!(
+            nodes.len() <= target.max_depth(),
+            "{} nodes do not fit in a proof of depth {}",
+            nodes.len(),
+            target.max_depth()
+        );
+        for (i, node_target) in target.nodes.iter().enumerate() {
+            let node = nodes.get(i).map_or(&[][..], |node| node.as_slice());
+            assert!(
+                node.len() <= node_target.bytes.len(),
+                "A node of {} bytes does not fit in {} bytes",
+                node.len(),
+                node_target.bytes.len()
+            );
+            for (j, &t) in node_target.bytes.iter().enumerate() {
+                let byte = node.get(j).copied().unwrap_or(0);
+                self.set_target(t, F::from_canonical_u8(byte));
+            }
+            self.set_target(node_target.len, F::from_canonical_usize(node.len()));
+        }
+    }
+
     /// Set the targets in a `ProofWithPublicInputsTarget` to their corresponding values in a
     /// `ProofWithPublicInputs`.
     fn set_proof_with_pis_target<C: GenericConfig<D, F = F>, const D: usize>(
@@ -216,6 +284,48 @@ pub trait Witness<F: Field>: WitnessWrite<F> {
         panic!("not a bool")
     }
 
+    fn get_nonnative_target<FF: PrimeField>(&self, target: &NonNativeTarget<FF>) -> FF
+    where
+        F: PrimeField64,
+    {
+        let limbs = self
+            .get_targets(&target.limbs)
+            .into_iter()
+            .map(|limb| limb.to_canonical_u64())
+            .collect::<Vec<_>>();
+        FF::from_noncanonical_biguint(limbs_to_biguint(&limbs))
+    }
+
+    fn get_u32_target(&self, target: U32Target) -> u32
+    where
+        F: PrimeField64,
+    {
+        let value = self.get_target(target.0).to_canonical_u64();
+        u32::try_from(value).expect("U32Target holds a value of more than 32 bits")
+    }
+
+    fn get_u64_target(&self, target: U64Target) -> u64
+    where
+        F: PrimeField64,
+    {
+        let low = self.get_u32_target(target.low()) as u64;
+        let high = self.get_u32_target(target.high()) as u64;
+        (high << 32) | low
+    }
+
+    fn get_biguint_target(&self, target: &BigUintTarget) -> BigUint
+    where
+        F: PrimeField64,
+    {
+        BigUint::new(
+            target
+                .limbs
+                .iter()
+                .map(|&limb| self.get_u32_target(limb))
+                .collect(),
+        )
+    }
+
     fn get_hash_target(&self, ht: HashOutTarget) -> HashOut<F> {
         HashOut {
             elements: self.get_targets(&ht.elements).try_into().unwrap(),
@@ -318,18 +428,31 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     /// Set a `Target`. On success, returns the representative index of the newly-set target. If the
     /// target was already set, returns `None`.
     pub fn set_target_returning_rep(&mut self, target: Target, value: F) -> Option<usize> {
+        self.try_set_target_returning_rep(target, value)
+            .unwrap_or_else(|old_value| {
+                panic!(
+                    "Partition containing {:?} was set twice with different values: {} != {}",
+                    target, old_value, value
+                )
+            })
+    }
+
+    /// Like `set_target_returning_rep`, but if the partition of `target` already holds a value
+    /// other than `value`, leaves it unchanged and returns that value as an error.
+    pub fn try_set_target_returning_rep(
+        &mut self,
+        target: Target,
+        value: F,
+    ) -> Result<Option<usize>, F> {
         let rep_index = self.representative_map[self.target_index(target)];
         let rep_value = &mut self.values[rep_index];
-        if let Some(old_value) = *rep_value {
-            assert_eq!(
-                value, old_value,
-                "Partition containing {:?} was set twice with different values: {} != {}",
-                target, old_value, value
-            );
-            None
-        } else {
-            *rep_value = Some(value);
-            Some(rep_index)
+        match *rep_value {
+            Some(old_value) if old_value != value => Err(old_value),
+            Some(_) => Ok(None),
+            None => {
+                *rep_value = Some(value);
+                Ok(Some(rep_index))
+            }
         }
     }
 
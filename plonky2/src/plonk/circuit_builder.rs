@@ -1,8 +1,10 @@
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::max;
+use core::fmt::{Display, Formatter};
 #[cfg(feature = "std")]
 use std::time::Instant;
 
@@ -26,11 +28,14 @@ use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
 use crate::gates::constant::ConstantGate;
 use crate::gates::gate::{CurrentSlot, Gate, GateInstance, GateRef};
 use crate::gates::lookup::{Lookup, LookupGate};
-use crate::gates::lookup_table::LookupTable;
+use crate::gates::lookup_table::{LookupTable, LookupTableGate};
 use crate::gates::noop::NoopGate;
 use crate::gates::public_input::PublicInputGate;
-use crate::gates::selectors::{selector_ends_lookups, selector_polynomials, selectors_lookup};
+use crate::gates::selectors::{
+    selector_ends_lookups, selector_polynomials, selectors_lookup, LookupSelectors,
+};
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
@@ -43,10 +48,16 @@ use crate::plonk::circuit_data::{
     CircuitConfig, CircuitData, CommonCircuitData, MockCircuitData, ProverCircuitData,
     ProverOnlyCircuitData, VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
+use crate::plonk::circuit_stats::{
+    estimate_proof_costs, CircuitStats, GateStats, LookupTableStats,
+};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::copy_constraint::CopyConstraint;
 use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::unconstrained_targets::{
+    find_unconstrained_targets, UnconstrainedTarget, UnconstrainedTargetsError,
+};
 use crate::timed;
 use crate::util::context_tree::ContextTree;
 use crate::util::partial_products::num_partial_products;
@@ -108,9 +119,25 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// A tree of named scopes, used for debugging.
     context_log: ContextTree,
 
+    /// The distinct stacks of open contexts seen so far, and their indices in that list.
+    contexts: Vec<String>,
+    context_indices: HashMap<String, usize>,
+
+    /// The index in `contexts` of the stack of currently open contexts.
+    current_context: usize,
+
+    /// For each gate, the index in `contexts` of the stack open when it was added.
+    gate_contexts: Vec<usize>,
+
     /// Generators used to generate the witness.
     generators: Vec<WitnessGeneratorRef<F, D>>,
 
+    /// For each generator, the index in `contexts` of the stack open when it was added.
+    generator_contexts: Vec<usize>,
+
+    /// For each virtual target, the index in `contexts` of the stack open when it was added.
+    virtual_target_contexts: Vec<usize>,
+
     constants_to_targets: HashMap<F, Target>,
     targets_to_constants: HashMap<Target, F>,
 
@@ -120,6 +147,9 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Memoized results of `arithmetic_extension` calls.
     pub(crate) arithmetic_results: HashMap<ExtensionArithmeticOperation<F, D>, ExtensionTarget<D>>,
 
+    /// The arithmetic operations performed so far, if tracing was enabled with `trace_arithmetic`.
+    arithmetic_trace: Option<Vec<TracedArithmeticOperation<F>>>,
+
     /// Map between gate type and the current gate of this type with available slots.
     current_slots: HashMap<GateRef<F, D>, CurrentSlot<F, D>>,
 
@@ -135,6 +165,10 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     // Lookup tables in the form of `Vec<(input_value, output_value)>`.
     luts: Vec<LookupTable>,
 
+    /// The index in `luts` of the table of all byte values used by `range_check_lookup`, once it
+    /// has been added.
+    pub(crate) byte_table_index: Option<usize>,
+
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
     /// This is used in cyclic recursion.
@@ -145,9 +179,83 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     pub(crate) verifier_data_public_input: Option<VerifierCircuitTarget>,
 }
 
+/// An operation `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend` performed by
+/// `CircuitBuilder::arithmetic` or `CircuitBuilder::arithmetic_extension`. Operands and results have
+/// one target in the former case, and `D` in the latter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TracedArithmeticOperation<F: Field> {
+    pub const_0: F,
+    pub const_1: F,
+    pub multiplicand_0: Vec<Target>,
+    pub multiplicand_1: Vec<Target>,
+    pub addend: Vec<Target>,
+    pub result: Vec<Target>,
+}
+
+/// An error returned by `CircuitBuilder::build_with_target_shape` when a circuit can't be padded to
+/// the target `CommonCircuitData`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CircuitShapeError {
+    /// The config of the circuit differs from that of the target.
+    ConfigMismatch,
+    /// The circuit has more public inputs than the target.
+    TooManyPublicInputs {
+        num_public_inputs: usize,
+        target: usize,
+    },
+    /// The IDs of the gates used by the circuit, or added when building it, which the target
+    /// doesn't have.
+    MissingGates(Vec<String>),
+    /// The lookup tables of the circuit differ from those of the target.
+    LookupTablesMismatch,
+    /// The circuit doesn't fit in the degree of the target.
+    TooManyRows {
+        estimated_rows: usize,
+        degree: usize,
+    },
+    /// The circuit was padded and built, but the listed fields of its common data differ from
+    /// those of the target.
+    CommonDataMismatch(Vec<&'static str>),
+}
+
+impl Display for CircuitShapeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ConfigMismatch => write!(f, "the circuit config differs from the target's"),
+            Self::TooManyPublicInputs {
+                num_public_inputs,
+                target,
+            } => write!(
+                f,
+                "the circuit has {num_public_inputs} public inputs, but the target only {target}"
+            ),
+            Self::MissingGates(ids) => {
+                write!(f, "the target is missing gates of the circuit: {ids:?}")
+            }
+            Self::LookupTablesMismatch => {
+                write!(f, "the circuit lookup tables differ from the target's")
+            }
+            Self::TooManyRows {
+                estimated_rows,
+                degree,
+            } => write!(
+                f,
+                "the circuit takes about {estimated_rows} rows, more than the target degree {degree}"
+            ),
+            Self::CommonDataMismatch(fields) => write!(
+                f,
+                "the built circuit differs from the target in {fields:?}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CircuitShapeError {}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn new(config: CircuitConfig) -> Self {
-        let builder = CircuitBuilder {
+        let mut builder = CircuitBuilder {
             config,
             domain_separator: None,
             gates: HashSet::new(),
@@ -156,19 +264,28 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             virtual_target_index: 0,
             copy_constraints: Vec::new(),
             context_log: ContextTree::new(),
+            contexts: Vec::new(),
+            context_indices: HashMap::new(),
+            current_context: 0,
+            gate_contexts: Vec::new(),
             generators: Vec::new(),
+            generator_contexts: Vec::new(),
+            virtual_target_contexts: Vec::new(),
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
             arithmetic_results: HashMap::new(),
+            arithmetic_trace: None,
             current_slots: HashMap::new(),
             constant_generators: Vec::new(),
             lookup_rows: Vec::new(),
             lut_to_lookups: Vec::new(),
             luts: Vec::new(),
+            byte_table_index: None,
             goal_common_data: None,
             verifier_data_public_input: None,
         };
+        builder.update_current_context();
         builder.check_config();
         builder
     }
@@ -205,6 +322,43 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.gate_instances.len()
     }
 
+    /// Starts recording the arithmetic operations performed by this builder, which can then be read
+    /// with `arithmetic_trace`. While tracing, gadgets which would otherwise offload work to
+    /// special-purpose gates use arithmetic operations, so that a value computed from virtual
+    /// targets can be traced back to them as long as it only relies on arithmetic.
+    pub fn trace_arithmetic(&mut self) {
+        self.arithmetic_trace.get_or_insert_with(Vec::new);
+    }
+
+    pub fn is_tracing_arithmetic(&self) -> bool {
+        self.arithmetic_trace.is_some()
+    }
+
+    /// The arithmetic operations performed since `trace_arithmetic` was called, in order. Operations
+    /// which were memoized or simplified away aren't repeated.
+    pub fn arithmetic_trace(&self) -> &[TracedArithmeticOperation<F>] {
+        self.arithmetic_trace.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn record_arithmetic(
+        &mut self,
+        const_0: F,
+        const_1: F,
+        [multiplicand_0, multiplicand_1, addend]: [&[Target]; 3],
+        result: &[Target],
+    ) {
+        if let Some(trace) = &mut self.arithmetic_trace {
+            trace.push(TracedArithmeticOperation {
+                const_0,
+                const_1,
+                multiplicand_0: multiplicand_0.to_vec(),
+                multiplicand_1: multiplicand_1.to_vec(),
+                addend: addend.to_vec(),
+                result: result.to_vec(),
+            });
+        }
+    }
+
     /// Registers the given target as a public input.
     pub fn register_public_input(&mut self, target: Target) {
         self.public_inputs.push(target);
@@ -259,6 +413,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn add_virtual_target(&mut self) -> Target {
         let index = self.virtual_target_index;
         self.virtual_target_index += 1;
+        self.virtual_target_contexts.push(self.current_context);
         Target::VirtualTarget { index }
     }
 
@@ -389,6 +544,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             gate_ref,
             constants,
         });
+        self.gate_contexts.push(self.current_context);
 
         row
     }
@@ -451,10 +607,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn add_generators(&mut self, generators: Vec<WitnessGeneratorRef<F, D>>) {
+        let num_generators = self.generators.len() + generators.len();
+        self.generator_contexts
+            .resize(num_generators, self.current_context);
         self.generators.extend(generators);
     }
 
     pub fn add_simple_generator<G: SimpleGenerator<F, D>>(&mut self, generator: G) {
+        self.generator_contexts.push(self.current_context);
         self.generators
             .push(WitnessGeneratorRef::new(generator.adapter()));
     }
@@ -565,10 +725,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     pub fn push_context(&mut self, level: log::Level, ctx: &str) {
         self.context_log.push(ctx, level, self.num_gates());
+        self.update_current_context();
     }
 
     pub fn pop_context(&mut self) {
         self.context_log.pop(self.num_gates());
+        self.update_current_context();
+    }
+
+    fn update_current_context(&mut self) {
+        let stack = self.context_log.open_stack();
+        self.current_context = match self.context_indices.get(&stack) {
+            Some(&index) => index,
+            None => {
+                self.contexts.push(stack.clone());
+                self.context_indices.insert(stack, self.contexts.len() - 1);
+                self.contexts.len() - 1
+            }
+        };
     }
 
     /// Returns the total number of LUTs.
@@ -745,8 +919,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// The number of polynomial values that will be revealed per opening, both for the "regular"
     /// polynomials (which are opened at only one location) and for the Z polynomials (which are
     /// opened at two).
-    fn blinding_counts(&self) -> (usize, usize) {
-        let num_gates = self.gate_instances.len();
+    fn blinding_counts(&self, num_gates: usize) -> (usize, usize) {
         let mut degree_estimate = 1 << log2_ceil(num_gates);
 
         loop {
@@ -777,7 +950,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     fn blind(&mut self) {
-        let (regular_poly_openings, z_openings) = self.blinding_counts();
+        let (regular_poly_openings, z_openings) = self.blinding_counts(self.gate_instances.len());
         info!(
             "Adding {} blinding terms for witness polynomials, and {}*2 for Z polynomials",
             regular_poly_openings, z_openings
@@ -903,6 +1076,179 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Returns statistics about the cost of the circuit. The rows added when building it with the
+    /// configuration `C`, and the figures depending on them, are estimated.
+    pub fn stats<C: GenericConfig<D, F = F>>(&self) -> CircuitStats {
+        let num_wires = self.config.num_wires;
+        let quotient_degree_factor = self.config.max_quotient_degree_factor;
+
+        let mut gates = BTreeMap::<String, GateStats>::new();
+        for instance in &self.gate_instances {
+            let gate = &instance.gate_ref.0;
+            let gate_stats = gates.entry(gate.id()).or_insert_with(|| GateStats {
+                rows: 0,
+                wires_per_row: gate.num_wires(),
+                unused_slots: 0,
+                wasted_wires: 0,
+            });
+            gate_stats.rows += 1;
+            gate_stats.wasted_wires += num_wires - gate.num_wires();
+        }
+        for (gate_ref, slots) in &self.current_slots {
+            let gate = &gate_ref.0;
+            let num_ops = gate.num_ops();
+            let wires_per_op = gate.num_wires() / num_ops;
+            let gate_stats = gates
+                .get_mut(&gate.id())
+                .expect("Slots of a gate which was never added");
+            for &(_, next_op) in slots.current_slot.values() {
+                gate_stats.unused_slots += num_ops - next_op;
+                gate_stats.wasted_wires += (num_ops - next_op) * wires_per_op;
+            }
+        }
+        let wasted_wires = gates.values().map(|g| g.wasted_wires).sum();
+
+        let mut context_rows = BTreeMap::new();
+        for &context in &self.gate_contexts {
+            *context_rows
+                .entry(self.contexts[context].clone())
+                .or_insert(0) += 1;
+        }
+
+        let lookup_tables = self.lookup_table_stats();
+
+        let num_rows = self.gate_instances.len();
+        let num_public_inputs = self.public_inputs.len();
+        let constant_rows = self.estimated_constant_rows();
+        let unblinded_rows = num_rows + self.estimated_unblinded_build_rows::<C>();
+        let degree_bits = log2_ceil(self.estimated_num_rows::<C>());
+
+        // Estimate the number of constant polynomials, selectors included, from the gates which
+        // will be used once built.
+        let mut all_gates = self.gates.clone();
+        all_gates.insert(GateRef::new(PublicInputGate));
+        if constant_rows > 0 {
+            all_gates.insert(GateRef::new(ConstantGate {
+                num_consts: self.config.num_constants,
+            }));
+        }
+        if unblinded_rows < 1 << degree_bits {
+            all_gates.insert(GateRef::new(NoopGate));
+        }
+        for lut in &self.luts {
+            all_gates.insert(GateRef::new(LookupGate::new_from_table(
+                &self.config,
+                lut.clone(),
+            )));
+            all_gates.insert(GateRef::new(LookupTableGate::new_from_table(
+                &self.config,
+                lut.clone(),
+                0,
+            )));
+        }
+        let mut all_gates = all_gates.into_iter().collect::<Vec<_>>();
+        all_gates.sort_unstable_by_key(|g| (g.0.degree(), g.0.id()));
+        let (_, selectors_info) = selector_polynomials(&all_gates, &[], quotient_degree_factor + 1);
+        let (num_lookup_selectors, num_lookup_polys) = if self.luts.is_empty() {
+            (0, 0)
+        } else {
+            (
+                LookupSelectors::StartEnd as usize + self.luts.len(),
+                self.num_lookup_polys(),
+            )
+        };
+        let max_gate_constants = all_gates
+            .iter()
+            .map(|g| g.0.num_constants())
+            .max()
+            .unwrap_or(0);
+        let num_constant_polys =
+            selectors_info.num_selectors() + num_lookup_selectors + max_gate_constants;
+
+        let (estimated_proof_size, estimated_prover_cost) = estimate_proof_costs(
+            &self.config,
+            &self.fri_params(degree_bits),
+            num_constant_polys,
+            num_lookup_polys,
+            C::Hasher::HASH_SIZE,
+            D,
+        );
+
+        CircuitStats {
+            num_rows,
+            gates,
+            context_rows,
+            wasted_wires,
+            num_constants: self.constants_to_targets.len(),
+            num_free_constant_slots: self.constant_generators.len(),
+            num_wires,
+            num_routed_wires: self.config.num_routed_wires,
+            num_copy_constraints: self.copy_constraints.len(),
+            num_public_inputs,
+            lookup_tables,
+            estimated_build_rows: (1 << degree_bits) - num_rows,
+            degree_bits,
+            estimated_proof_size,
+            estimated_prover_cost,
+        }
+    }
+
+    /// Estimates the rows added by `build` before blinding and padding, assuming that a
+    /// permutation of the inner hasher takes a single row, as with `PoseidonGate`.
+    fn estimated_unblinded_build_rows<C: GenericConfig<D, F = F>>(&self) -> usize {
+        let rate = <<C::InnerHasher as Hasher<F>>::Permutation as PlonkyPermutation<F>>::RATE;
+        let public_input_rows = 1 + ceil_div_usize(self.public_inputs.len(), rate);
+        let lookup_rows = self
+            .lookup_table_stats()
+            .iter()
+            .map(|t| t.estimated_rows)
+            .sum::<usize>();
+        public_input_rows + lookup_rows + self.estimated_constant_rows()
+    }
+
+    fn lookup_table_stats(&self) -> Vec<LookupTableStats> {
+        let lookup_slots = LookupGate::num_slots(&self.config);
+        let lut_slots = LookupTableGate::num_slots(&self.config);
+        self.luts
+            .iter()
+            .zip(&self.lut_to_lookups)
+            .map(|(lut, lookups)| LookupTableStats {
+                num_entries: lut.len(),
+                num_lookups: lookups.len(),
+                estimated_rows: ceil_div_usize(lookups.len(), lookup_slots)
+                    + ceil_div_usize(lut.len(), lut_slots),
+            })
+            .collect()
+    }
+
+    /// Estimates the number of rows of the circuit once built, before padding to a power of two.
+    fn estimated_num_rows<C: GenericConfig<D, F = F>>(&self) -> usize {
+        let unblinded_rows = self.num_gates() + self.estimated_unblinded_build_rows::<C>();
+        if self.config.zero_knowledge {
+            let (regular_poly_openings, z_openings) = self.blinding_counts(unblinded_rows);
+            unblinded_rows + regular_poly_openings + 2 * z_openings
+        } else {
+            unblinded_rows
+        }
+    }
+
+    /// The rows of `ConstantGate`s added by `build` for constants without a free slot.
+    fn estimated_constant_rows(&self) -> usize {
+        ceil_div_usize(
+            self.constants_to_targets
+                .len()
+                .saturating_sub(self.constant_generators.len()),
+            self.config.num_constants,
+        )
+    }
+
+    /// The number of lookup polynomials for each challenge, when the circuit has lookups: there
+    /// is one RE polynomial and several Sum/LDC polynomials.
+    fn num_lookup_polys(&self) -> usize {
+        let lookup_degree = self.config.max_quotient_degree_factor - 1;
+        ceil_div_usize(LookupGate::num_slots(&self.config), lookup_degree) + 1
+    }
+
     /// In PLONK's permutation argument, there's a slight chance of division by zero. We can
     /// mitigate this by randomizing some unused witness elements, so if proving fails with
     /// division by zero, the next attempt will have an (almost) independent chance of success.
@@ -920,7 +1266,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self,
         commit_to_sigma: bool,
     ) -> CircuitData<F, C, D> {
-        let (circuit_data, success) = self.try_build_with_options(commit_to_sigma);
+        let (circuit_data, success, _, unconstrained_targets) =
+            self.try_build_with_constants(commit_to_sigma, false);
+        if !unconstrained_targets.is_empty() {
+            panic!(
+                "{}",
+                UnconstrainedTargetsError {
+                    targets: unconstrained_targets
+                }
+            );
+        }
         if !success {
             panic!("Failed to build circuit");
         }
@@ -928,9 +1283,137 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn try_build_with_options<C: GenericConfig<D, F = F>>(
-        mut self,
+        self,
         commit_to_sigma: bool,
     ) -> (CircuitData<F, C, D>, bool) {
+        let (circuit_data, success, _, unconstrained_targets) =
+            self.try_build_with_constants(commit_to_sigma, false);
+        for unconstrained_target in &unconstrained_targets {
+            warn!("Unconstrained target: {}", unconstrained_target);
+        }
+        (circuit_data, success && unconstrained_targets.is_empty())
+    }
+
+    /// Builds the circuit, and fails with the virtual targets which are not constrained by any
+    /// gate, if there are some. Such a target is set by the prover inputs or by a generator, but
+    /// nothing checks its value, which is usually a soundness bug. This is the same check as with
+    /// `CircuitConfig::lint_unconstrained_targets`, but reported as an error instead of a panic.
+    pub fn build_with_lint<C: GenericConfig<D, F = F>>(
+        self,
+    ) -> Result<CircuitData<F, C, D>, UnconstrainedTargetsError> {
+        let (circuit_data, success, _, unconstrained_targets) =
+            self.try_build_with_constants(true, true);
+        if !success {
+            panic!("Failed to build circuit");
+        }
+        if unconstrained_targets.is_empty() {
+            Ok(circuit_data)
+        } else {
+            Err(UnconstrainedTargetsError {
+                targets: unconstrained_targets,
+            })
+        }
+    }
+
+    /// Builds the circuit so that its common data is `target`, as required to verify its proofs
+    /// with a recursive verifier for `target`. The public inputs are padded with zeros, the gates of
+    /// `target` are added to the selector groups, and `NoopGate`s are added to reach its degree.
+    ///
+    /// Fails if the circuit has a different config or lookup tables, more public inputs, gates which
+    /// `target` doesn't have, or more rows than its degree.
+    pub fn build_with_target_shape<C: GenericConfig<D, F = F>>(
+        mut self,
+        target: &CommonCircuitData<F, D>,
+    ) -> Result<CircuitData<F, C, D>, CircuitShapeError> {
+        self.pad_to_target_shape::<C>(target)?;
+
+        let circuit_data = self.build::<C>();
+        let mismatches = common_data_mismatches(&circuit_data.common, target);
+        if mismatches.is_empty() {
+            Ok(circuit_data)
+        } else {
+            Err(CircuitShapeError::CommonDataMismatch(mismatches))
+        }
+    }
+
+    /// Pads the circuit as `build_with_target_shape` does, without building it. On failure, the
+    /// circuit may already be partly padded: if it has the config, lookup tables, and public inputs
+    /// of `target`, it then has all the gates of `target` and at least as many rows.
+    pub(crate) fn pad_to_target_shape<C: GenericConfig<D, F = F>>(
+        &mut self,
+        target: &CommonCircuitData<F, D>,
+    ) -> Result<(), CircuitShapeError> {
+        if self.config != target.config {
+            return Err(CircuitShapeError::ConfigMismatch);
+        }
+        if self.num_public_inputs() > target.num_public_inputs {
+            return Err(CircuitShapeError::TooManyPublicInputs {
+                num_public_inputs: self.num_public_inputs(),
+                target: target.num_public_inputs,
+            });
+        }
+        if self.luts != target.luts {
+            return Err(CircuitShapeError::LookupTablesMismatch);
+        }
+
+        for gate in &target.gates {
+            self.add_gate_to_gate_set(gate.clone());
+        }
+        if self.num_public_inputs() < target.num_public_inputs {
+            let zero = self.zero();
+            while self.num_public_inputs() < target.num_public_inputs {
+                self.register_public_input(zero);
+            }
+        }
+        while self.estimated_num_rows::<C>() <= target.degree() / 2 {
+            self.add_gate(NoopGate, vec![]);
+        }
+
+        // Besides the gates of the circuit, `build` adds a `PublicInputGate`, `ConstantGate`s for
+        // constants without a free slot, and `NoopGate`s to pad to a power of two.
+        let estimated_rows = self.estimated_num_rows::<C>();
+        let mut gates = self.gates.clone();
+        gates.insert(GateRef::new(PublicInputGate));
+        if self.estimated_constant_rows() > 0 {
+            gates.insert(GateRef::new(ConstantGate {
+                num_consts: self.config.num_constants,
+            }));
+        }
+        if estimated_rows < target.degree() {
+            gates.insert(GateRef::new(NoopGate));
+        }
+        let mut missing_gates = gates
+            .iter()
+            .filter(|&gate| !target.gates.contains(gate))
+            .map(|gate| gate.0.id())
+            .collect::<Vec<_>>();
+        if !missing_gates.is_empty() {
+            missing_gates.sort();
+            return Err(CircuitShapeError::MissingGates(missing_gates));
+        }
+        if estimated_rows > target.degree() {
+            return Err(CircuitShapeError::TooManyRows {
+                estimated_rows,
+                degree: target.degree(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Same as `try_build_with_options`, but also returns the values of the constant polynomials,
+    /// selectors included, and, if `lint_unconstrained_targets` is set in the arguments or in the
+    /// config, the virtual targets which are not constrained by any gate.
+    fn try_build_with_constants<C: GenericConfig<D, F = F>>(
+        mut self,
+        commit_to_sigma: bool,
+        lint_unconstrained_targets: bool,
+    ) -> (
+        CircuitData<F, C, D>,
+        bool,
+        Vec<PolynomialValues<F>>,
+        Vec<UnconstrainedTarget>,
+    ) {
         let mut timing = TimingTree::new("preprocess", Level::Trace);
 
         #[cfg(feature = "std")]
@@ -1038,7 +1521,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let fft_root_table = fft_root_table(max_fft_points);
 
         let constants_sigmas_commitment = if commit_to_sigma {
-            let constants_sigmas_vecs = [constant_vecs, sigma_vecs.clone()].concat();
+            let constants_sigmas_vecs = [constant_vecs.clone(), sigma_vecs.clone()].concat();
             PolynomialBatch::<F, C, D>::from_values(
                 constants_sigmas_vecs,
                 rate_bits,
@@ -1058,21 +1541,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .flat_map(|current_slot| current_slot.current_slot.values().copied())
             .collect::<HashMap<_, _>>();
 
-        // Add gate generators.
-        self.add_generators(
-            self.gate_instances
-                .iter()
-                .enumerate()
-                .flat_map(|(index, gate)| {
-                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
-                    // Remove unused generators, if any.
-                    if let Some(&op) = incomplete_gates.get(&index) {
-                        gens.drain(op..);
-                    }
-                    gens
-                })
-                .collect(),
-        );
+        // Add gate generators, attributed to the context of their gate.
+        let (gate_generators, gate_generator_contexts): (Vec<_>, Vec<_>) = self
+            .gate_instances
+            .iter()
+            .zip(&self.gate_contexts)
+            .enumerate()
+            .flat_map(|(index, (gate, &context))| {
+                let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
+                // Remove unused generators, if any.
+                if let Some(&op) = incomplete_gates.get(&index) {
+                    gens.drain(op..);
+                }
+                gens.into_iter().map(move |generator| (generator, context))
+            })
+            .unzip();
+        self.generators.extend(gate_generators);
+        self.generator_contexts.extend(gate_generator_contexts);
 
         // Index generator indices by their watched targets.
         let mut generator_indices_by_watches = BTreeMap::new();
@@ -1100,12 +1585,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let num_partial_products =
             num_partial_products(self.config.num_routed_wires, quotient_degree_factor);
 
-        let lookup_degree = self.config.max_quotient_degree_factor - 1;
         let num_lookup_polys = if num_luts == 0 {
             0
         } else {
-            // There is 1 RE polynomial and multiple Sum/LDC polynomials.
-            ceil_div_usize(LookupGate::num_slots(&self.config), lookup_degree) + 1
+            self.num_lookup_polys()
         };
         let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
         let domain_separator = self.domain_separator.unwrap_or_default();
@@ -1146,6 +1629,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             }
         }
 
+        let unconstrained_targets =
+            if lint_unconstrained_targets || common.config.lint_unconstrained_targets {
+                find_unconstrained_targets(
+                    &self.gate_instances,
+                    &forest,
+                    common.config.num_wires,
+                    &self.virtual_target_contexts,
+                    &self.contexts,
+                )
+            } else {
+                Vec::new()
+            };
+
         let prover_only = ProverOnlyCircuitData::<F, C, D> {
             generators: self.generators,
             generator_indices_by_watches,
@@ -1158,6 +1654,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             circuit_digest,
             lookup_rows: self.lookup_rows.clone(),
             lut_to_lookups: self.lut_to_lookups.clone(),
+            contexts: self.contexts,
+            generator_contexts: self.generator_contexts,
+            gate_contexts: self.gate_contexts,
         };
 
         let verifier_only = VerifierOnlyCircuitData::<C, D> {
@@ -1175,6 +1674,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 common,
             },
             success,
+            constant_vecs,
+            unconstrained_targets,
         )
     }
 
@@ -1182,13 +1683,31 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.build_with_options(true)
     }
 
+    /// Builds a circuit for witness generation only, which can also check a witness against the
+    /// gate constraints with `MockCircuitData::check_witness`.
     pub fn mock_build<C: GenericConfig<D, F = F>>(self) -> MockCircuitData<F, C, D> {
-        let circuit_data = self.build_with_options(false);
+        let context_tree = self.context_log.clone();
+        let (circuit_data, success, constant_vecs, unconstrained_targets) =
+            self.try_build_with_constants(false, false);
+        if !unconstrained_targets.is_empty() {
+            panic!(
+                "{}",
+                UnconstrainedTargetsError {
+                    targets: unconstrained_targets
+                }
+            );
+        }
+        if !success {
+            panic!("Failed to build circuit");
+        }
         MockCircuitData {
             prover_only: circuit_data.prover_only,
             common: circuit_data.common,
+            constants: transpose_poly_values(constant_vecs),
+            context_tree,
         }
     }
+
     /// Builds a "prover circuit", with data needed to generate proofs but not verify them.
     pub fn build_prover<C: GenericConfig<D, F = F>>(self) -> ProverCircuitData<F, C, D> {
         // TODO: Can skip parts of this.
@@ -1203,3 +1722,140 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         circuit_data.verifier_data()
     }
 }
+
+/// The names of the fields which differ between two `CommonCircuitData`s.
+fn common_data_mismatches<F: RichField + Extendable<D>, const D: usize>(
+    a: &CommonCircuitData<F, D>,
+    b: &CommonCircuitData<F, D>,
+) -> Vec<&'static str> {
+    [
+        ("config", a.config == b.config),
+        ("fri_params", a.fri_params == b.fri_params),
+        ("gates", a.gates == b.gates),
+        ("selectors_info", a.selectors_info == b.selectors_info),
+        (
+            "quotient_degree_factor",
+            a.quotient_degree_factor == b.quotient_degree_factor,
+        ),
+        (
+            "num_gate_constraints",
+            a.num_gate_constraints == b.num_gate_constraints,
+        ),
+        ("num_constants", a.num_constants == b.num_constants),
+        (
+            "num_public_inputs",
+            a.num_public_inputs == b.num_public_inputs,
+        ),
+        ("k_is", a.k_is == b.k_is),
+        (
+            "num_partial_products",
+            a.num_partial_products == b.num_partial_products,
+        ),
+        ("num_lookup_polys", a.num_lookup_polys == b.num_lookup_polys),
+        (
+            "num_lookup_selectors",
+            a.num_lookup_selectors == b.num_lookup_selectors,
+        ),
+        ("luts", a.luts == b.luts),
+    ]
+    .into_iter()
+    .filter(|&(_, eq)| !eq)
+    .map(|(field, _)| field)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::gates::multiplication_extension::MulExtensionGate;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A circuit hashing a chain of `n` hashes, which also has an `ArithmeticGate` in its gate set.
+    fn hash_chain_circuit(config: &CircuitConfig, n: usize) -> CircuitBuilder<F, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let preimage = builder.add_virtual_target();
+        let mut hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![preimage]);
+        for _ in 1..n {
+            hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(hash.elements.to_vec());
+        }
+        builder.register_public_inputs(&hash.elements);
+        builder.add_gate_to_gate_set(GateRef::new(ArithmeticGate::new_from_config(config)));
+        builder
+    }
+
+    #[test]
+    fn test_build_with_target_shape() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let target = hash_chain_circuit(&config, 100).build::<C>().common;
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let y = builder.mul_const_add(F::TWO, x, x);
+        builder.register_public_input(y);
+        let data = builder.build_with_target_shape::<C>(&target)?;
+        assert_eq!(data.common, target);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            [F::from_canonical_u64(3), F::ZERO, F::ZERO, F::ZERO]
+        );
+        data.verify(proof)?;
+
+        // Lower-degree circuits aren't padded if the target doesn't use `NoopGate`s.
+        let target = hash_chain_circuit(&config, 1).build::<C>().common;
+        assert!(!target.gates.contains(&GateRef::new(NoopGate)));
+        let data = hash_chain_circuit(&config, 1).build_with_target_shape::<C>(&target)?;
+        assert_eq!(data.common, target);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_with_unreachable_target_shape() {
+        let config = CircuitConfig::standard_recursion_config();
+        let target = hash_chain_circuit(&config, 10).build::<C>().common;
+
+        let builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_ecc_config());
+        assert_eq!(
+            builder.build_with_target_shape::<C>(&target).unwrap_err(),
+            CircuitShapeError::ConfigMismatch
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let inputs = builder.add_virtual_targets(5);
+        builder.register_public_inputs(&inputs);
+        assert_eq!(
+            builder.build_with_target_shape::<C>(&target).unwrap_err(),
+            CircuitShapeError::TooManyPublicInputs {
+                num_public_inputs: 5,
+                target: 4
+            }
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_extension_target();
+        builder.mul_extension(x, x);
+        let gate = MulExtensionGate::<D>::new_from_config(&config);
+        assert_eq!(
+            builder.build_with_target_shape::<C>(&target).unwrap_err(),
+            CircuitShapeError::MissingGates(vec![Gate::<F, D>::id(&gate)])
+        );
+
+        let builder = hash_chain_circuit(&config, 20);
+        assert!(matches!(
+            builder.build_with_target_shape::<C>(&target).unwrap_err(),
+            CircuitShapeError::TooManyRows { degree: 16, .. }
+        ));
+    }
+}
@@ -1,9 +1,10 @@
 use alloc::collections::BTreeMap;
-use alloc::vec;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::ops::{Range, RangeFrom};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::Serialize;
 
 use super::circuit_builder::LookupWire;
@@ -24,21 +25,26 @@ use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::generator::{generate_partial_witness, WitnessGeneratorRef};
+use crate::iop::generator::{
+    generate_partial_witness, WitnessGenerationError, WitnessGeneratorRef,
+};
 use crate::iop::target::Target;
-use crate::iop::witness::{PartialWitness, PartitionWitness};
+use crate::iop::witness::{PartialWitness, PartitionWitness, Witness};
 use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_graph::CircuitGraph;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
-use crate::plonk::verifier::verify;
+use crate::plonk::prover::{prove, prove_with_checkpoints, resume_proof, ProverCheckpoint};
+use crate::plonk::verifier::{verify, verify_batch, BatchVerificationError};
+use crate::plonk::witness_check::{find_constraint_failures, ConstraintFailure};
+use crate::util::context_tree::ContextTree;
 use crate::util::serialization::{
     Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
 use crate::util::timing::TimingTree;
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct CircuitConfig {
     pub num_wires: usize,
     pub num_routed_wires: usize,
@@ -55,8 +61,41 @@ pub struct CircuitConfig {
     /// systematically, but will never exceed this value.
     pub max_quotient_degree_factor: usize,
     pub fri_config: FriConfig,
+    /// Whether building the circuit should fail if some virtual targets are not constrained by any
+    /// gate, which usually means that a value set by a generator is never checked. This doesn't
+    /// change the circuit, so it is neither serialized nor compared.
+    #[serde(skip)]
+    pub lint_unconstrained_targets: bool,
+}
+
+impl PartialEq for CircuitConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let CircuitConfig {
+            num_wires,
+            num_routed_wires,
+            num_constants,
+            use_base_arithmetic_gate,
+            security_bits,
+            num_challenges,
+            zero_knowledge,
+            max_quotient_degree_factor,
+            fri_config,
+            lint_unconstrained_targets: _,
+        } = self;
+        *num_wires == other.num_wires
+            && *num_routed_wires == other.num_routed_wires
+            && *num_constants == other.num_constants
+            && *use_base_arithmetic_gate == other.use_base_arithmetic_gate
+            && *security_bits == other.security_bits
+            && *num_challenges == other.num_challenges
+            && *zero_knowledge == other.zero_knowledge
+            && *max_quotient_degree_factor == other.max_quotient_degree_factor
+            && *fri_config == other.fri_config
+    }
 }
 
+impl Eq for CircuitConfig {}
+
 impl Default for CircuitConfig {
     fn default() -> Self {
         Self::standard_recursion_config()
@@ -86,6 +125,7 @@ impl CircuitConfig {
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 28,
             },
+            lint_unconstrained_targets: false,
         }
     }
 
@@ -117,14 +157,77 @@ pub struct MockCircuitData<F: RichField + Extendable<D>, C: GenericConfig<D, F =
 {
     pub prover_only: ProverOnlyCircuitData<F, C, D>,
     pub common: CommonCircuitData<F, D>,
+    /// The values of the constant polynomials, selectors included, at each row.
+    pub(crate) constants: Vec<Vec<F>>,
+    /// The contexts pushed while building the circuit, to locate the gates of failing constraints.
+    pub(crate) context_tree: ContextTree,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     MockCircuitData<F, C, D>
 {
-    pub fn generate_witness(&self, inputs: PartialWitness<F>) -> PartitionWitness<F> {
+    pub fn generate_witness(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<PartitionWitness<'_, F>, WitnessGenerationError> {
         generate_partial_witness::<F, C, D>(inputs, &self.prover_only, &self.common)
     }
+
+    /// Returns the graph of copy constraints and generators of the circuit, generating a witness
+    /// from `inputs` to find the targets set by each generator.
+    pub fn circuit_graph(&self, inputs: PartialWitness<F>) -> CircuitGraph {
+        CircuitGraph::new(&self.prover_only, &self.common, inputs)
+    }
+
+    /// Generates a witness from `inputs`, and returns the gate constraints which don't hold for
+    /// it. Lookups are not checked. Copy constraints are enforced by witness generation itself,
+    /// which fails with `WitnessGenerationError::Conflict` if a wire is set inconsistently.
+    pub fn find_constraint_failures(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<Vec<ConstraintFailure<F>>, WitnessGenerationError> {
+        let partition_witness = self.generate_witness(inputs)?;
+        let public_inputs = partition_witness.get_targets(&self.prover_only.public_inputs);
+        let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+        let witness = partition_witness.full_witness();
+        Ok(find_constraint_failures(
+            &self.common,
+            &self.constants,
+            &self.context_tree,
+            &witness,
+            &public_inputs_hash,
+        ))
+    }
+
+    /// Generates a witness from `inputs`, and checks it against the gate constraints. On failure,
+    /// the error lists the failing constraints, with their rows, gates and contexts.
+    pub fn check_witness(&self, inputs: PartialWitness<F>) -> Result<()> {
+        const MAX_REPORTED_FAILURES: usize = 20;
+
+        let failures = self
+            .find_constraint_failures(inputs)
+            .map_err(anyhow::Error::msg)?;
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        let mut report = failures
+            .iter()
+            .take(MAX_REPORTED_FAILURES)
+            .map(|failure| failure.to_string())
+            .collect::<Vec<_>>();
+        if failures.len() > MAX_REPORTED_FAILURES {
+            report.push(format!(
+                "... and {} more",
+                failures.len() - MAX_REPORTED_FAILURES
+            ));
+        }
+        Err(anyhow!(
+            "{} constraints failed:\n{}",
+            failures.len(),
+            report.join("\n")
+        ))
+    }
 }
 
 /// Circuit data required by the prover or the verifier.
@@ -166,10 +269,55 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    /// Like `prove`, but calls `on_checkpoint` at the end of each phase of the prover, so that the
+    /// proof can be finished with `resume_proof` if the prover is interrupted.
+    pub fn prove_with_checkpoints(
+        &self,
+        inputs: PartialWitness<F>,
+        on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_checkpoints::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            on_checkpoint,
+            &mut TimingTree::default(),
+        )
+    }
+
+    pub fn resume_proof(
+        &self,
+        checkpoint: ProverCheckpoint<F, C, D>,
+        on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        resume_proof::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            checkpoint,
+            on_checkpoint,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Returns the graph of copy constraints and generators of the circuit, generating a witness
+    /// from `inputs` to find the targets set by each generator.
+    pub fn circuit_graph(&self, inputs: PartialWitness<F>) -> CircuitGraph {
+        CircuitGraph::new(&self.prover_only, &self.common, inputs)
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies proofs of this circuit in parallel. On failure, reports the index of every invalid
+    /// proof in `proofs_with_pis`.
+    pub fn verify_batch(
+        &self,
+        proofs_with_pis: &[ProofWithPublicInputs<F, C, D>],
+    ) -> core::result::Result<(), BatchVerificationError> {
+        verify_batch::<F, C, D>(proofs_with_pis, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -262,6 +410,36 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    /// Like `prove`, but calls `on_checkpoint` at the end of each phase of the prover, so that the
+    /// proof can be finished with `resume_proof` if the prover is interrupted.
+    pub fn prove_with_checkpoints(
+        &self,
+        inputs: PartialWitness<F>,
+        on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_checkpoints::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            on_checkpoint,
+            &mut TimingTree::default(),
+        )
+    }
+
+    pub fn resume_proof(
+        &self,
+        checkpoint: ProverCheckpoint<F, C, D>,
+        on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        resume_proof::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            checkpoint,
+            on_checkpoint,
+            &mut TimingTree::default(),
+        )
+    }
 }
 
 /// Circuit data required by the prover.
@@ -296,6 +474,15 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies proofs of this circuit in parallel. On failure, reports the index of every invalid
+    /// proof in `proofs_with_pis`.
+    pub fn verify_batch(
+        &self,
+        proofs_with_pis: &[ProofWithPublicInputs<F, C, D>],
+    ) -> core::result::Result<(), BatchVerificationError> {
+        verify_batch::<F, C, D>(proofs_with_pis, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -335,6 +522,13 @@ pub struct ProverOnlyCircuitData<
     pub lookup_rows: Vec<LookupWire>,
     /// A vector of (looking_in, looking_out) pairs for for each lookup table index.
     pub lut_to_lookups: Vec<Lookup>,
+    /// The distinct stacks of contexts which were open while building the circuit, used to report
+    /// where stalled generators come from and to annotate `CircuitGraph`s.
+    pub contexts: Vec<String>,
+    /// For each generator, the index in `contexts` of the stack open when it was added.
+    pub generator_contexts: Vec<usize>,
+    /// For each gate, the index in `contexts` of the stack open when it was added.
+    pub gate_contexts: Vec<usize>,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
@@ -498,6 +692,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
     }
 
     pub(crate) fn get_fri_instance(&self, zeta: F::Extension) -> FriInstanceInfo<F, D> {
+        let [zeta, zeta_next] = self.fri_opening_points(zeta);
+
         // All polynomials are opened at zeta.
         let zeta_batch = FriBatchInfo {
             point: zeta,
@@ -505,8 +701,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         };
 
         // The Z polynomials are also opened at g * zeta.
-        let g = F::Extension::primitive_root_of_unity(self.degree_bits());
-        let zeta_next = g * zeta;
         let zeta_next_batch = FriBatchInfo {
             point: zeta_next,
             polynomials: self.fri_next_batch_polys(),
@@ -519,6 +713,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         }
     }
 
+    /// The points of the batches of `get_fri_instance(zeta)`, in order. Setting them on a
+    /// `get_fri_instance` result for another point gives the instance for `zeta`.
+    pub(crate) fn fri_opening_points(&self, zeta: F::Extension) -> [F::Extension; 2] {
+        let g = F::Extension::primitive_root_of_unity(self.degree_bits());
+        [zeta, g * zeta]
+    }
+
     pub(crate) fn get_fri_instance_target(
         &self,
         builder: &mut CircuitBuilder<F, D>,
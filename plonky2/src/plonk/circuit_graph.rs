@@ -0,0 +1,292 @@
+//! Exporting the copy constraints and the generators of a circuit as a graph, in DOT or JSON
+//! format, to inspect how its witness is generated.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+use serde::Serialize;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::generate_partial_witness_observed;
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::PartialWitness;
+use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::plonk::config::GenericConfig;
+
+/// A graph of the partitions of targets which are copy-constrained together, and of the generators
+/// which read and set them.
+///
+/// The partitions set by each generator are only known once it has run, so they are found by
+/// generating a witness. If witness generation stalls, the generators which could not run have no
+/// outputs. Partitions made of a single wire which no generator touches, such as the wires of
+/// padding gates, are left out.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct CircuitGraph {
+    /// The distinct stacks of contexts which were open while building the circuit.
+    pub contexts: Vec<String>,
+    pub partitions: Vec<PartitionNode>,
+    /// The generators of the circuit, in the order of `ProverOnlyCircuitData::generators`.
+    pub generators: Vec<GeneratorNode>,
+}
+
+/// A set of targets which are copy-constrained to be equal.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PartitionNode {
+    /// The index of the partition's representative in `ProverOnlyCircuitData::representative_map`.
+    pub id: usize,
+    pub wires: Vec<Wire>,
+    /// The indices of the virtual targets in the partition.
+    pub virtual_targets: Vec<usize>,
+    /// The contexts of the gates owning the wires of the partition, as indices in `contexts`.
+    pub contexts: Vec<usize>,
+    /// Whether the partition was set in the inputs given to the prover.
+    pub input: bool,
+    /// Whether the partition has no wire, so that its value is not constrained by any gate.
+    pub unconstrained: bool,
+}
+
+/// A witness generator, with the partitions it reads and sets.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct GeneratorNode {
+    pub id: String,
+    /// The context open when the generator was added, as an index in `contexts`.
+    pub context: usize,
+    /// The IDs of the partitions in the generator's watch list.
+    pub dependencies: Vec<usize>,
+    /// The IDs of the partitions set by the generator while generating the witness.
+    pub outputs: Vec<usize>,
+}
+
+impl CircuitGraph {
+    pub fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        prover_data: &ProverOnlyCircuitData<F, C, D>,
+        common_data: &CommonCircuitData<F, D>,
+        inputs: PartialWitness<F>,
+    ) -> Self {
+        let num_wires = common_data.config.num_wires;
+        let degree = common_data.degree();
+        let representative_map = &prover_data.representative_map;
+        let rep = |t: Target| representative_map[t.index(num_wires, degree)];
+
+        let mut generators = prover_data
+            .generators
+            .iter()
+            .zip(&prover_data.generator_contexts)
+            .map(|(generator, &context)| {
+                let mut dependencies = generator
+                    .0
+                    .watch_list()
+                    .into_iter()
+                    .map(rep)
+                    .collect::<Vec<_>>();
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                GeneratorNode {
+                    id: generator.0.id(),
+                    context,
+                    dependencies,
+                    outputs: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let inputs_reps = inputs
+            .target_values
+            .keys()
+            .map(|&t| rep(t))
+            .collect::<HashSet<_>>();
+        // A stalled witness generation still tells which partitions the other generators set.
+        let _ = generate_partial_witness_observed(
+            inputs,
+            prover_data,
+            common_data,
+            |generator, partition| generators[generator].outputs.push(partition),
+        );
+
+        let mut partitions = BTreeMap::<usize, PartitionNode>::new();
+        let new_partition = |id: usize| PartitionNode {
+            id,
+            wires: Vec::new(),
+            virtual_targets: Vec::new(),
+            contexts: Vec::new(),
+            input: inputs_reps.contains(&id),
+            unconstrained: true,
+        };
+        for row in 0..degree {
+            for column in 0..num_wires {
+                let id = rep(Target::wire(row, column));
+                let node = partitions.entry(id).or_insert_with(|| new_partition(id));
+                node.wires.push(Wire { row, column });
+                node.contexts.push(prover_data.gate_contexts[row]);
+                node.unconstrained = false;
+            }
+        }
+        for index in 0..representative_map.len() - degree * num_wires {
+            let id = rep(Target::VirtualTarget { index });
+            let node = partitions.entry(id).or_insert_with(|| new_partition(id));
+            node.virtual_targets.push(index);
+        }
+
+        let used = generators
+            .iter()
+            .flat_map(|g| g.dependencies.iter().chain(&g.outputs))
+            .copied()
+            .collect::<HashSet<_>>();
+        let partitions = partitions
+            .into_values()
+            .filter(|p| {
+                p.wires.len() > 1
+                    || !p.virtual_targets.is_empty()
+                    || p.input
+                    || used.contains(&p.id)
+            })
+            .map(|mut p| {
+                p.contexts.sort_unstable();
+                p.contexts.dedup();
+                p
+            })
+            .collect();
+
+        Self {
+            contexts: prover_data.contexts.clone(),
+            partitions,
+            generators,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Returns the graph in the DOT format of Graphviz. Generators are boxes grouped by context,
+    /// and partitions are ellipses labeled with one of their targets. Edges go from partitions to
+    /// the generators depending on them, and from generators to the partitions they set. Inputs
+    /// are drawn with a double border, and unconstrained partitions in red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph circuit {\n");
+
+        for (context_index, context) in self.contexts.iter().enumerate() {
+            let mut generators = self
+                .generators
+                .iter()
+                .enumerate()
+                .filter(|(_, g)| g.context == context_index)
+                .peekable();
+            if generators.peek().is_none() {
+                continue;
+            }
+            dot += &format!("  subgraph cluster_{context_index} {{\n    label={context:?};\n");
+            for (i, generator) in generators {
+                dot += &format!("    g{i} [shape=box, label={:?}];\n", generator.id);
+            }
+            dot += "  }\n";
+        }
+
+        for partition in &self.partitions {
+            let mut label = match (partition.wires.first(), partition.virtual_targets.first()) {
+                (Some(wire), _) => format!("row {} column {}", wire.row, wire.column),
+                (None, Some(index)) => format!("virtual {index}"),
+                (None, None) => unreachable!("Empty partition"),
+            };
+            let num_targets = partition.wires.len() + partition.virtual_targets.len();
+            if num_targets > 1 {
+                label += &format!(" (+{})", num_targets - 1);
+            }
+            let mut attributes = format!("label={label:?}");
+            if partition.input {
+                attributes += ", peripheries=2";
+            }
+            if partition.unconstrained {
+                attributes += ", color=red";
+            }
+            dot += &format!("  p{} [{attributes}];\n", partition.id);
+        }
+
+        for (i, generator) in self.generators.iter().enumerate() {
+            for dependency in &generator.dependencies {
+                dot += &format!("  p{dependency} -> g{i};\n");
+            }
+            for output in &generator.outputs {
+                dot += &format!("  g{i} -> p{output};\n");
+            }
+        }
+
+        dot += "}\n";
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_circuit_graph() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let unused = builder.add_virtual_target();
+        let product = with_context!(builder, "multiply", builder.mul(x, y));
+        builder.register_public_input(product);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(4));
+        pw.set_target(unused, F::ONE);
+        let graph = data.circuit_graph(pw);
+
+        let partition_of = |t: Target| {
+            let num_wires = data.common.config.num_wires;
+            let id = data.prover_only.representative_map[t.index(num_wires, data.common.degree())];
+            graph.partitions.iter().find(|p| p.id == id).unwrap()
+        };
+        let x_partition = partition_of(x);
+        assert!(x_partition.input);
+        assert!(!x_partition.unconstrained);
+        let unused_partition = partition_of(unused);
+        assert!(unused_partition.input);
+        assert!(unused_partition.unconstrained);
+        assert!(unused_partition.wires.is_empty());
+
+        let product_partition = partition_of(product);
+        assert!(!product_partition.input);
+        let multiply_context = graph
+            .contexts
+            .iter()
+            .position(|c| c == "root > multiply")
+            .unwrap();
+        let generator = graph
+            .generators
+            .iter()
+            .find(|g| g.outputs.contains(&product_partition.id))
+            .unwrap();
+        assert_eq!(generator.context, multiply_context);
+        assert!(generator.dependencies.contains(&x_partition.id));
+        assert!(product_partition.contexts.contains(&multiply_context));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("label=\"root > multiply\""));
+        assert!(dot.contains(&format!("p{} [label=\"virtual", unused_partition.id)));
+        assert!(dot.contains(&format!("p{} -> g", x_partition.id)));
+        assert!(graph.to_json().unwrap().contains("\"unconstrained\": true"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Statistics about the cost of a circuit, as reported by `CircuitBuilder::stats`.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::Serialize;
+
+use crate::fri::oracle::SALT_SIZE;
+use crate::fri::FriParams;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::util::partial_products::num_partial_products;
+
+/// A report on the cost of a circuit, meant to be compared between versions of a circuit, e.g. by
+/// diffing its JSON encoding.
+///
+/// Rows are counted as the circuit stands when `CircuitBuilder::stats` is called. The rows which
+/// are only added when building it, to hash public inputs, place lookups and constants, and blind
+/// and pad the trace, are estimated, as are the figures which depend on them.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct CircuitStats {
+    /// The number of rows added so far.
+    pub num_rows: usize,
+    /// The rows taken by each gate, keyed by gate ID.
+    pub gates: BTreeMap<String, GateStats>,
+    /// The number of rows added in each context, keyed by the stack of open contexts, as in
+    /// `root > outer > inner`. Rows added in nested contexts are not counted in their parents.
+    /// Gates which hold several operations are attributed to the context of the first one.
+    pub context_rows: BTreeMap<String, usize>,
+    /// The number of wires left unused, summed over all gates.
+    pub wasted_wires: usize,
+    /// The number of distinct constants used by the circuit.
+    pub num_constants: usize,
+    /// The number of constants which can be stored in unused constant and routed wire slots of
+    /// existing gates, without adding `ConstantGate`s.
+    pub num_free_constant_slots: usize,
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    /// The number of copy constraints between routed wires.
+    pub num_copy_constraints: usize,
+    pub num_public_inputs: usize,
+    pub lookup_tables: Vec<LookupTableStats>,
+    /// The estimated number of rows added when building the circuit.
+    pub estimated_build_rows: usize,
+    /// The estimated `degree_bits` of the built circuit.
+    pub degree_bits: usize,
+    /// The estimated size of a proof, in bytes, without compression.
+    pub estimated_proof_size: usize,
+    /// A machine-independent estimate of proving time: the number of field elements in the
+    /// low-degree extensions committed to by the prover, whose computation and hashing dominate
+    /// proving time.
+    pub estimated_prover_cost: usize,
+}
+
+impl CircuitStats {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// The rows taken by a gate.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct GateStats {
+    pub rows: usize,
+    /// The number of wires used by the gate in each row.
+    pub wires_per_row: usize,
+    /// The number of operations left unused in rows filled with `CircuitBuilder::find_slot`.
+    pub unused_slots: usize,
+    /// The number of wires left unused in the rows of the gate, including those of unused slots.
+    pub wasted_wires: usize,
+}
+
+/// The size of a lookup table and the number of lookups in it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct LookupTableStats {
+    pub num_entries: usize,
+    pub num_lookups: usize,
+    /// The number of `LookupGate` and `LookupTableGate` rows to be added when building.
+    pub estimated_rows: usize,
+}
+
+/// Estimates the size in bytes of an uncompressed proof, and the number of field elements in the
+/// low-degree extensions committed to by the prover.
+pub(crate) fn estimate_proof_costs(
+    config: &CircuitConfig,
+    fri_params: &FriParams,
+    num_constant_polys: usize,
+    num_lookup_polys: usize,
+    hash_size: usize,
+    extension_degree: usize,
+) -> (usize, usize) {
+    // Field elements are serialized as `u64`s.
+    let field_size = 8;
+    let ext_size = extension_degree * field_size;
+    let num_challenges = config.num_challenges;
+    let quotient_degree_factor = config.max_quotient_degree_factor;
+    let cap_height = config.fri_config.cap_height;
+    let cap_size = (1 << cap_height) * hash_size;
+    let salt_size = if config.zero_knowledge { SALT_SIZE } else { 0 };
+
+    let num_partial_products =
+        num_partial_products(config.num_routed_wires, quotient_degree_factor);
+    let num_zs_polys = num_challenges * (1 + num_partial_products + num_lookup_polys);
+    let num_quotient_polys = num_challenges * quotient_degree_factor;
+    let leaf_sizes = [
+        num_constant_polys + config.num_routed_wires,
+        config.num_wires + salt_size,
+        num_zs_polys + salt_size,
+        num_quotient_polys + salt_size,
+    ];
+
+    // Caps of the wires, Zs and quotient commitments.
+    let caps_size = 3 * cap_size;
+    let openings_size = ext_size
+        * (num_constant_polys
+            + config.num_routed_wires
+            + config.num_wires
+            + num_challenges * (2 + num_partial_products + 2 * num_lookup_polys)
+            + num_quotient_polys);
+
+    let fri_size = fri_params.estimate_proof_size(&leaf_sizes, hash_size, extension_degree);
+    let proof_size = caps_size + openings_size + fri_size;
+
+    let num_committed_polys = leaf_sizes[1..].iter().sum::<usize>();
+    let prover_cost = num_committed_polys * fri_params.lde_size();
+
+    (proof_size, prover_cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::gate::Gate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_circuit_stats() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let arithmetic_gate = ArithmeticGate::new_from_config(&config);
+        let num_ops = arithmetic_gate.num_ops;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let mut y = x;
+        with_context!(builder, "square", {
+            for _ in 0..num_ops + 5 {
+                y = builder.mul(y, y);
+            }
+        });
+        builder.register_public_input(x);
+        builder.register_public_input(y);
+
+        let stats = builder.stats::<C>();
+        let arithmetic_stats = &stats.gates[&Gate::<F, D>::id(&arithmetic_gate)];
+        assert_eq!(arithmetic_stats.rows, 2);
+        assert_eq!(arithmetic_stats.unused_slots, num_ops - 5);
+        assert_eq!(
+            arithmetic_stats.wasted_wires,
+            2 * (stats.num_wires - arithmetic_stats.wires_per_row)
+                + (num_ops - 5) * arithmetic_stats.wires_per_row / num_ops
+        );
+        assert_eq!(stats.context_rows["root > square"], 2);
+        assert_eq!(stats.num_public_inputs, 2);
+        assert!(stats.to_json()?.contains("\"degree_bits\""));
+
+        let data = builder.build::<C>();
+        assert_eq!(stats.degree_bits, data.common.degree_bits());
+        assert_eq!(
+            stats.num_rows + stats.estimated_build_rows,
+            data.common.degree()
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        let proof = data.prove(pw)?;
+        // The estimate ignores the lengths prefixed to serialized vectors, and public inputs.
+        let proof_size = proof.to_bytes().len();
+        assert!(stats.estimated_proof_size <= proof_size);
+        assert!(proof_size - stats.estimated_proof_size < proof_size / 50);
+
+        data.verify(proof)
+    }
+}
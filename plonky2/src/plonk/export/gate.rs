@@ -0,0 +1,157 @@
+//! Tracing of the constraints of gates, as arithmetic operations over the openings of the wires
+//! and constants.
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::gates::gate::GateRef;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::{CircuitBuilder, TracedArithmeticOperation};
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::export::ExportError;
+use crate::plonk::vars::EvaluationTargets;
+
+/// The unfiltered constraints of a gate.
+#[derive(Clone, Debug)]
+pub(crate) struct TracedGate {
+    pub(crate) ops: Vec<TracedOp>,
+    /// Each constraint, as its limbs in the extension field.
+    pub(crate) constraints: Vec<Vec<TracedSignal>>,
+}
+
+/// An operation computing `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`, in the
+/// extension field if the operands have `D` limbs, or in the base field if they have one.
+#[derive(Clone, Debug)]
+pub(crate) struct TracedOp {
+    pub(crate) const_0: u64,
+    pub(crate) const_1: u64,
+    pub(crate) multiplicand_0: Vec<TracedSignal>,
+    pub(crate) multiplicand_1: Vec<TracedSignal>,
+    pub(crate) addend: Vec<TracedSignal>,
+}
+
+/// A value in the constraints of a gate.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum TracedSignal {
+    /// A limb of the opening of a wire.
+    Wire { index: usize, limb: usize },
+    /// A limb of the opening of a constant polynomial, not counting the selector polynomials.
+    Constant { index: usize, limb: usize },
+    /// An element of the hash of the public inputs.
+    PublicInputsHash { index: usize },
+    /// A base field element.
+    Value(u64),
+    /// A limb of the result of an operation of the gate.
+    Op { index: usize, limb: usize },
+}
+
+/// Traces the unfiltered constraints of a gate, by evaluating them in a circuit and collecting the
+/// arithmetic operations they depend on.
+pub(crate) fn trace_gate<F: RichField + Extendable<D>, const D: usize>(
+    gate: &GateRef<F, D>,
+    config: &CircuitConfig,
+) -> Result<TracedGate, ExportError> {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    builder.trace_arithmetic();
+    let local_constants = builder.add_virtual_extension_targets(gate.0.num_constants());
+    let local_wires = builder.add_virtual_extension_targets(gate.0.num_wires());
+    let public_inputs_hash = builder.add_virtual_hash();
+    let constraints = gate.0.eval_unfiltered_circuit(
+        &mut builder,
+        EvaluationTargets {
+            local_constants: &local_constants,
+            local_wires: &local_wires,
+            public_inputs_hash: &public_inputs_hash,
+        },
+    );
+
+    let mut tracer = GateTracer {
+        builder: &builder,
+        signals: HashMap::new(),
+        producers: HashMap::new(),
+        ops: Vec::new(),
+    };
+    for (index, wire) in local_wires.iter().enumerate() {
+        for (limb, &target) in wire.0.iter().enumerate() {
+            tracer
+                .signals
+                .insert(target, TracedSignal::Wire { index, limb });
+        }
+    }
+    for (index, constant) in local_constants.iter().enumerate() {
+        for (limb, &target) in constant.0.iter().enumerate() {
+            tracer
+                .signals
+                .insert(target, TracedSignal::Constant { index, limb });
+        }
+    }
+    for (index, &target) in public_inputs_hash.elements.iter().enumerate() {
+        tracer
+            .signals
+            .insert(target, TracedSignal::PublicInputsHash { index });
+    }
+    for op in builder.arithmetic_trace() {
+        for &target in &op.result {
+            tracer.producers.insert(target, op);
+        }
+    }
+
+    let constraints = constraints
+        .iter()
+        .map(|c| c.0.iter().map(|&t| tracer.signal(t)).collect())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| ExportError::UntraceableGate(gate.0.id()))?;
+
+    Ok(TracedGate {
+        ops: tracer.ops,
+        constraints,
+    })
+}
+
+struct GateTracer<'a, F: RichField + Extendable<D>, const D: usize> {
+    builder: &'a CircuitBuilder<F, D>,
+    signals: HashMap<Target, TracedSignal>,
+    producers: HashMap<Target, &'a TracedArithmeticOperation<F>>,
+    ops: Vec<TracedOp>,
+}
+
+impl<'a, F: RichField + Extendable<D>, const D: usize> GateTracer<'a, F, D> {
+    /// The signal of a target, adding the operations it depends on if needed. Returns `None` if the
+    /// target wasn't computed by arithmetic operations.
+    fn signal(&mut self, target: Target) -> Option<TracedSignal> {
+        if let Some(&signal) = self.signals.get(&target) {
+            return Some(signal);
+        }
+        if let Some(value) = self.builder.target_as_constant(target) {
+            return Some(TracedSignal::Value(value.to_canonical_u64()));
+        }
+
+        let producer = *self.producers.get(&target)?;
+        let [multiplicand_0, multiplicand_1, addend] = [
+            &producer.multiplicand_0,
+            &producer.multiplicand_1,
+            &producer.addend,
+        ]
+        .map(|operand| {
+            operand
+                .iter()
+                .map(|&t| self.signal(t))
+                .collect::<Option<Vec<_>>>()
+        });
+        let index = self.ops.len();
+        self.ops.push(TracedOp {
+            const_0: producer.const_0.to_canonical_u64(),
+            const_1: producer.const_1.to_canonical_u64(),
+            multiplicand_0: multiplicand_0?,
+            multiplicand_1: multiplicand_1?,
+            addend: addend?,
+        });
+        for (limb, &t) in producer.result.iter().enumerate() {
+            self.signals.insert(t, TracedSignal::Op { index, limb });
+        }
+        self.signals.get(&target).copied()
+    }
+}
@@ -0,0 +1,1148 @@
+//! Export of the verification of a proof as a straight-line program, for verifiers outside of
+//! plonky2, such as circuits over BN254 verifying proofs of `PoseidonBN254GoldilocksConfig`.
+//!
+//! The program's inputs are the proof, and its instructions are arithmetic operations over the
+//! base field and over BN254, decompositions into bits and digits, Poseidon permutations and
+//! assertions. The program holds the whole verification of the circuit: the challenges are
+//! derived with the sponge of the challenger, the constraints of each gate are traced as
+//! arithmetic operations, filtered by their selectors and combined with the permutation argument
+//! into the quotient check, and the openings are checked with the FRI query rounds, with their
+//! Merkle proofs, and with the proof of work. A proof is valid if all of the assertions of the
+//! program hold. The parameters of both Poseidon permutations are exported along with the program,
+//! so that it can be run on its own.
+//!
+//! The program is not a constraint system over BN254. Only the BN254 instructions map directly to
+//! BN254 constraints. A BN254 circuit running the program has to emulate the base field itself:
+//! it must reduce the results of the base field instructions modulo the base field order, with
+//! range-checked quotients and remainders, and it must range-check the bits and digits output by
+//! `Bits` and `Bn254ToDigits`. The exporter doesn't emit these constraints.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::{Display, Formatter};
+
+use plonky2_field::bn254_scalar::Bn254Scalar;
+use serde::Serialize;
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField};
+use crate::fri::structure::FriInstanceInfo;
+use crate::fri::validate_shape::validate_fri_proof_shape;
+use crate::gates::selectors::UNUSED_SELECTOR;
+use crate::hash::hash_types::RichField;
+use crate::hash::poseidon::{self, PoseidonHash, ALL_ROUND_CONSTANTS};
+use crate::hash::poseidon_bn254::{self, mds_entry, round_constant, PoseidonBN254Hash};
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, GenericHashOut};
+use crate::plonk::export::gate::{trace_gate, TracedGate, TracedSignal};
+use crate::plonk::export::program::{
+    bn254_to_hex, ExtensionSignal, ProgramBuilder, ProgramChallenger,
+};
+pub use crate::plonk::export::program::{Input, InputValue, Instruction, Radix, Signal};
+use crate::plonk::proof::ProofWithPublicInputs;
+use crate::plonk::validate_shape::validate_proof_with_pis_shape;
+use crate::util::reverse_index_bits_in_place;
+
+mod gate;
+mod program;
+
+/// The verification of a proof as a program over the base field and BN254, to be serialized with
+/// `to_json`.
+#[derive(Clone, Debug, Serialize)]
+pub struct VerificationProgram {
+    pub field: FieldDescription,
+    /// The permutation of `Poseidon` instructions, over the base field.
+    pub poseidon: PermutationDescription,
+    /// The permutation of `PoseidonBn254` instructions, over BN254.
+    pub poseidon_bn254: PermutationDescription,
+    pub inputs: Vec<Input>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl VerificationProgram {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// The base field, and its extension `F[X] / (X^extension_degree - extension_w)`. Inputs in the
+/// extension field are given as their `extension_degree` coefficients.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldDescription {
+    pub order: u64,
+    pub extension_degree: usize,
+    pub extension_w: u64,
+}
+
+/// The parameters of a Poseidon permutation, whose field elements are written in hexadecimal.
+///
+/// Each round adds its round constants to the state, applies the S-box `x^sbox_degree` to every
+/// element in the `full_rounds / 2` first and last rounds and to the first element in the others,
+/// and multiplies the state by the MDS matrix.
+#[derive(Clone, Debug, Serialize)]
+pub struct PermutationDescription {
+    pub order: String,
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    pub sbox_degree: u64,
+    /// `width` round constants per round.
+    pub round_constants: Vec<String>,
+    pub mds_matrix: Vec<Vec<String>>,
+}
+
+/// An error returned by `export_verification` when a proof can't be exported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExportError {
+    /// The circuit uses lookup tables, whose argument isn't exported.
+    LookupTables,
+    /// The constraints of the gate with this ID rely on other gates than arithmetic ones.
+    UntraceableGate(String),
+    /// The proof doesn't have the shape of the proofs of the circuit.
+    ProofShape,
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LookupTables => write!(f, "circuits with lookup tables can't be exported"),
+            Self::UntraceableGate(id) => write!(
+                f,
+                "the constraints of {id} use operations which can't be exported"
+            ),
+            Self::ProofShape => write!(f, "the proof doesn't match the circuit data"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExportError {}
+
+/// Exports the verification of a proof of the circuit with the given data.
+pub fn export_verification<F, C, const D: usize>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<VerificationProgram, ExportError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F, Hasher = PoseidonBN254Hash, InnerHasher = PoseidonHash>,
+{
+    if !common_data.luts.is_empty() {
+        return Err(ExportError::LookupTables);
+    }
+
+    // Only the polynomials of the instance are used: the opening points are computed in the
+    // program.
+    let fri_instance = common_data.get_fri_instance(F::Extension::ZERO);
+    let fri_params = &common_data.fri_params;
+    let fri_proof = &proof_with_pis.proof.opening_proof;
+    let valid_shape = validate_proof_with_pis_shape(proof_with_pis, common_data).is_ok()
+        && validate_fri_proof_shape::<F, C, D>(fri_proof, &fri_instance, fri_params).is_ok()
+        && fri_proof.commit_phase_merkle_caps.len() == fri_params.reduction_arity_bits.len()
+        && fri_proof.query_round_proofs.len() == fri_params.config.num_query_rounds;
+    if !valid_shape {
+        return Err(ExportError::ProofShape);
+    }
+
+    let gates = common_data
+        .gates
+        .iter()
+        .map(|gate| trace_gate(gate, &common_data.config))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut builder = ProgramBuilder::<F, D>::new();
+    let proof = ProofSignals::new(&mut builder, proof_with_pis);
+    let challenges = get_challenges(&mut builder, &proof, verifier_data, common_data);
+    verify_quotient(&mut builder, &proof, &challenges, &gates, common_data);
+    verify_fri_proof(
+        &mut builder,
+        &proof,
+        &challenges,
+        verifier_data,
+        common_data,
+        &fri_instance,
+    );
+
+    Ok(VerificationProgram {
+        field: FieldDescription {
+            order: F::ORDER,
+            extension_degree: D,
+            extension_w: F::W.to_canonical_u64(),
+        },
+        poseidon: poseidon_description::<F>(),
+        poseidon_bn254: poseidon_bn254_description(),
+        inputs: builder.inputs,
+        instructions: builder.instructions,
+    })
+}
+
+fn poseidon_description<F: RichField>() -> PermutationDescription {
+    let width = poseidon::SPONGE_WIDTH;
+    let to_hex = |x: u64| format!("0x{x:016x}");
+    PermutationDescription {
+        order: to_hex(F::ORDER),
+        width,
+        full_rounds: 2 * poseidon::HALF_N_FULL_ROUNDS,
+        partial_rounds: poseidon::N_PARTIAL_ROUNDS,
+        sbox_degree: 7,
+        round_constants: ALL_ROUND_CONSTANTS[..width * poseidon::N_ROUNDS]
+            .iter()
+            .map(|&c| to_hex(c))
+            .collect(),
+        // `Poseidon::mds_layer` multiplies by a circulant matrix plus a diagonal one.
+        mds_matrix: (0..width)
+            .map(|row| {
+                (0..width)
+                    .map(|col| {
+                        let circ = F::MDS_MATRIX_CIRC[(col + width - row) % width];
+                        let diag = if row == col {
+                            F::MDS_MATRIX_DIAG[row]
+                        } else {
+                            0
+                        };
+                        to_hex(circ + diag)
+                    })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+fn poseidon_bn254_description() -> PermutationDescription {
+    let to_hex = |x: Bn254Scalar| bn254_to_hex(&x.to_canonical_biguint());
+    PermutationDescription {
+        order: bn254_to_hex(&Bn254Scalar::order()),
+        width: poseidon_bn254::WIDTH,
+        full_rounds: poseidon_bn254::FULL_ROUNDS,
+        partial_rounds: poseidon_bn254::PARTIAL_ROUNDS,
+        sbox_degree: 5,
+        round_constants: (0..poseidon_bn254::N_ROUNDS)
+            .flat_map(|round| (0..poseidon_bn254::WIDTH).map(move |i| round_constant(round, i)))
+            .map(to_hex)
+            .collect(),
+        mds_matrix: (0..poseidon_bn254::WIDTH)
+            .map(|row| {
+                (0..poseidon_bn254::WIDTH)
+                    .map(|col| to_hex(mds_entry(row, col)))
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// The inputs of the program, in the layout of `ProofWithPublicInputs`.
+struct ProofSignals<const D: usize> {
+    public_inputs: Vec<Signal>,
+    wires_cap: Vec<Signal>,
+    plonk_zs_partial_products_cap: Vec<Signal>,
+    quotient_polys_cap: Vec<Signal>,
+    openings: OpeningSignals<D>,
+    commit_phase_merkle_caps: Vec<Vec<Signal>>,
+    query_round_proofs: Vec<QueryRoundSignals<D>>,
+    final_poly: Vec<ExtensionSignal<D>>,
+    pow_witness: Signal,
+}
+
+struct OpeningSignals<const D: usize> {
+    constants: Vec<ExtensionSignal<D>>,
+    plonk_sigmas: Vec<ExtensionSignal<D>>,
+    wires: Vec<ExtensionSignal<D>>,
+    plonk_zs: Vec<ExtensionSignal<D>>,
+    plonk_zs_next: Vec<ExtensionSignal<D>>,
+    partial_products: Vec<ExtensionSignal<D>>,
+    quotient_polys: Vec<ExtensionSignal<D>>,
+}
+
+struct QueryRoundSignals<const D: usize> {
+    /// The leaves opened in the initial trees, with the siblings of their Merkle proofs.
+    initial_trees_proof: Vec<(Vec<Signal>, Vec<Signal>)>,
+    /// The cosets opened in each FRI layer, with the siblings of their Merkle proofs.
+    steps: Vec<(Vec<ExtensionSignal<D>>, Vec<Signal>)>,
+}
+
+impl<const D: usize> ProofSignals<D> {
+    fn new<F, C>(
+        builder: &mut ProgramBuilder<F, D>,
+        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    ) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F, Hasher = PoseidonBN254Hash>,
+    {
+        let proof = &proof_with_pis.proof;
+        let openings = &proof.openings;
+        let fri_proof = &proof.opening_proof;
+        Self {
+            public_inputs: builder.add_field_inputs("public_inputs", &proof_with_pis.public_inputs),
+            wires_cap: builder.add_hash_inputs("wires_cap", &proof.wires_cap.0),
+            plonk_zs_partial_products_cap: builder.add_hash_inputs(
+                "plonk_zs_partial_products_cap",
+                &proof.plonk_zs_partial_products_cap.0,
+            ),
+            quotient_polys_cap: builder
+                .add_hash_inputs("quotient_polys_cap", &proof.quotient_polys_cap.0),
+            openings: OpeningSignals {
+                constants: builder.add_extension_inputs("constants", &openings.constants),
+                plonk_sigmas: builder.add_extension_inputs("plonk_sigmas", &openings.plonk_sigmas),
+                wires: builder.add_extension_inputs("wires", &openings.wires),
+                plonk_zs: builder.add_extension_inputs("plonk_zs", &openings.plonk_zs),
+                plonk_zs_next: builder
+                    .add_extension_inputs("plonk_zs_next", &openings.plonk_zs_next),
+                partial_products: builder
+                    .add_extension_inputs("partial_products", &openings.partial_products),
+                quotient_polys: builder
+                    .add_extension_inputs("quotient_polys", &openings.quotient_polys),
+            },
+            commit_phase_merkle_caps: fri_proof
+                .commit_phase_merkle_caps
+                .iter()
+                .enumerate()
+                .map(|(i, cap)| {
+                    builder.add_hash_inputs(&format!("commit_phase_merkle_caps[{i}]"), &cap.0)
+                })
+                .collect(),
+            query_round_proofs: fri_proof
+                .query_round_proofs
+                .iter()
+                .enumerate()
+                .map(|(i, round)| QueryRoundSignals {
+                    initial_trees_proof: round
+                        .initial_trees_proof
+                        .evals_proofs
+                        .iter()
+                        .enumerate()
+                        .map(|(j, (evals, merkle_proof))| {
+                            let name = format!("query_round_proofs[{i}].initial_trees_proof[{j}]");
+                            (
+                                builder.add_field_inputs(&format!("{name}.evals"), evals),
+                                builder.add_hash_inputs(
+                                    &format!("{name}.siblings"),
+                                    &merkle_proof.siblings,
+                                ),
+                            )
+                        })
+                        .collect(),
+                    steps: round
+                        .steps
+                        .iter()
+                        .enumerate()
+                        .map(|(j, step)| {
+                            let name = format!("query_round_proofs[{i}].steps[{j}]");
+                            (
+                                builder.add_extension_inputs(&format!("{name}.evals"), &step.evals),
+                                builder.add_hash_inputs(
+                                    &format!("{name}.siblings"),
+                                    &step.merkle_proof.siblings,
+                                ),
+                            )
+                        })
+                        .collect(),
+                })
+                .collect(),
+            final_poly: builder.add_extension_inputs("final_poly", &fri_proof.final_poly.coeffs),
+            pow_witness: builder.add_field_input("pow_witness".into(), fri_proof.pow_witness),
+        }
+    }
+}
+
+impl<const D: usize> OpeningSignals<D> {
+    /// The values of the batches opened at `zeta` and at `g zeta`, as in `to_fri_openings`.
+    fn to_fri_openings(&self) -> [Vec<ExtensionSignal<D>>; 2] {
+        [
+            [
+                self.constants.as_slice(),
+                self.plonk_sigmas.as_slice(),
+                self.wires.as_slice(),
+                self.plonk_zs.as_slice(),
+                self.partial_products.as_slice(),
+                self.quotient_polys.as_slice(),
+            ]
+            .concat(),
+            self.plonk_zs_next.clone(),
+        ]
+    }
+}
+
+struct ChallengeSignals<const D: usize> {
+    public_inputs_hash: [Signal; 4],
+    plonk_betas: Vec<Signal>,
+    plonk_gammas: Vec<Signal>,
+    plonk_alphas: Vec<Signal>,
+    plonk_zeta: ExtensionSignal<D>,
+    fri_alpha: ExtensionSignal<D>,
+    fri_betas: Vec<ExtensionSignal<D>>,
+    fri_pow_response: Signal,
+    fri_query_indices: Vec<Signal>,
+}
+
+/// Derives the challenges as in `ProofWithPublicInputs::get_challenges`.
+fn get_challenges<F, C, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    proof: &ProofSignals<D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> ChallengeSignals<D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F, Hasher = PoseidonBN254Hash>,
+{
+    let num_challenges = common_data.config.num_challenges;
+    let public_inputs_hash = builder.poseidon_hash_no_pad(&proof.public_inputs);
+
+    let mut challenger = ProgramChallenger::new(builder);
+    let circuit_digest = GenericHashOut::<F>::to_vec(&verifier_data.circuit_digest)
+        .into_iter()
+        .map(|x| builder.constant(x))
+        .collect::<Vec<_>>();
+    challenger.observe_elements(builder, &circuit_digest);
+    challenger.observe_elements(builder, &public_inputs_hash);
+
+    challenger.observe_cap(builder, &proof.wires_cap);
+    let plonk_betas = challenger.get_n_challenges(builder, num_challenges);
+    let plonk_gammas = challenger.get_n_challenges(builder, num_challenges);
+
+    challenger.observe_cap(builder, &proof.plonk_zs_partial_products_cap);
+    let plonk_alphas = challenger.get_n_challenges(builder, num_challenges);
+
+    challenger.observe_cap(builder, &proof.quotient_polys_cap);
+    let plonk_zeta = challenger.get_extension_challenge(builder);
+
+    for batch in proof.openings.to_fri_openings() {
+        challenger.observe_extension_elements(builder, &batch);
+    }
+
+    let fri_alpha = challenger.get_extension_challenge(builder);
+    let fri_betas = proof
+        .commit_phase_merkle_caps
+        .iter()
+        .map(|cap| {
+            challenger.observe_cap(builder, cap);
+            challenger.get_extension_challenge(builder)
+        })
+        .collect();
+    challenger.observe_extension_elements(builder, &proof.final_poly);
+    challenger.observe_element(builder, proof.pow_witness);
+    let fri_pow_response = challenger.get_challenge(builder);
+    let fri_query_indices =
+        challenger.get_n_challenges(builder, common_data.fri_params.config.num_query_rounds);
+
+    ChallengeSignals {
+        public_inputs_hash,
+        plonk_betas,
+        plonk_gammas,
+        plonk_alphas,
+        plonk_zeta,
+        fri_alpha,
+        fri_betas,
+        fri_pow_response,
+        fri_query_indices,
+    }
+}
+
+/// Checks that the vanishing polynomial at `zeta` is a multiple of `Z_H(zeta)`, as in
+/// `verify_with_challenges`.
+fn verify_quotient<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    proof: &ProofSignals<D>,
+    challenges: &ChallengeSignals<D>,
+    gates: &[TracedGate],
+    common_data: &CommonCircuitData<F, D>,
+) {
+    let openings = &proof.openings;
+    let num_challenges = common_data.config.num_challenges;
+    let max_degree = common_data.quotient_degree_factor;
+    let num_prods = common_data.num_partial_products;
+    let zeta = challenges.plonk_zeta;
+    let one = builder.constant_extension(F::Extension::ONE);
+    let zero = builder.constant_extension(F::Extension::ZERO);
+
+    let zeta_pow_deg = builder.square_n_extension(zeta, common_data.degree_bits());
+    let z_h_zeta = builder.sub_extension(zeta_pow_deg, one);
+    // `L_0(zeta) = Z_H(zeta) / (n (zeta - 1))`.
+    let zeta_minus_one = builder.sub_extension(zeta, one);
+    let denominator = builder.mul_const_extension(
+        F::from_canonical_usize(common_data.degree()),
+        zeta_minus_one,
+    );
+    let denominator_inverse = builder.inverse_extension(denominator);
+    let l_0_zeta = builder.mul_extension(z_h_zeta, denominator_inverse);
+
+    let mut vanishing_z_1_terms = Vec::new();
+    let mut vanishing_partial_products_terms = Vec::new();
+    for i in 0..num_challenges {
+        let z_x = openings.plonk_zs[i];
+        let z_gx = openings.plonk_zs_next[i];
+        let z_x_minus_one = builder.sub_extension(z_x, one);
+        vanishing_z_1_terms.push(builder.mul_extension(l_0_zeta, z_x_minus_one));
+
+        let beta = challenges.plonk_betas[i];
+        let gamma = builder.convert_to_extension(challenges.plonk_gammas[i]);
+        let mut numerators = Vec::new();
+        let mut denominators = Vec::new();
+        for j in 0..common_data.config.num_routed_wires {
+            let wire = openings.wires[j];
+            let s_id = builder.mul_const_extension(common_data.k_is[j], zeta);
+            let numerator = builder.scalar_mul_add_extension(beta, s_id, wire);
+            numerators.push(builder.add_extension(numerator, gamma));
+            let denominator =
+                builder.scalar_mul_add_extension(beta, openings.plonk_sigmas[j], wire);
+            denominators.push(builder.add_extension(denominator, gamma));
+        }
+
+        // The partial products, checked as in `check_partial_products`.
+        let partial_products = &openings.partial_products[i * num_prods..(i + 1) * num_prods];
+        let accs = [&[z_x], partial_products, &[z_gx]].concat();
+        for ((numerator_chunk, denominator_chunk), accs) in numerators
+            .chunks(max_degree)
+            .zip(denominators.chunks(max_degree))
+            .zip(accs.windows(2))
+        {
+            let numerator_product = numerator_chunk
+                .iter()
+                .fold(one, |acc, &x| builder.mul_extension(acc, x));
+            let denominator_product = denominator_chunk
+                .iter()
+                .fold(one, |acc, &x| builder.mul_extension(acc, x));
+            let prev = builder.mul_extension(accs[0], numerator_product);
+            let next = builder.mul_extension(accs[1], denominator_product);
+            vanishing_partial_products_terms.push(builder.sub_extension(prev, next));
+        }
+    }
+
+    let constraint_terms = eval_gate_constraints(
+        builder,
+        gates,
+        common_data,
+        openings,
+        &challenges.public_inputs_hash,
+    );
+    let vanishing_terms = [
+        vanishing_z_1_terms,
+        vanishing_partial_products_terms,
+        constraint_terms,
+    ]
+    .concat();
+
+    let quotient_polys = openings.quotient_polys.chunks(max_degree);
+    for (&alpha, quotient_chunk) in challenges.plonk_alphas.iter().zip(quotient_polys) {
+        let vanishing = vanishing_terms.iter().rev().fold(zero, |acc, &term| {
+            builder.scalar_mul_add_extension(alpha, acc, term)
+        });
+        let quotient = quotient_chunk.iter().rev().fold(zero, |acc, &q| {
+            builder.mul_add_extension_with_const(F::ONE, acc, zeta_pow_deg, q)
+        });
+        let expected = builder.mul_extension(z_h_zeta, quotient);
+        builder.assert_equal_extension(vanishing, expected);
+    }
+}
+
+/// Evaluates the filtered constraints of the gates at `zeta`, as in `evaluate_gate_constraints`.
+fn eval_gate_constraints<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    gates: &[TracedGate],
+    common_data: &CommonCircuitData<F, D>,
+    openings: &OpeningSignals<D>,
+    public_inputs_hash: &[Signal; 4],
+) -> Vec<ExtensionSignal<D>> {
+    let selectors_info = &common_data.selectors_info;
+    let num_selectors = selectors_info.num_selectors();
+    let one = builder.constant_extension(F::Extension::ONE);
+    let zero = builder.constant_extension(F::Extension::ZERO);
+
+    let mut constraints = vec![zero; common_data.num_gate_constraints];
+    for (row, gate) in gates.iter().enumerate() {
+        let selector_index = selectors_info.selector_indices[row];
+        let s = openings.constants[selector_index];
+        let filter = selectors_info.groups[selector_index]
+            .clone()
+            .filter(|&i| i != row)
+            .chain((num_selectors > 1).then_some(UNUSED_SELECTOR))
+            .fold(one, |acc, i| {
+                let i = builder.constant_extension(F::Extension::from_canonical_usize(i));
+                let term = builder.sub_extension(i, s);
+                builder.mul_extension(acc, term)
+            });
+
+        let values = inline_gate(
+            builder,
+            gate,
+            &openings.wires,
+            &openings.constants[num_selectors..],
+            public_inputs_hash,
+        );
+        for (constraint, value) in constraints.iter_mut().zip(values) {
+            *constraint = builder.mul_add_extension_with_const(F::ONE, filter, value, *constraint);
+        }
+    }
+    constraints
+}
+
+/// Adds the operations of a traced gate to the program, and returns its unfiltered constraints.
+fn inline_gate<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    gate: &TracedGate,
+    wires: &[ExtensionSignal<D>],
+    constants: &[ExtensionSignal<D>],
+    public_inputs_hash: &[Signal; 4],
+) -> Vec<ExtensionSignal<D>> {
+    let signal = |results: &[Vec<Signal>], signal: TracedSignal| match signal {
+        TracedSignal::Wire { index, limb } => wires[index][limb],
+        TracedSignal::Constant { index, limb } => constants[index][limb],
+        TracedSignal::PublicInputsHash { index } => public_inputs_hash[index],
+        TracedSignal::Value(x) => Signal::Value(x),
+        TracedSignal::Op { index, limb } => results[index][limb],
+    };
+
+    let mut results: Vec<Vec<Signal>> = Vec::with_capacity(gate.ops.len());
+    for op in &gate.ops {
+        let [multiplicand_0, multiplicand_1, addend] =
+            [&op.multiplicand_0, &op.multiplicand_1, &op.addend].map(|operand| {
+                operand
+                    .iter()
+                    .map(|&s| signal(&results, s))
+                    .collect::<Vec<_>>()
+            });
+        let const_0 = F::from_canonical_u64(op.const_0);
+        let const_1 = F::from_canonical_u64(op.const_1);
+        let result = if multiplicand_0.len() == 1 {
+            vec![builder.arithmetic(
+                const_0,
+                multiplicand_0[0],
+                multiplicand_1[0],
+                const_1,
+                addend[0],
+            )]
+        } else {
+            let [multiplicand_0, multiplicand_1, addend] = [multiplicand_0, multiplicand_1, addend]
+                .map(|operand| ExtensionSignal::<D>::try_from(operand).unwrap());
+            let addend = builder.mul_const_extension(const_1, addend);
+            builder
+                .mul_add_extension_with_const(const_0, multiplicand_0, multiplicand_1, addend)
+                .to_vec()
+        };
+        results.push(result);
+    }
+
+    gate.constraints
+        .iter()
+        .map(|c| core::array::from_fn(|limb| signal(&results, c[limb])))
+        .collect()
+}
+
+/// Verifies the FRI proof of the openings, as in `verify_fri_proof`.
+fn verify_fri_proof<F, C, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    proof: &ProofSignals<D>,
+    challenges: &ChallengeSignals<D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    instance: &FriInstanceInfo<F, D>,
+) where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F, Hasher = PoseidonBN254Hash>,
+{
+    let params = &common_data.fri_params;
+    let zero = builder.zero();
+
+    // The proof of work: the leading bits of the response are zero.
+    let pow_response_bits = builder.split_le(challenges.fri_pow_response, 64);
+    let num_leading_zeros =
+        params.config.proof_of_work_bits as usize + 64 - F::order().bits() as usize;
+    for &bit in &pow_response_bits[64 - num_leading_zeros..] {
+        builder.assert_equal(bit, zero);
+    }
+
+    // The openings reduced by `alpha`, and the points at which they are opened.
+    let alpha = challenges.fri_alpha;
+    let ext_zero = builder.constant_extension(F::Extension::ZERO);
+    let reduced_openings = proof.openings.to_fri_openings().map(|values| {
+        values.iter().rev().fold(ext_zero, |acc, &x| {
+            builder.mul_add_extension_with_const(F::ONE, acc, alpha, x)
+        })
+    });
+    let g = F::primitive_root_of_unity(common_data.degree_bits());
+    let zeta = challenges.plonk_zeta;
+    let points = [zeta, builder.mul_const_extension(g, zeta)];
+    // The powers of `alpha` by which the sums of the previous batches are shifted.
+    let shifts = instance
+        .batches
+        .iter()
+        .map(|batch| {
+            (0..batch.polynomials.len())
+                .fold(builder.constant_extension(F::Extension::ONE), |acc, _| {
+                    builder.mul_extension(acc, alpha)
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let constants_sigmas_cap = verifier_data
+        .constants_sigmas_cap
+        .0
+        .iter()
+        .map(|hash| builder.bn254_hash_constant(hash))
+        .collect::<Vec<_>>();
+    let initial_merkle_caps = [
+        &constants_sigmas_cap,
+        &proof.wires_cap,
+        &proof.plonk_zs_partial_products_cap,
+        &proof.quotient_polys_cap,
+    ];
+
+    let log_n = params.lde_bits();
+    for (round_proof, &x_index) in proof
+        .query_round_proofs
+        .iter()
+        .zip(&challenges.fri_query_indices)
+    {
+        let mut x_index_bits = builder.split_le(x_index, log_n);
+        for ((evals, siblings), cap) in round_proof
+            .initial_trees_proof
+            .iter()
+            .zip(initial_merkle_caps)
+        {
+            builder.verify_merkle_proof_to_cap(evals, &x_index_bits, cap, siblings);
+        }
+
+        // `subgroup_x` is `subgroup[x_index]`, i.e., the actual field element in the domain.
+        let omega = F::primitive_root_of_unity(log_n);
+        let mut subgroup_x = builder.constant(F::MULTIPLICATIVE_GROUP_GENERATOR);
+        for (i, &bit) in x_index_bits.iter().rev().enumerate() {
+            subgroup_x = builder.mul_const_if(bit, omega.exp_power_of_2(i), subgroup_x);
+        }
+
+        // Combine the initial openings, as in `fri_combine_initial`.
+        let subgroup_x_ext = builder.convert_to_extension(subgroup_x);
+        let mut old_eval = ext_zero;
+        for (((batch, &reduced_openings), &point), &shift) in instance
+            .batches
+            .iter()
+            .zip(&reduced_openings)
+            .zip(&points)
+            .zip(&shifts)
+        {
+            let reduced_evals = batch.polynomials.iter().rev().fold(ext_zero, |acc, p| {
+                let eval = round_proof.initial_trees_proof[p.oracle_index].0[p.polynomial_index];
+                let eval = builder.convert_to_extension(eval);
+                builder.mul_add_extension_with_const(F::ONE, acc, alpha, eval)
+            });
+            let numerator = builder.sub_extension(reduced_evals, reduced_openings);
+            let denominator = builder.sub_extension(subgroup_x_ext, point);
+            let denominator_inverse = builder.inverse_extension(denominator);
+            let shifted = builder.mul_extension(shift, old_eval);
+            old_eval = builder.mul_add_extension_with_const(
+                F::ONE,
+                numerator,
+                denominator_inverse,
+                shifted,
+            );
+        }
+
+        for (i, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+            let (evals, siblings) = &round_proof.steps[i];
+
+            // Split x_index into the index of the coset x is in, and the index of x within that
+            // coset.
+            let (x_index_within_coset_bits, coset_index_bits) = x_index_bits.split_at(arity_bits);
+
+            // Check consistency with our old evaluation from the previous round.
+            let eval = builder.random_access_extension(x_index_within_coset_bits, evals);
+            builder.assert_equal_extension(eval, old_eval);
+
+            old_eval = compute_evaluation(
+                builder,
+                subgroup_x,
+                x_index_within_coset_bits,
+                evals,
+                challenges.fri_betas[i],
+            );
+
+            let leaf = evals.iter().flatten().copied().collect::<Vec<_>>();
+            builder.verify_merkle_proof_to_cap(
+                &leaf,
+                coset_index_bits,
+                &proof.commit_phase_merkle_caps[i],
+                siblings,
+            );
+
+            subgroup_x = builder.square_n(subgroup_x, arity_bits);
+            x_index_bits = coset_index_bits.to_vec();
+        }
+
+        // The final polynomial agrees with the last derived evaluation.
+        let final_eval = proof.final_poly.iter().rev().fold(ext_zero, |acc, &c| {
+            builder.scalar_mul_add_extension(subgroup_x, acc, c)
+        });
+        builder.assert_equal_extension(final_eval, old_eval);
+    }
+}
+
+/// Computes `P'(x^arity)` from the evaluations of `P` on the coset of `x`, as in
+/// `compute_evaluation`. The evaluations are interpolated in Lagrange form: for the points
+/// `y_i = coset_start g^i`,
+/// `P'(beta) = sum_i P(y_i) w_i prod_{j != i} (beta - y_j) / coset_start^(arity - 1)`, where
+/// `w_i = 1 / prod_{j != i} (g^i - g^j)` doesn't depend on the proof.
+fn compute_evaluation<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut ProgramBuilder<F, D>,
+    x: Signal,
+    x_index_within_coset_bits: &[Signal],
+    evals: &[ExtensionSignal<D>],
+    beta: ExtensionSignal<D>,
+) -> ExtensionSignal<D> {
+    let arity_bits = x_index_within_coset_bits.len();
+    let arity = 1 << arity_bits;
+    let g = F::primitive_root_of_unity(arity_bits);
+
+    // The evaluation vector needs to be reordered first.
+    let mut evals = evals.to_vec();
+    reverse_index_bits_in_place(&mut evals);
+    // `coset_start = x g^(arity - rev(x_index_within_coset))`.
+    let g_inverse = g.inverse();
+    let mut coset_start = x;
+    for (i, &bit) in x_index_within_coset_bits.iter().rev().enumerate() {
+        coset_start = builder.mul_const_if(bit, g_inverse.exp_power_of_2(i), coset_start);
+    }
+
+    let g_powers = g.powers().take(arity).collect::<Vec<_>>();
+    let differences = g_powers
+        .iter()
+        .map(|&g_power| {
+            let point = builder.mul_const(g_power, coset_start);
+            let point = builder.convert_to_extension(point);
+            builder.sub_extension(beta, point)
+        })
+        .collect::<Vec<_>>();
+    let one = builder.constant_extension(F::Extension::ONE);
+    let mut prefix_products = vec![one];
+    for &difference in &differences {
+        let product = builder.mul_extension(*prefix_products.last().unwrap(), difference);
+        prefix_products.push(product);
+    }
+    let mut suffix_products = vec![one];
+    for &difference in differences.iter().rev() {
+        let product = builder.mul_extension(*suffix_products.last().unwrap(), difference);
+        suffix_products.push(product);
+    }
+    suffix_products.reverse();
+
+    let mut sum = builder.constant_extension(F::Extension::ZERO);
+    for (i, &eval) in evals.iter().enumerate() {
+        let weight = g_powers
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, &g_power)| g_powers[i] - g_power)
+            .product::<F>()
+            .inverse();
+        let others = builder.mul_extension(prefix_products[i], suffix_products[i + 1]);
+        sum = builder.mul_add_extension_with_const(weight, eval, others, sum);
+    }
+
+    let scale = builder.exp_u64(coset_start, arity as u64 - 1);
+    let scale_inverse = builder.inverse(scale);
+    builder.scalar_mul_extension(scale_inverse, sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::{BigUint, One, Zero};
+
+    use super::*;
+    use crate::field::extension::FieldExtension;
+    use crate::field::types::Sample;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{Hasher, PoseidonBN254GoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
+
+    const D: usize = 2;
+    type C = PoseidonBN254GoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FE = <C as GenericConfig<D>>::FE;
+
+    fn parse_hex(hex: &str) -> BigUint {
+        BigUint::parse_bytes(hex.trim_start_matches("0x").as_bytes(), 16).unwrap()
+    }
+
+    /// A Poseidon permutation, parsed from its description.
+    struct Permutation {
+        order: BigUint,
+        full_rounds: usize,
+        partial_rounds: usize,
+        sbox_degree: BigUint,
+        round_constants: Vec<BigUint>,
+        mds_matrix: Vec<Vec<BigUint>>,
+    }
+
+    impl Permutation {
+        fn new(description: &PermutationDescription) -> Self {
+            Self {
+                order: parse_hex(&description.order),
+                full_rounds: description.full_rounds,
+                partial_rounds: description.partial_rounds,
+                sbox_degree: BigUint::from(description.sbox_degree),
+                round_constants: description
+                    .round_constants
+                    .iter()
+                    .map(|c| parse_hex(c))
+                    .collect(),
+                mds_matrix: description
+                    .mds_matrix
+                    .iter()
+                    .map(|row| row.iter().map(|m| parse_hex(m)).collect())
+                    .collect(),
+            }
+        }
+
+        fn permute(&self, state: &mut [BigUint]) {
+            let width = state.len();
+            let half_full_rounds = self.full_rounds / 2;
+            for round in 0..self.full_rounds + self.partial_rounds {
+                for (x, c) in state.iter_mut().zip(&self.round_constants[round * width..]) {
+                    *x = (&*x + c) % &self.order;
+                }
+                let full_round =
+                    round < half_full_rounds || round >= half_full_rounds + self.partial_rounds;
+                for x in state.iter_mut().take(if full_round { width } else { 1 }) {
+                    *x = x.modpow(&self.sbox_degree, &self.order);
+                }
+                let old_state = state.to_vec();
+                for (x, row) in state.iter_mut().zip(&self.mds_matrix) {
+                    *x = old_state
+                        .iter()
+                        .zip(row)
+                        .map(|(y, m)| y * m)
+                        .sum::<BigUint>()
+                        % &self.order;
+                }
+            }
+        }
+    }
+
+    /// Runs an exported program, and returns the number of its assertions which don't hold. This
+    /// only relies on the exported data.
+    fn run(program: &VerificationProgram) -> usize {
+        let p = BigUint::from(program.field.order);
+        let q = parse_hex(&program.poseidon_bn254.order);
+        let poseidon = Permutation::new(&program.poseidon);
+        let poseidon_bn254 = Permutation::new(&program.poseidon_bn254);
+        let inputs = program
+            .inputs
+            .iter()
+            .map(|input| match &input.value {
+                InputValue::Field(x) => BigUint::from(*x),
+                InputValue::Bn254(x) => parse_hex(x),
+            })
+            .collect::<Vec<_>>();
+        let mut outputs: Vec<Vec<BigUint>> = Vec::new();
+        let mut num_failures = 0;
+
+        let radix = |radix: &Radix| match radix {
+            Radix::PowerOfTwo(bits) => BigUint::one() << bits,
+            Radix::FieldOrder => p.clone(),
+        };
+        for instruction in &program.instructions {
+            let value = |signal: &Signal| match *signal {
+                Signal::Value(x) => BigUint::from(x),
+                Signal::Input(i) => inputs[i].clone(),
+                Signal::Output(i, j) => outputs[i][j].clone(),
+            };
+            let output = match instruction {
+                Instruction::Arithmetic {
+                    const_0,
+                    const_1,
+                    multiplicand_0,
+                    multiplicand_1,
+                    addend,
+                } => vec![
+                    (BigUint::from(*const_0) * value(multiplicand_0) * value(multiplicand_1)
+                        + BigUint::from(*const_1) * value(addend))
+                        % &p,
+                ],
+                Instruction::Inverse(x) => vec![value(x).modpow(&(&p - 2u32), &p)],
+                Instruction::Bits { x, num_bits } => {
+                    let x = value(x);
+                    (0..*num_bits)
+                        .map(|i| BigUint::from(x.bit(i as u64) as u32))
+                        .collect()
+                }
+                Instruction::Poseidon(inputs) => {
+                    let mut state = inputs.iter().map(value).collect::<Vec<_>>();
+                    poseidon.permute(&mut state);
+                    state
+                }
+                Instruction::AssertEqual(x, y) | Instruction::AssertBn254Equal(x, y) => {
+                    if value(x) != value(y) {
+                        num_failures += 1;
+                    }
+                    vec![]
+                }
+                Instruction::Bn254Constant(x) => vec![parse_hex(x)],
+                Instruction::Bn254FromDigits { digits, radix: r } => {
+                    let r = radix(r);
+                    vec![
+                        digits
+                            .iter()
+                            .rev()
+                            .fold(BigUint::zero(), |acc, d| acc * &r + value(d))
+                            % &q,
+                    ]
+                }
+                Instruction::Bn254ToDigits {
+                    x,
+                    radix: r,
+                    num_digits,
+                } => {
+                    let r = radix(r);
+                    let mut x = value(x);
+                    (0..*num_digits)
+                        .map(|_| {
+                            let digit = &x % &r;
+                            x /= &r;
+                            digit
+                        })
+                        .collect()
+                }
+                Instruction::Bn254Add(x, y) => vec![(value(x) + value(y)) % &q],
+                Instruction::Bn254Select {
+                    bit,
+                    if_zero,
+                    if_one,
+                } => vec![if value(bit).is_one() {
+                    value(if_one)
+                } else {
+                    value(if_zero)
+                }],
+                Instruction::PoseidonBn254(inputs) => {
+                    let mut state = inputs.iter().map(value).collect::<Vec<_>>();
+                    poseidon_bn254.permute(&mut state);
+                    state
+                }
+            };
+            outputs.push(output);
+        }
+        num_failures
+    }
+
+    fn prove() -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x]);
+        let y = builder.mul_add(hash.elements[0], x, hash.elements[1]);
+        builder.register_public_input(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_export_verification() -> Result<()> {
+        let (data, proof) = prove()?;
+        let program = export_verification(&proof, &data.verifier_only, &data.common)?;
+        assert!(program.to_json()?.contains("PoseidonBn254"));
+        assert_eq!(run(&program), 0);
+
+        // The program rejects a proof with a wrong public input.
+        let mut bad_proof = proof.clone();
+        bad_proof.public_inputs[1] += F::ONE;
+        let program = export_verification(&bad_proof, &data.verifier_only, &data.common)?;
+        assert_ne!(run(&program), 0);
+
+        // And one with a wrong opening.
+        let mut bad_proof = proof;
+        bad_proof.proof.openings.wires[0] += FE::ONE;
+        let program = export_verification(&bad_proof, &data.verifier_only, &data.common)?;
+        assert_ne!(run(&program), 0);
+
+        Ok(())
+    }
+
+    fn limb(x: &FE, limb: usize) -> F {
+        FieldExtension::<D>::to_basefield_array(x)[limb]
+    }
+
+    fn from_limbs(signals: &[TracedSignal], values: &[FE], vars: &EvaluationVars<F, D>) -> FE {
+        let limbs = signals
+            .iter()
+            .map(|&signal| match signal {
+                TracedSignal::Wire { index, limb: i } => limb(&vars.local_wires[index], i),
+                TracedSignal::Constant { index, limb: i } => limb(&vars.local_constants[index], i),
+                TracedSignal::PublicInputsHash { index } => vars.public_inputs_hash.elements[index],
+                TracedSignal::Value(value) => F::from_canonical_u64(value),
+                TracedSignal::Op { index, limb: i } => limb(&values[index], i),
+            })
+            .collect::<Vec<_>>();
+        if limbs.len() == 1 {
+            <FE as FieldExtension<D>>::from_basefield(limbs[0])
+        } else {
+            <FE as FieldExtension<D>>::from_basefield_array(limbs.try_into().unwrap())
+        }
+    }
+
+    /// Evaluates the traced constraints of a gate.
+    fn eval_gate(gate: &TracedGate, vars: &EvaluationVars<F, D>) -> Vec<FE> {
+        let mut values = Vec::new();
+        for op in &gate.ops {
+            let [m0, m1, addend] = [&op.multiplicand_0, &op.multiplicand_1, &op.addend]
+                .map(|signals| from_limbs(signals, &values, vars));
+            values.push(
+                m0 * m1 * FE::from_canonical_u64(op.const_0)
+                    + addend * FE::from_canonical_u64(op.const_1),
+            );
+        }
+        gate.constraints
+            .iter()
+            .map(|c| from_limbs(c, &values, vars))
+            .collect()
+    }
+
+    #[test]
+    fn test_trace_gates() -> Result<()> {
+        let (data, proof) = prove()?;
+
+        // The traced constraints agree with those of the gates at the openings of the proof.
+        let openings = &proof.proof.openings;
+        let public_inputs_hash = PoseidonHash::hash_no_pad(&proof.public_inputs);
+        let vars = EvaluationVars {
+            local_constants: &openings.constants[data.common.selectors_info.num_selectors()..],
+            local_wires: &openings.wires,
+            public_inputs_hash: &public_inputs_hash,
+        };
+        for gate in &data.common.gates {
+            let traced = trace_gate(gate, &data.common.config)?;
+            assert_eq!(eval_gate(&traced, &vars), gate.0.eval_unfiltered(vars));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_lookup_tables() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let table = builder.add_lookup_table_from_fn(|x| x ^ 1, &[0, 1]);
+        let x = builder.add_virtual_target();
+        let y = builder.add_lookup_from_index(x, table);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            export_verification(&proof, &data.verifier_only, &data.common).unwrap_err(),
+            ExportError::LookupTables
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,713 @@
+//! The straight-line programs over the base field and BN254 in which verifications are exported,
+//! and a builder for them.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use num::BigUint;
+use plonky2_field::bn254_scalar::Bn254Scalar;
+use serde::Serialize;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::types::{Field, PrimeField};
+use crate::hash::hash_types::{BytesHash, RichField};
+use crate::hash::poseidon;
+use crate::hash::poseidon_bn254::{
+    CHALLENGER_ELEMENTS_PER_BN254, ELEMENTS_PER_BN254, RATE, SPONGE_RATE, WIDTH,
+};
+
+/// A value used by an instruction: a base field constant, an input or an output of a previous
+/// instruction. Inputs and outputs are base field or BN254 elements, depending on the input or on
+/// the instruction.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum Signal {
+    /// A base field element.
+    Value(u64),
+    /// The input with this index.
+    Input(usize),
+    /// An output of an instruction, as the index of the instruction and of the output.
+    Output(usize, usize),
+}
+
+/// The radix of a decomposition of BN254 elements into base field elements.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum Radix {
+    /// `2^bits`.
+    PowerOfTwo(usize),
+    /// The order of the base field.
+    FieldOrder,
+}
+
+/// An instruction of the program. Instructions only use the signals of inputs and of earlier
+/// instructions, and the program accepts its inputs if all of its assertions hold. Instructions over
+/// the base field compute modulo its order, so a BN254 circuit has to emulate them with reductions
+/// and range checks of its own.
+#[derive(Clone, Debug, Serialize)]
+pub enum Instruction {
+    /// Outputs `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`, in the base field.
+    Arithmetic {
+        const_0: u64,
+        const_1: u64,
+        multiplicand_0: Signal,
+        multiplicand_1: Signal,
+        addend: Signal,
+    },
+    /// Outputs the inverse of a nonzero base field element.
+    Inverse(Signal),
+    /// Outputs the `num_bits` lowest bits of the canonical representative of a base field element,
+    /// from the least significant one.
+    Bits { x: Signal, num_bits: usize },
+    /// Outputs the Poseidon permutation of `poseidon.width` base field elements.
+    Poseidon(Vec<Signal>),
+    /// Asserts that two base field elements are equal.
+    AssertEqual(Signal, Signal),
+    /// Outputs a BN254 element, written in hexadecimal.
+    Bn254Constant(String),
+    /// Outputs the BN254 element `sum digits[i] radix^i`, whose digits are base field elements.
+    Bn254FromDigits { digits: Vec<Signal>, radix: Radix },
+    /// Outputs the `num_digits` lowest base-`radix` digits of the canonical representative of a
+    /// BN254 element, as base field elements.
+    Bn254ToDigits {
+        x: Signal,
+        radix: Radix,
+        num_digits: usize,
+    },
+    /// Outputs the sum of two BN254 elements.
+    Bn254Add(Signal, Signal),
+    /// Outputs the BN254 element `if_one` if the base field element `bit`, which is 0 or 1, is 1,
+    /// and `if_zero` otherwise.
+    Bn254Select {
+        bit: Signal,
+        if_zero: Signal,
+        if_one: Signal,
+    },
+    /// Outputs the Poseidon permutation of `poseidon_bn254.width` BN254 elements.
+    PoseidonBn254(Vec<Signal>),
+    /// Asserts that two BN254 elements are equal.
+    AssertBn254Equal(Signal, Signal),
+}
+
+/// An input of the program, taken from the proof.
+#[derive(Clone, Debug, Serialize)]
+pub struct Input {
+    pub name: String,
+    pub value: InputValue,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum InputValue {
+    Field(u64),
+    /// A BN254 element, written in hexadecimal.
+    Bn254(String),
+}
+
+/// The limbs of an extension field element.
+pub(crate) type ExtensionSignal<const D: usize> = [Signal; D];
+
+pub(crate) struct ProgramBuilder<F: RichField + Extendable<D>, const D: usize> {
+    pub(crate) inputs: Vec<Input>,
+    pub(crate) instructions: Vec<Instruction>,
+    bn254_zero: Option<Signal>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> ProgramBuilder<F, D> {
+    pub(crate) fn new() -> Self {
+        Self {
+            inputs: Vec::new(),
+            instructions: Vec::new(),
+            bn254_zero: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn add_instruction(&mut self, instruction: Instruction, num_outputs: usize) -> Vec<Signal> {
+        let index = self.instructions.len();
+        self.instructions.push(instruction);
+        (0..num_outputs).map(|i| Signal::Output(index, i)).collect()
+    }
+
+    fn add_input(&mut self, name: String, value: InputValue) -> Signal {
+        self.inputs.push(Input { name, value });
+        Signal::Input(self.inputs.len() - 1)
+    }
+
+    pub(crate) fn add_field_input(&mut self, name: String, x: F) -> Signal {
+        self.add_input(name, InputValue::Field(x.to_canonical_u64()))
+    }
+
+    pub(crate) fn add_field_inputs(&mut self, name: &str, xs: &[F]) -> Vec<Signal> {
+        xs.iter()
+            .enumerate()
+            .map(|(i, &x)| self.add_field_input(format!("{name}[{i}]"), x))
+            .collect()
+    }
+
+    pub(crate) fn add_extension_inputs(
+        &mut self,
+        name: &str,
+        xs: &[F::Extension],
+    ) -> Vec<ExtensionSignal<D>> {
+        xs.iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let limbs = x.to_basefield_array();
+                core::array::from_fn(|limb| {
+                    self.add_field_input(format!("{name}[{i}][{limb}]"), limbs[limb])
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn add_hash_inputs(&mut self, name: &str, hashes: &[BytesHash<32>]) -> Vec<Signal> {
+        hashes
+            .iter()
+            .enumerate()
+            .map(|(i, hash)| {
+                self.add_input(format!("{name}[{i}]"), InputValue::Bn254(hash_to_hex(hash)))
+            })
+            .collect()
+    }
+
+    pub(crate) fn constant(&self, x: F) -> Signal {
+        Signal::Value(x.to_canonical_u64())
+    }
+
+    pub(crate) fn zero(&self) -> Signal {
+        self.constant(F::ZERO)
+    }
+
+    pub(crate) fn one(&self) -> Signal {
+        self.constant(F::ONE)
+    }
+
+    fn constant_value(signal: Signal) -> Option<F> {
+        match signal {
+            Signal::Value(x) => Some(F::from_canonical_u64(x)),
+            _ => None,
+        }
+    }
+
+    /// Computes `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`, folding the
+    /// operations on constants.
+    pub(crate) fn arithmetic(
+        &mut self,
+        const_0: F,
+        multiplicand_0: Signal,
+        multiplicand_1: Signal,
+        const_1: F,
+        addend: Signal,
+    ) -> Signal {
+        let [m0, m1, a] = [multiplicand_0, multiplicand_1, addend].map(Self::constant_value);
+        if let (Some(m0), Some(m1), Some(a)) = (m0, m1, a) {
+            return self.constant(const_0 * m0 * m1 + const_1 * a);
+        }
+        let no_product = const_0 == F::ZERO || m0 == Some(F::ZERO) || m1 == Some(F::ZERO);
+        if no_product && const_1 == F::ONE {
+            return addend;
+        }
+        let no_addend = const_1 == F::ZERO || a == Some(F::ZERO);
+        if no_product && no_addend {
+            return self.zero();
+        }
+        if no_addend && const_0 == F::ONE && m0 == Some(F::ONE) {
+            return multiplicand_1;
+        }
+        if no_addend && const_0 == F::ONE && m1 == Some(F::ONE) {
+            return multiplicand_0;
+        }
+        self.add_instruction(
+            Instruction::Arithmetic {
+                const_0: const_0.to_canonical_u64(),
+                const_1: const_1.to_canonical_u64(),
+                multiplicand_0,
+                multiplicand_1,
+                addend,
+            },
+            1,
+        )[0]
+    }
+
+    pub(crate) fn add(&mut self, x: Signal, y: Signal) -> Signal {
+        let one = self.one();
+        self.arithmetic(F::ONE, x, one, F::ONE, y)
+    }
+
+    pub(crate) fn sub(&mut self, x: Signal, y: Signal) -> Signal {
+        let one = self.one();
+        self.arithmetic(F::ONE, x, one, F::NEG_ONE, y)
+    }
+
+    pub(crate) fn mul(&mut self, x: Signal, y: Signal) -> Signal {
+        let zero = self.zero();
+        self.arithmetic(F::ONE, x, y, F::ZERO, zero)
+    }
+
+    pub(crate) fn mul_const(&mut self, c: F, x: Signal) -> Signal {
+        let (one, zero) = (self.one(), self.zero());
+        self.arithmetic(c, x, one, F::ZERO, zero)
+    }
+
+    pub(crate) fn inverse(&mut self, x: Signal) -> Signal {
+        match Self::constant_value(x) {
+            Some(x) => self.constant(x.inverse()),
+            None => self.add_instruction(Instruction::Inverse(x), 1)[0],
+        }
+    }
+
+    /// Returns `x` if `bit` is 0, and `c x` if it is 1.
+    pub(crate) fn mul_const_if(&mut self, bit: Signal, c: F, x: Signal) -> Signal {
+        self.arithmetic(c - F::ONE, bit, x, F::ONE, x)
+    }
+
+    pub(crate) fn square_n(&mut self, mut x: Signal, n: usize) -> Signal {
+        for _ in 0..n {
+            x = self.mul(x, x);
+        }
+        x
+    }
+
+    pub(crate) fn exp_u64(&mut self, x: Signal, power: u64) -> Signal {
+        let mut result = self.one();
+        for i in (0..64 - power.leading_zeros()).rev() {
+            result = self.mul(result, result);
+            if (power >> i) & 1 == 1 {
+                result = self.mul(result, x);
+            }
+        }
+        result
+    }
+
+    pub(crate) fn split_le(&mut self, x: Signal, num_bits: usize) -> Vec<Signal> {
+        self.add_instruction(Instruction::Bits { x, num_bits }, num_bits)
+    }
+
+    pub(crate) fn assert_equal(&mut self, x: Signal, y: Signal) {
+        self.add_instruction(Instruction::AssertEqual(x, y), 0);
+    }
+
+    pub(crate) fn poseidon(
+        &mut self,
+        inputs: [Signal; poseidon::SPONGE_WIDTH],
+    ) -> [Signal; poseidon::SPONGE_WIDTH] {
+        self.add_instruction(
+            Instruction::Poseidon(inputs.to_vec()),
+            poseidon::SPONGE_WIDTH,
+        )
+        .try_into()
+        .unwrap()
+    }
+
+    /// The Poseidon hash of base field elements, as computed by `PoseidonHash::hash_no_pad`.
+    pub(crate) fn poseidon_hash_no_pad(&mut self, inputs: &[Signal]) -> [Signal; 4] {
+        let mut state = [self.zero(); poseidon::SPONGE_WIDTH];
+        for chunk in inputs.chunks(poseidon::SPONGE_RATE) {
+            state[..chunk.len()].copy_from_slice(chunk);
+            state = self.poseidon(state);
+        }
+        state[..4].try_into().unwrap()
+    }
+
+    pub(crate) fn constant_extension(&self, x: F::Extension) -> ExtensionSignal<D> {
+        x.to_basefield_array().map(|limb| self.constant(limb))
+    }
+
+    pub(crate) fn convert_to_extension(&self, x: Signal) -> ExtensionSignal<D> {
+        core::array::from_fn(|i| if i == 0 { x } else { self.zero() })
+    }
+
+    pub(crate) fn add_extension(
+        &mut self,
+        x: ExtensionSignal<D>,
+        y: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        core::array::from_fn(|i| self.add(x[i], y[i]))
+    }
+
+    pub(crate) fn sub_extension(
+        &mut self,
+        x: ExtensionSignal<D>,
+        y: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        core::array::from_fn(|i| self.sub(x[i], y[i]))
+    }
+
+    /// Computes `c x y + z` in the extension field.
+    pub(crate) fn mul_add_extension_with_const(
+        &mut self,
+        c: F,
+        x: ExtensionSignal<D>,
+        y: ExtensionSignal<D>,
+        z: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        let mut result = z;
+        for i in 0..D {
+            for j in 0..D {
+                let (k, coeff) = if i + j < D {
+                    (i + j, c)
+                } else {
+                    (i + j - D, c * F::W)
+                };
+                result[k] = self.arithmetic(coeff, x[i], y[j], F::ONE, result[k]);
+            }
+        }
+        result
+    }
+
+    pub(crate) fn mul_extension(
+        &mut self,
+        x: ExtensionSignal<D>,
+        y: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        let zero = self.constant_extension(F::Extension::ZERO);
+        self.mul_add_extension_with_const(F::ONE, x, y, zero)
+    }
+
+    /// Computes `c x`, for a constant `c` of the base field.
+    pub(crate) fn mul_const_extension(
+        &mut self,
+        c: F,
+        x: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        core::array::from_fn(|i| self.mul_const(c, x[i]))
+    }
+
+    /// Computes `s x + y`, for a signal `s` of the base field.
+    pub(crate) fn scalar_mul_add_extension(
+        &mut self,
+        s: Signal,
+        x: ExtensionSignal<D>,
+        y: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        core::array::from_fn(|i| self.arithmetic(F::ONE, s, x[i], F::ONE, y[i]))
+    }
+
+    pub(crate) fn scalar_mul_extension(
+        &mut self,
+        s: Signal,
+        x: ExtensionSignal<D>,
+    ) -> ExtensionSignal<D> {
+        let zero = self.constant_extension(F::Extension::ZERO);
+        self.scalar_mul_add_extension(s, x, zero)
+    }
+
+    /// The inverse of a nonzero extension field element `x`, as `x^(r - 1) / N(x)` where
+    /// `x^(r - 1)` is the product of the nontrivial Frobenius conjugates of `x` and `N(x)` is its
+    /// norm, which lies in the base field.
+    pub(crate) fn inverse_extension(&mut self, x: ExtensionSignal<D>) -> ExtensionSignal<D> {
+        let mut conjugates = self.constant_extension(F::Extension::ONE);
+        for count in 1..D {
+            let z = F::DTH_ROOT.exp_u64(count as u64);
+            let conjugate = core::array::from_fn(|i| self.mul_const(z.exp_u64(i as u64), x[i]));
+            conjugates = self.mul_extension(conjugates, conjugate);
+        }
+        let norm = self.mul_extension(x, conjugates)[0];
+        let norm_inverse = self.inverse(norm);
+        self.scalar_mul_extension(norm_inverse, conjugates)
+    }
+
+    pub(crate) fn square_n_extension(
+        &mut self,
+        mut x: ExtensionSignal<D>,
+        n: usize,
+    ) -> ExtensionSignal<D> {
+        for _ in 0..n {
+            x = self.mul_extension(x, x);
+        }
+        x
+    }
+
+    pub(crate) fn assert_equal_extension(&mut self, x: ExtensionSignal<D>, y: ExtensionSignal<D>) {
+        for (x, y) in x.into_iter().zip(y) {
+            self.assert_equal(x, y);
+        }
+    }
+
+    /// Returns `values[index]`, for an index given by its bits, from the least significant one.
+    pub(crate) fn random_access_extension(
+        &mut self,
+        index_bits: &[Signal],
+        values: &[ExtensionSignal<D>],
+    ) -> ExtensionSignal<D> {
+        debug_assert_eq!(values.len(), 1 << index_bits.len());
+        let mut values = values.to_vec();
+        for &bit in index_bits {
+            values = values
+                .chunks_exact(2)
+                .map(|pair| {
+                    let diff = self.sub_extension(pair[1], pair[0]);
+                    self.scalar_mul_add_extension(bit, diff, pair[0])
+                })
+                .collect();
+        }
+        values[0]
+    }
+
+    pub(crate) fn bn254_constant(&mut self, x: Bn254Scalar) -> Signal {
+        let hex = bn254_to_hex(&x.to_canonical_biguint());
+        self.add_instruction(Instruction::Bn254Constant(hex), 1)[0]
+    }
+
+    pub(crate) fn bn254_hash_constant(&mut self, hash: &BytesHash<32>) -> Signal {
+        self.add_instruction(Instruction::Bn254Constant(hash_to_hex(hash)), 1)[0]
+    }
+
+    pub(crate) fn bn254_zero(&mut self) -> Signal {
+        match self.bn254_zero {
+            Some(zero) => zero,
+            None => {
+                let zero = self.bn254_constant(Bn254Scalar::ZERO);
+                self.bn254_zero = Some(zero);
+                zero
+            }
+        }
+    }
+
+    pub(crate) fn bn254_from_digits(&mut self, digits: &[Signal], radix: Radix) -> Signal {
+        let digits = digits.to_vec();
+        self.add_instruction(Instruction::Bn254FromDigits { digits, radix }, 1)[0]
+    }
+
+    pub(crate) fn bn254_to_digits(
+        &mut self,
+        x: Signal,
+        radix: Radix,
+        num_digits: usize,
+    ) -> Vec<Signal> {
+        self.add_instruction(
+            Instruction::Bn254ToDigits {
+                x,
+                radix,
+                num_digits,
+            },
+            num_digits,
+        )
+    }
+
+    pub(crate) fn bn254_add(&mut self, x: Signal, y: Signal) -> Signal {
+        self.add_instruction(Instruction::Bn254Add(x, y), 1)[0]
+    }
+
+    pub(crate) fn bn254_select(&mut self, bit: Signal, if_zero: Signal, if_one: Signal) -> Signal {
+        self.add_instruction(
+            Instruction::Bn254Select {
+                bit,
+                if_zero,
+                if_one,
+            },
+            1,
+        )[0]
+    }
+
+    pub(crate) fn assert_bn254_equal(&mut self, x: Signal, y: Signal) {
+        self.add_instruction(Instruction::AssertBn254Equal(x, y), 0);
+    }
+
+    pub(crate) fn poseidon_bn254(&mut self, inputs: [Signal; WIDTH]) -> [Signal; WIDTH] {
+        self.add_instruction(Instruction::PoseidonBn254(inputs.to_vec()), WIDTH)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Packs up to `ELEMENTS_PER_BN254` base field elements into a BN254 element, as in `pack`.
+    fn bn254_pack(&mut self, elements: &[Signal]) -> Signal {
+        self.bn254_from_digits(elements, Radix::PowerOfTwo(64))
+    }
+
+    /// The digits of a digest, as observed by the challenger.
+    pub(crate) fn bn254_hash_to_vec(&mut self, hash: Signal) -> Vec<Signal> {
+        self.bn254_to_digits(hash, Radix::PowerOfTwo(56), 5)
+    }
+
+    /// As `PoseidonBN254Hash::hash_no_pad`.
+    pub(crate) fn bn254_hash_no_pad(&mut self, inputs: &[Signal]) -> Signal {
+        let mut state = [self.bn254_zero(); WIDTH];
+        for chunk in inputs.chunks(RATE * ELEMENTS_PER_BN254) {
+            for (i, elements) in chunk.chunks(ELEMENTS_PER_BN254).enumerate() {
+                let packed = self.bn254_pack(elements);
+                state[i + 1] = self.bn254_add(state[i + 1], packed);
+            }
+            state = self.poseidon_bn254(state);
+        }
+        state[0]
+    }
+
+    /// As `PoseidonBN254Hash::hash_or_noop`.
+    pub(crate) fn bn254_hash_or_noop(&mut self, inputs: &[Signal]) -> Signal {
+        if inputs.len() <= ELEMENTS_PER_BN254 {
+            self.bn254_pack(inputs)
+        } else {
+            self.bn254_hash_no_pad(inputs)
+        }
+    }
+
+    /// As `PoseidonBN254Hash::two_to_one`.
+    pub(crate) fn bn254_two_to_one(&mut self, left: Signal, right: Signal) -> Signal {
+        let zero = self.bn254_zero();
+        self.poseidon_bn254([zero, left, right, zero])[0]
+    }
+
+    /// Verifies a Merkle proof of the leaf whose index is given by its bits, from the least
+    /// significant one, as `verify_merkle_proof_to_cap`.
+    pub(crate) fn verify_merkle_proof_to_cap(
+        &mut self,
+        leaf: &[Signal],
+        leaf_index_bits: &[Signal],
+        cap: &[Signal],
+        siblings: &[Signal],
+    ) {
+        let mut current = self.bn254_hash_or_noop(leaf);
+        for (&bit, &sibling) in leaf_index_bits.iter().zip(siblings) {
+            let left = self.bn254_select(bit, current, sibling);
+            let right = self.bn254_select(bit, sibling, current);
+            current = self.bn254_two_to_one(left, right);
+        }
+
+        let cap_index_bits = &leaf_index_bits[siblings.len()..];
+        debug_assert_eq!(cap.len(), 1 << cap_index_bits.len());
+        let mut cap = cap.to_vec();
+        for &bit in cap_index_bits {
+            cap = cap
+                .chunks_exact(2)
+                .map(|pair| self.bn254_select(bit, pair[0], pair[1]))
+                .collect();
+        }
+        self.assert_bn254_equal(current, cap[0]);
+    }
+}
+
+/// The challenger of `Challenger<F, PoseidonBN254Hash>`, in a program.
+///
+/// The rate of the sponge is kept as base field elements, and its capacity as a BN254 element,
+/// which is equal to the value of its base-`|F|` digits.
+pub(crate) struct ProgramChallenger {
+    rate: [Signal; SPONGE_RATE],
+    capacity: Signal,
+    input_buffer: Vec<Signal>,
+    output_buffer: Vec<Signal>,
+}
+
+impl ProgramChallenger {
+    pub(crate) fn new<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut ProgramBuilder<F, D>,
+    ) -> Self {
+        Self {
+            rate: [builder.zero(); SPONGE_RATE],
+            capacity: builder.bn254_zero(),
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+        }
+    }
+
+    pub(crate) fn observe_element<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+        element: Signal,
+    ) {
+        self.output_buffer.clear();
+        self.input_buffer.push(element);
+        if self.input_buffer.len() == SPONGE_RATE {
+            self.duplexing(builder);
+        }
+    }
+
+    pub(crate) fn observe_elements<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+        elements: &[Signal],
+    ) {
+        for &element in elements {
+            self.observe_element(builder, element);
+        }
+    }
+
+    pub(crate) fn observe_extension_elements<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+        elements: &[ExtensionSignal<D>],
+    ) {
+        for element in elements {
+            self.observe_elements(builder, element);
+        }
+    }
+
+    pub(crate) fn observe_cap<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+        cap: &[Signal],
+    ) {
+        for &hash in cap {
+            let elements = builder.bn254_hash_to_vec(hash);
+            self.observe_elements(builder, &elements);
+        }
+    }
+
+    pub(crate) fn get_challenge<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+    ) -> Signal {
+        if !self.input_buffer.is_empty() || self.output_buffer.is_empty() {
+            self.duplexing(builder);
+        }
+        self.output_buffer
+            .pop()
+            .expect("Output buffer should be non-empty")
+    }
+
+    pub(crate) fn get_n_challenges<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+        n: usize,
+    ) -> Vec<Signal> {
+        (0..n).map(|_| self.get_challenge(builder)).collect()
+    }
+
+    pub(crate) fn get_extension_challenge<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+    ) -> ExtensionSignal<D> {
+        core::array::from_fn(|_| self.get_challenge(builder))
+    }
+
+    fn duplexing<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut ProgramBuilder<F, D>,
+    ) {
+        for (x, input) in self.rate.iter_mut().zip(self.input_buffer.drain(..)) {
+            *x = input;
+        }
+
+        let mut state = [self.capacity; WIDTH];
+        for (x, digits) in state[1..]
+            .iter_mut()
+            .zip(self.rate.chunks_exact(CHALLENGER_ELEMENTS_PER_BN254))
+        {
+            *x = builder.bn254_from_digits(digits, Radix::FieldOrder);
+        }
+        let state = builder.poseidon_bn254(state);
+
+        self.capacity = state[0];
+        for (digits, &x) in self
+            .rate
+            .chunks_exact_mut(CHALLENGER_ELEMENTS_PER_BN254)
+            .zip(&state[1..])
+        {
+            let x_digits =
+                builder.bn254_to_digits(x, Radix::FieldOrder, CHALLENGER_ELEMENTS_PER_BN254);
+            digits.copy_from_slice(&x_digits);
+        }
+
+        self.output_buffer.clear();
+        self.output_buffer.extend_from_slice(&self.rate);
+    }
+}
+
+pub(crate) fn bn254_to_hex(x: &BigUint) -> String {
+    format!("0x{x:064x}")
+}
+
+fn hash_to_hex(hash: &BytesHash<32>) -> String {
+    bn254_to_hex(&BigUint::from_bytes_le(&hash.0))
+}
@@ -32,17 +32,15 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
     commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
     final_poly: &PolynomialCoeffs<F::Extension>,
     pow_witness: F,
-    circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+    mut challenger: Challenger<F, C::Hasher>,
     common_data: &CommonCircuitData<F, D>,
 ) -> anyhow::Result<ProofChallenges<F, D>> {
     let config = &common_data.config;
     let num_challenges = config.num_challenges;
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
     let has_lookup = common_data.num_lookup_polys != 0;
 
-    // Observe the instance.
-    challenger.observe_hash::<C::Hasher>(*circuit_digest);
+    // Observe the rest of the instance; `challenger` has already observed the circuit digest.
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
     challenger.observe_cap::<C::Hasher>(wires_cap);
@@ -89,6 +87,20 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
     })
 }
 
+/// Returns a challenger which has observed the circuit digest, the first element of every proof's
+/// transcript. It doesn't depend on the proof, so it can be reused across proofs of one circuit.
+pub(crate) fn circuit_challenger<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+) -> Challenger<F, C::Hasher> {
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_hash::<C::Hasher>(*circuit_digest);
+    challenger
+}
+
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ProofWithPublicInputs<F, C, D>
 {
@@ -109,6 +121,20 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
         common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_from(
+            circuit_challenger::<F, C, D>(circuit_digest),
+            public_inputs_hash,
+            common_data,
+        )
+    }
+
+    /// Like `get_challenges`, but starting from a `challenger` returned by `circuit_challenger`.
+    pub(crate) fn get_challenges_from(
+        &self,
+        challenger: Challenger<F, C::Hasher>,
+        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let Proof {
             wires_cap,
@@ -133,7 +159,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
-            circuit_digest,
+            challenger,
             common_data,
         )
     }
@@ -172,7 +198,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
-            circuit_digest,
+            circuit_challenger::<F, C, D>(circuit_digest),
             common_data,
         )
     }
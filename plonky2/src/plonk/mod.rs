@@ -1,13 +1,18 @@
 pub mod circuit_builder;
 pub mod circuit_data;
+pub mod circuit_graph;
+pub mod circuit_stats;
 pub mod config;
 pub(crate) mod copy_constraint;
+pub mod export;
 mod get_challenges;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
 pub mod prover;
+pub mod unconstrained_targets;
 mod validate_shape;
 pub(crate) mod vanishing_poly;
 pub mod vars;
 pub mod verifier;
+pub mod witness_check;
@@ -216,7 +216,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             self.proof
                 .decompress(&challenges, fri_inferred_elements, &common_data.fri_params);
         verify_with_challenges::<F, C, D>(
-            decompressed_proof,
+            &decompressed_proof,
             public_inputs_hash,
             challenges,
             verifier_data,
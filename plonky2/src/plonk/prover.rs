@@ -22,7 +22,7 @@ use crate::iop::generator::generate_partial_witness;
 use crate::iop::target::Target;
 use crate::iop::witness::{MatrixWitness, PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::NUM_COINS_LOOKUP;
-use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
@@ -30,6 +30,7 @@ use crate::plonk::vanishing_poly::{eval_vanishing_poly_base_batch, get_lut_poly}
 use crate::plonk::vars::EvaluationVarsBaseBatch;
 use crate::timed;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
 use crate::util::timing::TimingTree;
 use crate::util::{ceil_div_usize, log2_ceil, transpose};
 
@@ -121,7 +122,8 @@ where
         timing,
         &format!("run {} generators", prover_data.generators.len()),
         generate_partial_witness(inputs, prover_data, common_data)
-    );
+    )
+    .map_err(anyhow::Error::msg)?;
 
     prove_with_partition_witness(prover_data, common_data, partition_witness, timing)
 }
@@ -133,18 +135,180 @@ pub fn prove_with_partition_witness<
 >(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
-    mut partition_witness: PartitionWitness<F>,
+    partition_witness: PartitionWitness<F>,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
 where
     C::Hasher: Hasher<F>,
     C::InnerHasher: Hasher<F>,
 {
-    let has_lookup = !common_data.luts.is_empty();
+    let (checkpoint, witness) =
+        commit_to_wires(prover_data, common_data, partition_witness, timing);
+    finish_proof(
+        prover_data,
+        common_data,
+        checkpoint,
+        Some(witness),
+        |_| Ok(()),
+        timing,
+    )
+}
+
+/// Like `prove`, but calls `on_checkpoint` at the end of each phase of the prover, e.g. to store
+/// the checkpoint with `ProverCheckpoint::write_to`, so that the proof can be finished with
+/// `resume_proof` if the prover is interrupted. An error returned by `on_checkpoint` aborts the
+/// proof.
+pub fn prove_with_checkpoints<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    mut on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        generate_partial_witness(inputs, prover_data, common_data)
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    let (checkpoint, witness) =
+        commit_to_wires(prover_data, common_data, partition_witness, timing);
+    on_checkpoint(&checkpoint)?;
+    finish_proof(
+        prover_data,
+        common_data,
+        checkpoint,
+        Some(witness),
+        on_checkpoint,
+        timing,
+    )
+}
+
+/// Finishes the proof from `checkpoint`, calling `on_checkpoint` at the end of each remaining
+/// phase. The proof is the same as if the prover had not been interrupted.
+pub fn resume_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: ProverCheckpoint<F, C, D>,
+    on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    ensure!(
+        checkpoint.circuit_digest == prover_data.circuit_digest
+            && checkpoint.config == common_data.config,
+        "The checkpoint was not taken while proving this circuit."
+    );
+    ensure!(
+        checkpoint.wires_commitment.degree_log == common_data.degree_bits()
+            && checkpoint.wires_commitment.polynomials.len() == common_data.config.num_wires,
+        "The checkpoint does not match the shape of the circuit."
+    );
+    finish_proof(
+        prover_data,
+        common_data,
+        checkpoint,
+        None,
+        on_checkpoint,
+        timing,
+    )
+}
+
+/// A phase of the prover, after which it takes a `ProverCheckpoint`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ProverPhase {
+    WiresCommitted,
+    ZsCommitted,
+    QuotientCommitted,
+    /// The polynomials have been opened, and only the FRI proof of the openings is left.
+    Opened,
+}
+
+/// The state of the prover at the end of a phase, from which `resume_proof` can finish the proof.
+pub struct ProverCheckpoint<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// The digest of the circuit being proven, which `resume_proof` checks along with its config.
+    pub circuit_digest: <C::Hasher as Hasher<F>>::Hash,
+    pub config: CircuitConfig,
+    pub public_inputs: Vec<F>,
+    /// The transcript at the end of the phase.
+    pub challenger: Challenger<F, C::Hasher>,
+    pub wires_commitment: PolynomialBatch<F, C, D>,
+    /// The challenges drawn after committing to the wires, which are used again to compute the
+    /// quotient polynomials. They are empty until the Zs are committed.
+    pub betas: Vec<F>,
+    pub gammas: Vec<F>,
+    pub deltas: Vec<F>,
+    pub partial_products_zs_and_lookup_commitment: Option<PolynomialBatch<F, C, D>>,
+    pub quotient_polys_commitment: Option<PolynomialBatch<F, C, D>>,
+    /// The opening point `zeta`, and the openings at `zeta` and `g * zeta`.
+    pub openings: Option<(F::Extension, OpeningSet<F, D>)>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProverCheckpoint<F, C, D>
+{
+    pub fn phase(&self) -> ProverPhase {
+        if self.openings.is_some() {
+            ProverPhase::Opened
+        } else if self.quotient_polys_commitment.is_some() {
+            ProverPhase::QuotientCommitted
+        } else if self.partial_products_zs_and_lookup_commitment.is_some() {
+            ProverPhase::ZsCommitted
+        } else {
+            ProverPhase::WiresCommitted
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write_to(&mut buffer)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Serializes the checkpoint into `writer`, without buffering the commitments in memory, e.g.
+    /// to a file through `IoWriter`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        writer.write_prover_checkpoint(self)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>, common_data: &CommonCircuitData<F, D>) -> Result<Self> {
+        let mut buffer = Buffer::new(&bytes);
+        let checkpoint = buffer
+            .read_prover_checkpoint(common_data)
+            .map_err(anyhow::Error::msg)?;
+        Ok(checkpoint)
+    }
+}
+
+/// Commits to the wires, and returns the checkpoint of the first phase, with the witness.
+fn commit_to_wires<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut partition_witness: PartitionWitness<F>,
+    timing: &mut TimingTree,
+) -> (ProverCheckpoint<F, C, D>, MatrixWitness<F>)
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
     let config = &common_data.config;
-    let num_challenges = config.num_challenges;
-    let quotient_degree = common_data.quotient_degree();
-    let degree = common_data.degree();
 
     set_lookup_wires(prover_data, common_data, &mut partition_witness);
 
@@ -188,6 +352,86 @@ where
 
     challenger.observe_cap::<C::Hasher>(&wires_commitment.merkle_tree.cap);
 
+    let checkpoint = ProverCheckpoint {
+        circuit_digest: prover_data.circuit_digest,
+        config: config.clone(),
+        public_inputs,
+        challenger,
+        wires_commitment,
+        betas: Vec::new(),
+        gammas: Vec::new(),
+        deltas: Vec::new(),
+        partial_products_zs_and_lookup_commitment: None,
+        quotient_polys_commitment: None,
+        openings: None,
+    };
+    (checkpoint, witness)
+}
+
+/// Runs the phases following that of `checkpoint`. `witness` is recovered from the wire
+/// polynomials if not given.
+fn finish_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut checkpoint: ProverCheckpoint<F, C, D>,
+    mut witness: Option<MatrixWitness<F>>,
+    mut on_checkpoint: impl FnMut(&ProverCheckpoint<F, C, D>) -> Result<()>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    loop {
+        match checkpoint.phase() {
+            ProverPhase::WiresCommitted => {
+                let witness = witness.take().unwrap_or_else(|| {
+                    timed!(
+                        timing,
+                        "recover witness from wire polynomials",
+                        MatrixWitness {
+                            wire_values: checkpoint
+                                .wires_commitment
+                                .polynomials
+                                .par_iter()
+                                .map(|p| p.clone().fft().values)
+                                .collect(),
+                        }
+                    )
+                });
+                commit_to_zs(prover_data, common_data, &mut checkpoint, &witness, timing);
+            }
+            ProverPhase::ZsCommitted => {
+                commit_to_quotient(prover_data, common_data, &mut checkpoint, timing)
+            }
+            ProverPhase::QuotientCommitted => {
+                open(prover_data, common_data, &mut checkpoint, timing)?
+            }
+            ProverPhase::Opened => {
+                return Ok(prove_openings(prover_data, common_data, checkpoint, timing))
+            }
+        }
+        on_checkpoint(&checkpoint)?;
+    }
+}
+
+/// Draws the permutation and lookup challenges, and commits to the partial products, the Zs and
+/// the lookup polynomials.
+fn commit_to_zs<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: &mut ProverCheckpoint<F, C, D>,
+    witness: &MatrixWitness<F>,
+    timing: &mut TimingTree,
+) where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let has_lookup = !common_data.luts.is_empty();
+    let config = &common_data.config;
+    let num_challenges = config.num_challenges;
+    let challenger = &mut checkpoint.challenger;
+
     // We need 4 values per challenge: 2 for the combos, 1 for (X-combo) in the accumulators and 1 to prove that the lookup table was computed correctly.
     // We can reuse betas and gammas for two of them.
     let num_lookup_challenges = NUM_COINS_LOOKUP * num_challenges;
@@ -214,7 +458,7 @@ where
     let mut partial_products_and_zs = timed!(
         timing,
         "compute partial products",
-        all_wires_permutation_partial_products(&witness, &betas, &gammas, prover_data, common_data)
+        all_wires_permutation_partial_products(witness, &betas, &gammas, prover_data, common_data)
     );
 
     // Z is expected at the front of our batch; see `zs_range` and `partial_products_range`.
@@ -226,7 +470,7 @@ where
 
     // All lookup polys: RE and partial SLDCs.
     let lookup_polys =
-        compute_all_lookup_polys(&witness, &deltas, prover_data, common_data, has_lookup);
+        compute_all_lookup_polys(witness, &deltas, prover_data, common_data, has_lookup);
 
     let zs_partial_products_lookups = if has_lookup {
         [zs_partial_products, lookup_polys].concat()
@@ -249,7 +493,35 @@ where
 
     challenger.observe_cap::<C::Hasher>(&partial_products_zs_and_lookup_commitment.merkle_tree.cap);
 
-    let alphas = challenger.get_n_challenges(num_challenges);
+    checkpoint.betas = betas;
+    checkpoint.gammas = gammas;
+    checkpoint.deltas = deltas;
+    checkpoint.partial_products_zs_and_lookup_commitment =
+        Some(partial_products_zs_and_lookup_commitment);
+}
+
+/// Draws the constraint combination challenges, and commits to the quotient polynomials.
+fn commit_to_quotient<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: &mut ProverCheckpoint<F, C, D>,
+    timing: &mut TimingTree,
+) where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let config = &common_data.config;
+    let quotient_degree = common_data.quotient_degree();
+    let degree = common_data.degree();
+    let public_inputs_hash = C::InnerHasher::hash_no_pad(&checkpoint.public_inputs);
+    let partial_products_zs_and_lookup_commitment = checkpoint
+        .partial_products_zs_and_lookup_commitment
+        .as_ref()
+        .expect("The Zs are committed");
+
+    let alphas = checkpoint
+        .challenger
+        .get_n_challenges(config.num_challenges);
 
     let quotient_polys = timed!(
         timing,
@@ -258,11 +530,11 @@ where
             common_data,
             prover_data,
             &public_inputs_hash,
-            &wires_commitment,
-            &partial_products_zs_and_lookup_commitment,
-            &betas,
-            &gammas,
-            &deltas,
+            &checkpoint.wires_commitment,
+            partial_products_zs_and_lookup_commitment,
+            &checkpoint.betas,
+            &checkpoint.gammas,
+            &checkpoint.deltas,
             &alphas,
         )
     );
@@ -295,9 +567,24 @@ where
         )
     );
 
-    challenger.observe_cap::<C::Hasher>(&quotient_polys_commitment.merkle_tree.cap);
+    checkpoint
+        .challenger
+        .observe_cap::<C::Hasher>(&quotient_polys_commitment.merkle_tree.cap);
+    checkpoint.quotient_polys_commitment = Some(quotient_polys_commitment);
+}
 
-    let zeta = challenger.get_extension_challenge::<D>();
+/// Draws the opening point `zeta`, and opens all polynomials at `zeta` and `g * zeta`.
+fn open<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: &mut ProverCheckpoint<F, C, D>,
+    timing: &mut TimingTree,
+) -> Result<()>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let zeta = checkpoint.challenger.get_extension_challenge::<D>();
     // To avoid leaking witness data, we want to ensure that our opening locations, `zeta` and
     // `g * zeta`, are not in our subgroup `H`. It suffices to check `zeta` only, since
     // `(g * zeta)^n = zeta^n`, where `n` is the order of `g`.
@@ -314,13 +601,49 @@ where
             zeta,
             g,
             &prover_data.constants_sigmas_commitment,
-            &wires_commitment,
-            &partial_products_zs_and_lookup_commitment,
-            &quotient_polys_commitment,
+            &checkpoint.wires_commitment,
+            checkpoint
+                .partial_products_zs_and_lookup_commitment
+                .as_ref()
+                .expect("The Zs are committed"),
+            checkpoint
+                .quotient_polys_commitment
+                .as_ref()
+                .expect("The quotient is committed"),
             common_data
         )
     );
-    challenger.observe_openings(&openings.to_fri_openings());
+    checkpoint
+        .challenger
+        .observe_openings(&openings.to_fri_openings());
+    checkpoint.openings = Some((zeta, openings));
+    Ok(())
+}
+
+/// Proves the openings with FRI, and returns the final proof.
+fn prove_openings<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: ProverCheckpoint<F, C, D>,
+    timing: &mut TimingTree,
+) -> ProofWithPublicInputs<F, C, D>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let ProverCheckpoint {
+        public_inputs,
+        mut challenger,
+        wires_commitment,
+        partial_products_zs_and_lookup_commitment,
+        quotient_polys_commitment,
+        openings,
+        ..
+    } = checkpoint;
+    let partial_products_zs_and_lookup_commitment =
+        partial_products_zs_and_lookup_commitment.expect("The Zs are committed");
+    let quotient_polys_commitment = quotient_polys_commitment.expect("The quotient is committed");
+    let (zeta, openings) = openings.expect("The polynomials are opened");
     let instance = common_data.get_fri_instance(zeta);
 
     let opening_proof = timed!(
@@ -347,10 +670,10 @@ where
         openings,
         opening_proof,
     };
-    Ok(ProofWithPublicInputs::<F, C, D> {
+    ProofWithPublicInputs::<F, C, D> {
         proof,
         public_inputs,
-    })
+    }
 }
 
 /// Compute the partial products used in the `Z` polynomials.
@@ -805,3 +1128,123 @@ fn compute_quotient_polys<
         .map(|values| values.coset_ifft(F::coset_shift()))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use anyhow::{anyhow, Result};
+
+    use super::*;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::IoWriter;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_resume_from_checkpoints() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let product = builder.mul(x, y);
+        let table = Arc::new((0..16).map(|i| (i, i * i)).collect::<Vec<_>>());
+        let table_index = builder.add_lookup_table_from_pairs(table);
+        let square = builder.add_lookup_from_index(x, table_index);
+        builder.register_public_input(product);
+        builder.register_public_input(square);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(4));
+
+        let mut checkpoints = Vec::new();
+        let proof = data.prove_with_checkpoints(pw.clone(), |checkpoint| {
+            checkpoints.push((checkpoint.phase(), checkpoint.to_bytes()));
+            Ok(())
+        })?;
+        let phases = checkpoints
+            .iter()
+            .map(|(phase, _)| *phase)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases,
+            [
+                ProverPhase::WiresCommitted,
+                ProverPhase::ZsCommitted,
+                ProverPhase::QuotientCommitted,
+                ProverPhase::Opened,
+            ]
+        );
+
+        for (phase, bytes) in checkpoints {
+            let checkpoint = ProverCheckpoint::from_bytes(bytes, &data.common)?;
+            assert_eq!(checkpoint.phase(), phase);
+            let mut remaining_phases = Vec::new();
+            let resumed_proof = data.resume_proof(checkpoint, |checkpoint| {
+                remaining_phases.push(checkpoint.phase());
+                Ok(())
+            })?;
+            assert!(remaining_phases.iter().all(|&p| p > phase));
+            assert_eq!(resumed_proof, proof);
+        }
+
+        // An error from `on_checkpoint` aborts the proof.
+        let aborted = data.prove_with_checkpoints(pw, |checkpoint| match checkpoint.phase() {
+            ProverPhase::ZsCommitted => Err(anyhow!("preempted")),
+            _ => Ok(()),
+        });
+        assert!(aborted.is_err());
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_resume_rejects_other_circuit() -> Result<()> {
+        let build = |add: bool| {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_target();
+            let y = builder.add_virtual_target();
+            let z = if add {
+                builder.add(x, y)
+            } else {
+                builder.mul(x, y)
+            };
+            builder.register_public_input(z);
+            (builder.build::<C>(), x, y)
+        };
+        let (data, x, y) = build(false);
+        let (other_data, _, _) = build(true);
+        assert_eq!(data.common.degree_bits(), other_data.common.degree_bits());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(4));
+        let mut bytes = Vec::new();
+        data.prove_with_checkpoints(pw, |checkpoint| {
+            if checkpoint.phase() == ProverPhase::WiresCommitted {
+                checkpoint
+                    .write_to(&mut IoWriter(&mut bytes))
+                    .map_err(anyhow::Error::msg)?;
+            }
+            Ok(())
+        })?;
+
+        // The checkpoint resumes the proof of its own circuit, but not of another one of the same
+        // shape.
+        let checkpoint = ProverCheckpoint::from_bytes(bytes.clone(), &data.common)?;
+        data.verify(data.resume_proof(checkpoint, |_| Ok(()))?)?;
+        let checkpoint = ProverCheckpoint::from_bytes(bytes, &other_data.common)?;
+        assert!(other_data.resume_proof(checkpoint, |_| Ok(())).is_err());
+
+        Ok(())
+    }
+}
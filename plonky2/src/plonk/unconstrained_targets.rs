@@ -0,0 +1,166 @@
+//! A lint finding virtual targets whose values are not constrained by any gate. Such a target is
+//! set by the prover inputs or by a generator, but nothing checks its value, which is usually a
+//! soundness bug.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+use crate::field::extension::Extendable;
+use crate::gates::gate::GateInstance;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::permutation_argument::Forest;
+
+/// A virtual target which is not copy-constrained to any wire used by a gate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnconstrainedTarget {
+    pub target: Target,
+    /// Whether the target is copy-constrained to some wires, which no gate uses.
+    pub routed: bool,
+    /// The contexts which were open when the target was added.
+    pub context: String,
+}
+
+impl Display for UnconstrainedTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.routed {
+            write!(
+                f,
+                "{:?} ({}) is only routed to wires which no gate uses",
+                self.target, self.context
+            )
+        } else {
+            write!(
+                f,
+                "{:?} ({}) is never routed to a wire",
+                self.target, self.context
+            )
+        }
+    }
+}
+
+/// An error returned by `CircuitBuilder::build_with_lint` when some virtual targets are not
+/// constrained by any gate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnconstrainedTargetsError {
+    pub targets: Vec<UnconstrainedTarget>,
+}
+
+impl Display for UnconstrainedTargetsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} targets are unconstrained", self.targets.len())?;
+        for target in &self.targets {
+            write!(f, "\n{target}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnconstrainedTargetsError {}
+
+/// Returns the virtual targets whose partition in `forest` holds no wire used by a gate, i.e. no
+/// wire below the gate's `num_wires`. Generators don't declare the targets they set, so every
+/// virtual target is checked, rather than only those set by generators. `forest` should have its
+/// paths compressed.
+pub(crate) fn find_unconstrained_targets<F: RichField + Extendable<D>, const D: usize>(
+    gate_instances: &[GateInstance<F, D>],
+    forest: &Forest,
+    num_wires: usize,
+    virtual_target_contexts: &[usize],
+    contexts: &[String],
+) -> Vec<UnconstrainedTarget> {
+    let mut routed = vec![false; forest.parents.len()];
+    let mut constrained = vec![false; forest.parents.len()];
+    for (row, instance) in gate_instances.iter().enumerate() {
+        let num_gate_wires = instance.gate_ref.0.num_wires();
+        for column in 0..num_wires {
+            let rep = forest.parents[forest.target_index(Target::wire(row, column))];
+            routed[rep] = true;
+            constrained[rep] |= column < num_gate_wires;
+        }
+    }
+
+    virtual_target_contexts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &context)| {
+            let target = Target::VirtualTarget { index };
+            let rep = forest.parents[forest.target_index(target)];
+            (!constrained[rep]).then(|| UnconstrainedTarget {
+                target,
+                routed: routed[rep],
+                context: contexts[context].clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::target::Target;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn builder() -> CircuitBuilder<F, D> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        let z = builder.add_const(z, F::from_canonical_u64(7));
+        builder.register_public_input(z);
+        builder
+    }
+
+    #[test]
+    fn test_constrained_targets() {
+        assert!(builder().build_with_lint::<C>().is_ok());
+    }
+
+    #[test]
+    fn test_unrouted_target() {
+        let mut builder = builder();
+        let target = builder.add_virtual_target();
+        let error = builder.build_with_lint::<C>().unwrap_err();
+        assert_eq!(error.targets.len(), 1);
+        assert_eq!(error.targets[0].target, target);
+        assert!(!error.targets[0].routed);
+    }
+
+    #[test]
+    #[should_panic(expected = "1 targets are unconstrained")]
+    fn test_config_lint() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig {
+            lint_unconstrained_targets: true,
+            ..CircuitConfig::standard_recursion_config()
+        });
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        builder.add_virtual_target();
+        builder.build::<C>();
+    }
+
+    #[test]
+    fn test_target_routed_to_unused_wire() {
+        let mut builder = builder();
+        let target = builder.add_virtual_target();
+        let row = builder.add_gate(NoopGate, vec![]);
+        builder.connect(target, Target::wire(row, 0));
+        let error = builder.build_with_lint::<C>().unwrap_err();
+        assert_eq!(error.targets.len(), 1);
+        assert_eq!(error.targets[0].target, target);
+        assert!(error.targets[0].routed);
+    }
+}
@@ -1,35 +1,169 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
 use anyhow::{ensure, Result};
+use plonky2_maybe_rayon::*;
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
+use crate::fri::structure::FriInstanceInfo;
 use crate::fri::verifier::verify_fri_proof;
 use crate::hash::hash_types::RichField;
+use crate::iop::challenger::Challenger;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::get_challenges::circuit_challenger;
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::plonk::proof::{Proof, ProofChallenges, ProofWithPublicInputs};
 use crate::plonk::validate_shape::validate_proof_with_pis_shape;
 use crate::plonk::vanishing_poly::eval_vanishing_poly;
 use crate::plonk::vars::EvaluationVars;
 
+/// An error returned by `verify_batch` when some proofs of the batch are invalid.
+#[derive(Debug)]
+pub struct BatchVerificationError {
+    /// The index in the batch of each invalid proof, in increasing order, with the reason it was
+    /// rejected.
+    pub failures: Vec<(usize, anyhow::Error)>,
+}
+
+impl Display for BatchVerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        const MAX_REPORTED_FAILURES: usize = 20;
+
+        write!(f, "{} proofs are invalid", self.failures.len())?;
+        for (index, error) in self.failures.iter().take(MAX_REPORTED_FAILURES) {
+            write!(f, "\nproof {index}: {error}")?;
+        }
+        if self.failures.len() > MAX_REPORTED_FAILURES {
+            write!(
+                f,
+                "\n... and {} more",
+                self.failures.len() - MAX_REPORTED_FAILURES
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BatchVerificationError {}
+
 pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     proof_with_pis: ProofWithPublicInputs<F, C, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, D>,
 ) -> Result<()> {
-    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+    verify_ref(&proof_with_pis, verifier_data, common_data)
+}
+
+/// Verifies proofs of the same circuit, and reports all the invalid ones. The proofs are verified
+/// in parallel, as by `verify`, except that the work which only depends on the circuit (the
+/// challenger state after observing the circuit digest, and the structure of the FRI instance) is
+/// done once for the whole batch.
+pub(crate) fn verify_batch<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proofs_with_pis: &[ProofWithPublicInputs<F, C, D>],
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<(), BatchVerificationError> {
+    let circuit_data = CircuitVerificationData::new(verifier_data, common_data);
+    let failures = proofs_with_pis
+        .par_iter()
+        .enumerate()
+        .filter_map(|(index, proof_with_pis)| {
+            verify_with_circuit_data(proof_with_pis, &circuit_data, verifier_data, common_data)
+                .err()
+                .map(|error| (index, error))
+        })
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(BatchVerificationError { failures })
+    }
+}
+
+/// The part of verifying a proof which doesn't depend on the proof, only on the circuit.
+struct CircuitVerificationData<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// The challenger after observing the circuit digest.
+    challenger: Challenger<F, C::Hasher>,
+    /// The FRI instance, with placeholder opening points which are set from each proof's `zeta`.
+    fri_instance: FriInstanceInfo<F, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CircuitVerificationData<F, C, D>
+{
+    fn new(
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Self {
+        Self {
+            challenger: circuit_challenger::<F, C, D>(&verifier_data.circuit_digest),
+            fri_instance: common_data.get_fri_instance(F::Extension::ZERO),
+        }
+    }
+
+    fn fri_instance(
+        &self,
+        zeta: F::Extension,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> FriInstanceInfo<F, D> {
+        let mut fri_instance = self.fri_instance.clone();
+        for (batch, point) in fri_instance
+            .batches
+            .iter_mut()
+            .zip(common_data.fri_opening_points(zeta))
+        {
+            batch.point = point;
+        }
+        fri_instance
+    }
+}
+
+fn verify_ref<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    let circuit_data = CircuitVerificationData::new(verifier_data, common_data);
+    verify_with_circuit_data(proof_with_pis, &circuit_data, verifier_data, common_data)
+}
+
+fn verify_with_circuit_data<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    circuit_data: &CircuitVerificationData<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    validate_proof_with_pis_shape(proof_with_pis, common_data)?;
 
     let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
-    let challenges = proof_with_pis.get_challenges(
+    let challenges = proof_with_pis.get_challenges_from(
+        circuit_data.challenger.clone(),
         public_inputs_hash,
-        &verifier_data.circuit_digest,
         common_data,
     )?;
+    let fri_instance = circuit_data.fri_instance(challenges.plonk_zeta, common_data);
 
-    verify_with_challenges::<F, C, D>(
-        proof_with_pis.proof,
+    verify_with_challenges_and_instance::<F, C, D>(
+        &proof_with_pis.proof,
         public_inputs_hash,
         challenges,
+        &fri_instance,
         verifier_data,
         common_data,
     )
@@ -40,9 +174,33 @@ pub(crate) fn verify_with_challenges<
     C: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    proof: Proof<F, C, D>,
+    proof: &Proof<F, C, D>,
+    public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    challenges: ProofChallenges<F, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    let fri_instance = common_data.get_fri_instance(challenges.plonk_zeta);
+    verify_with_challenges_and_instance(
+        proof,
+        public_inputs_hash,
+        challenges,
+        &fri_instance,
+        verifier_data,
+        common_data,
+    )
+}
+
+/// Like `verify_with_challenges`, with `fri_instance` being `common_data.get_fri_instance(zeta)`.
+fn verify_with_challenges_and_instance<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: &Proof<F, C, D>,
     public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
     challenges: ProofChallenges<F, D>,
+    fri_instance: &FriInstanceInfo<F, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, D>,
 ) -> Result<()> {
@@ -97,14 +255,14 @@ pub(crate) fn verify_with_challenges<
 
     let merkle_caps = &[
         verifier_data.constants_sigmas_cap.clone(),
-        proof.wires_cap,
+        proof.wires_cap.clone(),
         // In the lookup case, `plonk_zs_partial_products_cap` should also include the lookup commitment.
-        proof.plonk_zs_partial_products_cap,
-        proof.quotient_polys_cap,
+        proof.plonk_zs_partial_products_cap.clone(),
+        proof.quotient_polys_cap.clone(),
     ];
 
     verify_fri_proof::<F, C, D>(
-        &common_data.get_fri_instance(challenges.plonk_zeta),
+        fri_instance,
         &proof.openings.to_fri_openings(),
         &challenges.fri_challenges,
         merkle_caps,
@@ -114,3 +272,46 @@ pub(crate) fn verify_with_challenges<
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_verify_batch() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let x_cubed = builder.cube(x);
+        builder.register_public_input(x_cubed);
+        let data = builder.build::<C>();
+
+        let mut proofs = (0..4)
+            .map(|_| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(x, F::rand());
+                data.prove(pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        data.verify_batch(&proofs)?;
+
+        proofs[1].public_inputs[1] += F::ONE;
+        proofs[3].proof.opening_proof.pow_witness += F::ONE;
+        let error = data.verify_batch(&proofs).unwrap_err();
+        let invalid = error.failures.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+        assert_eq!(invalid, [1, 3]);
+        assert!(error.to_string().starts_with("2 proofs are invalid"));
+
+        Ok(())
+    }
+}
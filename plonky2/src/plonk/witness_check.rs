@@ -0,0 +1,159 @@
+//! Checking a witness against the gate constraints of a circuit, to help debug witness generation.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::gates::selectors::UNUSED_SELECTOR;
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::iop::witness::MatrixWitness;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::vars::EvaluationVarsBaseBatch;
+use crate::util::context_tree::ContextTree;
+
+/// A gate constraint which does not hold for a witness.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConstraintFailure<F: Field> {
+    /// The row of the gate in the circuit.
+    pub row: usize,
+    /// The ID of the gate.
+    pub gate_id: String,
+    /// The index of the constraint among those of the gate.
+    pub constraint_index: usize,
+    /// The value of the constraint, which should have been zero.
+    pub value: F,
+    /// The contexts which were open when the gate was added, as pushed with
+    /// `CircuitBuilder::push_context` or `with_context!`. Gates which hold several operations are
+    /// attributed to the context of the first one.
+    pub context: String,
+}
+
+impl<F: Field> Display for ConstraintFailure<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "row {} ({}): constraint {} of {} evaluates to {}",
+            self.row, self.context, self.constraint_index, self.gate_id, self.value
+        )
+    }
+}
+
+/// Evaluates the constraints of the gate at each row on `witness`, and returns those which don't
+/// hold. `constants` holds the values of the constant polynomials, selectors included, at each row.
+pub(crate) fn find_constraint_failures<F: RichField + Extendable<D>, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+    constants: &[Vec<F>],
+    context_tree: &ContextTree,
+    witness: &MatrixWitness<F>,
+    public_inputs_hash: &HashOut<F>,
+) -> Vec<ConstraintFailure<F>> {
+    let num_selectors = common.selectors_info.num_selectors();
+    let num_prefix_constants = num_selectors + common.num_lookup_selectors;
+    let unused_selector = F::from_canonical_usize(UNUSED_SELECTOR);
+
+    let mut failures = Vec::new();
+    for (row, row_constants) in constants.iter().enumerate() {
+        // Each row has a single selector which isn't unused, holding the index of its gate.
+        let gate_index = row_constants[..num_selectors]
+            .iter()
+            .find(|&&s| s != unused_selector)
+            .expect("No selector for this row")
+            .to_canonical_u64() as usize;
+        let gate = &common.gates[gate_index];
+
+        let local_wires = (0..common.config.num_wires)
+            .map(|column| witness.get_wire(row, column))
+            .collect::<Vec<_>>();
+        let vars = EvaluationVarsBaseBatch::new(
+            1,
+            &row_constants[num_prefix_constants..],
+            &local_wires,
+            public_inputs_hash,
+        );
+
+        for (constraint_index, value) in gate
+            .0
+            .eval_unfiltered_base_batch(vars)
+            .into_iter()
+            .enumerate()
+        {
+            if value != F::ZERO {
+                failures.push(ConstraintFailure {
+                    row,
+                    gate_id: gate.0.id(),
+                    constraint_index,
+                    value,
+                    context: context_tree.open_stack_at(row),
+                });
+            }
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::gates::public_input::PublicInputGate;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_check_witness() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let product = with_context!(builder, "multiply", builder.mul(x, y));
+        builder.register_public_input(product);
+
+        // `PublicInputGate`s have no generators, so their wires can be set directly.
+        let row = with_context!(builder, "outer", {
+            with_context!(builder, "hash", builder.add_gate(PublicInputGate, vec![]))
+        });
+
+        let data = builder.mock_build::<C>();
+
+        let public_inputs_hash = PoseidonHash::hash_no_pad(&[F::from_canonical_u64(12)]);
+        let witness = |error: F| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(3));
+            pw.set_target(y, F::from_canonical_u64(4));
+            let mut hash = public_inputs_hash.elements;
+            hash[2] += error;
+            for (wire, h) in PublicInputGate::wires_public_inputs_hash().zip(hash) {
+                pw.set_target(Target::wire(row, wire), h);
+            }
+            pw
+        };
+
+        data.check_witness(witness(F::ZERO))?;
+
+        let failures = data.find_constraint_failures(witness(F::ONE))?;
+        assert_eq!(failures.len(), 1);
+        let failure = &failures[0];
+        assert_eq!(failure.row, row);
+        assert_eq!(failure.gate_id, "PublicInputGate");
+        assert_eq!(failure.constraint_index, 2);
+        assert_eq!(failure.value, F::ONE);
+        assert_eq!(failure.context, "root > outer > hash");
+        assert!(data.check_witness(witness(F::ONE)).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! Aggregation of many proofs of a leaf circuit into one, by folding them in a binary tree of
+//! recursive proofs.
+
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use plonky2_maybe_rayon::*;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitData, CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget,
+    VerifierOnlyCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::cyclic_recursion::{
+    build_cyclic_circuit, check_cyclic_proof_verifier_data, cyclic_common_data_seed,
+    find_cyclic_common_data,
+};
+
+/// The circuits aggregating proofs of a leaf circuit in a binary tree.
+///
+/// Each node of the tree holds a value with as many elements as the public inputs of the leaf
+/// circuit. The value of a leaf is the public inputs of its proof, and the value of an inner node
+/// is given by a merge function of the values of its children, e.g. the Poseidon hash of their
+/// concatenation.
+///
+/// The aggregation circuit verifies two child proofs, each of which is either an aggregation proof,
+/// or a leaf proof wrapped by the wrapper circuit so that it has the same shape. So the aggregation
+/// circuit is cyclic, and its verifier data is passed as public inputs, after the value of the
+/// node. Both circuits are padded to the same `CommonCircuitData`, found by rebuilding them until
+/// their shape stabilizes.
+pub struct AggregationCircuitData<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// Verifies a leaf proof, and exposes its public inputs.
+    pub wrapper: CircuitData<F, C, D>,
+    /// Verifies two child proofs, and exposes the merge of their values.
+    pub aggregation: CircuitData<F, C, D>,
+    value_len: usize,
+    wrapper_leaf_proof: ProofWithPublicInputsTarget<D>,
+    wrapper_cyclic_vk: VerifierCircuitTarget,
+    lhs: AggregationChildTarget<D>,
+    rhs: AggregationChildTarget<D>,
+    cyclic_vk: VerifierCircuitTarget,
+}
+
+/// A child of a node of the aggregation tree, which is either an aggregation proof or a wrapped
+/// leaf proof.
+struct AggregationChildTarget<const D: usize> {
+    is_agg: BoolTarget,
+    agg_proof: ProofWithPublicInputsTarget<D>,
+    wrapper_proof: ProofWithPublicInputsTarget<D>,
+}
+
+impl<const D: usize> AggregationChildTarget<D> {
+    fn value<F: RichField + Extendable<D>>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        value_len: usize,
+    ) -> Vec<Target> {
+        self.agg_proof.public_inputs[..value_len]
+            .iter()
+            .zip(&self.wrapper_proof.public_inputs)
+            .map(|(&agg, &wrapper)| builder.select(self.is_agg, agg, wrapper))
+            .collect()
+    }
+}
+
+impl<F, C, const D: usize> AggregationCircuitData<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds the circuits aggregating proofs of the `leaf` circuit. Given the values of two
+    /// children, `merge` adds the constraints computing the value of their parent, which must have
+    /// as many elements as the public inputs of the leaf circuit.
+    ///
+    /// `merge` is called each time the aggregation circuit is rebuilt while looking for its common
+    /// data, so it must add the same gates every time.
+    pub fn new<M>(leaf: &VerifierCircuitData<F, C, D>, merge: M) -> Result<Self>
+    where
+        M: Fn(&mut CircuitBuilder<F, D>, &[Target], &[Target]) -> Vec<Target>,
+    {
+        let config = &leaf.common.config;
+        let value_len = leaf.common.num_public_inputs;
+        let mut seed = cyclic_common_data_seed::<F, C, D>(config);
+        seed.num_public_inputs = value_len + 4 + 4 * config.fri_config.num_cap_elements();
+
+        find_cyclic_common_data(seed, |common_data| {
+            let data = Self::build(leaf, &merge, common_data)?;
+            let next_common_data = if &data.wrapper.common == common_data
+                && &data.aggregation.common == common_data
+            {
+                common_data.clone()
+            } else if data.wrapper.common.degree_bits() > data.aggregation.common.degree_bits() {
+                data.wrapper.common.clone()
+            } else {
+                data.aggregation.common.clone()
+            };
+            Ok((data, next_common_data))
+        })
+    }
+
+    /// Builds the circuits verifying child proofs with the given common data, padded to it as with
+    /// `build_cyclic_circuit`.
+    fn build<M>(
+        leaf: &VerifierCircuitData<F, C, D>,
+        merge: &M,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<Self>
+    where
+        M: Fn(&mut CircuitBuilder<F, D>, &[Target], &[Target]) -> Vec<Target>,
+    {
+        let config = &leaf.common.config;
+        let value_len = leaf.common.num_public_inputs;
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let wrapper_leaf_proof = builder.add_virtual_proof_with_pis(&leaf.common);
+        let leaf_vk = builder.constant_verifier_data(&leaf.verifier_only);
+        builder.verify_proof::<C>(&wrapper_leaf_proof, &leaf_vk, &leaf.common);
+        builder.register_public_inputs(&wrapper_leaf_proof.public_inputs);
+        // Wrapped proofs have the public inputs of aggregation proofs, but their verifier data is
+        // ignored.
+        let wrapper_cyclic_vk = builder.add_verifier_data_public_inputs();
+        let wrapper = build_cyclic_circuit::<F, C, D>(builder, common_data);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let wrapper_vk = builder.constant_verifier_data(&wrapper.verifier_only);
+        let add_child = |builder: &mut CircuitBuilder<F, D>| AggregationChildTarget {
+            is_agg: builder.add_virtual_bool_target_safe(),
+            agg_proof: builder.add_virtual_proof_with_pis(common_data),
+            wrapper_proof: builder.add_virtual_proof_with_pis(common_data),
+        };
+        let lhs = add_child(&mut builder);
+        let rhs = add_child(&mut builder);
+        let lhs_value = lhs.value(&mut builder, value_len);
+        let rhs_value = rhs.value(&mut builder, value_len);
+        let value = merge(&mut builder, &lhs_value, &rhs_value);
+        ensure!(
+            value.len() == value_len,
+            "The merge function output {} elements, instead of {}",
+            value.len(),
+            value_len
+        );
+        builder.register_public_inputs(&value);
+        let cyclic_vk = builder.add_verifier_data_public_inputs();
+        for child in [&lhs, &rhs] {
+            builder.conditionally_verify_cyclic_proof::<C>(
+                child.is_agg,
+                &child.agg_proof,
+                &child.wrapper_proof,
+                &wrapper_vk,
+                common_data,
+            )?;
+        }
+        // If the wrapper circuit doesn't fit in `common_data` yet, the next candidate needs its gates.
+        for gate in &wrapper.common.gates {
+            builder.add_gate_to_gate_set(gate.clone());
+        }
+        let aggregation = build_cyclic_circuit::<F, C, D>(builder, common_data);
+
+        Ok(Self {
+            wrapper,
+            aggregation,
+            value_len,
+            wrapper_leaf_proof,
+            wrapper_cyclic_vk,
+            lhs,
+            rhs,
+            cyclic_vk,
+        })
+    }
+
+    /// Wraps a leaf proof into a proof with the shape of aggregation proofs.
+    pub fn prove_wrapper(
+        &self,
+        leaf_proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&self.wrapper_leaf_proof, leaf_proof);
+        pw.set_verifier_data_target(&self.wrapper_cyclic_vk, &self.aggregation.verifier_only);
+        self.wrapper.prove(pw)
+    }
+
+    /// Aggregates two child proofs, each of which is an aggregation proof if its `is_agg` flag is
+    /// set, or a wrapped leaf proof otherwise.
+    pub fn prove_aggregation(
+        &self,
+        lhs_is_agg: bool,
+        lhs_proof: &ProofWithPublicInputs<F, C, D>,
+        rhs_is_agg: bool,
+        rhs_proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        for (child, is_agg, proof) in [
+            (&self.lhs, lhs_is_agg, lhs_proof),
+            (&self.rhs, rhs_is_agg, rhs_proof),
+        ] {
+            pw.set_bool_target(child.is_agg, is_agg);
+            // The proof which isn't selected is not verified, but it still needs a witness.
+            pw.set_proof_with_pis_target(&child.agg_proof, proof);
+            pw.set_proof_with_pis_target(&child.wrapper_proof, proof);
+        }
+        pw.set_verifier_data_target(&self.cyclic_vk, &self.aggregation.verifier_only);
+        self.aggregation.prove(pw)
+    }
+
+    /// Aggregates proofs of the leaf circuit into one aggregation proof, whose value is the root of
+    /// a binary tree with the leaf proofs at its leaves, in order. Each level of the tree is proven
+    /// in parallel. When a level has an odd number of nodes, the last one is moved up to the next
+    /// level.
+    pub fn prove_tree(
+        &self,
+        leaf_proofs: &[ProofWithPublicInputs<F, C, D>],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            leaf_proofs.len() >= 2,
+            "At least two proofs are needed for aggregation"
+        );
+
+        // Each node is a proof, with a flag set for aggregation proofs.
+        let mut nodes = leaf_proofs
+            .par_iter()
+            .map(|leaf_proof| Ok((false, self.prove_wrapper(leaf_proof)?)))
+            .collect::<Result<Vec<_>>>()?;
+        while nodes.len() > 1 {
+            nodes = nodes
+                .par_chunks(2)
+                .map(|pair| match pair {
+                    [(lhs_is_agg, lhs), (rhs_is_agg, rhs)] => Ok((
+                        true,
+                        self.prove_aggregation(*lhs_is_agg, lhs, *rhs_is_agg, rhs)?,
+                    )),
+                    [node] => Ok(node.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<Vec<_>>>()?;
+        }
+
+        let (_, root) = nodes.pop().unwrap();
+        Ok(root)
+    }
+
+    /// Verifies an aggregation proof, including that the verifier data in its public inputs is that
+    /// of the aggregation circuit.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.aggregation.verifier_only,
+            &self.aggregation.common,
+        )?;
+        self.aggregation.verify(proof)
+    }
+
+    pub fn verifier_data(&self) -> &VerifierOnlyCircuitData<C, D> {
+        &self.aggregation.verifier_only
+    }
+
+    /// The value of the node proven by an aggregation or wrapped leaf proof.
+    pub fn value<'a>(&self, proof: &'a ProofWithPublicInputs<F, C, D>) -> &'a [F] {
+        &proof.public_inputs[..self.value_len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::hashing::hash_n_to_hash_no_pad;
+    use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn hash(values: &[F]) -> Vec<F> {
+        hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(values)
+            .elements
+            .to_vec()
+    }
+
+    #[test]
+    fn test_prove_tree() -> Result<()> {
+        // Leaves prove knowledge of a preimage of their public hash.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let preimage = builder.add_virtual_target();
+        let leaf_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![preimage]);
+        builder.register_public_inputs(&leaf_hash.elements);
+        let leaf = builder.build::<C>();
+
+        let aggregation =
+            AggregationCircuitData::<F, C, D>::new(&leaf.verifier_data(), |builder, lhs, rhs| {
+                let inputs = [lhs, rhs].concat();
+                builder
+                    .hash_n_to_hash_no_pad::<PoseidonHash>(inputs)
+                    .elements
+                    .to_vec()
+            })?;
+
+        let leaf_proofs = (0..3)
+            .map(|_| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(preimage, F::rand());
+                leaf.prove(pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let root = aggregation.prove_tree(&leaf_proofs)?;
+
+        let leaf_values = leaf_proofs
+            .iter()
+            .map(|p| p.public_inputs.clone())
+            .collect::<Vec<_>>();
+        let expected = hash(
+            &[
+                hash(&[leaf_values[0].clone(), leaf_values[1].clone()].concat()),
+                leaf_values[2].clone(),
+            ]
+            .concat(),
+        );
+        assert_eq!(aggregation.value(&root), expected);
+        aggregation.verify(root)?;
+
+        assert!(aggregation.prove_tree(&leaf_proofs[..1]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+//! Recursive verification of proofs from any circuit of a set of allowed circuits, which share
+//! the same `CommonCircuitData`.
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::hash::merkle_tree::MerkleTree;
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::util::log2_ceil;
+
+/// A set of circuits sharing the same `CommonCircuitData`, committed to by the root of a Merkle
+/// tree whose leaves are their verifier data.
+///
+/// Circuits can be given a shared shape with `CircuitBuilder::build_with_target_shape`, e.g. with
+/// the common data of the largest circuit of the set, built with the gates of all the others.
+#[derive(Debug)]
+pub struct VerifierCircuitSet<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    pub common: CommonCircuitData<F, D>,
+    verifier_data: Vec<VerifierOnlyCircuitData<C, D>>,
+    tree: MerkleTree<F, C::Hasher>,
+}
+
+/// The targets of a proof verified by `CircuitBuilder::verify_proof_from_set`, with the verifier
+/// data of its circuit and the Merkle proof that the circuit is in the set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProofFromSetTarget<const D: usize> {
+    pub proof_with_pis: ProofWithPublicInputsTarget<D>,
+    pub verifier_data: VerifierCircuitTarget,
+    /// The index of the circuit in the set.
+    pub index: Target,
+    pub merkle_proof: MerkleProofTarget,
+}
+
+impl<F, C, const D: usize> VerifierCircuitSet<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Commits to the circuits with the given verifier data, all of which have the common data
+    /// `common`. The Merkle tree is padded to a power of two leaves by repeating the last circuit.
+    pub fn new(
+        common: CommonCircuitData<F, D>,
+        verifier_data: Vec<VerifierOnlyCircuitData<C, D>>,
+    ) -> Result<Self> {
+        ensure!(!verifier_data.is_empty(), "The set of circuits is empty");
+        let num_leaves = 1 << log2_ceil(verifier_data.len());
+        let leaves = (0..num_leaves)
+            .map(|i| verifier_data_leaf(&verifier_data[i.min(verifier_data.len() - 1)]))
+            .collect();
+        let tree = MerkleTree::new(leaves, 0);
+        Ok(Self {
+            common,
+            verifier_data,
+            tree,
+        })
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        self.tree.cap.0[0]
+    }
+
+    /// The number of siblings in the Merkle proofs of the set.
+    pub fn height(&self) -> usize {
+        log2_ceil(self.verifier_data.len())
+    }
+
+    pub fn index_of(&self, verifier_data: &VerifierOnlyCircuitData<C, D>) -> Option<usize> {
+        self.verifier_data.iter().position(|vd| vd == verifier_data)
+    }
+
+    pub fn merkle_proof(&self, index: usize) -> MerkleProof<F, C::Hasher> {
+        self.tree.prove(index)
+    }
+
+    /// Sets the targets of a proof of the circuit with the given verifier data, which must be in
+    /// the set.
+    pub fn set_proof_from_set_target(
+        &self,
+        pw: &mut PartialWitness<F>,
+        target: &ProofFromSetTarget<D>,
+        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+    ) -> Result<()> {
+        let index = self
+            .index_of(verifier_data)
+            .ok_or_else(|| anyhow!("The circuit is not in the set"))?;
+        ensure!(
+            target.merkle_proof.siblings.len() == self.height(),
+            "The Merkle proof target has {} siblings, instead of {}",
+            target.merkle_proof.siblings.len(),
+            self.height()
+        );
+
+        pw.set_proof_with_pis_target(&target.proof_with_pis, proof_with_pis);
+        pw.set_verifier_data_target(&target.verifier_data, verifier_data);
+        pw.set_target(target.index, F::from_canonical_usize(index));
+        for (&sibling_target, &sibling) in target
+            .merkle_proof
+            .siblings
+            .iter()
+            .zip(&self.merkle_proof(index).siblings)
+        {
+            pw.set_hash_target(sibling_target, sibling);
+        }
+        Ok(())
+    }
+}
+
+/// The data of a Merkle leaf committing to a circuit: its digest, followed by its constants and
+/// sigmas cap. The cap is included because the recursive verifier doesn't check that it matches
+/// the digest.
+fn verifier_data_leaf<C: GenericConfig<D>, const D: usize>(
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+) -> Vec<C::F> {
+    [
+        verifier_data.circuit_digest.to_vec(),
+        verifier_data.constants_sigmas_cap.flatten(),
+    ]
+    .concat()
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds the targets of a proof from a set of `2^set_height` circuits with the given common
+    /// data, to be verified with `verify_proof_from_set`.
+    pub fn add_virtual_proof_from_set(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+        set_height: usize,
+    ) -> ProofFromSetTarget<D> {
+        ProofFromSetTarget {
+            proof_with_pis: self.add_virtual_proof_with_pis(common_data),
+            verifier_data: self.add_virtual_verifier_data(common_data.config.fri_config.cap_height),
+            index: self.add_virtual_target(),
+            merkle_proof: MerkleProofTarget {
+                siblings: self.add_virtual_hashes(set_height),
+            },
+        }
+    }
+
+    /// Verifies a proof of any circuit of a set committed to by `set_root`, as computed by
+    /// `VerifierCircuitSet::root`. All the circuits of the set must have the common data
+    /// `common_data`.
+    pub fn verify_proof_from_set<C: GenericConfig<D, F = F>>(
+        &mut self,
+        set_root: HashOutTarget,
+        proof: &ProofFromSetTarget<D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let leaf = [
+            proof.verifier_data.circuit_digest.elements.to_vec(),
+            proof
+                .verifier_data
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .flat_map(|h| h.elements)
+                .collect(),
+        ]
+        .concat();
+        let index_bits = self.split_le(proof.index, proof.merkle_proof.siblings.len());
+        self.verify_merkle_proof::<C::Hasher>(leaf, &index_bits, set_root, &proof.merkle_proof);
+        self.verify_proof::<C>(&proof.proof_with_pis, &proof.verifier_data, common_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::gate::GateRef;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_verify_proof_from_set() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+
+        // A circuit proving knowledge of a preimage of a hash chain, which sets the shape of the set.
+        // It must have the gates of all the circuits of the set.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let preimage = builder.add_virtual_target();
+        let mut hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![preimage]);
+        for _ in 0..8 {
+            hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(hash.elements.to_vec());
+        }
+        builder.register_public_inputs(&hash.elements);
+        builder.add_gate_to_gate_set(GateRef::new(ArithmeticGate::new_from_config(&config)));
+        let hash_data = builder.build::<C>();
+        let common_data = hash_data.common.clone();
+
+        // Circuits proving knowledge of a `2^num_squarings`-th root, padded to the same shape.
+        let root_circuit = |num_squarings: usize| {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let root = builder.add_virtual_target();
+            let power = (0..num_squarings).fold(root, |x, _| builder.square(x));
+            builder.register_public_input(power);
+            let data = builder.build_with_target_shape::<C>(&common_data).unwrap();
+            (data, root)
+        };
+        let (square_data, root): (CircuitData<F, C, D>, _) = root_circuit(1);
+        let (fourth_power_data, fourth_root) = root_circuit(2);
+        assert_eq!(square_data.common, common_data);
+        assert_eq!(fourth_power_data.common, common_data);
+
+        let set = VerifierCircuitSet::<F, C, D>::new(
+            common_data.clone(),
+            vec![
+                hash_data.verifier_only.clone(),
+                square_data.verifier_only.clone(),
+            ],
+        )?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let set_root = builder.constant_hash(set.root());
+        let proof_target = builder.add_virtual_proof_from_set(&common_data, set.height());
+        builder.verify_proof_from_set::<C>(set_root, &proof_target, &common_data);
+        builder.register_public_inputs(&proof_target.proof_with_pis.public_inputs);
+        let outer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(preimage, F::rand());
+        let hash_proof = hash_data.prove(pw)?;
+        let mut pw = PartialWitness::new();
+        pw.set_target(root, F::rand());
+        let square_proof = square_data.prove(pw)?;
+        assert_eq!(square_proof.public_inputs[1..], [F::ZERO; 3]);
+
+        for (proof, data) in [(&hash_proof, &hash_data), (&square_proof, &square_data)] {
+            let mut pw = PartialWitness::new();
+            set.set_proof_from_set_target(&mut pw, &proof_target, proof, &data.verifier_only)?;
+            let outer_proof = outer.prove(pw)?;
+            assert_eq!(outer_proof.public_inputs, proof.public_inputs);
+            outer.verify(outer_proof)?;
+        }
+
+        // A circuit with the same shape, but outside of the set, is rejected.
+        let mut pw = PartialWitness::new();
+        assert!(set
+            .set_proof_from_set_target(
+                &mut pw,
+                &proof_target,
+                &square_proof,
+                &fourth_power_data.verifier_only
+            )
+            .is_err());
+
+        // Setting the targets directly, without that check, gives a witness which can't be proven,
+        // since its Merkle proof doesn't lead to the root of the set.
+        let mut pw = PartialWitness::new();
+        pw.set_target(fourth_root, F::rand());
+        let fourth_power_proof = fourth_power_data.prove(pw)?;
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_target.proof_with_pis, &fourth_power_proof);
+        pw.set_verifier_data_target(
+            &proof_target.verifier_data,
+            &fourth_power_data.verifier_only,
+        );
+        pw.set_target(proof_target.index, F::ONE);
+        for (&sibling_target, &sibling) in proof_target
+            .merkle_proof
+            .siblings
+            .iter()
+            .zip(&set.merkle_proof(1).siblings)
+        {
+            pw.set_hash_target(sibling_target, sibling);
+        }
+        assert!(outer.prove(pw).is_err());
+
+        Ok(())
+    }
+}
@@ -5,12 +5,14 @@ use alloc::vec::Vec;
 use anyhow::{ensure, Result};
 
 use crate::field::extension::Extendable;
+use crate::gates::constant::ConstantGate;
+use crate::gates::gate::GateRef;
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{
-    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
@@ -196,6 +198,78 @@ where
     Ok(())
 }
 
+/// Returns the `CommonCircuitData` of a circuit verifying a small proof, which is a good starting
+/// point when looking for the common data of a cyclic circuit, by rebuilding it until its shape
+/// stabilizes. Its gates include a `ConstantGate`, as dummy circuits always have one.
+pub(crate) fn cyclic_common_data_seed<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+) -> CommonCircuitData<F, D>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let inner_common = CircuitBuilder::<F, D>::new(config.clone())
+        .build::<C>()
+        .common;
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let proof = builder.add_virtual_proof_with_pis(&inner_common);
+    let verifier_data = builder.add_virtual_verifier_data(config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof, &verifier_data, &inner_common);
+    builder.add_gate_to_gate_set(GateRef::new(ConstantGate::new(config.num_constants)));
+    builder.build::<C>().common
+}
+
+/// The maximum number of times cyclic circuits are rebuilt to find their `CommonCircuitData`.
+const MAX_FIXPOINT_ITERATIONS: usize = 8;
+
+/// Finds the `CommonCircuitData` of cyclic circuits, i.e. common data such that the circuits
+/// verifying proofs with it have it themselves, starting from `seed`, e.g. the result of
+/// `cyclic_common_data_seed`.
+///
+/// Given candidate common data, `build` builds the circuits verifying proofs with it, e.g. with
+/// `build_cyclic_circuit`, and returns them along with the next candidate, which must be equal to
+/// the given one only if the circuits have it as their common data.
+pub(crate) fn find_cyclic_common_data<F: RichField + Extendable<D>, const D: usize, T>(
+    seed: CommonCircuitData<F, D>,
+    mut build: impl FnMut(&CommonCircuitData<F, D>) -> Result<(T, CommonCircuitData<F, D>)>,
+) -> Result<T> {
+    let mut common_data = seed;
+    for _ in 0..MAX_FIXPOINT_ITERATIONS {
+        let (circuits, next_common_data) = build(&common_data)?;
+        if next_common_data == common_data {
+            return Ok(circuits);
+        }
+        common_data = next_common_data;
+    }
+
+    Err(anyhow::anyhow!(
+        "The common data of the cyclic circuit didn't converge after {} builds",
+        MAX_FIXPOINT_ITERATIONS
+    ))
+}
+
+/// Builds a circuit with the shape `common_data` if it fits, as with `build_with_target_shape`.
+/// Otherwise, it is built with all the gates of `common_data` and at least its degree, so that its
+/// common data is a better candidate for `find_cyclic_common_data`.
+pub(crate) fn build_cyclic_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    mut builder: CircuitBuilder<F, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> CircuitData<F, C, D> {
+    // A failure only means that `common_data` isn't the fixpoint yet.
+    let _ = builder.pad_to_target_shape::<C>(common_data);
+    // The common data of a circuit verifying cyclic proofs differs from the expected one until the
+    // fixpoint is found, which `build` would panic on.
+    let (circuit_data, _) = builder.try_build_with_options::<C>(true);
+    circuit_data
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -5,11 +5,9 @@ use alloc::vec::Vec;
 use hashbrown::HashMap;
 use plonky2_field::extension::Extendable;
 use plonky2_field::polynomial::PolynomialCoeffs;
-use plonky2_util::ceil_div_usize;
 
 use crate::fri::proof::{FriProof, FriProofTarget};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
-use crate::gates::noop::NoopGate;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
@@ -91,31 +89,13 @@ pub(crate) fn dummy_circuit<
 >(
     common_data: &CommonCircuitData<F, D>,
 ) -> CircuitData<F, C, D> {
-    let config = common_data.config.clone();
-    assert!(
-        !common_data.config.zero_knowledge,
-        "Degree calculation can be off if zero-knowledge is on."
-    );
-
-    // Number of `NoopGate`s to add to get a circuit of size `degree` in the end.
-    // Need to account for public input hashing, a `PublicInputGate` and a `ConstantGate`.
-    let degree = common_data.degree();
-    let num_noop_gate = degree - ceil_div_usize(common_data.num_public_inputs, 8) - 2;
-
-    let mut builder = CircuitBuilder::<F, D>::new(config);
-    for _ in 0..num_noop_gate {
-        builder.add_gate(NoopGate, vec![]);
-    }
-    for gate in &common_data.gates {
-        builder.add_gate_to_gate_set(gate.clone());
-    }
+    let mut builder = CircuitBuilder::<F, D>::new(common_data.config.clone());
     for _ in 0..common_data.num_public_inputs {
         builder.add_virtual_public_input();
     }
-
-    let circuit = builder.build::<C>();
-    assert_eq!(&circuit.common, common_data);
-    circuit
+    builder
+        .build_with_target_shape::<C>(common_data)
+        .unwrap_or_else(|e| panic!("Failed to build a dummy circuit: {e}"))
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -0,0 +1,264 @@
+//! Incrementally verifiable computation on top of cyclic recursion: a chain of proofs, each of
+//! which applies a step circuit to the state output by the previous one.
+
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::cyclic_recursion::{
+    build_cyclic_circuit, check_cyclic_proof_verifier_data, cyclic_common_data_seed,
+    find_cyclic_common_data,
+};
+use crate::recursion::dummy_circuit::cyclic_base_proof;
+
+/// An incrementally verifiable computation, whose proofs show that a step circuit was applied some
+/// number of times to an initial state.
+///
+/// Each proof verifies the previous one in the chain, or a dummy proof for the first step. The
+/// circuit is cyclic: it verifies proofs of itself, so its verifier data is passed as public
+/// inputs, and its `CommonCircuitData` must be the one it is built with. This padding to a
+/// fixpoint is found by `Ivc::new`, by rebuilding the circuit until its shape stabilizes.
+///
+/// The public inputs of each proof are the initial state, the current state, the number of steps,
+/// and the verifier data of the circuit, in this order. Step circuits using lookup tables are not
+/// supported, since dummy proofs for the base case can't be generated for them.
+pub struct Ivc<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    pub circuit: CircuitData<F, C, D>,
+    state_len: usize,
+    condition: BoolTarget,
+    inner_proof: ProofWithPublicInputsTarget<D>,
+    verifier_data: VerifierCircuitTarget,
+    aux: Vec<Target>,
+}
+
+impl<F, C, const D: usize> Ivc<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds the cyclic circuit of a computation whose state has `state_len` elements. Given the
+    /// state input to a step, and `num_aux` auxiliary inputs given to the prover, `step` adds the
+    /// constraints of the step and returns its output state.
+    ///
+    /// `step` is called each time the circuit is rebuilt while looking for its common data, so it
+    /// must add the same gates every time.
+    pub fn new<S>(config: CircuitConfig, state_len: usize, num_aux: usize, step: S) -> Result<Self>
+    where
+        S: Fn(&mut CircuitBuilder<F, D>, &[Target], &[Target]) -> Vec<Target>,
+    {
+        let seed = cyclic_common_data_seed::<F, C, D>(&config);
+        find_cyclic_common_data(seed, |common_data| {
+            let ivc = Self::build(&config, state_len, num_aux, &step, common_data)?;
+            let next_common_data = ivc.circuit.common.clone();
+            Ok((ivc, next_common_data))
+        })
+    }
+
+    /// Builds the cyclic circuit, verifying proofs with the given common data, and padded to it as
+    /// with `build_cyclic_circuit`.
+    fn build<S>(
+        config: &CircuitConfig,
+        state_len: usize,
+        num_aux: usize,
+        step: &S,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<Self>
+    where
+        S: Fn(&mut CircuitBuilder<F, D>, &[Target], &[Target]) -> Vec<Target>,
+    {
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let one = builder.one();
+
+        let initial_state = builder.add_virtual_targets(state_len);
+        builder.register_public_inputs(&initial_state);
+        let state_out = builder.add_virtual_targets(state_len);
+        builder.register_public_inputs(&state_out);
+        let num_steps = builder.add_virtual_public_input();
+        let verifier_data = builder.add_verifier_data_public_inputs();
+
+        let mut common_data = common_data.clone();
+        common_data.num_public_inputs = builder.num_public_inputs();
+
+        let condition = builder.add_virtual_bool_target_safe();
+        let inner_proof = builder.add_virtual_proof_with_pis(&common_data);
+        let inner_initial_state = &inner_proof.public_inputs[..state_len];
+        let inner_state = &inner_proof.public_inputs[state_len..2 * state_len];
+        let inner_num_steps = inner_proof.public_inputs[2 * state_len];
+
+        // The initial state is carried along the chain. In the base case, the dummy proof holds it.
+        for (&x, &y) in initial_state.iter().zip(inner_initial_state) {
+            builder.connect(x, y);
+        }
+        // The step starts from the state of the previous proof, or the initial state in the base
+        // case.
+        let state_in = inner_state
+            .iter()
+            .zip(&initial_state)
+            .map(|(&inner, &initial)| builder.select(condition, inner, initial))
+            .collect::<Vec<_>>();
+        let aux = builder.add_virtual_targets(num_aux);
+        let step_out = step(&mut builder, &state_in, &aux);
+        ensure!(
+            step_out.len() == state_len,
+            "The step circuit output {} elements, instead of {}",
+            step_out.len(),
+            state_len
+        );
+        for (&x, &y) in state_out.iter().zip(&step_out) {
+            builder.connect(x, y);
+        }
+        let new_num_steps = builder.mul_add(condition.target, inner_num_steps, one);
+        builder.connect(num_steps, new_num_steps);
+
+        builder.conditionally_verify_cyclic_proof_or_dummy::<C>(
+            condition,
+            &inner_proof,
+            &common_data,
+        )?;
+
+        let circuit = build_cyclic_circuit::<F, C, D>(builder, &common_data);
+
+        Ok(Self {
+            circuit,
+            state_len,
+            condition,
+            inner_proof,
+            verifier_data,
+            aux,
+        })
+    }
+
+    /// Proves the first step of a chain, from `initial_state`.
+    pub fn prove_first_step(
+        &self,
+        initial_state: &[F],
+        aux: &[F],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            initial_state.len() == self.state_len,
+            "Expected a state of {} elements",
+            self.state_len
+        );
+        let initial_state_pis = initial_state
+            .iter()
+            .copied()
+            .enumerate()
+            .collect::<HashMap<_, _>>();
+        let base_proof = cyclic_base_proof(
+            &self.circuit.common,
+            &self.circuit.verifier_only,
+            initial_state_pis,
+        );
+        self.prove(false, &base_proof, aux)
+    }
+
+    /// Proves the step following the one proven by `previous`.
+    pub fn prove_step(
+        &self,
+        previous: &ProofWithPublicInputs<F, C, D>,
+        aux: &[F],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        self.prove(true, previous, aux)
+    }
+
+    fn prove(
+        &self,
+        condition: bool,
+        inner_proof: &ProofWithPublicInputs<F, C, D>,
+        aux: &[F],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            aux.len() == self.aux.len(),
+            "Expected {} auxiliary inputs",
+            self.aux.len()
+        );
+        let mut pw = PartialWitness::new();
+        pw.set_bool_target(self.condition, condition);
+        pw.set_proof_with_pis_target(&self.inner_proof, inner_proof);
+        pw.set_verifier_data_target(&self.verifier_data, &self.circuit.verifier_only);
+        for (&target, &value) in self.aux.iter().zip(aux) {
+            pw.set_target(target, value);
+        }
+        self.circuit.prove(pw)
+    }
+
+    /// Verifies a proof of the chain, including that the verifier data in its public inputs is
+    /// that of the circuit.
+    pub fn verify_chain(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.circuit.verifier_only,
+            &self.circuit.common,
+        )?;
+        self.circuit.verify(proof)
+    }
+
+    pub fn verifier_data(&self) -> &VerifierOnlyCircuitData<C, D> {
+        &self.circuit.verifier_only
+    }
+
+    pub fn initial_state<'a>(&self, proof: &'a ProofWithPublicInputs<F, C, D>) -> &'a [F] {
+        &proof.public_inputs[..self.state_len]
+    }
+
+    pub fn state<'a>(&self, proof: &'a ProofWithPublicInputs<F, C, D>) -> &'a [F] {
+        &proof.public_inputs[self.state_len..2 * self.state_len]
+    }
+
+    pub fn num_steps(&self, proof: &ProofWithPublicInputs<F, C, D>) -> F {
+        proof.public_inputs[2 * self.state_len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_ivc() -> Result<()> {
+        // Each step maps `(a, b)` to `(b, a + b * aux)`.
+        let ivc = Ivc::<F, C, D>::new(
+            CircuitConfig::standard_recursion_config(),
+            2,
+            1,
+            |builder, state, aux| vec![state[1], builder.mul_add(state[1], aux[0], state[0])],
+        )?;
+
+        let initial_state = [F::ONE, F::TWO];
+        let mut proof = ivc.prove_first_step(&initial_state, &[F::ONE])?;
+        ivc.verify_chain(proof.clone())?;
+        for aux in [F::TWO, F::ONE] {
+            proof = ivc.prove_step(&proof, &[aux])?;
+            ivc.verify_chain(proof.clone())?;
+        }
+
+        // (1, 2) -> (2, 3) -> (3, 8) -> (8, 11)
+        assert_eq!(ivc.initial_state(&proof), initial_state);
+        assert_eq!(
+            ivc.state(&proof),
+            [F::from_canonical_u64(8), F::from_canonical_u64(11)]
+        );
+        assert_eq!(ivc.num_steps(&proof), F::from_canonical_u64(3));
+
+        assert!(ivc.prove_step(&proof, &[]).is_err());
+        Ok(())
+    }
+}
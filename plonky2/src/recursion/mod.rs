@@ -1,4 +1,7 @@
+pub mod aggregation;
+pub mod circuit_set;
 pub mod conditional_recursive_verifier;
 pub mod cyclic_recursion;
 pub mod dummy_circuit;
+pub mod ivc;
 pub mod recursive_verifier;
@@ -5,6 +5,7 @@ use alloc::vec::Vec;
 use log::{log, Level};
 
 /// The hierarchy of contexts, and the gate count contributed by each one. Useful for debugging.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct ContextTree {
     /// The name of this scope.
     name: String,
@@ -50,6 +51,23 @@ impl ContextTree {
         }
     }
 
+    /// A description of the stack of scopes which were open when the given gate was added.
+    pub fn open_stack_at(&self, gate: usize) -> String {
+        let mut stack = Vec::new();
+        self.open_stack_at_helper(gate, &mut stack);
+        stack.join(" > ")
+    }
+
+    fn open_stack_at_helper(&self, gate: usize, stack: &mut Vec<String>) {
+        stack.push(self.name.clone());
+        let child = self.children.iter().find(|c| {
+            c.enter_gate_count <= gate && !matches!(c.exit_gate_count, Some(exit) if exit <= gate)
+        });
+        if let Some(child) = child {
+            child.open_stack_at_helper(gate, stack);
+        }
+    }
+
     pub fn push(&mut self, ctx: &str, mut level: log::Level, current_gate_count: usize) {
         assert!(self.is_open());
 
@@ -94,7 +94,9 @@ pub mod default {
 
     use crate::gates::arithmetic_base::ArithmeticGate;
     use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+    use crate::gates::arithmetic_u32::U32ArithmeticGate;
     use crate::gates::base_sum::BaseSumGate;
+    use crate::gates::bitwise::{BitOperationGate, BitwiseGate};
     use crate::gates::constant::ConstantGate;
     use crate::gates::coset_interpolation::CosetInterpolationGate;
     use crate::gates::exponentiation::ExponentiationGate;
@@ -103,16 +105,23 @@ pub mod default {
     use crate::gates::multiplication_extension::MulExtensionGate;
     use crate::gates::noop::NoopGate;
     use crate::gates::poseidon::PoseidonGate;
+    use crate::gates::poseidon2::Poseidon2Gate;
     use crate::gates::poseidon_mds::PoseidonMdsGate;
     use crate::gates::public_input::PublicInputGate;
     use crate::gates::random_access::RandomAccessGate;
     use crate::gates::reducing::ReducingGate;
     use crate::gates::reducing_extension::ReducingExtensionGate;
+    use crate::gates::subtraction_u32::U32SubtractionGate;
     use crate::hash::hash_types::RichField;
+    use crate::hash::poseidon2::Poseidon2;
     use crate::util::serialization::GateSerializer;
 
+    /// Serializes the gates of this crate. Fields without Poseidon2 constants can build their own
+    /// serializer with `impl_gate_serializer!`, leaving out `Poseidon2Gate`.
     pub struct DefaultGateSerializer;
-    impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for DefaultGateSerializer {
+    impl<F: RichField + Extendable<D> + Poseidon2, const D: usize> GateSerializer<F, D>
+        for DefaultGateSerializer
+    {
         impl_gate_serializer! {
             DefaultGateSerializer,
             ArithmeticGate,
@@ -127,10 +136,15 @@ pub mod default {
             NoopGate,
             PoseidonMdsGate<F, D>,
             PoseidonGate<F, D>,
+            Poseidon2Gate<F, D>,
             PublicInputGate,
             RandomAccessGate<F, D>,
             ReducingExtensionGate<D>,
-            ReducingGate<D>
+            ReducingGate<D>,
+            U32ArithmeticGate<F, D>,
+            U32SubtractionGate<F, D>,
+            BitwiseGate,
+            BitOperationGate
         }
     }
 }
@@ -100,23 +100,33 @@ pub mod default {
 
     use crate::gadgets::arithmetic::EqualityGenerator;
     use crate::gadgets::arithmetic_extension::QuotientGeneratorExtension;
-    use crate::gadgets::range_check::LowHighGenerator;
+    use crate::gadgets::arithmetic_u32::U32DivRemGenerator;
+    use crate::gadgets::glv::GLVDecompositionGenerator;
+    use crate::gadgets::nonnative::{
+        CarryGenerator, NonNativeInverseGenerator, NonNativeReduceGenerator,
+    };
+    use crate::gadgets::range_check::{ByteSplitGenerator, LowHighGenerator};
     use crate::gadgets::split_base::BaseSumGenerator;
     use crate::gadgets::split_join::{SplitGenerator, WireSplitGenerator};
     use crate::gates::arithmetic_base::ArithmeticBaseGenerator;
     use crate::gates::arithmetic_extension::ArithmeticExtensionGenerator;
+    use crate::gates::arithmetic_u32::U32ArithmeticGenerator;
     use crate::gates::base_sum::BaseSplitGenerator;
+    use crate::gates::bitwise::{BitOperationGenerator, BitwiseGenerator};
     use crate::gates::coset_interpolation::InterpolationGenerator;
     use crate::gates::exponentiation::ExponentiationGenerator;
     use crate::gates::lookup::LookupGenerator;
     use crate::gates::lookup_table::LookupTableGenerator;
     use crate::gates::multiplication_extension::MulExtensionGenerator;
     use crate::gates::poseidon::PoseidonGenerator;
+    use crate::gates::poseidon2::Poseidon2Generator;
     use crate::gates::poseidon_mds::PoseidonMdsGenerator;
     use crate::gates::random_access::RandomAccessGenerator;
     use crate::gates::reducing::ReducingGenerator;
     use crate::gates::reducing_extension::ReducingGenerator as ReducingExtensionGenerator;
+    use crate::gates::subtraction_u32::U32SubtractionGenerator;
     use crate::hash::hash_types::RichField;
+    use crate::hash::poseidon2::Poseidon2;
     use crate::iop::generator::{
         ConstantGenerator, CopyGenerator, NonzeroTestGenerator, RandomValueGenerator,
     };
@@ -130,7 +140,7 @@ pub mod default {
 
     impl<F, C, const D: usize> WitnessGeneratorSerializer<F, D> for DefaultGeneratorSerializer<C, D>
     where
-        F: RichField + Extendable<D>,
+        F: RichField + Extendable<D> + Poseidon2,
         C: GenericConfig<D, F = F> + 'static,
         C::Hasher: AlgebraicHasher<F>,
     {
@@ -140,18 +150,26 @@ pub mod default {
             ArithmeticExtensionGenerator<F, D>,
             BaseSplitGenerator<2>,
             BaseSumGenerator<2>,
+            BitOperationGenerator,
+            BitwiseGenerator,
+            ByteSplitGenerator,
+            CarryGenerator,
             ConstantGenerator<F>,
             CopyGenerator,
             DummyProofGenerator<F, C, D>,
             EqualityGenerator,
             ExponentiationGenerator<F, D>,
+            GLVDecompositionGenerator,
             InterpolationGenerator<F, D>,
             LookupGenerator,
             LookupTableGenerator,
             LowHighGenerator,
             MulExtensionGenerator<F, D>,
+            NonNativeInverseGenerator,
+            NonNativeReduceGenerator,
             NonzeroTestGenerator,
             PoseidonGenerator<F, D>,
+            Poseidon2Generator<F, D>,
             PoseidonMdsGenerator<D>,
             QuotientGeneratorExtension<D>,
             RandomAccessGenerator<F, D>,
@@ -159,6 +177,9 @@ pub mod default {
             ReducingGenerator<D>,
             ReducingExtensionGenerator<D>,
             SplitGenerator,
+            U32ArithmeticGenerator<F, D>,
+            U32DivRemGenerator,
+            U32SubtractionGenerator<F, D>,
             WireSplitGenerator
         }
     }
@@ -5,6 +5,7 @@ pub mod generator_serialization;
 pub mod gate_serialization;
 
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -34,8 +35,10 @@ use crate::gates::gate::GateRef;
 use crate::gates::lookup::Lookup;
 use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
 use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::iop::challenger::Challenger;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::WitnessGeneratorRef;
 use crate::iop::target::{BoolTarget, Target};
@@ -51,6 +54,7 @@ use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, OpeningSet, OpeningSetTarget, Proof,
     ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget,
 };
+use crate::plonk::prover::ProverCheckpoint;
 
 /// A no_std compatible variant of `std::io::Error`
 #[derive(Debug)]
@@ -332,8 +336,8 @@ pub trait Read {
         let cap_height = self.read_usize()?;
         let cap = self.read_merkle_cap::<F, H>(cap_height)?;
         Ok(MerkleTree {
-            leaves,
-            digests,
+            leaves: leaves.into(),
+            digests: digests.into(),
             cap,
         })
     }
@@ -673,6 +677,7 @@ pub trait Read {
             use_base_arithmetic_gate,
             zero_knowledge,
             fri_config,
+            lint_unconstrained_targets: false,
         })
     }
 
@@ -746,6 +751,72 @@ pub trait Read {
         })
     }
 
+    fn read_challenger<F: RichField, H: Hasher<F>>(&mut self) -> IoResult<Challenger<F, H>> {
+        let sponge_state = H::Permutation::new(self.read_field_vec(H::Permutation::WIDTH)?);
+        let input_len = self.read_usize()?;
+        let input_buffer = self.read_field_vec(input_len)?;
+        let output_len = self.read_usize()?;
+        let output_buffer = self.read_field_vec(output_len)?;
+
+        Ok(Challenger {
+            sponge_state,
+            input_buffer,
+            output_buffer,
+        })
+    }
+
+    fn read_prover_checkpoint<F, C, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<ProverCheckpoint<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let circuit_digest = self.read_hash::<F, C::Hasher>()?;
+        let config = self.read_circuit_config()?;
+        let pi_len = self.read_usize()?;
+        let public_inputs = self.read_field_vec(pi_len)?;
+        let challenger = self.read_challenger()?;
+        let wires_commitment = self.read_polynomial_batch()?;
+        let betas_len = self.read_usize()?;
+        let betas = self.read_field_vec(betas_len)?;
+        let gammas_len = self.read_usize()?;
+        let gammas = self.read_field_vec(gammas_len)?;
+        let deltas_len = self.read_usize()?;
+        let deltas = self.read_field_vec(deltas_len)?;
+        let partial_products_zs_and_lookup_commitment = if self.read_bool()? {
+            Some(self.read_polynomial_batch()?)
+        } else {
+            None
+        };
+        let quotient_polys_commitment = if self.read_bool()? {
+            Some(self.read_polynomial_batch()?)
+        } else {
+            None
+        };
+        let openings = if self.read_bool()? {
+            let zeta = self.read_field_ext::<F, D>()?;
+            Some((zeta, self.read_opening_set::<F, C, D>(common_data)?))
+        } else {
+            None
+        };
+
+        Ok(ProverCheckpoint {
+            circuit_digest,
+            config,
+            public_inputs,
+            challenger,
+            wires_commitment,
+            betas,
+            gammas,
+            deltas,
+            partial_products_zs_and_lookup_commitment,
+            quotient_polys_commitment,
+            openings,
+        })
+    }
+
     fn read_common_circuit_data<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         gate_serializer: &dyn GateSerializer<F, D>,
@@ -891,6 +962,14 @@ pub trait Read {
             lut_to_lookups.push(self.read_target_lut()?);
         }
 
+        let length = self.read_usize()?;
+        let mut contexts = Vec::with_capacity(length);
+        for _ in 0..length {
+            contexts.push(self.read_string()?);
+        }
+        let generator_contexts = self.read_usize_vec()?;
+        let gate_contexts = self.read_usize_vec()?;
+
         Ok(ProverOnlyCircuitData {
             generators,
             generator_indices_by_watches,
@@ -903,6 +982,9 @@ pub trait Read {
             circuit_digest,
             lookup_rows,
             lut_to_lookups,
+            contexts,
+            generator_contexts,
+            gate_contexts,
         })
     }
 
@@ -1181,6 +1263,15 @@ pub trait Read {
 
         Ok(lut)
     }
+
+    /// Reads a UTF-8 `String` from `self`.
+    #[inline]
+    fn read_string(&mut self) -> IoResult<String> {
+        let length = self.read_usize()?;
+        let mut bytes = vec![0; length];
+        self.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|_| IoError)
+    }
 }
 
 /// Writing
@@ -1697,6 +1788,8 @@ pub trait Write {
             use_base_arithmetic_gate,
             zero_knowledge,
             fri_config,
+            // Only used while building the circuit.
+            lint_unconstrained_targets: _,
         } = config;
 
         self.write_usize(*num_wires)?;
@@ -1762,6 +1855,67 @@ pub trait Write {
         Ok(())
     }
 
+    fn write_challenger<F: RichField, H: Hasher<F>>(
+        &mut self,
+        challenger: &Challenger<F, H>,
+    ) -> IoResult<()> {
+        self.write_field_vec(challenger.sponge_state.as_ref())?;
+        self.write_usize(challenger.input_buffer.len())?;
+        self.write_field_vec(&challenger.input_buffer)?;
+        self.write_usize(challenger.output_buffer.len())?;
+        self.write_field_vec(&challenger.output_buffer)
+    }
+
+    fn write_prover_checkpoint<F, C, const D: usize>(
+        &mut self,
+        checkpoint: &ProverCheckpoint<F, C, D>,
+    ) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let ProverCheckpoint {
+            circuit_digest,
+            config,
+            public_inputs,
+            challenger,
+            wires_commitment,
+            betas,
+            gammas,
+            deltas,
+            partial_products_zs_and_lookup_commitment,
+            quotient_polys_commitment,
+            openings,
+        } = checkpoint;
+
+        self.write_hash::<F, C::Hasher>(*circuit_digest)?;
+        self.write_circuit_config(config)?;
+        self.write_usize(public_inputs.len())?;
+        self.write_field_vec(public_inputs)?;
+        self.write_challenger(challenger)?;
+        self.write_polynomial_batch(wires_commitment)?;
+        for challenges in [betas, gammas, deltas] {
+            self.write_usize(challenges.len())?;
+            self.write_field_vec(challenges)?;
+        }
+        for commitment in [
+            partial_products_zs_and_lookup_commitment,
+            quotient_polys_commitment,
+        ] {
+            self.write_bool(commitment.is_some())?;
+            if let Some(commitment) = commitment {
+                self.write_polynomial_batch(commitment)?;
+            }
+        }
+        self.write_bool(openings.is_some())?;
+        if let Some((zeta, openings)) = openings {
+            self.write_field_ext::<F, D>(*zeta)?;
+            self.write_opening_set(openings)?;
+        }
+
+        Ok(())
+    }
+
     fn write_common_circuit_data<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         common_data: &CommonCircuitData<F, D>,
@@ -1853,6 +2007,9 @@ pub trait Write {
             circuit_digest,
             lookup_rows,
             lut_to_lookups,
+            contexts,
+            generator_contexts,
+            gate_contexts,
         } = prover_only_circuit_data;
 
         self.write_usize(generators.len())?;
@@ -1903,6 +2060,13 @@ pub trait Write {
             self.write_target_lut(tlut)?;
         }
 
+        self.write_usize(contexts.len())?;
+        for context in contexts.iter() {
+            self.write_string(context)?;
+        }
+        self.write_usize_vec(generator_contexts)?;
+        self.write_usize_vec(gate_contexts)?;
+
         Ok(())
     }
 
@@ -2133,6 +2297,13 @@ pub trait Write {
 
         Ok(())
     }
+
+    /// Writes a `String` `s` to `self`, as UTF-8.
+    #[inline]
+    fn write_string(&mut self, s: &str) -> IoResult<()> {
+        self.write_usize(s.len())?;
+        self.write_all(s.as_bytes())
+    }
 }
 
 impl Write for Vec<u8> {
@@ -2163,6 +2334,44 @@ impl Write for Vec<u8> {
     }
 }
 
+/// Adapts a `std::io::Write` to `Write`, to serialize into a file or a socket without buffering
+/// the bytes in memory.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoWriter<W>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Write for IoWriter<W> {
+    type Error = std::io::Error;
+
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.0.write_all(bytes).map_err(|_| IoError)
+    }
+
+    fn write_gate<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate: &GateRef<F, D>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        let mut bytes = Vec::new();
+        gate_serializer.write_gate(&mut bytes, gate, common_data)?;
+        self.write_all(&bytes)
+    }
+
+    fn write_generator<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        generator: &WitnessGeneratorRef<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        let mut bytes = Vec::new();
+        generator_serializer.write_generator(&mut bytes, generator, common_data)?;
+        self.write_all(&bytes)
+    }
+}
+
 /// Buffer
 #[derive(Debug)]
 pub struct Buffer<'a> {
@@ -0,0 +1,234 @@
+//! Storage for the large buffers of the prover, such as the low-degree extensions and Merkle
+//! digests of a `PolynomialBatch`, either in memory or in memory-mapped files.
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::slice;
+
+#[cfg(all(feature = "mmap", unix))]
+pub use self::mmap::MmapBuffer;
+
+/// Where to store the large buffers of the prover.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Storage {
+    #[default]
+    Memory,
+    /// In files created in the given directory and mapped to memory, so that the OS pages them in
+    /// and out as needed. This lets the prover commit to traces larger than physical memory, at
+    /// the cost of disk I/O. The files are removed as soon as they are mapped, so they don't
+    /// outlive the buffers, even if the prover crashes.
+    #[cfg(all(feature = "mmap", unix))]
+    Mmap(std::path::PathBuf),
+}
+
+impl Storage {
+    /// Allocates a buffer of `len` elements, and lets `init` initialize it.
+    ///
+    /// # Safety
+    ///
+    /// `init` must initialize every element of the slice it is given.
+    pub(crate) unsafe fn alloc_with<T: Copy>(
+        &self,
+        len: usize,
+        init: impl FnOnce(&mut [MaybeUninit<T>]),
+    ) -> Buffer<T> {
+        match self {
+            Storage::Memory => {
+                let mut v = Vec::<T>::with_capacity(len);
+                // SAFETY: `v` has a capacity of at least `len`, and the caller guarantees that
+                // `init` initializes its first `len` elements.
+                init(slice::from_raw_parts_mut(
+                    v.as_mut_ptr().cast::<MaybeUninit<T>>(),
+                    len,
+                ));
+                v.set_len(len);
+                Buffer::Memory(v)
+            }
+            #[cfg(all(feature = "mmap", unix))]
+            Storage::Mmap(dir) => {
+                let mut buffer = MmapBuffer::new(dir, len).unwrap_or_else(|e| {
+                    panic!("Failed to map a buffer of {len} elements in {dir:?}: {e}")
+                });
+                init(buffer.as_uninit_mut());
+                Buffer::Mmap(buffer)
+            }
+        }
+    }
+}
+
+/// A fixed-length buffer, stored as described by a `Storage`.
+pub enum Buffer<T> {
+    Memory(Vec<T>),
+    #[cfg(all(feature = "mmap", unix))]
+    Mmap(MmapBuffer<T>),
+}
+
+impl<T> Deref for Buffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Buffer::Memory(v) => v,
+            #[cfg(all(feature = "mmap", unix))]
+            Buffer::Mmap(m) => m,
+        }
+    }
+}
+
+impl<T> DerefMut for Buffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            Buffer::Memory(v) => v,
+            #[cfg(all(feature = "mmap", unix))]
+            Buffer::Mmap(m) => m,
+        }
+    }
+}
+
+impl<T> Default for Buffer<T> {
+    fn default() -> Self {
+        Buffer::Memory(Vec::new())
+    }
+}
+
+impl<T> From<Vec<T>> for Buffer<T> {
+    fn from(v: Vec<T>) -> Self {
+        Buffer::Memory(v)
+    }
+}
+
+/// Clones are held in memory, whatever the storage of the original buffer.
+impl<T: Clone> Clone for Buffer<T> {
+    fn clone(&self) -> Self {
+        Buffer::Memory(self.to_vec())
+    }
+}
+
+impl<T: Debug> Debug for Buffer<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Buffer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for Buffer<T> {}
+
+#[cfg(all(feature = "mmap", unix))]
+mod mmap {
+    use core::marker::PhantomData;
+    use core::mem::{size_of, MaybeUninit};
+    use core::ops::{Deref, DerefMut};
+    use core::ptr::{self, NonNull};
+    use core::slice;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::fs::{self, OpenOptions};
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    /// A buffer mapped to a file which has been removed, so that its pages are only written to
+    /// disk when the OS needs to reclaim memory.
+    pub struct MmapBuffer<T> {
+        ptr: NonNull<T>,
+        len: usize,
+        _marker: PhantomData<T>,
+    }
+
+    // SAFETY: The mapping is owned by the buffer, like the allocation of a `Vec`.
+    unsafe impl<T: Send> Send for MmapBuffer<T> {}
+    unsafe impl<T: Sync> Sync for MmapBuffer<T> {}
+
+    impl<T: Copy> MmapBuffer<T> {
+        /// Creates a buffer of `len` elements in a new file in `dir`, filled with zero bytes.
+        pub(crate) fn new(dir: &Path, len: usize) -> io::Result<Self> {
+            static NEXT_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
+            let size = len * size_of::<T>();
+            if size == 0 {
+                return Ok(Self {
+                    ptr: NonNull::dangling(),
+                    len,
+                    _marker: PhantomData,
+                });
+            }
+
+            let path = dir.join(format!(
+                "plonky2-{}-{}.bin",
+                std::process::id(),
+                NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            // The mapping keeps the file's data alive after it is removed.
+            fs::remove_file(&path)?;
+            file.set_len(size as u64)?;
+
+            // SAFETY: We map a fresh file of `size` bytes, which nothing else can access.
+            let ptr = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    size,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Self {
+                ptr: NonNull::new(ptr.cast()).expect("mmap returned a null pointer"),
+                len,
+                _marker: PhantomData,
+            })
+        }
+
+        /// Returns the elements of the buffer as uninitialized memory. They hold zero bytes until
+        /// written.
+        pub(crate) fn as_uninit_mut(&mut self) -> &mut [MaybeUninit<T>] {
+            // SAFETY: `MaybeUninit<T>` has the layout of `T`.
+            unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr().cast(), self.len) }
+        }
+    }
+
+    impl<T> Deref for MmapBuffer<T> {
+        type Target = [T];
+
+        fn deref(&self) -> &[T] {
+            // SAFETY: The mapping holds `len` elements, and is page-aligned. Buffers are only
+            // created from `Storage::alloc_with`, whose callers initialize every element.
+            unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+        }
+    }
+
+    impl<T> DerefMut for MmapBuffer<T> {
+        fn deref_mut(&mut self) -> &mut [T] {
+            // SAFETY: See `deref`.
+            unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+        }
+    }
+
+    impl<T> Drop for MmapBuffer<T> {
+        fn drop(&mut self) {
+            let size = self.len * size_of::<T>();
+            if size != 0 {
+                // SAFETY: The mapping was created with this address and size in `new`.
+                unsafe {
+                    libc::munmap(self.ptr.as_ptr().cast(), size);
+                }
+            }
+        }
+    }
+}
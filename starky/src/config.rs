@@ -1,5 +1,6 @@
 use plonky2::fri::reduction_strategies::FriReductionStrategy;
 use plonky2::fri::{FriConfig, FriParams};
+use plonky2::util::storage::Storage;
 
 pub struct StarkConfig {
     pub security_bits: usize,
@@ -9,6 +10,9 @@ pub struct StarkConfig {
     pub num_challenges: usize,
 
     pub fri_config: FriConfig,
+
+    /// Where the prover stores the LDEs and Merkle trees of its commitments.
+    pub storage: Storage,
 }
 
 impl StarkConfig {
@@ -25,9 +29,15 @@ impl StarkConfig {
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 84,
             },
+            storage: Storage::Memory,
         }
     }
 
+    /// Stores the LDEs and Merkle trees of the prover's commitments as described by `storage`.
+    pub fn with_storage(self, storage: Storage) -> Self {
+        Self { storage, ..self }
+    }
+
     pub(crate) fn fri_params(&self, degree_bits: usize) -> FriParams {
         self.fri_config.fri_params(degree_bits, false)
     }
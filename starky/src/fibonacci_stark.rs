@@ -183,6 +183,33 @@ mod tests {
         verify_stark_proof(stark, proof, &config)
     }
 
+    #[test]
+    #[cfg(all(feature = "mmap", unix))]
+    fn test_fibonacci_stark_mmap() -> Result<()> {
+        use plonky2::util::storage::Storage;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = FibonacciStark<F, D>;
+
+        let config =
+            StarkConfig::standard_fast_config().with_storage(Storage::Mmap(std::env::temp_dir()));
+        let num_rows = 1 << 5;
+        let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &public_inputs,
+            &mut TimingTree::default(),
+        )?;
+
+        verify_stark_proof(stark, proof, &config)
+    }
+
     #[test]
     fn test_fibonacci_stark_degree() -> Result<()> {
         const D: usize = 2;
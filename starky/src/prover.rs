@@ -54,13 +54,14 @@ where
     let trace_commitment = timed!(
         timing,
         "compute trace commitment",
-        PolynomialBatch::<F, C, D>::from_values(
+        PolynomialBatch::<F, C, D>::from_values_with_storage(
             trace_poly_values.clone(),
             rate_bits,
             false,
             cap_height,
             timing,
             None,
+            &config.storage,
         )
     );
 
@@ -85,13 +86,14 @@ where
         let permutation_zs_commitment = timed!(
             timing,
             "compute permutation Z commitments",
-            PolynomialBatch::from_values(
+            PolynomialBatch::from_values_with_storage(
                 permutation_z_polys,
                 rate_bits,
                 false,
                 config.fri_config.cap_height,
                 timing,
                 None,
+                &config.storage,
             )
         );
         (permutation_zs_commitment, permutation_challenge_sets)
@@ -129,13 +131,14 @@ where
     let quotient_commitment = timed!(
         timing,
         "compute quotient commitment",
-        PolynomialBatch::from_coeffs(
+        PolynomialBatch::from_coeffs_with_storage(
             all_quotient_chunks,
             rate_bits,
             false,
             config.fri_config.cap_height,
             timing,
             None,
+            &config.storage,
         )
     );
     let quotient_polys_cap = quotient_commitment.merkle_tree.cap.clone();